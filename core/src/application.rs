@@ -18,11 +18,20 @@ mod generated_icons {
 }
 
 use crate::{
+    battery::BatteryMonitor,
+    clock::{clock_label, shift_time_of_day, TimeSource, CLOCK_UNSET_DEFAULT_SECS},
     display::RefreshMode,
     framebuffer::{DisplayBuffers, Rotation, HEIGHT as FB_HEIGHT, WIDTH as FB_WIDTH},
-    image_viewer::{EntryKind, ImageData, ImageEntry, ImageError, ImageSource},
+    image_viewer::{
+        gray2_luma, EntryKind, EpubInfo, ImageData, ImageEntry, ImageError, ImageSource, ResumeState,
+        Settings,
+    },
     input,
-    ui::{flush_queue, ListItem, ListView, ReaderView, Rect, RenderQueue, UiContext, View},
+    ui::{
+        flush_queue, prefers_grayscale, render_gray_plane, ConfirmChoice, ConfirmDialog, ListItem,
+        ListView, ProgressBar, ReaderView, Rect, RenderQueue, ScrollTextView, TextView, UiContext,
+        View,
+    },
 };
 
 fn basename_from_path(path: &str) -> String {
@@ -33,12 +42,38 @@ const LIST_TOP: i32 = 60;
 const LINE_HEIGHT: i32 = 24;
 const LIST_MARGIN_X: i32 = 16;
 const HEADER_Y: i32 = 24;
-const BOOK_FULL_REFRESH_EVERY: usize = 10;
+const SLIDESHOW_RESUME_MS: u32 = 30_000;
+/// The last option (0) never sleeps.
+const IDLE_TIMEOUT_OPTIONS_MS: [u32; 6] = [60_000, 120_000, 300_000, 600_000, 1_800_000, 0];
+const SLIDESHOW_OPTIONS_MS: [u32; 5] = [5_000, 10_000, 30_000, 60_000, 300_000];
+const FULL_REFRESH_OPTIONS: [usize; 5] = [1, 5, 10, 20, 50];
+const SETTINGS_ROWS: usize = 6;
 const PAGE_INDICATOR_MARGIN: i32 = 12;
 const PAGE_INDICATOR_Y: i32 = 24;
+const FOOTER_HEIGHT: i32 = 24;
+const FOOTER_MIN_MARGIN: i32 = 8;
+const FOOTER_BAR_HEIGHT: i32 = 6;
+const HRULE_THICKNESS: i32 = 2;
 const START_MENU_MARGIN: i32 = 16;
 const START_MENU_RECENT_THUMB: i32 = 44;
+const MENU_THUMB_PANEL_W: i32 = 140;
+const RECENT_LIMIT: usize = 8;
+const RECENT_FOLDER: &str = "Recent";
 const START_MENU_ACTION_GAP: i32 = 12;
+/// Characters offered by the search prompt, in cycling order. Index 0 is the
+/// blank a new position starts with.
+const SEARCH_CHARSET: &[u8] = b" abcdefghijklmnopqrstuvwxyz0123456789'-.,";
+const SEARCH_MAX_CHARS: usize = 20;
+/// Pages a running search reads per update, so Back still gets through.
+const SEARCH_PAGES_PER_TICK: usize = 4;
+const PROMPT_PADDING: i32 = 12;
+/// Quiet time in a book before the next page is read ahead.
+const PREFETCH_IDLE_MS: u32 = 300;
+/// How long Up/Down wait for Confirm to join them in a chord before turning
+/// the page anyway.
+const CHORD_WINDOW_MS: u32 = 250;
+const BATTERY_ICON_W: i32 = 22;
+const BATTERY_ICON_H: i32 = 11;
 
 pub struct Application<'a, S: ImageSource> {
     dirty: bool,
@@ -46,31 +81,77 @@ pub struct Application<'a, S: ImageSource> {
     source: &'a mut S,
     entries: Vec<ImageEntry>,
     selected: usize,
+    sort_mode: SortMode,
     state: AppState,
     current_image: Option<ImageData>,
     current_book: Option<crate::trbk::TrbkBookInfo>,
     current_page_ops: Option<crate::trbk::TrbkPage>,
     toc_selected: usize,
     toc_labels: Option<Vec<String>>,
+    goto_digits: Vec<u8>,
+    goto_cursor: usize,
+    /// Position picked in the percentage scrubber, 0..=100.
+    scrub_percent: u8,
+    /// Search prompt contents as indices into `SEARCH_CHARSET`.
+    search_chars: Vec<u8>,
+    search_cursor: usize,
+    /// Last query run on the open book and the pages that matched it.
+    search_hits: Option<(String, Vec<usize>)>,
+    /// Search still reading through the book, a few pages per update.
+    search_scan: Option<SearchScan>,
+    /// Page and offset (pixels below the top margin) a TOC entry that starts
+    /// mid-page jumped to; that page is drawn scrolled so the entry is at the
+    /// top until the reader turns away from it.
+    toc_target: Option<(usize, u16)>,
+    /// File name and extracted text of the EPUB being previewed.
+    epub_preview: Option<(String, String)>,
+    epub_preview_scroll: usize,
+    /// Converted copy in the current folder that Confirm opens from the preview.
+    epub_preview_trbk: Option<String>,
+    /// Replaces the preview's hint after Right saved (or failed to save) a
+    /// text copy.
+    epub_preview_status: Option<String>,
+    confirm_tap: bool,
+    back_tap: bool,
+    /// Page turn (true = forward) held back while Up/Down might still become
+    /// a Confirm chord.
+    pending_turn: Option<bool>,
+    pending_confirm: Option<PendingConfirm>,
+    menu_thumbnail: Option<(String, Option<ImageData>)>,
     current_page: usize,
     book_turns_since_full: usize,
+    book_full_refresh_every: usize,
     current_entry: Option<String>,
     last_viewed_entry: Option<String>,
     page_turn_indicator: Option<PageTurnIndicator>,
     last_rendered_page: Option<usize>,
     error_message: Option<String>,
+    error_scroll: usize,
     sleep_transition: bool,
     wake_transition: bool,
     full_refresh: bool,
     sleep_after_error: bool,
     idle_ms: u32,
     idle_timeout_ms: u32,
+    slideshow: bool,
+    slideshow_paused: bool,
+    slideshow_interval_ms: u32,
+    slideshow_ms: u32,
+    settings_selected: usize,
+    settings_dirty: bool,
     sleep_overlay: Option<SleepOverlay>,
     sleep_overlay_pending: bool,
+    sleep_cover_shown: bool,
     wake_restore_only: bool,
-    resume_name: Option<String>,
+    sleep_return_state: Option<AppState>,
+    panel_powered_down: bool,
+    resume_state: Option<ResumeState>,
     book_positions: BTreeMap<String, usize>,
     recent_entries: Vec<String>,
+    /// The root listing starts with the virtual "Recent" folder.
+    recent_folder_shown: bool,
+    /// The menu lists `recent_entries` rather than a directory.
+    in_recent_folder: bool,
     path: Vec<String>,
     start_menu_section: StartMenuSection,
     start_menu_index: usize,
@@ -78,9 +159,18 @@ pub struct Application<'a, S: ImageSource> {
     sleep_from_home: bool,
     recent_dirty: bool,
     book_positions_dirty: bool,
-    last_saved_resume: Option<String>,
+    last_saved_resume: Option<ResumeState>,
     exit_from: ExitFrom,
     exit_overlay_drawn: bool,
+    /// Last charge reported through `update_battery`.
+    battery_percent: Option<u8>,
+    /// Time read by the last `sync_clock`, or the value being set from the
+    /// settings screen.
+    clock_secs: Option<u64>,
+    /// `clock_secs` was changed in settings and goes to the source next sync.
+    clock_set_pending: bool,
+    /// Page last handed to `prefetch_trbk_page` for the open book.
+    prefetched_page: Option<usize>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -88,9 +178,16 @@ enum AppState {
     StartMenu,
     Menu,
     Viewing,
+    BookInfo,
     BookViewing,
     ExitingPending,
+    Settings,
     Toc,
+    GotoPage,
+    Scrub,
+    Search,
+    EpubPreview,
+    Confirm,
     SleepingPending,
     Sleeping,
     Error,
@@ -114,6 +211,33 @@ enum StartMenuSection {
     Actions,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SortMode {
+    Name,
+    Newest,
+}
+
+impl SortMode {
+    fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "Name",
+            SortMode::Newest => "Newest",
+        }
+    }
+}
+
+/// A yes/no prompt shown in `AppState::Confirm` and what Yes does.
+struct PendingConfirm {
+    message: String,
+    choice: ConfirmChoice,
+    action: ConfirmAction,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ConfirmAction {
+    DeleteEntry(usize),
+}
+
 #[derive(Clone, Copy, Debug)]
 enum StartMenuAction {
     FileBrowser,
@@ -121,6 +245,13 @@ enum StartMenuAction {
     Battery,
 }
 
+/// A search in progress: the next page to read and the hits so far.
+struct SearchScan {
+    query: String,
+    next_page: usize,
+    hits: Vec<usize>,
+}
+
 struct RecentPreview {
     path: String,
     title: String,
@@ -130,43 +261,85 @@ struct RecentPreview {
 impl<'a, S: ImageSource> Application<'a, S> {
     pub fn new(display_buffers: &'a mut DisplayBuffers, source: &'a mut S) -> Self {
         display_buffers.set_rotation(Rotation::Rotate90);
-        let resume_name = source.load_resume();
+        let resume_state = source.load_resume();
+        let settings = source.load_settings().unwrap_or_default();
         let book_positions = source
             .load_book_positions()
             .into_iter()
             .collect::<BTreeMap<_, _>>();
-        let recent_entries = source.load_recent_entries();
+        let mut recent_entries: Vec<String> = Vec::new();
+        for entry in source.load_recent_entries() {
+            if !recent_entries.contains(&entry) {
+                recent_entries.push(entry);
+            }
+        }
+        recent_entries.truncate(RECENT_LIMIT);
         let mut app = Application {
             dirty: true,
             display_buffers,
             source,
             entries: Vec::new(),
             selected: 0,
+            sort_mode: if settings.sort_newest {
+                SortMode::Newest
+            } else {
+                SortMode::Name
+            },
             state: AppState::StartMenu,
             current_image: None,
             current_book: None,
             current_page_ops: None,
             toc_selected: 0,
             toc_labels: None,
+            goto_digits: Vec::new(),
+            goto_cursor: 0,
+            scrub_percent: 0,
+            epub_preview: None,
+            epub_preview_scroll: 0,
+            epub_preview_trbk: None,
+            epub_preview_status: None,
+            search_chars: Vec::new(),
+            search_cursor: 0,
+            search_hits: None,
+            search_scan: None,
+            toc_target: None,
+            confirm_tap: false,
+            back_tap: false,
+            pending_turn: None,
+            pending_confirm: None,
+            menu_thumbnail: None,
             current_page: 0,
             book_turns_since_full: 0,
+            book_full_refresh_every: settings.full_refresh_every.max(1),
             current_entry: None,
             last_viewed_entry: None,
             page_turn_indicator: None,
             last_rendered_page: None,
             error_message: None,
+            error_scroll: 0,
             sleep_transition: false,
             wake_transition: false,
             full_refresh: true,
             sleep_after_error: false,
             idle_ms: 0,
-            idle_timeout_ms: 300_000,
+            idle_timeout_ms: settings.idle_timeout_ms,
+            slideshow: false,
+            slideshow_paused: false,
+            slideshow_interval_ms: settings.slideshow_interval_ms,
+            slideshow_ms: 0,
+            settings_selected: 0,
+            settings_dirty: false,
             sleep_overlay: None,
             sleep_overlay_pending: false,
+            sleep_cover_shown: false,
             wake_restore_only: false,
-            resume_name,
+            sleep_return_state: None,
+            panel_powered_down: false,
+            resume_state,
             book_positions,
             recent_entries,
+            recent_folder_shown: false,
+            in_recent_folder: false,
             path: Vec::new(),
             start_menu_section: StartMenuSection::Recents,
             start_menu_index: 0,
@@ -177,6 +350,10 @@ impl<'a, S: ImageSource> Application<'a, S> {
             last_saved_resume: None,
             exit_from: ExitFrom::Image,
             exit_overlay_drawn: false,
+            battery_percent: None,
+            clock_secs: None,
+            clock_set_pending: false,
+            prefetched_page: None,
         };
         app.refresh_entries();
         app.try_resume();
@@ -190,11 +367,27 @@ impl<'a, S: ImageSource> Application<'a, S> {
         {
             self.source.wake();
             let mut resumed_viewer = false;
+            let return_state = self.sleep_return_state.take();
             if let Some(overlay) = self.sleep_overlay.take() {
                 self.restore_rect_bits(&overlay);
-                self.state = AppState::Viewing;
+                if return_state == Some(AppState::BookViewing) && self.current_book.is_some() {
+                    self.state = AppState::BookViewing;
+                    self.current_page_ops = self.source.trbk_page(self.current_page).ok();
+                    self.last_rendered_page = None;
+                } else {
+                    self.state = AppState::Viewing;
+                }
                 self.wake_restore_only = true;
                 resumed_viewer = true;
+            } else if core::mem::take(&mut self.sleep_cover_shown)
+                && return_state == Some(AppState::BookViewing)
+                && self.current_book.is_some()
+            {
+                // The cover replaced the page, so redraw it in full.
+                self.state = AppState::BookViewing;
+                self.current_page_ops = self.source.trbk_page(self.current_page).ok();
+                self.last_rendered_page = None;
+                resumed_viewer = true;
             } else {
                 self.state = AppState::StartMenu;
             }
@@ -283,9 +476,10 @@ impl<'a, S: ImageSource> Application<'a, S> {
                                     self.dirty = true;
                                 }
                                 1 => {
-                                    self.set_error(ImageError::Message(
-                                        "Settings not implemented yet.".into(),
-                                    ));
+                                    self.state = AppState::Settings;
+                                    self.settings_selected = 0;
+                                    self.full_refresh = true;
+                                    self.dirty = true;
                                 }
                                 _ => {}
                             }
@@ -293,26 +487,44 @@ impl<'a, S: ImageSource> Application<'a, S> {
                     }
                 } else {
                     self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
-                    if self.idle_ms >= self.idle_timeout_ms {
+                    if self.idle_expired() {
                         self.start_sleep_request();
                     }
                 }
             }
             AppState::Menu => {
-                if buttons.is_pressed(input::Buttons::Up) {
+                let step = Self::list_step(buttons);
+                if step < 0 {
                     if !self.entries.is_empty() {
                         self.selected = self.selected.saturating_sub(1);
                     }
                     self.dirty = true;
-                } else if buttons.is_pressed(input::Buttons::Down) {
+                } else if step > 0 {
                     if !self.entries.is_empty() {
                         self.selected = (self.selected + 1).min(self.entries.len() - 1);
                     }
                     self.dirty = true;
+                } else if buttons.is_pressed(input::Buttons::Left)
+                    || buttons.is_pressed(input::Buttons::Right)
+                {
+                    self.cycle_sort_mode();
                 } else if buttons.is_pressed(input::Buttons::Confirm) {
                     self.open_selected();
                 } else if buttons.is_pressed(input::Buttons::Back) {
-                    if !self.path.is_empty() {
+                    self.back_tap = true;
+                } else if buttons.is_long_press(input::Buttons::Back) {
+                    if core::mem::take(&mut self.back_tap) {
+                        self.request_delete_selected();
+                    }
+                } else if buttons.is_released(input::Buttons::Back)
+                    && core::mem::take(&mut self.back_tap)
+                {
+                    // Back acts on release so a long press can delete instead.
+                    if self.in_recent_folder {
+                        self.in_recent_folder = false;
+                        self.selected = 0;
+                        self.refresh_entries();
+                    } else if !self.path.is_empty() {
                         self.path.pop();
                         self.refresh_entries();
                     } else {
@@ -321,7 +533,7 @@ impl<'a, S: ImageSource> Application<'a, S> {
                     }
                 } else {
                     self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
-                    if self.idle_ms >= self.idle_timeout_ms {
+                    if self.idle_expired() {
                         self.start_sleep_request();
                     }
                 }
@@ -332,57 +544,116 @@ impl<'a, S: ImageSource> Application<'a, S> {
                         let next = self.selected.saturating_sub(1);
                         self.open_index(next);
                     }
+                    self.slideshow_paused = true;
                 } else if buttons.is_pressed(input::Buttons::Right) {
                     if !self.entries.is_empty() {
                         let next = (self.selected + 1).min(self.entries.len() - 1);
                         self.open_index(next);
                     }
+                    self.slideshow_paused = true;
+                } else if buttons.is_pressed(input::Buttons::Up) {
+                    // Rotates the view only; reopening the image starts upright again.
+                    if let Some(image) = self.current_image.take() {
+                        self.current_image = Some(image.rotated_cw());
+                        self.full_refresh = true;
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Down) {
+                    self.slideshow = !self.slideshow;
+                    self.slideshow_paused = false;
+                    self.slideshow_ms = 0;
+                    log::info!("Slideshow {}", if self.slideshow { "on" } else { "off" });
                 } else if buttons.is_pressed(input::Buttons::Back)
                     || buttons.is_pressed(input::Buttons::Confirm)
                 {
+                    self.slideshow = false;
                     self.exit_from = ExitFrom::Image;
                     self.exit_overlay_drawn = false;
                     self.state = AppState::ExitingPending;
                     self.dirty = true;
                 } else {
                     self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
-                    if self.idle_ms >= self.idle_timeout_ms {
+                    if self.slideshow {
+                        self.tick_slideshow(elapsed_ms);
+                    } else if self.idle_expired() {
+                        self.start_sleep_request();
+                    }
+                }
+            }
+            AppState::BookInfo => {
+                if buttons.is_pressed(input::Buttons::Confirm) {
+                    self.start_reading_selected();
+                } else if buttons.is_pressed(input::Buttons::Back) {
+                    self.current_book = None;
+                    self.source.close_trbk();
+                    self.state = AppState::Menu;
+                    self.dirty = true;
+                } else {
+                    self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
+                    if self.idle_expired() {
                         self.start_sleep_request();
                     }
                 }
             }
             AppState::BookViewing => {
-                if buttons.is_pressed(input::Buttons::Left)
-                    || buttons.is_pressed(input::Buttons::Up)
+                if buttons.is_pressed(input::Buttons::Confirm)
+                    && (buttons.is_pressed(input::Buttons::Up)
+                        || buttons.is_held(input::Buttons::Up))
                 {
-                    if self.current_page > 0 {
-                        self.current_page = self.current_page.saturating_sub(1);
-                        self.current_page_ops = None;
-                        self.book_turns_since_full = self.book_turns_since_full.saturating_add(1);
-                        self.page_turn_indicator = Some(PageTurnIndicator::Backward);
-                        self.dirty = true;
-                    }
-                } else if buttons.is_pressed(input::Buttons::Right)
-                    || buttons.is_pressed(input::Buttons::Down)
+                    self.confirm_tap = false;
+                    self.pending_turn = None;
+                    self.cycle_book_size();
+                } else if buttons.is_pressed(input::Buttons::Confirm)
+                    && (buttons.is_pressed(input::Buttons::Down)
+                        || buttons.is_held(input::Buttons::Down))
                 {
-                    if let Some(book) = &self.current_book {
-                        if self.current_page + 1 < book.page_count {
-                            self.current_page += 1;
-                            self.current_page_ops = None;
-                            self.book_turns_since_full = self.book_turns_since_full.saturating_add(1);
-                            self.page_turn_indicator = Some(PageTurnIndicator::Forward);
-                            self.dirty = true;
-                        }
-                    }
+                    self.confirm_tap = false;
+                    self.pending_turn = None;
+                    self.enter_search();
+                } else if (buttons.is_pressed(input::Buttons::Confirm)
+                    || buttons.is_held(input::Buttons::Confirm))
+                    && (buttons.is_pressed(input::Buttons::Left)
+                        || buttons.is_pressed(input::Buttons::Right))
+                {
+                    self.confirm_tap = false;
+                    self.jump_chapter(buttons.is_pressed(input::Buttons::Right));
+                } else if (buttons.is_pressed(input::Buttons::Confirm)
+                    || buttons.is_held(input::Buttons::Confirm))
+                    && buttons.is_pressed(input::Buttons::Back)
+                {
+                    self.confirm_tap = false;
+                    self.enter_scrub();
                 } else if buttons.is_pressed(input::Buttons::Confirm) {
-                    if let Some(book) = &self.current_book {
-                        if !book.toc.is_empty() {
-                            self.toc_selected = find_toc_selection(book, self.current_page);
-                            self.toc_labels = None;
-                            self.state = AppState::Toc;
-                            self.dirty = true;
-                        }
+                    self.confirm_tap = true;
+                } else if buttons.is_long_press(input::Buttons::Confirm) {
+                    if core::mem::take(&mut self.confirm_tap) {
+                        self.enter_goto_page();
                     }
+                } else if buttons.is_released(input::Buttons::Confirm) {
+                    // A short press opens the TOC; long presses were handled while held.
+                    if core::mem::take(&mut self.confirm_tap) {
+                        self.open_toc();
+                    }
+                } else if buttons.is_pressed(input::Buttons::Left)
+                    || buttons.is_pressed(input::Buttons::Right)
+                {
+                    self.turn_book_page(buttons.is_pressed(input::Buttons::Right));
+                } else if buttons.is_pressed(input::Buttons::Up)
+                    || buttons.is_pressed(input::Buttons::Down)
+                {
+                    // Up and Down also start the Confirm chords, so their
+                    // turn waits for the release or the chord window.
+                    self.pending_turn = Some(buttons.is_pressed(input::Buttons::Down));
+                } else if let Some(forward) = self.pending_turn.filter(|&forward| {
+                    let button = if forward {
+                        input::Buttons::Down
+                    } else {
+                        input::Buttons::Up
+                    };
+                    !buttons.is_held(button) || buttons.held_ms(button) >= CHORD_WINDOW_MS
+                }) {
+                    self.pending_turn = None;
+                    self.turn_book_page(forward);
                 } else if buttons.is_pressed(input::Buttons::Back) {
                     self.exit_from = ExitFrom::Book;
                     self.exit_overlay_drawn = false;
@@ -390,20 +661,23 @@ impl<'a, S: ImageSource> Application<'a, S> {
                     self.dirty = true;
                 } else {
                     self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
-                    if self.idle_ms >= self.idle_timeout_ms {
+                    if self.idle_expired() {
                         self.start_sleep_request();
+                    } else if self.idle_ms >= PREFETCH_IDLE_MS && !self.dirty {
+                        self.prefetch_next_page();
                     }
                 }
             }
             AppState::Toc => {
+                let step = Self::list_step(buttons);
                 if let Some(book) = &self.current_book {
                     let toc_len = book.toc.len();
-                    if buttons.is_pressed(input::Buttons::Up) {
+                    if step < 0 {
                         if self.toc_selected > 0 {
                             self.toc_selected -= 1;
                             self.dirty = true;
                         }
-                    } else if buttons.is_pressed(input::Buttons::Down) {
+                    } else if step > 0 {
                         if self.toc_selected + 1 < toc_len {
                             self.toc_selected += 1;
                             self.dirty = true;
@@ -411,6 +685,8 @@ impl<'a, S: ImageSource> Application<'a, S> {
                     } else if buttons.is_pressed(input::Buttons::Confirm) {
                         if let Some(entry) = book.toc.get(self.toc_selected) {
                             self.current_page = entry.page_index as usize;
+                            self.toc_target = (entry.y_offset > 0)
+                                .then_some((self.current_page, entry.y_offset));
                             self.current_page_ops = None;
                             self.last_rendered_page = None;
                             self.state = AppState::BookViewing;
@@ -423,7 +699,7 @@ impl<'a, S: ImageSource> Application<'a, S> {
                         self.dirty = true;
                     } else {
                         self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
-                        if self.idle_ms >= self.idle_timeout_ms {
+                        if self.idle_expired() {
                             self.start_sleep_request();
                         }
                     }
@@ -432,9 +708,213 @@ impl<'a, S: ImageSource> Application<'a, S> {
                     self.dirty = true;
                 }
             }
+            AppState::GotoPage => {
+                let digits = self.goto_digits.len();
+                if buttons.is_pressed(input::Buttons::Up) {
+                    if let Some(digit) = self.goto_digits.get_mut(self.goto_cursor) {
+                        *digit = (*digit + 1) % 10;
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Down) {
+                    if let Some(digit) = self.goto_digits.get_mut(self.goto_cursor) {
+                        *digit = (*digit + 9) % 10;
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Left) {
+                    if self.goto_cursor > 0 {
+                        self.goto_cursor -= 1;
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Right) {
+                    if self.goto_cursor + 1 < digits {
+                        self.goto_cursor += 1;
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Confirm) {
+                    let total = self.current_book.as_ref().map(|book| book.page_count).unwrap_or(0);
+                    let entered = self
+                        .goto_digits
+                        .iter()
+                        .fold(0usize, |acc, digit| acc * 10 + *digit as usize);
+                    self.jump_to_page(entered.saturating_sub(1).min(total.saturating_sub(1)));
+                } else if buttons.is_pressed(input::Buttons::Back) {
+                    self.state = AppState::BookViewing;
+                    self.full_refresh = true;
+                    self.dirty = true;
+                } else {
+                    self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
+                    if self.idle_expired() {
+                        self.start_sleep_request();
+                    }
+                }
+            }
+            AppState::Scrub => {
+                let step: i32 = if buttons.is_pressed(input::Buttons::Right) {
+                    1
+                } else if buttons.is_pressed(input::Buttons::Left) {
+                    -1
+                } else if buttons.is_pressed(input::Buttons::Up) {
+                    10
+                } else if buttons.is_pressed(input::Buttons::Down) {
+                    -10
+                } else {
+                    0
+                };
+                if step != 0 {
+                    let percent = (self.scrub_percent as i32 + step).clamp(0, 100) as u8;
+                    if percent != self.scrub_percent {
+                        self.scrub_percent = percent;
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Confirm) {
+                    self.jump_to_page(self.scrub_target_page());
+                } else if buttons.is_pressed(input::Buttons::Back) {
+                    self.state = AppState::BookViewing;
+                    self.full_refresh = true;
+                    self.dirty = true;
+                } else {
+                    self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
+                    if self.idle_expired() {
+                        self.start_sleep_request();
+                    }
+                }
+            }
+            AppState::Search if self.search_scan.is_some() => {
+                if buttons.is_pressed(input::Buttons::Back) {
+                    // The query stays in the prompt; only the scan is dropped.
+                    self.search_scan = None;
+                    self.dirty = true;
+                } else {
+                    self.continue_search();
+                }
+            }
+            AppState::Search => {
+                if buttons.is_pressed(input::Buttons::Up)
+                    || buttons.is_pressed(input::Buttons::Down)
+                {
+                    let len = SEARCH_CHARSET.len() as u8;
+                    let step = if buttons.is_pressed(input::Buttons::Up) { 1 } else { len - 1 };
+                    if let Some(ch) = self.search_chars.get_mut(self.search_cursor) {
+                        *ch = (*ch + step) % len;
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Left) {
+                    if self.search_cursor > 0 {
+                        self.search_cursor -= 1;
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Right) {
+                    // Stepping past the end grows the query by a blank.
+                    if self.search_cursor + 1 == self.search_chars.len()
+                        && self.search_chars.len() < SEARCH_MAX_CHARS
+                    {
+                        self.search_chars.push(0);
+                    }
+                    if self.search_cursor + 1 < self.search_chars.len() {
+                        self.search_cursor += 1;
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Confirm) {
+                    self.run_search();
+                } else if buttons.is_pressed(input::Buttons::Back) {
+                    self.state = AppState::BookViewing;
+                    self.full_refresh = true;
+                    self.dirty = true;
+                } else {
+                    self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
+                    if self.idle_expired() {
+                        self.start_sleep_request();
+                    }
+                }
+            }
+            AppState::Settings => {
+                let step = Self::list_step(buttons);
+                if step != 0 {
+                    self.settings_selected = (self.settings_selected as i32 + step)
+                        .clamp(0, SETTINGS_ROWS as i32 - 1)
+                        as usize;
+                    self.dirty = true;
+                } else if buttons.is_pressed(input::Buttons::Left) {
+                    self.adjust_setting(false);
+                } else if buttons.is_pressed(input::Buttons::Right)
+                    || buttons.is_pressed(input::Buttons::Confirm)
+                {
+                    self.adjust_setting(true);
+                } else if buttons.is_pressed(input::Buttons::Back) {
+                    self.save_settings_now();
+                    self.state = AppState::StartMenu;
+                    self.full_refresh = true;
+                    self.dirty = true;
+                } else {
+                    self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
+                    if self.idle_expired() {
+                        self.start_sleep_request();
+                    }
+                }
+            }
             AppState::SleepingPending => {}
             AppState::Sleeping => {}
             AppState::ExitingPending => {}
+            AppState::Confirm => {
+                if buttons.is_pressed(input::Buttons::Left)
+                    || buttons.is_pressed(input::Buttons::Right)
+                {
+                    if let Some(pending) = self.pending_confirm.as_mut() {
+                        pending.choice = pending.choice.toggle();
+                        self.dirty = true;
+                    }
+                } else if buttons.is_pressed(input::Buttons::Confirm) {
+                    let accepted = self
+                        .pending_confirm
+                        .as_ref()
+                        .is_some_and(|pending| pending.choice == ConfirmChoice::Yes);
+                    self.resolve_confirm(accepted);
+                } else if buttons.is_pressed(input::Buttons::Back) {
+                    self.resolve_confirm(false);
+                }
+            }
+            AppState::EpubPreview => {
+                if buttons.is_pressed(input::Buttons::Back) {
+                    self.epub_preview = None;
+                    self.state = AppState::Menu;
+                    self.full_refresh = true;
+                    self.dirty = true;
+                } else if buttons.is_pressed(input::Buttons::Confirm) {
+                    let target = self
+                        .epub_preview_trbk
+                        .as_ref()
+                        .and_then(|name| self.entries.iter().position(|entry| entry.name == *name));
+                    if let Some(index) = target {
+                        self.epub_preview = None;
+                        self.open_index(index);
+                    }
+                } else if buttons.is_pressed(input::Buttons::Right) {
+                    self.export_epub_text();
+                } else if let Some((_, text)) = &self.epub_preview {
+                    // Up/Down turn a screenful at a time; the view clamps the end.
+                    let step = Self::list_step(buttons);
+                    let rect = self.epub_preview_rect();
+                    let view = ScrollTextView::new(text);
+                    let page = view.visible_lines(rect.h);
+                    let max = view.max_scroll(rect);
+                    let scroll = if step < 0 {
+                        self.epub_preview_scroll.saturating_sub(page)
+                    } else if step > 0 {
+                        (self.epub_preview_scroll + page).min(max)
+                    } else {
+                        self.epub_preview_scroll
+                    };
+                    if scroll != self.epub_preview_scroll {
+                        self.epub_preview_scroll = scroll;
+                        self.dirty = true;
+                    } else if step == 0 {
+                        self.idle_ms = self.idle_ms.saturating_add(elapsed_ms);
+                        if self.idle_expired() {
+                            self.start_sleep_request();
+                        }
+                    }
+                }
+            }
             AppState::Error => {
                 if buttons.is_pressed(input::Buttons::Back)
                     || buttons.is_pressed(input::Buttons::Confirm)
@@ -442,6 +922,15 @@ impl<'a, S: ImageSource> Application<'a, S> {
                     self.state = AppState::StartMenu;
                     self.error_message = None;
                     self.dirty = true;
+                } else {
+                    let step = Self::list_step(buttons);
+                    if step < 0 && self.error_scroll > 0 {
+                        self.error_scroll -= 1;
+                        self.dirty = true;
+                    } else if step > 0 {
+                        self.error_scroll += 1;
+                        self.dirty = true;
+                    }
                 }
             }
         }
@@ -453,10 +942,15 @@ impl<'a, S: ImageSource> Application<'a, S> {
         }
 
         self.dirty = false;
+        if self.panel_powered_down {
+            display.power_up();
+            self.panel_powered_down = false;
+        }
         match self.state {
             AppState::StartMenu => self.draw_start_menu(display),
             AppState::Menu => self.draw_menu(display),
             AppState::Viewing => self.draw_image(display),
+            AppState::BookInfo => self.draw_book_info(display),
             AppState::BookViewing => {
                 if let Some(indicator) = self.page_turn_indicator.take() {
                     self.draw_page_turn_indicator(display, indicator);
@@ -494,27 +988,36 @@ impl<'a, S: ImageSource> Application<'a, S> {
                 self.dirty = true;
             }
             AppState::Toc => self.draw_toc(display),
+            AppState::GotoPage => self.draw_goto_page(display),
+            AppState::Scrub => self.draw_scrub(display),
+            AppState::Search => self.draw_search(display),
             AppState::SleepingPending => {
                 self.draw_sleeping_indicator(display);
                 if self.save_resume_checked() {
                     self.state = AppState::Sleeping;
                     self.sleep_transition = true;
                     self.sleep_overlay_pending = true;
-                    self.draw_sleep_overlay(display);
+                    self.draw_sleep_screen(display);
                     self.source.sleep();
                     self.sleep_overlay_pending = false;
                 }
             }
             AppState::Sleeping => {
                 if self.sleep_overlay_pending {
-                    self.draw_sleep_overlay(display);
+                    self.draw_sleep_screen(display);
                     self.source.sleep();
                     self.sleep_overlay_pending = false;
                 }
             }
+            AppState::Settings => self.draw_settings(display),
+            AppState::Confirm => self.draw_confirm(display),
+            AppState::EpubPreview => self.draw_epub_preview(display),
             AppState::Error => self.draw_error(display),
         }
         self.full_refresh = false;
+        if display.take_error() && self.state != AppState::Error {
+            self.set_error(ImageError::Message("Display error: panel not responding.".into()));
+        }
         if self.state == AppState::Error && self.sleep_after_error {
             self.sleep_after_error = false;
             self.state = AppState::Sleeping;
@@ -524,6 +1027,25 @@ impl<'a, S: ImageSource> Application<'a, S> {
         }
     }
 
+    /// Up/Down movement for list screens: one step per press, then
+    /// auto-repeat while held.
+    fn list_step(buttons: &input::ButtonState) -> i32 {
+        use input::Buttons::{Down, Up};
+        if buttons.is_pressed(Up) || buttons.is_repeat(Up) {
+            -1
+        } else if buttons.is_pressed(Down) || buttons.is_repeat(Down) {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Whether the device has sat idle long enough to sleep. A timeout of 0
+    /// means never.
+    fn idle_expired(&self) -> bool {
+        self.idle_timeout_ms != 0 && self.idle_ms >= self.idle_timeout_ms
+    }
+
     fn has_input(buttons: &input::ButtonState) -> bool {
         use input::Buttons::*;
         let list = [Back, Confirm, Left, Right, Up, Down, Power];
@@ -543,6 +1065,38 @@ impl<'a, S: ImageSource> Application<'a, S> {
         value
     }
 
+    /// Polls `monitor`; the new level shows up the next time a header or
+    /// footer is drawn.
+    pub fn update_battery(&mut self, monitor: &mut impl BatteryMonitor) {
+        self.battery_percent = monitor.battery_percent();
+    }
+
+    /// Pushes a time set from the settings screen to `time`, then reads it
+    /// back. Headers and footers pick the time up on their next redraw.
+    pub fn sync_clock(&mut self, time: &mut impl TimeSource) {
+        if self.clock_set_pending {
+            if let Some(secs) = self.clock_secs {
+                time.set_now_secs(secs);
+            }
+            self.clock_set_pending = false;
+        }
+        self.clock_secs = time.now_secs();
+    }
+
+    /// Reads the page after the current one into the source's cache while
+    /// the reader is idle, once per page, so the next turn skips the card.
+    fn prefetch_next_page(&mut self) {
+        let Some(book) = &self.current_book else {
+            return;
+        };
+        let next = self.current_page + 1;
+        if next >= book.page_count || self.prefetched_page == Some(next) {
+            return;
+        }
+        self.prefetched_page = Some(next);
+        self.source.prefetch_trbk_page(next);
+    }
+
     fn open_selected(&mut self) {
         if self.entries.is_empty() {
             self.error_message = Some("No entries found in /images.".into());
@@ -553,6 +1107,16 @@ impl<'a, S: ImageSource> Application<'a, S> {
         let Some(entry) = self.entries.get(self.selected).cloned() else {
             return;
         };
+        if self.in_recent_folder {
+            self.open_recent_path(&entry.name);
+            return;
+        }
+        if self.recent_folder_shown && self.selected == 0 {
+            self.in_recent_folder = true;
+            self.selected = 0;
+            self.refresh_entries();
+            return;
+        }
         match entry.kind {
             EntryKind::Dir => {
                 self.path.push(entry.name);
@@ -565,25 +1129,14 @@ impl<'a, S: ImageSource> Application<'a, S> {
             }
             EntryKind::File => {
                 if is_trbk(&entry.name) {
-                match self.source.open_trbk(&self.path, &entry) {
-                    Ok(info) => {
-                        let entry_name = self.entry_path_string(&entry);
-                        self.current_entry = Some(entry_name.clone());
-                        self.last_viewed_entry = Some(entry_name.clone());
-                        self.mark_recent(entry_name);
-                        log::info!("Opened book entry: {:?}", self.current_entry);
+                    // Show the book's details first; reading starts on Confirm.
+                    match self.source.open_trbk(&self.path, &entry) {
+                        Ok(info) => {
                             self.current_book = Some(info);
                             self.toc_labels = None;
-                            self.current_page = self
-                                .current_entry
-                                .as_ref()
-                                .and_then(|name| self.book_positions.get(name).copied())
-                                .unwrap_or(0);
-                            self.current_page_ops = self.source.trbk_page(self.current_page).ok();
-                            self.last_rendered_page = None;
-                            self.state = AppState::BookViewing;
-                            self.full_refresh = true;
-                            self.book_turns_since_full = 0;
+                            self.search_hits = None;
+                            self.prefetched_page = None;
+                            self.state = AppState::BookInfo;
                             self.dirty = true;
                         }
                         Err(err) => self.set_error(err),
@@ -617,6 +1170,25 @@ impl<'a, S: ImageSource> Application<'a, S> {
         }
     }
 
+    /// Commits to reading the book previewed on the info screen.
+    fn start_reading_selected(&mut self) {
+        let Some(entry) = self.entries.get(self.selected).cloned() else {
+            return;
+        };
+        let entry_name = self.entry_path_string(&entry);
+        self.current_entry = Some(entry_name.clone());
+        self.last_viewed_entry = Some(entry_name.clone());
+        self.mark_recent(entry_name);
+        log::info!("Opened book entry: {:?}", self.current_entry);
+        self.current_page = self.stored_book_page();
+        self.current_page_ops = self.source.trbk_page(self.current_page).ok();
+        self.last_rendered_page = None;
+        self.state = AppState::BookViewing;
+        self.full_refresh = true;
+        self.book_turns_since_full = 0;
+        self.dirty = true;
+    }
+
     fn open_index(&mut self, index: usize) {
         if self.entries.is_empty() {
             return;
@@ -638,11 +1210,10 @@ impl<'a, S: ImageSource> Application<'a, S> {
                     log::info!("Opened book entry: {:?}", self.current_entry);
                     self.current_book = Some(info);
                     self.toc_labels = None;
-                    self.current_page = self
-                        .current_entry
-                        .as_ref()
-                        .and_then(|name| self.book_positions.get(name).copied())
-                        .unwrap_or(0);
+                    self.search_hits = None;
+                    self.toc_target = None;
+                    self.prefetched_page = None;
+                    self.current_page = self.stored_book_page();
                     self.current_page_ops = self.source.trbk_page(self.current_page).ok();
                     self.last_rendered_page = None;
                     self.state = AppState::BookViewing;
@@ -655,9 +1226,31 @@ impl<'a, S: ImageSource> Application<'a, S> {
             return;
         }
         if is_epub(&entry.name) {
-            self.set_error(ImageError::Message(
-                "EPUB files must be converted to .trbk.".into(),
-            ));
+            // Full previews where the source can read EPUBs, otherwise the
+            // details a converter left in a sidecar.
+            let preview = match self.source.epub_preview_text(&self.path, &entry) {
+                Err(ImageError::Unsupported) => self
+                    .source
+                    .epub_info(&self.path, &entry)
+                    .map(|info| self.describe_epub_info(&info)),
+                preview => preview.map(|text| (text, None)),
+            };
+            match preview {
+                Ok((text, trbk)) => {
+                    self.selected = index;
+                    self.epub_preview = Some((entry.name.clone(), text));
+                    self.epub_preview_scroll = 0;
+                    self.epub_preview_trbk = trbk;
+                    self.epub_preview_status = None;
+                    self.state = AppState::EpubPreview;
+                    self.full_refresh = true;
+                    self.dirty = true;
+                }
+                Err(ImageError::Unsupported) => self.set_error(ImageError::Message(
+                    "EPUB files must be converted to .trbk.".into(),
+                )),
+                Err(err) => self.set_error(err),
+            }
             return;
         }
         match self.source.load(&self.path, &entry) {
@@ -680,41 +1273,566 @@ impl<'a, S: ImageSource> Application<'a, S> {
         }
     }
 
-    fn refresh_entries(&mut self) {
-        match self.source.refresh(&self.path) {
-            Ok(entries) => {
-                self.entries = entries;
-                self.current_image = None;
-                self.current_book = None;
-                self.current_page_ops = None;
-                self.current_page = 0;
-                self.toc_labels = None;
-                if self.selected >= self.entries.len() {
-                    self.selected = 0;
-                }
-                if self.state != AppState::StartMenu {
-                    self.state = AppState::Menu;
-                }
-                self.error_message = None;
-                self.dirty = true;
+    /// Advances the slideshow once the interval has passed. Manual navigation
+    /// pauses it until the buttons have been left alone for a while.
+    fn tick_slideshow(&mut self, elapsed_ms: u32) {
+        if self.slideshow_paused {
+            if self.idle_ms < SLIDESHOW_RESUME_MS {
+                return;
             }
-            Err(err) => self.set_error(err),
+            self.slideshow_paused = false;
+            self.slideshow_ms = 0;
+        }
+        self.slideshow_ms = self.slideshow_ms.saturating_add(elapsed_ms);
+        if self.slideshow_ms < self.slideshow_interval_ms {
+            return;
+        }
+        self.slideshow_ms = 0;
+        if let Some(next) = self.next_image_index().filter(|&next| next != self.selected) {
+            self.open_index(next);
         }
     }
 
-    fn set_error(&mut self, err: ImageError) {
-        let message = match err {
-            ImageError::Io => "I/O error while accessing /images.".into(),
-            ImageError::Decode => "Failed to decode image.".into(),
-            ImageError::Unsupported => "Unsupported image format.".into(),
-            ImageError::Message(message) => message,
-        };
-        self.error_message = Some(message);
-        self.state = AppState::Error;
-        self.dirty = true;
+    /// Next image after the current one, wrapping at the end of the folder
+    /// and skipping folders and books.
+    fn next_image_index(&self) -> Option<usize> {
+        let len = self.entries.len();
+        (1..=len)
+            .map(|step| (self.selected + step) % len)
+            .find(|&index| {
+                let entry = &self.entries[index];
+                entry.kind == EntryKind::File && !is_trbk(&entry.name) && !is_epub(&entry.name)
+            })
     }
 
-    fn draw_start_menu(&mut self, display: &mut impl crate::display::Display) {
+    /// Turns one page, stopping at either end. Turning back from a page
+    /// opened scrolled to a TOC entry first shows that page from the top.
+    fn turn_book_page(&mut self, forward: bool) {
+        if forward {
+            let page_count = self.current_book.as_ref().map_or(0, |book| book.page_count);
+            if self.current_page + 1 < page_count {
+                self.current_page += 1;
+                self.toc_target = None;
+                self.current_page_ops = None;
+                self.book_turns_since_full = self.book_turns_since_full.saturating_add(1);
+                self.page_turn_indicator = Some(PageTurnIndicator::Forward);
+                self.dirty = true;
+            }
+        } else if self
+            .toc_target
+            .take()
+            .is_some_and(|(page, _)| page == self.current_page)
+        {
+            // Scroll back to the top of the page before leaving it.
+            self.dirty = true;
+        } else if self.current_page > 0 {
+            self.current_page -= 1;
+            self.current_page_ops = None;
+            self.book_turns_since_full = self.book_turns_since_full.saturating_add(1);
+            self.page_turn_indicator = Some(PageTurnIndicator::Backward);
+            self.dirty = true;
+        }
+    }
+
+    /// Moves to the start of the next or previous TOC entry; stops at either end.
+    fn jump_chapter(&mut self, forward: bool) {
+        let Some(book) = &self.current_book else {
+            return;
+        };
+        if book.toc.is_empty() {
+            return;
+        }
+        let target = if forward {
+            book.toc
+                .iter()
+                .map(|entry| entry.page_index as usize)
+                .find(|page| *page > self.current_page)
+        } else {
+            let current = find_toc_selection(book, self.current_page);
+            let start = book.toc[current].page_index as usize;
+            if start > self.current_page {
+                None
+            } else {
+                book.toc[..current]
+                    .iter()
+                    .rev()
+                    .map(|entry| entry.page_index as usize)
+                    .find(|page| *page < start)
+            }
+        };
+        let Some(page) = target.filter(|page| *page < book.page_count) else {
+            return;
+        };
+        self.current_page = page;
+        self.toc_target = None;
+        self.current_page_ops = self.source.trbk_page(self.current_page).ok();
+        self.last_rendered_page = None;
+        self.full_refresh = true;
+        self.book_turns_since_full = 0;
+        self.dirty = true;
+    }
+
+    /// Saves the previewed EPUB as plain text beside it.
+    fn export_epub_text(&mut self) {
+        let Some(entry) = self.entries.get(self.selected).cloned() else {
+            return;
+        };
+        let status = match self.source.export_epub_text(&self.path, &entry) {
+            Ok(name) => format!("Saved {name}"),
+            Err(ImageError::Unsupported) => "Text export isn't available here".into(),
+            Err(err) => return self.set_error(err),
+        };
+        self.epub_preview_status = Some(status);
+        self.dirty = true;
+    }
+
+    fn open_toc(&mut self) {
+        if let Some(book) = &self.current_book {
+            if !book.toc.is_empty() {
+                self.toc_selected = find_toc_selection(book, self.current_page);
+                self.toc_labels = None;
+                self.state = AppState::Toc;
+                self.dirty = true;
+            }
+        }
+    }
+
+    fn enter_goto_page(&mut self) {
+        let Some(book) = &self.current_book else {
+            return;
+        };
+        if book.page_count < 2 {
+            return;
+        }
+        let width = book.page_count.to_string().len();
+        let current = format!("{:0width$}", self.current_page + 1, width = width);
+        self.goto_digits = current.bytes().map(|b| b - b'0').collect();
+        self.goto_cursor = width - 1;
+        self.state = AppState::GotoPage;
+        self.dirty = true;
+    }
+
+    fn enter_scrub(&mut self) {
+        let Some(book) = &self.current_book else {
+            return;
+        };
+        if book.page_count < 2 {
+            return;
+        }
+        let last = book.page_count - 1;
+        self.scrub_percent = ((self.current_page.min(last) * 100 + last / 2) / last) as u8;
+        self.state = AppState::Scrub;
+        self.dirty = true;
+    }
+
+    /// Page the scrubber points at: 0% is the first page, 100% the last.
+    fn scrub_target_page(&self) -> usize {
+        let last = self
+            .current_book
+            .as_ref()
+            .map(|book| book.page_count.saturating_sub(1))
+            .unwrap_or(0);
+        (self.scrub_percent as usize * last + 50) / 100
+    }
+
+    /// Leaves a prompt for `page` of the open book with a full refresh.
+    fn jump_to_page(&mut self, page: usize) {
+        self.current_page = page;
+        self.toc_target = None;
+        self.current_page_ops = self.source.trbk_page(self.current_page).ok();
+        self.last_rendered_page = None;
+        self.state = AppState::BookViewing;
+        self.full_refresh = true;
+        self.book_turns_since_full = 0;
+        self.dirty = true;
+    }
+
+    fn enter_search(&mut self) {
+        if self.current_book.is_none() {
+            return;
+        }
+        // Keep the previous query so repeated searches step through its hits.
+        if self.search_chars.is_empty() {
+            self.search_chars.push(0);
+            self.search_cursor = 0;
+        }
+        self.search_scan = None;
+        self.state = AppState::Search;
+        self.dirty = true;
+    }
+
+    fn search_query(&self) -> String {
+        let query: String = self
+            .search_chars
+            .iter()
+            .map(|&ch| SEARCH_CHARSET[ch as usize] as char)
+            .collect();
+        query.trim().to_string()
+    }
+
+    /// Jumps to the next hit of the prompt's query when it was already run,
+    /// otherwise starts a scan of the book that `continue_search` works
+    /// through.
+    fn run_search(&mut self) {
+        let query = self.search_query();
+        if query.is_empty() {
+            return;
+        }
+        let cached = self
+            .search_hits
+            .as_ref()
+            .is_some_and(|(previous, _)| *previous == query);
+        if cached {
+            self.jump_to_search_hit(true);
+            return;
+        }
+        self.search_hits = None;
+        self.search_scan = Some(SearchScan {
+            query,
+            next_page: 0,
+            hits: Vec::new(),
+        });
+        self.dirty = true;
+    }
+
+    /// Reads the next few pages of the running search. Once the whole book is
+    /// read the hits are kept for the query and the first one is shown.
+    fn continue_search(&mut self) {
+        let page_count = self.current_book.as_ref().map_or(0, |book| book.page_count);
+        let Some(scan) = &mut self.search_scan else {
+            return;
+        };
+        let start = scan.next_page;
+        let end = (start + SEARCH_PAGES_PER_TICK).min(page_count);
+        let hits = self.source.trbk_search(&scan.query, start..end);
+        scan.hits.extend(hits);
+        scan.next_page = end;
+        if end < page_count {
+            // Redraw only when the shown percentage moves on by ten.
+            if start * 10 / page_count != end * 10 / page_count {
+                self.dirty = true;
+            }
+            return;
+        }
+        if let Some(scan) = self.search_scan.take() {
+            self.search_hits = Some((scan.query, scan.hits));
+        }
+        self.jump_to_search_hit(false);
+    }
+
+    /// Moves to the first hit after the current page, wrapping to the start.
+    /// A fresh search may land on the current page; repeating one moves on.
+    /// Stays on the prompt when nothing matches.
+    fn jump_to_search_hit(&mut self, cached: bool) {
+        let Some((_, pages)) = &self.search_hits else {
+            return;
+        };
+        let next = pages
+            .iter()
+            .copied()
+            .find(|&page| page > self.current_page || (!cached && page == self.current_page))
+            .or_else(|| pages.first().copied());
+        let Some(page) = next else {
+            self.dirty = true;
+            return;
+        };
+        self.current_page = page;
+        self.toc_target = None;
+        self.current_page_ops = self.source.trbk_page(self.current_page).ok();
+        self.last_rendered_page = None;
+        self.state = AppState::BookViewing;
+        self.full_refresh = true;
+        self.book_turns_since_full = 0;
+        self.dirty = true;
+    }
+
+    /// Reopens the current book from the next `<stem>-<size>.trbk` sibling,
+    /// keeping the reading position proportional to the page count.
+    fn cycle_book_size(&mut self) {
+        let Some(current) = self.current_entry.clone() else {
+            return;
+        };
+        let Some(old_count) = self.current_book.as_ref().map(|book| book.page_count) else {
+            return;
+        };
+        let file = basename_from_path(&current);
+        let Some((stem, _)) = split_size_suffix(&file) else {
+            return;
+        };
+        let mut variants: Vec<(u16, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.kind == EntryKind::File)
+            .filter_map(|(index, entry)| {
+                let (other, size) = split_size_suffix(&entry.name)?;
+                other.eq_ignore_ascii_case(stem).then_some((size, index))
+            })
+            .collect();
+        if variants.len() < 2 {
+            log::info!("No other font sizes available for {}", current);
+            return;
+        }
+        variants.sort();
+        let Some(position) = variants
+            .iter()
+            .position(|(_, index)| self.entries[*index].name == file)
+        else {
+            return;
+        };
+        let (size, next_index) = variants[(position + 1) % variants.len()];
+        let old_page = self.current_page;
+        self.update_book_position();
+        self.source.close_trbk();
+        self.open_index(next_index);
+        let Some(new_count) = self.current_book.as_ref().map(|book| book.page_count) else {
+            return;
+        };
+        self.selected = next_index;
+        let mapped = if old_count > 1 && new_count > 1 {
+            (old_page * (new_count - 1) + (old_count - 1) / 2) / (old_count - 1)
+        } else {
+            0
+        };
+        self.current_page = mapped.min(new_count.saturating_sub(1));
+        self.toc_target = None;
+        self.current_page_ops = self.source.trbk_page(self.current_page).ok();
+        log::info!("Switched to font size {} at page {}", size, self.current_page);
+    }
+
+    fn refresh_entries(&mut self) {
+        let listing = if self.in_recent_folder {
+            Ok(self
+                .recent_entries
+                .iter()
+                .map(|path| ImageEntry {
+                    name: path.clone(),
+                    kind: EntryKind::File,
+                    modified: None,
+                })
+                .collect())
+        } else {
+            self.source.refresh(&self.path)
+        };
+        match listing {
+            Ok(entries) => {
+                self.entries = entries;
+                self.recent_folder_shown = false;
+                self.sort_entries();
+                if self.path.is_empty() && !self.in_recent_folder && !self.recent_entries.is_empty()
+                {
+                    self.entries.insert(
+                        0,
+                        ImageEntry {
+                            name: RECENT_FOLDER.to_string(),
+                            kind: EntryKind::Dir,
+                            modified: None,
+                        },
+                    );
+                    self.recent_folder_shown = true;
+                }
+                self.menu_thumbnail = None;
+                self.current_image = None;
+                self.current_book = None;
+                self.current_page_ops = None;
+                self.current_page = 0;
+                self.toc_labels = None;
+                self.search_hits = None;
+                self.prefetched_page = None;
+                if self.selected >= self.entries.len() {
+                    self.selected = 0;
+                }
+                if self.state != AppState::StartMenu {
+                    self.state = AppState::Menu;
+                }
+                self.error_message = None;
+                self.dirty = true;
+            }
+            Err(err) => self.set_error(err),
+        }
+    }
+
+    /// Asks before deleting the selected file; folders are refused.
+    fn request_delete_selected(&mut self) {
+        let Some(entry) = self.entries.get(self.selected) else {
+            return;
+        };
+        if entry.kind != EntryKind::File {
+            self.set_error(ImageError::Message("Folders can't be deleted.".into()));
+            return;
+        }
+        if self.in_recent_folder {
+            self.set_error(ImageError::Message(
+                "Open the file's folder to delete it.".into(),
+            ));
+            return;
+        }
+        self.pending_confirm = Some(PendingConfirm {
+            message: format!("Delete {}?", entry.name),
+            choice: ConfirmChoice::No,
+            action: ConfirmAction::DeleteEntry(self.selected),
+        });
+        self.state = AppState::Confirm;
+        self.dirty = true;
+    }
+
+    fn resolve_confirm(&mut self, accepted: bool) {
+        self.state = AppState::Menu;
+        self.dirty = true;
+        let Some(pending) = self.pending_confirm.take() else {
+            return;
+        };
+        if !accepted {
+            return;
+        }
+        match pending.action {
+            ConfirmAction::DeleteEntry(index) => {
+                let Some(entry) = self.entries.get(index).cloned() else {
+                    return;
+                };
+                match self.source.delete(&self.path, &entry) {
+                    Ok(()) => {
+                        let deleted = self.entry_path_string(&entry);
+                        if self.recent_entries.contains(&deleted) {
+                            self.recent_entries.retain(|path| path != &deleted);
+                            self.recent_dirty = true;
+                        }
+                        self.refresh_entries();
+                    }
+                    Err(err) => self.set_error(err),
+                }
+            }
+        }
+    }
+
+    fn current_settings(&self) -> Settings {
+        Settings {
+            idle_timeout_ms: self.idle_timeout_ms,
+            sort_newest: self.sort_mode == SortMode::Newest,
+            slideshow_interval_ms: self.slideshow_interval_ms,
+            full_refresh_every: self.book_full_refresh_every,
+        }
+    }
+
+    fn save_settings_now(&mut self) {
+        if !self.settings_dirty {
+            return;
+        }
+        let settings = self.current_settings();
+        self.source.save_settings(&settings);
+        self.settings_dirty = false;
+    }
+
+    /// Steps the selected settings row to its next or previous value.
+    fn adjust_setting(&mut self, forward: bool) {
+        match self.settings_selected {
+            0 => {
+                self.idle_timeout_ms =
+                    cycle_option(&IDLE_TIMEOUT_OPTIONS_MS, self.idle_timeout_ms, forward);
+            }
+            1 => {
+                self.sort_mode = match self.sort_mode {
+                    SortMode::Name => SortMode::Newest,
+                    SortMode::Newest => SortMode::Name,
+                };
+            }
+            2 => {
+                self.slideshow_interval_ms =
+                    cycle_option(&SLIDESHOW_OPTIONS_MS, self.slideshow_interval_ms, forward);
+            }
+            3 => {
+                self.book_full_refresh_every =
+                    cycle_option(&FULL_REFRESH_OPTIONS, self.book_full_refresh_every, forward);
+            }
+            row => {
+                // The clock lives in the time source, not the settings file.
+                let step = if row == 4 { 3_600 } else { 60 };
+                let secs = self.clock_secs.unwrap_or(CLOCK_UNSET_DEFAULT_SECS);
+                let delta = if forward { step } else { -step };
+                self.clock_secs = Some(shift_time_of_day(secs - secs % 60, delta));
+                self.clock_set_pending = true;
+                self.dirty = true;
+                return;
+            }
+        }
+        self.settings_dirty = true;
+        self.dirty = true;
+    }
+
+    fn settings_labels(&self) -> [String; SETTINGS_ROWS] {
+        let full_refresh = if self.book_full_refresh_every == 1 {
+            "every page".to_string()
+        } else {
+            format!("every {} pages", self.book_full_refresh_every)
+        };
+        let clock = self.clock_secs.map(clock_label);
+        let clock = clock.as_deref().unwrap_or("--:--");
+        let sleep = if self.idle_timeout_ms == 0 {
+            "never".to_string()
+        } else {
+            duration_label(self.idle_timeout_ms)
+        };
+        [
+            format!("Sleep after: {}", sleep),
+            format!("Sort files: {}", self.sort_mode.label()),
+            format!("Slideshow: every {}", duration_label(self.slideshow_interval_ms)),
+            format!("Full refresh: {}", full_refresh),
+            format!("Clock hour: {}", clock),
+            format!("Clock minute: {}", clock),
+        ]
+    }
+
+    fn cycle_sort_mode(&mut self) {
+        let next = match self.sort_mode {
+            SortMode::Name => SortMode::Newest,
+            SortMode::Newest => SortMode::Name,
+        };
+        if self.in_recent_folder
+            || next == SortMode::Newest && self.entries.iter().all(|entry| entry.modified.is_none())
+        {
+            return;
+        }
+        let selected_name = self.entries.get(self.selected).map(|entry| entry.name.clone());
+        self.sort_mode = next;
+        self.settings_dirty = true;
+        self.sort_entries();
+        self.selected = selected_name
+            .and_then(|name| self.entries.iter().position(|entry| entry.name == name))
+            .unwrap_or(0);
+        self.dirty = true;
+    }
+
+    /// Folders stay first in either mode; sources already return name order.
+    /// The Recent folder keeps most-recent-first order and stays on top.
+    fn sort_entries(&mut self) {
+        if self.in_recent_folder {
+            return;
+        }
+        let start = usize::from(self.recent_folder_shown);
+        self.entries[start..].sort_by(|a, b| match (a.kind, b.kind) {
+            (EntryKind::Dir, EntryKind::File) => core::cmp::Ordering::Less,
+            (EntryKind::File, EntryKind::Dir) => core::cmp::Ordering::Greater,
+            _ => match self.sort_mode {
+                SortMode::Name => a.name.cmp(&b.name),
+                SortMode::Newest => b.modified.cmp(&a.modified).then_with(|| a.name.cmp(&b.name)),
+            },
+        });
+    }
+
+    fn set_error(&mut self, err: ImageError) {
+        let message = match err {
+            ImageError::Io => "I/O error while accessing /images.".into(),
+            ImageError::Decode => "Failed to decode image.".into(),
+            ImageError::Unsupported => "Unsupported image format.".into(),
+            ImageError::Message(message) => message,
+        };
+        self.error_message = Some(message);
+        self.error_scroll = 0;
+        self.state = AppState::Error;
+        self.dirty = true;
+    }
+
+    fn draw_start_menu(&mut self, display: &mut impl crate::display::Display) {
         self.display_buffers.clear(BinaryColor::On).ok();
         let size = self.display_buffers.size();
         let width = size.width as i32;
@@ -725,6 +1843,13 @@ impl<'a, S: ImageSource> Application<'a, S> {
         Text::new("Recents", Point::new(START_MENU_MARGIN, HEADER_Y), header_style)
             .draw(self.display_buffers)
             .ok();
+        Self::draw_status(
+            self.display_buffers,
+            width - START_MENU_MARGIN,
+            HEADER_Y,
+            self.clock_secs,
+            self.battery_percent,
+        );
 
         let recents = self.collect_recent_paths();
         self.ensure_start_menu_cache(&recents);
@@ -931,6 +2056,159 @@ impl<'a, S: ImageSource> Application<'a, S> {
         );
     }
 
+    fn draw_goto_page(&mut self, display: &mut impl crate::display::Display) {
+        let total = self.current_book.as_ref().map(|book| book.page_count).unwrap_or(0);
+        let mut number = String::with_capacity(self.goto_digits.len());
+        for digit in &self.goto_digits {
+            number.push((b'0' + *digit) as char);
+        }
+        number.push_str(&format!(" / {}", total));
+        self.draw_entry_prompt(display, "Go to page", &number, self.goto_cursor, None);
+    }
+
+    fn draw_search(&mut self, display: &mut impl crate::display::Display) {
+        let entry: String = self
+            .search_chars
+            .iter()
+            .map(|&ch| SEARCH_CHARSET[ch as usize] as char)
+            .collect();
+        let query = self.search_query();
+        let note = match (&self.search_scan, &self.search_hits) {
+            (Some(scan), _) => {
+                let total = self.current_book.as_ref().map_or(0, |book| book.page_count);
+                let percent = scan.next_page * 100 / total.max(1);
+                format!("Searching {}%  Back: cancel", percent)
+            }
+            (None, Some((previous, pages))) if *previous == query => {
+                if pages.is_empty() {
+                    "No matches".to_string()
+                } else if let Some(index) = pages.iter().position(|&page| page == self.current_page) {
+                    format!("Match {} of {}", index + 1, pages.len())
+                } else {
+                    format!("{} pages match", pages.len())
+                }
+            }
+            _ => "Confirm to search".to_string(),
+        };
+        self.draw_entry_prompt(display, "Search", &entry, self.search_cursor, Some(&note));
+    }
+
+    /// Centred prompt box over the current page: a title, an entry line with
+    /// an underline under char `cursor`, and an optional note below.
+    fn draw_entry_prompt(
+        &mut self,
+        display: &mut impl crate::display::Display,
+        title: &str,
+        entry: &str,
+        cursor: usize,
+        note: Option<&str>,
+    ) {
+        let entry_w = entry.len() as i32 * 10;
+        let note_w = note.map(|note| note.len() as i32 * 10).unwrap_or(0);
+        let padding = PROMPT_PADDING;
+        let note_h = if note.is_some() { 20 + 12 } else { 0 };
+        let Rect {
+            x,
+            y,
+            w: rect_w,
+            h: rect_h,
+        } = self.draw_prompt_box(
+            entry_w.max(title.len() as i32 * 10).max(note_w),
+            20 * 2 + 12 + note_h,
+        );
+
+        let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::Off);
+        Text::new(title, Point::new(x + padding, y + padding + 16), style)
+            .draw(self.display_buffers)
+            .ok();
+        let entry_x = x + (rect_w - entry_w) / 2;
+        let entry_y = y + padding + 20 + 12 + 16;
+        Text::new(entry, Point::new(entry_x, entry_y), style)
+            .draw(self.display_buffers)
+            .ok();
+        Rectangle::new(
+            Point::new(entry_x + cursor as i32 * 10, entry_y + 4),
+            Size::new(10, 2),
+        )
+        .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_fill(
+            BinaryColor::Off,
+        ))
+        .draw(self.display_buffers)
+        .ok();
+        if let Some(note) = note {
+            Text::new(note, Point::new(x + padding, entry_y + 20 + 12), style)
+                .draw(self.display_buffers)
+                .ok();
+        }
+
+        let mut rq = RenderQueue::default();
+        rq.push(Rect::new(x, y, rect_w, rect_h), RefreshMode::Fast);
+        flush_queue(display, self.display_buffers, &mut rq, RefreshMode::Fast);
+    }
+
+    /// Percentage, target page and a bar showing where it falls in the book.
+    fn draw_scrub(&mut self, display: &mut impl crate::display::Display) {
+        let total = self.current_book.as_ref().map(|book| book.page_count).unwrap_or(0);
+        let title = format!("Go to {}%", self.scrub_percent);
+        let entry = format!("page {} / {}", self.scrub_target_page() + 1, total);
+        let padding = PROMPT_PADDING;
+        let bar_h = FOOTER_BAR_HEIGHT * 2;
+        let content_w = (title.len().max(entry.len()) as i32 * 10).max(240);
+        let rect = self.draw_prompt_box(content_w, 20 * 2 + 12 * 2 + bar_h);
+
+        let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::Off);
+        Text::new(&title, Point::new(rect.x + padding, rect.y + padding + 16), style)
+            .draw(self.display_buffers)
+            .ok();
+        let entry_y = rect.y + padding + 20 + 12 + 16;
+        Text::new(&entry, Point::new(rect.x + padding, entry_y), style)
+            .draw(self.display_buffers)
+            .ok();
+        let mut bar = ProgressBar::from_steps(self.scrub_percent as usize, 100);
+        let mut rq = RenderQueue::default();
+        let mut ctx = UiContext {
+            buffers: self.display_buffers,
+        };
+        bar.render(
+            &mut ctx,
+            Rect::new(rect.x + padding, entry_y + 4 + 12, rect.w - padding * 2, bar_h),
+            &mut rq,
+        );
+        rq.push(rect, RefreshMode::Fast);
+        flush_queue(display, self.display_buffers, &mut rq, RefreshMode::Fast);
+    }
+
+    /// Copies the frame on screen into the active buffer and draws an empty
+    /// outlined box around `content_w`x`content_h` plus padding, centred.
+    /// Returns the box.
+    fn draw_prompt_box(&mut self, content_w: i32, content_h: i32) -> Rect {
+        let size = self.display_buffers.size();
+        // Draw over the page that is already on screen.
+        let inactive = *self.display_buffers.get_inactive_buffer();
+        self.display_buffers
+            .get_active_buffer_mut()
+            .copy_from_slice(&inactive);
+
+        let rect_w = (content_w + PROMPT_PADDING * 2).min(size.width as i32);
+        let rect_h = content_h + PROMPT_PADDING * 2;
+        let x = ((size.width as i32 - rect_w) / 2).max(0);
+        let y = ((size.height as i32 - rect_h) / 2).max(0);
+        Rectangle::new(Point::new(x, y), Size::new(rect_w as u32, rect_h as u32))
+            .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_fill(
+                BinaryColor::On,
+            ))
+            .draw(self.display_buffers)
+            .ok();
+        Rectangle::new(Point::new(x, y), Size::new(rect_w as u32, rect_h as u32))
+            .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_stroke(
+                BinaryColor::Off,
+                2,
+            ))
+            .draw(self.display_buffers)
+            .ok();
+        Rect::new(x, y, rect_w, rect_h)
+    }
+
     fn draw_exiting_overlay(&mut self, display: &mut impl crate::display::Display) {
         let size = self.display_buffers.size();
         let width = size.width as i32;
@@ -1011,9 +2289,10 @@ impl<'a, S: ImageSource> Application<'a, S> {
             .collect();
 
         let title = self.menu_title();
+        let footer = format!("Confirm: open  Back: up  Right: {}", self.sort_mode.label());
         let mut list = ListView::new(&items);
         list.title = Some(title.as_str());
-        list.footer = Some("Up/Down: select  Confirm: open  Back: up");
+        list.footer = Some(footer.as_str());
         list.empty_label = Some("No entries found in /images");
         list.selected = self.selected;
         list.margin_x = LIST_MARGIN_X;
@@ -1021,20 +2300,277 @@ impl<'a, S: ImageSource> Application<'a, S> {
         list.list_top = LIST_TOP;
         list.line_height = LINE_HEIGHT;
 
+        self.update_menu_thumbnail();
+        let thumbnail = self
+            .menu_thumbnail
+            .as_ref()
+            .and_then(|(_, thumb)| thumb.as_ref());
+        let size = self.display_buffers.size();
+        let panel_w = if thumbnail.is_some() {
+            MENU_THUMB_PANEL_W
+        } else {
+            0
+        };
+        let rect = Rect::new(0, 0, size.width as i32 - panel_w, size.height as i32);
+        let mut rq = RenderQueue::default();
+        let mut ctx = UiContext {
+            buffers: self.display_buffers,
+        };
+        list.render(&mut ctx, rect, &mut rq);
+        let right = size.width as i32 - LIST_MARGIN_X;
+        let left = Self::draw_status(
+            self.display_buffers,
+            right,
+            HEADER_Y,
+            self.clock_secs,
+            self.battery_percent,
+        );
+        if left < right {
+            rq.push(Rect::new(left, HEADER_Y - 18, right - left, 22), RefreshMode::Fast);
+        }
+        if let Some(thumb) = thumbnail {
+            // Sits over the list column so long names don't run under the preview.
+            let panel = Rect::new(
+                rect.w,
+                LIST_TOP - 18,
+                panel_w,
+                size.height as i32 - LIST_TOP - 22,
+            );
+            Self::draw_thumbnail_panel(self.display_buffers, thumb, panel);
+            rq.push(panel, RefreshMode::Fast);
+        }
+
+        let fallback = if self.full_refresh {
+            RefreshMode::Full
+        } else {
+            RefreshMode::Fast
+        };
+        flush_queue(display, self.display_buffers, &mut rq, fallback);
+    }
+
+    /// Points `menu_thumbnail` at the selected image, asking the source for a
+    /// preview only when the selection has moved to a new entry.
+    fn update_menu_thumbnail(&mut self) {
+        let Some(entry) = self.entries.get(self.selected).cloned() else {
+            self.menu_thumbnail = None;
+            return;
+        };
+        if entry.kind != EntryKind::File || is_trbk(&entry.name) || is_epub(&entry.name) {
+            self.menu_thumbnail = None;
+            return;
+        }
+        let key = self.entry_path_string(&entry);
+        if self.menu_thumbnail.as_ref().map(|(cached, _)| cached) != Some(&key) {
+            let thumb = self.source.thumbnail(&self.path, &entry);
+            self.menu_thumbnail = Some((key, thumb));
+        }
+    }
+
+    /// Clears `panel` and draws the thumbnail centered along its top with a
+    /// one pixel frame.
+    fn draw_thumbnail_panel(buffers: &mut DisplayBuffers, thumb: &ImageData, panel: Rect) {
+        let (width, height) = match thumb {
+            ImageData::Mono1 { width, height, .. } => (*width as i32, *height as i32),
+            ImageData::Gray8 { width, height, .. } => (*width as i32, *height as i32),
+            ImageData::Gray2 { width, height, .. } => (*width as i32, *height as i32),
+        };
+        embedded_graphics::primitives::Rectangle::new(
+            Point::new(panel.x, panel.y),
+            embedded_graphics::geometry::Size::new(panel.w as u32, panel.h as u32),
+        )
+        .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_fill(
+            BinaryColor::On,
+        ))
+        .draw(buffers)
+        .ok();
+        let x = panel.x + (panel.w - width) / 2;
+        let y = panel.y + 8;
+        embedded_graphics::primitives::Rectangle::new(
+            Point::new(x - 1, y - 1),
+            embedded_graphics::geometry::Size::new(width as u32 + 2, height as u32 + 2),
+        )
+        .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_stroke(
+            BinaryColor::Off,
+            1,
+        ))
+        .draw(buffers)
+        .ok();
+        Self::draw_trbk_image(buffers, thumb, x, y, width, height);
+    }
+
+    fn draw_book_info(&mut self, display: &mut impl crate::display::Display) {
+        self.display_buffers.clear(BinaryColor::On).ok();
+        let Some(book) = &self.current_book else {
+            self.set_error(ImageError::Decode);
+            return;
+        };
+        let size = self.display_buffers.size();
+        let max_chars = ((size.width as i32 - LIST_MARGIN_X * 2) / 10).max(1) as usize;
+        let fit = |text: &str| -> String {
+            if text.chars().count() <= max_chars {
+                return text.to_string();
+            }
+            let mut out: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+            out.push_str("...");
+            out
+        };
+        let title = if book.metadata.title.is_empty() {
+            self.entries
+                .get(self.selected)
+                .map(|entry| entry.name.clone())
+                .unwrap_or_default()
+        } else {
+            book.metadata.title.clone()
+        };
+        let mut lines: Vec<String> = Vec::new();
+        lines.push(fit(&title));
+        if !book.metadata.author.is_empty() {
+            lines.push(fit(&format!("by {}", book.metadata.author)));
+        }
+        lines.push(String::new());
+        if !book.metadata.language.is_empty() {
+            lines.push(fit(&format!("Language: {}", book.metadata.language)));
+        }
+        lines.push(format!("Pages: {}", book.page_count));
+        if let Some(page) = self
+            .entries
+            .get(self.selected)
+            .map(|entry| self.entry_path_string(entry))
+            .and_then(|name| self.book_positions.get(&name).copied())
+        {
+            lines.push(format!("Last read: page {}", page + 1));
+        }
+
+        let rect = Rect::new(0, 0, size.width as i32, size.height as i32);
+        let mut rq = RenderQueue::default();
+        let mut ctx = UiContext {
+            buffers: self.display_buffers,
+        };
+        for (idx, line) in lines.iter().enumerate() {
+            let mut view = TextView::new(line.as_str());
+            view.offset_x = LIST_MARGIN_X;
+            view.offset_y = HEADER_Y + idx as i32 * LINE_HEIGHT;
+            view.render(&mut ctx, rect, &mut rq);
+        }
+        let mut prompt = TextView::new("Confirm: read  Back: cancel");
+        prompt.offset_x = LIST_MARGIN_X;
+        prompt.offset_y = rect.h - 16;
+        prompt.render(&mut ctx, rect, &mut rq);
+        let refresh = if self.full_refresh {
+            RefreshMode::Full
+        } else {
+            RefreshMode::Fast
+        };
+        flush_queue(display, self.display_buffers, &mut rq, refresh);
+    }
+
+    /// Draws the pending prompt over the frame that is already on screen.
+    fn draw_confirm(&mut self, display: &mut impl crate::display::Display) {
+        let Some(pending) = &self.pending_confirm else {
+            return;
+        };
+        self.display_buffers.restore_active();
+        let size = self.display_buffers.size();
+        let mut dialog = ConfirmDialog::new(&pending.message);
+        dialog.selected = pending.choice;
+        let mut rq = RenderQueue::default();
+        let mut ctx = UiContext {
+            buffers: self.display_buffers,
+        };
+        dialog.render(
+            &mut ctx,
+            Rect::new(0, 0, size.width as i32, size.height as i32),
+            &mut rq,
+        );
+        flush_queue(display, self.display_buffers, &mut rq, RefreshMode::Fast);
+    }
+
+    /// Info screen text for a sidecar, plus the first converted copy found in
+    /// the current folder.
+    fn describe_epub_info(&self, info: &EpubInfo) -> (String, Option<String>) {
+        let mut text = String::new();
+        if !info.title.is_empty() {
+            text.push_str(&info.title);
+            text.push('\n');
+        }
+        if !info.author.is_empty() {
+            text.push_str(&format!("by {}\n", info.author));
+        }
+        text.push('\n');
+        if !info.language.is_empty() {
+            text.push_str(&format!("Language: {}\n", info.language));
+        }
+        text.push_str(&format!("{} chapters, {} words\n\n", info.chapters, info.words));
+        let mut trbk = None;
+        if info.books.is_empty() {
+            text.push_str("No converted copy listed. Convert it with trusty-book.");
+        } else {
+            text.push_str("Converted copies:\n");
+            for (name, pages) in &info.books {
+                let here = self.entries.iter().any(|entry| entry.name == *name);
+                if here && trbk.is_none() {
+                    trbk = Some(name.clone());
+                }
+                let note = if here { "" } else { ", not in this folder" };
+                text.push_str(&format!("{} ({} pages{})\n", name, pages, note));
+            }
+        }
+        (text, trbk)
+    }
+
+    /// Text area of the EPUB preview, between the title and the hint line.
+    fn epub_preview_rect(&self) -> Rect {
+        let size = self.display_buffers.size();
+        let hint_y = size.height as i32 - HEADER_Y;
+        Rect::new(
+            LIST_MARGIN_X,
+            LIST_TOP - HEADER_Y / 2,
+            size.width as i32 - LIST_MARGIN_X * 2,
+            hint_y - LIST_TOP - LINE_HEIGHT,
+        )
+    }
+
+    fn draw_epub_preview(&mut self, display: &mut impl crate::display::Display) {
+        self.display_buffers.clear(BinaryColor::On).ok();
         let size = self.display_buffers.size();
-        let rect = Rect::new(0, 0, size.width as i32, size.height as i32);
+        let rect = self.epub_preview_rect();
+        let header_style = MonoTextStyle::new(&FONT_10X20, BinaryColor::Off);
         let mut rq = RenderQueue::default();
-        let mut ctx = UiContext {
-            buffers: self.display_buffers,
+        if let Some((name, text)) = &self.epub_preview {
+            let max_chars = (rect.w / 10).max(1) as usize;
+            let title: String = name.chars().take(max_chars).collect();
+            Text::new(&title, Point::new(LIST_MARGIN_X, HEADER_Y), header_style)
+                .draw(self.display_buffers)
+                .ok();
+            let mut view = ScrollTextView::new(text);
+            view.scroll_offset = self.epub_preview_scroll;
+            let mut ctx = UiContext {
+                buffers: self.display_buffers,
+            };
+            view.render(&mut ctx, rect, &mut rq);
+            self.epub_preview_scroll = view.scroll_offset;
+        }
+        let hint = if let Some(status) = &self.epub_preview_status {
+            status.as_str()
+        } else if self.epub_preview_trbk.is_some() {
+            "Confirm to read, Back to return"
+        } else {
+            "Up/Down: page  Right: save .txt  Back: return"
         };
-        list.render(&mut ctx, rect, &mut rq);
-
-        let fallback = if self.full_refresh {
+        Text::new(
+            hint,
+            Point::new(LIST_MARGIN_X, size.height as i32 - HEADER_Y),
+            header_style,
+        )
+        .draw(self.display_buffers)
+        .ok();
+        let mode = if self.full_refresh {
             RefreshMode::Full
         } else {
             RefreshMode::Fast
         };
-        flush_queue(display, self.display_buffers, &mut rq, fallback);
+        rq.push(Rect::new(0, 0, size.width as i32, size.height as i32), mode);
+        flush_queue(display, self.display_buffers, &mut rq, mode);
     }
 
     fn draw_error(&mut self, display: &mut impl crate::display::Display) {
@@ -1043,20 +2579,32 @@ impl<'a, S: ImageSource> Application<'a, S> {
         Text::new("Error", Point::new(LIST_MARGIN_X, HEADER_Y), header_style)
             .draw(self.display_buffers)
             .ok();
+        let size = self.display_buffers.size();
+        let hint_y = size.height as i32 - HEADER_Y;
+        let mut rq = RenderQueue::default();
         if let Some(message) = &self.error_message {
-            Text::new(message, Point::new(LIST_MARGIN_X, LIST_TOP), header_style)
-                .draw(self.display_buffers)
-                .ok();
+            // Long messages wrap and scroll with Up/Down above the hint line.
+            let rect = Rect::new(
+                LIST_MARGIN_X,
+                LIST_TOP - HEADER_Y / 2,
+                size.width as i32 - LIST_MARGIN_X * 2,
+                hint_y - LIST_TOP - LINE_HEIGHT,
+            );
+            let mut view = ScrollTextView::new(message);
+            view.scroll_offset = self.error_scroll;
+            let mut ctx = UiContext {
+                buffers: self.display_buffers,
+            };
+            view.render(&mut ctx, rect, &mut rq);
+            self.error_scroll = view.scroll_offset;
         }
         Text::new(
             "Press Back to return",
-            Point::new(LIST_MARGIN_X, LIST_TOP + 40),
+            Point::new(LIST_MARGIN_X, hint_y),
             header_style,
         )
         .draw(self.display_buffers)
         .ok();
-        let size = self.display_buffers.size();
-        let mut rq = RenderQueue::default();
         rq.push(
             Rect::new(0, 0, size.width as i32, size.height as i32),
             RefreshMode::Full,
@@ -1064,6 +2612,36 @@ impl<'a, S: ImageSource> Application<'a, S> {
         flush_queue(display, self.display_buffers, &mut rq, RefreshMode::Full);
     }
 
+    fn draw_settings(&mut self, display: &mut impl crate::display::Display) {
+        let labels = self.settings_labels();
+        let items: Vec<ListItem<'_>> = labels
+            .iter()
+            .map(|label| ListItem { label: label.as_str() })
+            .collect();
+        let mut list = ListView::new(&items);
+        list.title = Some("Settings");
+        list.footer = Some("Left/Right: change  Back: save");
+        list.selected = self.settings_selected;
+        list.margin_x = LIST_MARGIN_X;
+        list.header_y = HEADER_Y;
+        list.list_top = LIST_TOP;
+        list.line_height = LINE_HEIGHT;
+
+        let size = self.display_buffers.size();
+        let rect = Rect::new(0, 0, size.width as i32, size.height as i32);
+        let mut rq = RenderQueue::default();
+        let mut ctx = UiContext {
+            buffers: self.display_buffers,
+        };
+        list.render(&mut ctx, rect, &mut rq);
+        let refresh = if self.full_refresh {
+            RefreshMode::Full
+        } else {
+            RefreshMode::Fast
+        };
+        flush_queue(display, self.display_buffers, &mut rq, refresh);
+    }
+
     fn draw_toc(&mut self, display: &mut impl crate::display::Display) {
         self.display_buffers.clear(BinaryColor::On).ok();
         let Some(book) = &self.current_book else {
@@ -1137,15 +2715,51 @@ impl<'a, S: ImageSource> Application<'a, S> {
         let mut ctx = UiContext {
             buffers: self.display_buffers,
         };
+        let grayscale = prefers_grayscale(&image);
         let mut reader = ReaderView::new(&image);
         reader.refresh = RefreshMode::Full;
+        reader.grayscale = grayscale;
         reader.render(&mut ctx, rect, &mut rq);
         flush_queue(display, self.display_buffers, &mut rq, RefreshMode::Full);
+        if grayscale {
+            self.draw_grayscale_layer(display, &image);
+        }
         self.current_image = Some(image);
         // Sleep is handled via inactivity timeout.
     }
 
+    /// Layers the gray levels of a photo over the black base that was just
+    /// shown.
+    fn draw_grayscale_layer(
+        &mut self,
+        display: &mut impl crate::display::Display,
+        image: &ImageData,
+    ) {
+        Self::show_gray_planes(display, self.display_buffers, |buffers, msb| {
+            let mut ctx = UiContext { buffers };
+            render_gray_plane(&mut ctx, image, msb);
+        });
+    }
+
+    /// Renders the LSB and MSB gray planes through the active buffer so they
+    /// pick up the current rotation, runs the grayscale waveform, then puts
+    /// the shown frame back. Each plane goes to the panel as soon as it is
+    /// rendered, so no second frame-sized buffer is needed.
+    fn show_gray_planes(
+        display: &mut impl crate::display::Display,
+        buffers: &mut DisplayBuffers,
+        mut render_plane: impl FnMut(&mut DisplayBuffers, bool),
+    ) {
+        render_plane(buffers, false);
+        display.copy_to_lsb(buffers.get_active_buffer());
+        render_plane(buffers, true);
+        display.copy_to_msb(buffers.get_active_buffer());
+        display.display_grayscale();
+        buffers.restore_active();
+    }
+
     fn draw_book(&mut self, display: &mut impl crate::display::Display) {
+        let restoring = core::mem::take(&mut self.wake_restore_only);
         self.display_buffers.clear(BinaryColor::On).ok();
         let Some(book) = &self.current_book else {
             self.set_error(ImageError::Decode);
@@ -1154,11 +2768,35 @@ impl<'a, S: ImageSource> Application<'a, S> {
         if self.current_page_ops.is_none() {
             self.current_page_ops = self.source.trbk_page(self.current_page).ok();
         }
+        // After a TOC jump into the middle of a page, draw it scrolled so the
+        // entry starts at the top margin; ops above the entry are left out.
+        let scroll = self
+            .toc_target
+            .filter(|(page, _)| *page == self.current_page)
+            .map_or(0, |(_, offset)| offset as i32);
+        let top = book.metadata.margin_top as i32 + scroll;
         if let Some(page) = self.current_page_ops.as_ref() {
             for op in &page.ops {
+                // Text and rules are placed by baseline or centre, images by
+                // their top edge.
+                let visible = match op {
+                    crate::trbk::TrbkOp::TextRun { y, .. }
+                    | crate::trbk::TrbkOp::HRule { y, .. } => *y > top,
+                    crate::trbk::TrbkOp::Image { y, .. } => *y >= top,
+                };
+                if scroll > 0 && !visible {
+                    continue;
+                }
                 match op {
                     crate::trbk::TrbkOp::TextRun { x, y, style, text } => {
-                        Self::draw_trbk_text(self.display_buffers, book, *x, *y, *style, text);
+                        Self::draw_trbk_text(
+                            self.display_buffers,
+                            book,
+                            *x,
+                            *y - scroll,
+                            *style,
+                            text,
+                        );
                     }
                     crate::trbk::TrbkOp::Image {
                         x,
@@ -1172,26 +2810,48 @@ impl<'a, S: ImageSource> Application<'a, S> {
                                 self.display_buffers,
                                 &image,
                                 *x,
-                                *y,
+                                *y - scroll,
                                 *width as i32,
                                 *height as i32,
                             );
                         }
                     }
+                    crate::trbk::TrbkOp::HRule { x, y, width } => {
+                        Rectangle::new(
+                            Point::new(*x, *y - scroll - HRULE_THICKNESS / 2),
+                            Size::new(*width as u32, HRULE_THICKNESS as u32),
+                        )
+                        .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_fill(
+                            BinaryColor::Off,
+                        ))
+                        .draw(self.display_buffers)
+                        .ok();
+                    }
                 }
             }
         }
         self.last_rendered_page = Some(self.current_page);
-        Self::draw_page_indicator(self.display_buffers, self.current_page, book.page_count);
-        if self.book_turns_since_full >= BOOK_FULL_REFRESH_EVERY {
+        Self::draw_progress_footer(
+            self.display_buffers,
+            book,
+            self.current_page,
+            self.clock_secs,
+            self.battery_percent,
+        );
+        if self.book_turns_since_full >= self.book_full_refresh_every {
             self.full_refresh = true;
-            self.book_turns_since_full = 0;
         }
-        let mode = if self.full_refresh {
+        // Waking from sleep only needs to redraw over the overlay, not flash the panel.
+        let mode = if self.full_refresh && !restoring {
             RefreshMode::Full
         } else {
             RefreshMode::Fast
         };
+        // Any full refresh clears the ghosting, so the count restarts whatever
+        // asked for it (jumps, the TOC, leaving a menu).
+        if mode == RefreshMode::Full {
+            self.book_turns_since_full = 0;
+        }
         let mut rq = RenderQueue::default();
         let size = self.display_buffers.size();
         rq.push(
@@ -1199,6 +2859,57 @@ impl<'a, S: ImageSource> Application<'a, S> {
             mode,
         );
         flush_queue(display, self.display_buffers, &mut rq, mode);
+        if !book.gray_glyphs {
+            return;
+        }
+        if let Some(page) = self.current_page_ops.as_ref() {
+            Self::show_gray_planes(display, self.display_buffers, |buffers, msb| {
+                buffers.clear_screen(0x00);
+                for op in &page.ops {
+                    if let crate::trbk::TrbkOp::TextRun { x, y, style, text } = op
+                        && (scroll == 0 || *y > top)
+                    {
+                        Self::draw_trbk_gray_text(
+                            buffers,
+                            book,
+                            *x,
+                            *y - scroll,
+                            *style,
+                            text,
+                            msb,
+                        );
+                    }
+                }
+            });
+        }
+    }
+
+    /// Gray-plane counterpart of `draw_trbk_text` for books with 2-bit glyphs.
+    fn draw_trbk_gray_text(
+        buffers: &mut DisplayBuffers,
+        book: &crate::trbk::TrbkBookInfo,
+        x: i32,
+        y: i32,
+        style: u8,
+        text: &str,
+        msb: bool,
+    ) {
+        let mut pen_x = x;
+        let mut prev = None;
+        for ch in text.chars() {
+            if ch == '\r' || ch == '\n' {
+                continue;
+            }
+            if let Some(prev) = prev.replace(ch) {
+                pen_x += book.kerning_adjust(style, prev, ch) as i32;
+            }
+            if let Some(glyph) = find_glyph(&book.glyphs, style, ch as u32) {
+                draw_glyph_gray(buffers, glyph, pen_x, y, msb);
+                pen_x += glyph.x_advance as i32;
+            } else {
+                pen_x += book.metadata.char_width as i32;
+            }
+        }
     }
 
     fn draw_trbk_text(
@@ -1219,10 +2930,14 @@ impl<'a, S: ImageSource> Application<'a, S> {
 
         let mut pen_x = x;
         let baseline = y;
+        let mut prev = None;
         for ch in text.chars() {
             if ch == '\r' || ch == '\n' {
                 continue;
             }
+            if let Some(prev) = prev.replace(ch) {
+                pen_x += book.kerning_adjust(style, prev, ch) as i32;
+            }
             let codepoint = ch as u32;
             if let Some(glyph) = find_glyph(&book.glyphs, style, codepoint) {
                 draw_glyph(buffers, glyph, pen_x, baseline);
@@ -1281,54 +2996,193 @@ impl<'a, S: ImageSource> Application<'a, S> {
                 width,
                 height,
                 pixels,
-            } => {
-                let src_w = *width as i32;
-                let src_h = *height as i32;
-                let dst_w = target_w.max(1);
-                let dst_h = target_h.max(1);
-                let bayer: [[u8; 4]; 4] = [
-                    [0, 8, 2, 10],
-                    [12, 4, 14, 6],
-                    [3, 11, 1, 9],
-                    [15, 7, 13, 5],
-                ];
-                for ty in 0..dst_h {
-                    let src_y = (ty as i64 * src_h as i64 / dst_h as i64) as i32;
-                    for tx in 0..dst_w {
-                        let src_x = (tx as i64 * src_w as i64 / dst_w as i64) as i32;
-                        let idx = (src_y as usize) * (*width as usize) + src_x as usize;
-                        if idx >= pixels.len() {
-                            continue;
-                        }
-                        let lum = pixels[idx];
-                        let threshold = (bayer[(ty as usize) & 3][(tx as usize) & 3] * 16 + 8)
-                            as u8;
-                        let color = if lum < threshold {
-                            BinaryColor::Off
-                        } else {
-                            BinaryColor::On
-                        };
-                        buffers.set_pixel(x + tx, y + ty, color);
-                    }
-                }
+            } => Self::draw_dithered_image(
+                buffers,
+                (*width, *height),
+                x,
+                y,
+                target_w,
+                target_h,
+                |idx| pixels.get(idx).copied(),
+            ),
+            ImageData::Gray2 {
+                width,
+                height,
+                pixels,
+            } => Self::draw_dithered_image(
+                buffers,
+                (*width, *height),
+                x,
+                y,
+                target_w,
+                target_h,
+                |idx| gray2_luma(pixels, idx),
+            ),
+        }
+    }
+
+    /// Scales a grayscale image into the target box with ordered dithering;
+    /// `luma` returns the source luminance at a row-major pixel index.
+    fn draw_dithered_image(
+        buffers: &mut DisplayBuffers,
+        (width, height): (u32, u32),
+        x: i32,
+        y: i32,
+        target_w: i32,
+        target_h: i32,
+        luma: impl Fn(usize) -> Option<u8>,
+    ) {
+        let src_w = width as i32;
+        let src_h = height as i32;
+        let dst_w = target_w.max(1);
+        let dst_h = target_h.max(1);
+        let bayer: [[u8; 4]; 4] = [
+            [0, 8, 2, 10],
+            [12, 4, 14, 6],
+            [3, 11, 1, 9],
+            [15, 7, 13, 5],
+        ];
+        for ty in 0..dst_h {
+            let src_y = (ty as i64 * src_h as i64 / dst_h as i64) as i32;
+            for tx in 0..dst_w {
+                let src_x = (tx as i64 * src_w as i64 / dst_w as i64) as i32;
+                let idx = (src_y as usize) * (width as usize) + src_x as usize;
+                let Some(lum) = luma(idx) else {
+                    continue;
+                };
+                let threshold = (bayer[(ty as usize) & 3][(tx as usize) & 3] * 16 + 8) as u8;
+                let color = if lum < threshold {
+                    BinaryColor::Off
+                } else {
+                    BinaryColor::On
+                };
+                buffers.set_pixel(x + tx, y + ty, color);
             }
         }
     }
 
-    fn draw_page_indicator(buffers: &mut DisplayBuffers, page: usize, total: usize) {
+    fn draw_progress_footer(
+        buffers: &mut DisplayBuffers,
+        book: &crate::trbk::TrbkBookInfo,
+        page: usize,
+        clock: Option<u64>,
+        battery: Option<u8>,
+    ) {
+        let total = book.page_count;
         if total == 0 {
             return;
         }
-        let label = format!("{}/{}", page.saturating_add(1), total);
-        let text_w = (label.len() as i32) * 10;
         let size = buffers.size();
-        let margin = 8;
-        let x = (size.width as i32 - margin - text_w).max(margin);
-        let y = (size.height as i32 - margin).max(0);
+        let width = size.width as i32;
+        let height = size.height as i32;
+        let left = (book.metadata.margin_left as i32).max(FOOTER_MIN_MARGIN);
+        let right = (book.metadata.margin_right as i32).max(FOOTER_MIN_MARGIN);
+        // Sit in the bottom margin; the label is drawn with the built-in font so
+        // it works whether or not the book embeds glyphs.
+        let bottom = (book.metadata.margin_bottom as i32).clamp(FOOTER_HEIGHT, FOOTER_HEIGHT * 2);
+        let baseline = height - (bottom - FOOTER_HEIGHT) / 2 - 6;
+        let mut label = format!("page {} / {}", page.saturating_add(1), total);
+        if let Some((chapter, chapters)) = book.chapter_at(page) {
+            label.push_str(&format!("  ch {chapter} / {chapters}"));
+        }
+        let text_w = (label.len() as i32) * 10;
         let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::Off);
-        Text::new(label.as_str(), Point::new(x, y), style)
+        Text::new(label.as_str(), Point::new(left, baseline), style)
+            .draw(buffers)
+            .ok();
+
+        let status_left = Self::draw_status(buffers, width - right, baseline, clock, battery);
+        let bar_right = if status_left < width - right {
+            status_left - 12
+        } else {
+            width - right
+        };
+        let bar_x = left + text_w + 12;
+        let bar_w = bar_right - bar_x;
+        if bar_w < 16 {
+            return;
+        }
+        let bar_y = baseline - 10;
+        let mut bar = ProgressBar::from_steps(page + 1, total);
+        let mut ctx = UiContext { buffers };
+        bar.render(
+            &mut ctx,
+            Rect::new(bar_x, bar_y, bar_w, FOOTER_BAR_HEIGHT),
+            &mut RenderQueue::default(),
+        );
+    }
+
+    /// Clock and battery, whichever are known, ending at `right` on the 10x20
+    /// text `baseline`. Returns the left edge, or `right` when nothing is drawn.
+    fn draw_status(
+        buffers: &mut DisplayBuffers,
+        right: i32,
+        baseline: i32,
+        clock: Option<u64>,
+        battery: Option<u8>,
+    ) -> i32 {
+        let mut left = right;
+        if let Some(percent) = battery {
+            left = Self::draw_battery(buffers, percent, left, baseline);
+        }
+        if let Some(secs) = clock {
+            if left < right {
+                left -= 12;
+            }
+            let label = clock_label(secs);
+            left -= label.len() as i32 * 10;
+            let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::Off);
+            Text::new(label.as_str(), Point::new(left, baseline), style)
+                .draw(buffers)
+                .ok();
+        }
+        left
+    }
+
+    /// Battery outline filled to `percent` with the percentage to its left,
+    /// ending at `right` and sharing the 10x20 text `baseline`. Returns the
+    /// left edge of what was drawn.
+    fn draw_battery(buffers: &mut DisplayBuffers, percent: u8, right: i32, baseline: i32) -> i32 {
+        let percent = percent.min(100) as i32;
+        let nub_w = 2;
+        let body_x = right - nub_w - BATTERY_ICON_W;
+        let body_y = baseline - 13;
+        let stroke = embedded_graphics::primitives::PrimitiveStyle::with_stroke(BinaryColor::Off, 1);
+        let fill = embedded_graphics::primitives::PrimitiveStyle::with_fill(BinaryColor::Off);
+        Rectangle::new(
+            Point::new(body_x, body_y),
+            Size::new(BATTERY_ICON_W as u32, BATTERY_ICON_H as u32),
+        )
+        .into_styled(stroke)
+        .draw(buffers)
+        .ok();
+        Rectangle::new(
+            Point::new(body_x + BATTERY_ICON_W, body_y + 3),
+            Size::new(nub_w as u32, (BATTERY_ICON_H - 6) as u32),
+        )
+        .into_styled(fill)
+        .draw(buffers)
+        .ok();
+        let level_w = (BATTERY_ICON_W - 4) * percent / 100;
+        if level_w > 0 {
+            Rectangle::new(
+                Point::new(body_x + 2, body_y + 2),
+                Size::new(level_w as u32, (BATTERY_ICON_H - 4) as u32),
+            )
+            .into_styled(fill)
             .draw(buffers)
             .ok();
+        }
+        let label = format!("{}%", percent);
+        let label_x = body_x - 6 - label.len() as i32 * 10;
+        Text::new(
+            label.as_str(),
+            Point::new(label_x, baseline),
+            MonoTextStyle::new(&FONT_10X20, BinaryColor::Off),
+        )
+        .draw(buffers)
+        .ok();
+        label_x
     }
 
     fn draw_page_turn_indicator(
@@ -1392,9 +3246,81 @@ impl<'a, S: ImageSource> Application<'a, S> {
         flush_queue(display, self.display_buffers, &mut rq, RefreshMode::Fast);
     }
 
+    /// Shows the book's cover while asleep when there is one, otherwise the
+    /// "Sleeping..." bar over the last frame.
+    fn draw_sleep_screen(&mut self, display: &mut impl crate::display::Display) {
+        self.sleep_cover_shown = false;
+        let cover = if self.sleep_return_state == Some(AppState::BookViewing) {
+            self.load_book_cover()
+        } else {
+            None
+        };
+        match cover {
+            Some(cover) => self.draw_sleep_cover(display, &cover),
+            None => self.draw_sleep_overlay(display),
+        }
+    }
+
+    /// Cover for the open book: the embedded image its header marks as the
+    /// cover, else a `.tri` or `.trimg` next to the book with the same stem.
+    fn load_book_cover(&mut self) -> Option<ImageData> {
+        // Only the declared cover: other embedded images are figures.
+        let cover_index = self.current_book.as_ref()?.cover_image;
+        let embedded = cover_index.and_then(|index| self.source.trbk_image(index as usize).ok());
+        if embedded.is_some() {
+            return embedded;
+        }
+        let entry_path = self.current_entry.clone()?;
+        let mut parts: Vec<String> = entry_path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(|part| part.to_string())
+            .collect();
+        let file = parts.pop()?;
+        let (stem, _) = file.rsplit_once('.')?;
+        for ext in ["tri", "trimg"] {
+            let entry = ImageEntry {
+                name: format!("{}.{}", stem, ext),
+                kind: EntryKind::File,
+                modified: None,
+            };
+            if let Ok(image) = self.source.load(&parts, &entry) {
+                return Some(image);
+            }
+        }
+        None
+    }
+
+    /// Draws the cover full screen with a full refresh so it stays on the
+    /// panel once power is cut.
+    fn draw_sleep_cover(&mut self, display: &mut impl crate::display::Display, cover: &ImageData) {
+        let size = self.display_buffers.size();
+        let rect = Rect::new(0, 0, size.width as i32, size.height as i32);
+        let mut rq = RenderQueue::default();
+        let mut ctx = UiContext {
+            buffers: self.display_buffers,
+        };
+        let grayscale = prefers_grayscale(cover);
+        let mut reader = ReaderView::new(cover);
+        reader.refresh = RefreshMode::Full;
+        reader.grayscale = grayscale;
+        reader.render(&mut ctx, rect, &mut rq);
+        flush_queue(display, self.display_buffers, &mut rq, RefreshMode::Full);
+        if grayscale {
+            self.draw_grayscale_layer(display, cover);
+        }
+        self.sleep_overlay = None;
+        self.sleep_cover_shown = true;
+        display.power_down();
+        self.panel_powered_down = true;
+    }
+
     fn draw_sleep_overlay(&mut self, display: &mut impl crate::display::Display) {
         let size = self.display_buffers.size();
-        let text = "Sleeping...";
+        let text = match self.clock_secs {
+            Some(secs) => format!("Sleeping since {}", clock_label(secs)),
+            None => "Sleeping...".to_string(),
+        };
         let text_w = (text.len() as i32) * 10;
         let padding = 8;
         let bar_h = 28;
@@ -1425,13 +3351,15 @@ impl<'a, S: ImageSource> Application<'a, S> {
         let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::On);
         let text_x = x + padding;
         let text_y = y + bar_h - 14;
-        Text::new(text, Point::new(text_x, text_y), style)
+        Text::new(text.as_str(), Point::new(text_x, text_y), style)
             .draw(self.display_buffers)
             .ok();
 
         let mut rq = RenderQueue::default();
         rq.push(rect, RefreshMode::Fast);
         flush_queue(display, self.display_buffers, &mut rq, RefreshMode::Fast);
+        display.power_down();
+        self.panel_powered_down = true;
     }
 
     fn save_rect_bits(&self, rect: Rect) -> Vec<u8> {
@@ -1466,7 +3394,7 @@ impl<'a, S: ImageSource> Application<'a, S> {
         if x < 0 || y < 0 || x as u32 >= size.width || y as u32 >= size.height {
             return true;
         }
-        let (x, y) = match self.display_buffers.rotation() {
+        let (x, y) = match self.display_buffers.buffer_rotation() {
             Rotation::Rotate0 => (x as usize, y as usize),
             Rotation::Rotate90 => (y as usize, FB_HEIGHT - 1 - x as usize),
             Rotation::Rotate180 => (FB_WIDTH - 1 - x as usize, FB_HEIGHT - 1 - y as usize),
@@ -1483,14 +3411,14 @@ impl<'a, S: ImageSource> Application<'a, S> {
     }
 
     fn try_resume(&mut self) {
-        let Some(raw) = self.resume_name.take() else {
+        let Some(resume) = self.resume_state.take() else {
             return;
         };
-        let name = raw;
-        if name == "HOME" {
+        if resume.name == "HOME" {
             return;
         }
-        let mut parts: Vec<String> = name
+        let mut parts: Vec<String> = resume
+            .name
             .split('/')
             .filter(|part| !part.is_empty())
             .map(|part| part.to_string())
@@ -1500,21 +3428,23 @@ impl<'a, S: ImageSource> Application<'a, S> {
         }
         let file = parts.pop().unwrap_or_default();
         self.path = parts;
+        self.in_recent_folder = false;
         self.refresh_entries();
         let idx = self.entries.iter().position(|entry| entry.name == file);
         if let Some(index) = idx {
+            // Books open straight into BookViewing at their stored page; the
+            // resume record wins when it names a page of its own.
             self.open_index(index);
-            if let Some(book) = &self.current_book {
-                if let Some(name) = &self.current_entry {
-                    if let Some(page) = self.book_positions.get(name).copied() {
-                        if page < book.page_count {
-                            self.current_page = page;
-                            self.current_page_ops = self.source.trbk_page(self.current_page).ok();
-                            self.full_refresh = true;
-                            self.book_turns_since_full = 0;
-                            self.dirty = true;
-                        }
-                    }
+            if let (Some(book), Some(page)) = (&self.current_book, resume.page) {
+                // The book may have been re-converted with fewer pages.
+                let page = page.min(book.page_count.saturating_sub(1));
+                if page != self.current_page {
+                    self.current_page = page;
+                    self.current_page_ops = self.source.trbk_page(self.current_page).ok();
+                    self.last_rendered_page = None;
+                    self.full_refresh = true;
+                    self.book_turns_since_full = 0;
+                    self.dirty = true;
                 }
             }
         } else {
@@ -1552,6 +3482,7 @@ impl<'a, S: ImageSource> Application<'a, S> {
         }
         let file = parts.pop().unwrap_or_default();
         self.path = parts;
+        self.in_recent_folder = false;
         self.refresh_entries();
         let idx = self.entries.iter().position(|entry| entry.name == file);
         if let Some(index) = idx {
@@ -1606,6 +3537,7 @@ impl<'a, S: ImageSource> Application<'a, S> {
             let entry = ImageEntry {
                 name: file,
                 kind: EntryKind::File,
+                modified: None,
             };
             if let Ok(image) = self.source.load(&parts, &entry) {
                 if let Some(thumb) = self.thumbnail_from_image(&image, 74) {
@@ -1630,6 +3562,7 @@ impl<'a, S: ImageSource> Application<'a, S> {
         let entry = ImageEntry {
             name: file,
             kind: EntryKind::File,
+            modified: None,
         };
         let info = match self.source.open_trbk(&parts, &entry) {
             Ok(info) => info,
@@ -1662,6 +3595,7 @@ impl<'a, S: ImageSource> Application<'a, S> {
         let (src_w, src_h) = match image {
             ImageData::Mono1 { width, height, .. } => (*width, *height),
             ImageData::Gray8 { width, height, .. } => (*width, *height),
+            ImageData::Gray2 { width, height, .. } => (*width, *height),
         };
         if src_w == 0 || src_h == 0 {
             return None;
@@ -1686,6 +3620,10 @@ impl<'a, S: ImageSource> Application<'a, S> {
                         let idx = (sy * (*width) + sx) as usize;
                         pixels.get(idx).copied().unwrap_or(255) > 127
                     }
+                    ImageData::Gray2 { width, pixels, .. } => {
+                        let idx = (sy * (*width) + sx) as usize;
+                        gray2_luma(pixels, idx).unwrap_or(255) > 127
+                    }
                 };
                 let dst_idx = (y * dst_w + x) as usize;
                 let dst_byte = dst_idx / 8;
@@ -1718,16 +3656,30 @@ impl<'a, S: ImageSource> Application<'a, S> {
         parts.join("/")
     }
 
-    fn current_resume_string(&self) -> Option<String> {
+    fn current_resume_state(&self) -> Option<ResumeState> {
         if self.state == AppState::StartMenu {
-            return Some("HOME".to_string());
+            return Some(ResumeState::new("HOME", None));
         }
         let name = self
             .current_entry
             .clone()
             .or_else(|| self.last_viewed_entry.clone())
             .or_else(|| self.current_entry_name_owned())?;
-        Some(name)
+        let page = self.current_book.as_ref().map(|_| self.current_page);
+        Some(ResumeState { name, page })
+    }
+
+    /// Page to open the current book at, clamped to the book's page count.
+    fn stored_book_page(&self) -> usize {
+        let Some(book) = &self.current_book else {
+            return 0;
+        };
+        let page = self
+            .current_entry
+            .as_ref()
+            .and_then(|name| self.book_positions.get(name).copied())
+            .unwrap_or(0);
+        page.min(book.page_count.saturating_sub(1))
     }
 
     fn save_resume_checked(&mut self) -> bool {
@@ -1743,24 +3695,25 @@ impl<'a, S: ImageSource> Application<'a, S> {
             self.last_rendered_page
         );
         let expected = if self.sleep_from_home {
-            Some("HOME".to_string())
+            Some(ResumeState::new("HOME", None))
         } else {
-            self.current_resume_string()
+            self.current_resume_state()
         };
         let Some(expected) = expected else {
             log::info!("No resume state to save. {}", resume_debug);
             return true;
         };
-        log::info!("Saving resume state: {} ({})", expected, resume_debug);
+        log::info!("Saving resume state: {:?} ({})", expected, resume_debug);
         self.update_book_position();
         self.save_book_positions_now();
         self.save_recent_entries_now();
-        if self.last_saved_resume.as_deref() != Some(expected.as_str()) {
-            self.source.save_resume(Some(expected.as_str()));
-            let actual = self.source.load_resume().unwrap_or_default();
-            log::info!("Resume state readback: {}", actual);
-            self.last_saved_resume = Some(actual.clone());
-            if actual.is_empty() || actual != expected {
+        self.save_settings_now();
+        if self.last_saved_resume.as_ref() != Some(&expected) {
+            self.source.save_resume(Some(&expected));
+            let actual = self.source.load_resume();
+            log::info!("Resume state readback: {:?}", actual);
+            self.last_saved_resume = actual.clone();
+            if actual.as_ref() != Some(&expected) {
                 self.error_message = Some("Failed to save resume state.".into());
                 self.state = AppState::Error;
                 self.sleep_after_error = true;
@@ -1791,9 +3744,7 @@ impl<'a, S: ImageSource> Application<'a, S> {
     fn mark_recent(&mut self, path: String) {
         self.recent_entries.retain(|entry| entry != &path);
         self.recent_entries.insert(0, path);
-        if self.recent_entries.len() > 10 {
-            self.recent_entries.truncate(10);
-        }
+        self.recent_entries.truncate(RECENT_LIMIT);
         self.recent_dirty = true;
     }
 
@@ -1823,6 +3774,15 @@ impl<'a, S: ImageSource> Application<'a, S> {
             return;
         }
         self.sleep_from_home = self.state == AppState::StartMenu;
+        self.sleep_return_state = match self.state {
+            AppState::BookViewing
+            | AppState::Toc
+            | AppState::GotoPage
+            | AppState::Scrub
+            | AppState::Search => Some(AppState::BookViewing),
+            AppState::Viewing => Some(AppState::Viewing),
+            _ => None,
+        };
         self.state = AppState::SleepingPending;
         self.sleep_transition = false;
         self.sleep_overlay_pending = false;
@@ -1830,7 +3790,9 @@ impl<'a, S: ImageSource> Application<'a, S> {
     }
 
     fn menu_title(&self) -> String {
-        if self.path.is_empty() {
+        if self.in_recent_folder {
+            format!("Images/{}", RECENT_FOLDER)
+        } else if self.path.is_empty() {
             "Images".to_string()
         } else {
             let mut title = String::from("Images/");
@@ -1849,6 +3811,13 @@ fn find_glyph<'a>(
     glyphs
         .iter()
         .find(|glyph| glyph.style == style && glyph.codepoint == codepoint)
+        .or_else(|| {
+            // Heading sizes may be missing a glyph; fall back to body text.
+            let base = style & (crate::trbk::TRBK_STYLE_MASK | crate::trbk::TRBK_STYLE_MONO);
+            glyphs
+                .iter()
+                .find(|glyph| glyph.style == base && glyph.codepoint == codepoint)
+        })
 }
 
 fn find_toc_selection(book: &crate::trbk::TrbkBookInfo, page: usize) -> usize {
@@ -1889,6 +3858,64 @@ fn draw_glyph(
     }
 }
 
+/// Sets the gray-plane bits for a 2-bit glyph. Black and dark gray are
+/// already black in the base frame; the waveform lifts dark gray (LSB) and
+/// darkens light gray on a white base (both planes).
+fn draw_glyph_gray(
+    buffers: &mut DisplayBuffers,
+    glyph: &crate::trbk::TrbkGlyph,
+    origin_x: i32,
+    baseline: i32,
+    msb: bool,
+) {
+    let plane = glyph.plane_len();
+    if glyph.bitmap.len() < plane * 2 {
+        return;
+    }
+    let (high, low) = glyph.bitmap.split_at(plane);
+    let start_x = origin_x + glyph.x_offset as i32;
+    let start_y = baseline - glyph.y_offset as i32;
+    let mut idx = 0usize;
+    for row in 0..glyph.height as i32 {
+        for col in 0..glyph.width as i32 {
+            let mask = 1 << (7 - (idx % 8));
+            let level = ((high[idx / 8] & mask) != 0, (low[idx / 8] & mask) != 0);
+            let set = match level {
+                // Dark gray
+                (true, false) => !msb,
+                // Light gray
+                (false, true) => true,
+                _ => false,
+            };
+            if set {
+                buffers.set_pixel(start_x + col, start_y + row, BinaryColor::On);
+            }
+            idx += 1;
+        }
+    }
+}
+
+/// Next or previous value in `options`, wrapping at either end. A value that
+/// isn't in the list (from a hand-edited settings file) starts over at an end.
+fn cycle_option<T: Copy + PartialEq>(options: &[T], current: T, forward: bool) -> T {
+    let len = options.len();
+    let index = match options.iter().position(|&option| option == current) {
+        Some(index) if forward => (index + 1) % len,
+        Some(index) => (index + len - 1) % len,
+        None if forward => 0,
+        None => len - 1,
+    };
+    options[index]
+}
+
+fn duration_label(ms: u32) -> String {
+    if ms >= 60_000 && ms.is_multiple_of(60_000) {
+        format!("{} min", ms / 60_000)
+    } else {
+        format!("{} s", ms / 1000)
+    }
+}
+
 fn is_epub(name: &str) -> bool {
     let name = name.to_ascii_lowercase();
     name.ends_with(".epub") || name.ends_with(".epb")
@@ -1898,6 +3925,19 @@ fn is_trbk(name: &str) -> bool {
     name.to_ascii_lowercase().ends_with(".trbk")
 }
 
+/// Splits a multi-size book name like `novel-12.trbk` into `("novel", 12)`.
+fn split_size_suffix(name: &str) -> Option<(&str, u16)> {
+    if !is_trbk(name) {
+        return None;
+    }
+    let stem = &name[..name.len() - ".trbk".len()];
+    let (base, size) = stem.rsplit_once('-')?;
+    if base.is_empty() || size.is_empty() || !size.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((base, size.parse().ok()?))
+}
+
 struct SleepOverlay {
     rect: Rect,
     pixels: Vec<u8>,
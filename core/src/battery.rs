@@ -0,0 +1,43 @@
+/// Battery voltages read as empty and full. The defaults suit a single
+/// Li-ion cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatteryRange {
+    pub empty_mv: u32,
+    pub full_mv: u32,
+}
+
+impl Default for BatteryRange {
+    fn default() -> Self {
+        Self {
+            empty_mv: 3300,
+            full_mv: 4200,
+        }
+    }
+}
+
+impl BatteryRange {
+    /// Linear charge estimate for `millivolts`, clamped to 0..=100.
+    pub fn percent(&self, millivolts: u32) -> u8 {
+        if millivolts <= self.empty_mv || self.full_mv <= self.empty_mv {
+            return 0;
+        }
+        let span = self.full_mv - self.empty_mv;
+        ((millivolts - self.empty_mv).min(span) * 100 / span) as u8
+    }
+}
+
+/// Something that can measure the battery, kept apart from `ImageSource` so
+/// the app can poll it on its own schedule.
+pub trait BatteryMonitor {
+    /// Battery voltage in millivolts, or `None` when it can't be read.
+    fn battery_millivolts(&mut self) -> Option<u32>;
+
+    fn battery_range(&self) -> BatteryRange {
+        BatteryRange::default()
+    }
+
+    fn battery_percent(&mut self) -> Option<u8> {
+        let millivolts = self.battery_millivolts()?;
+        Some(self.battery_range().percent(millivolts))
+    }
+}
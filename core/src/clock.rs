@@ -0,0 +1,47 @@
+extern crate alloc;
+
+use alloc::string::String;
+
+pub const SECS_PER_DAY: u64 = 86_400;
+/// Where setting an unset clock starts from (2026-01-01 00:00). Sources may
+/// treat anything earlier as never having been set.
+pub const CLOCK_UNSET_DEFAULT_SECS: u64 = 1_767_225_600;
+
+/// Wall-clock time as seconds since the Unix epoch. The device has no time
+/// zones, so whatever the user sets is shown as-is.
+pub trait TimeSource {
+    /// `None` until the clock has been set.
+    fn now_secs(&mut self) -> Option<u64>;
+    fn set_now_secs(&mut self, secs: u64);
+}
+
+/// `HH:MM` for `secs`.
+pub fn clock_label(secs: u64) -> String {
+    let minutes = secs % SECS_PER_DAY / 60;
+    alloc::format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Moves the time of day by `delta_secs`, wrapping within the same day so
+/// setting the hour never changes the date.
+pub fn shift_time_of_day(secs: u64, delta_secs: i64) -> u64 {
+    let day_start = secs - secs % SECS_PER_DAY;
+    let time_of_day = (secs % SECS_PER_DAY) as i64;
+    day_start + (time_of_day + delta_secs).rem_euclid(SECS_PER_DAY as i64) as u64
+}
+
+/// Calendar date `(year, month, day)` for `secs`, proleptic Gregorian.
+pub fn civil_date(secs: u64) -> (u16, u8, u8) {
+    // Days-to-civil from Howard Hinnant's date algorithms, with eras
+    // starting on 0000-03-01 so leap days fall at the end of each year.
+    let days = (secs / SECS_PER_DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as u16, month as u8, day as u8)
+}
@@ -1,4 +1,5 @@
 use crate::framebuffer::{BUFFER_SIZE, DisplayBuffers};
+use crate::ui::Rect;
 
 pub const WIDTH: usize = 800;
 pub const HEIGHT: usize = 480;
@@ -17,8 +18,33 @@ pub enum RefreshMode {
 
 pub trait Display {
     fn display(&mut self, buffers: &mut DisplayBuffers, mode: RefreshMode);
+    /// Refreshes only `rect` (in rotated coordinates), sending just the
+    /// affected rows. Drivers without windowed updates refresh everything.
+    fn display_region(&mut self, buffers: &mut DisplayBuffers, rect: Rect, mode: RefreshMode) {
+        let _ = rect;
+        self.display(buffers, mode);
+    }
     fn copy_to_lsb(&mut self, buffers: &[u8; BUFFER_SIZE]);
     fn copy_to_msb(&mut self, buffers: &[u8; BUFFER_SIZE]);
     fn copy_grayscale_buffers(&mut self, lsb: &[u8; BUFFER_SIZE], msb: &[u8; BUFFER_SIZE]);
     fn display_grayscale(&mut self);
+    /// Returns (and clears) whether a refresh failed since the last call,
+    /// e.g. the panel never released BUSY.
+    fn take_error(&mut self) -> bool {
+        false
+    }
+    /// Puts the panel controller into its lowest-power state. The image
+    /// stays on screen but controller state is lost until `power_up`.
+    fn power_down(&mut self) {}
+    /// Re-initializes the controller after `power_down`.
+    fn power_up(&mut self) {}
+    /// Whether the controller can show the framebuffer turned 180° by
+    /// reversing its RAM scan. When true (and passed to
+    /// `DisplayBuffers::set_hardware_flip`), `Rotate180` needs no per-pixel
+    /// transform and `Rotate270` only the axis swap of `Rotate90`; `Rotate0`
+    /// and `Rotate90` are unaffected. The driver takes the flip from
+    /// `DisplayBuffers::panel_flipped` on each `display`/`display_region`.
+    fn supports_hardware_flip(&self) -> bool {
+        false
+    }
 }
@@ -8,6 +8,8 @@ pub const WIDTH: usize = 800;
 pub const HEIGHT: usize = 480;
 pub const BUFFER_SIZE: usize = WIDTH * HEIGHT / 8;
 
+use crate::ui::Rect;
+
 /// Display rotation/orientation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Rotation {
@@ -25,6 +27,7 @@ pub struct DisplayBuffers {
     framebuffer: [[u8; BUFFER_SIZE]; 2],
     active: bool,
     rotation: Rotation,
+    hardware_flip: bool,
 }
 
 impl Default for DisplayBuffers {
@@ -37,6 +40,7 @@ impl Default for DisplayBuffers {
             framebuffer,
             active: false,
             rotation: Rotation::Rotate0,
+            hardware_flip: false,
         }
     }
 }
@@ -50,6 +54,29 @@ impl DisplayBuffers {
         self.rotation = rotation;
     }
 
+    /// Lets the panel controller take over the 180° part of the rotation;
+    /// pass [`Display::supports_hardware_flip`](crate::display::Display::supports_hardware_flip).
+    pub fn set_hardware_flip(&mut self, supported: bool) {
+        self.hardware_flip = supported;
+    }
+
+    /// Whether the panel should show the buffer turned 180°. True for
+    /// `Rotate180` and `Rotate270` when the controller can flip.
+    pub fn panel_flipped(&self) -> bool {
+        self.hardware_flip && matches!(self.rotation, Rotation::Rotate180 | Rotation::Rotate270)
+    }
+
+    /// The rotation applied per pixel when drawing into the buffer: what is
+    /// left of `rotation` once the panel has done its flip. `Rotate180` becomes
+    /// a straight copy and `Rotate270` is drawn as `Rotate90`.
+    pub fn buffer_rotation(&self) -> Rotation {
+        match (self.panel_flipped(), self.rotation) {
+            (true, Rotation::Rotate180) => Rotation::Rotate0,
+            (true, Rotation::Rotate270) => Rotation::Rotate90,
+            (_, rotation) => rotation,
+        }
+    }
+
     pub fn get_active_buffer_mut(&mut self) -> &mut [u8; BUFFER_SIZE] {
         if self.active {
             &mut self.framebuffer[1]
@@ -82,12 +109,43 @@ impl DisplayBuffers {
         self.active = !self.active;
     }
 
+    /// Copies the frame on screen (the inactive buffer after a refresh) back
+    /// into the active buffer, e.g. after it was used as scratch space.
+    pub fn restore_active(&mut self) {
+        let [first, second] = &mut self.framebuffer;
+        if self.active {
+            second.copy_from_slice(first);
+        } else {
+            first.copy_from_slice(second);
+        }
+    }
+
+    /// Maps a rect in the current (rotated) coordinates onto the 800x480
+    /// buffer, widened so the x-range covers whole bytes. Returns `None` when
+    /// the rect lies off screen. When [`Self::panel_flipped`] the driver still
+    /// has to mirror the result onto the panel.
+    pub fn physical_region(&self, rect: Rect) -> Option<Rect> {
+        let size = self.size();
+        let rect = rect.intersection(Rect::new(0, 0, size.width as i32, size.height as i32))?;
+        let (x0, y0, x1, y1) = (rect.x, rect.y, rect.x + rect.w, rect.y + rect.h);
+        let (w, h) = (WIDTH as i32, HEIGHT as i32);
+        let (px0, py0, px1, py1) = match self.buffer_rotation() {
+            Rotation::Rotate0 => (x0, y0, x1, y1),
+            Rotation::Rotate90 => (y0, h - x1, y1, h - x0),
+            Rotation::Rotate180 => (w - x1, h - y1, w - x0, h - y0),
+            Rotation::Rotate270 => (w - y1, x0, w - y0, x1),
+        };
+        let px0 = px0 & !7;
+        let px1 = ((px1 + 7) & !7).min(w);
+        Some(Rect::new(px0, py0, px1 - px0, py1 - py0))
+    }
+
     pub fn set_pixel(&mut self, x: i32, y: i32, color: BinaryColor) {
         let size = self.size();
         if x < 0 || y < 0 || x as u32 >= size.width || y as u32 >= size.height {
             return;
         }
-        let (x, y) = match self.rotation {
+        let (x, y) = match self.buffer_rotation() {
             Rotation::Rotate0 => (x as usize, y as usize),
             Rotation::Rotate90 => (y as usize, HEIGHT - 1 - x as usize),
             Rotation::Rotate180 => (WIDTH - 1 - x as usize, HEIGHT - 1 - y as usize),
@@ -109,6 +167,16 @@ impl DisplayBuffers {
     }
 }
 
+/// Row slices of `buffer` covered by a byte-aligned physical region, top to
+/// bottom, as returned by [`DisplayBuffers::physical_region`].
+pub fn region_rows(buffer: &[u8; BUFFER_SIZE], region: Rect) -> impl Iterator<Item = &[u8]> {
+    let stride = WIDTH / 8;
+    let start = region.x as usize / 8;
+    let len = region.w as usize / 8;
+    (region.y as usize..(region.y + region.h) as usize)
+        .map(move |row| &buffer[row * stride + start..row * stride + start + len])
+}
+
 impl OriginDimensions for DisplayBuffers {
     fn size(&self) -> Size {
         match self.rotation {
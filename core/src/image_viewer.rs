@@ -1,6 +1,6 @@
 extern crate alloc;
 
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -13,6 +13,9 @@ pub enum EntryKind {
 pub struct ImageEntry {
     pub name: String,
     pub kind: EntryKind,
+    /// Modification time as a sort key (larger is newer). Units are up to the
+    /// source; `None` when the source cannot provide it.
+    pub modified: Option<u64>,
 }
 
 #[derive(Clone, Debug)]
@@ -27,6 +30,164 @@ pub enum ImageData {
         height: u32,
         bits: Vec<u8>, // 1-bit packed, row-major, MSB first
     },
+    Gray2 {
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>, // 2-bit packed (0 = black, 3 = white), row-major, MSB first
+    },
+}
+
+/// Luminance of pixel `idx` in a packed Gray2 buffer, spread over 0..=255.
+pub fn gray2_luma(pixels: &[u8], idx: usize) -> Option<u8> {
+    let byte = pixels.get(idx / 4)?;
+    let level = (byte >> (6 - (idx % 4) * 2)) & 0x03;
+    Some(level * 85)
+}
+
+impl ImageData {
+    /// The image turned a quarter turn clockwise.
+    pub fn rotated_cw(&self) -> ImageData {
+        match self {
+            ImageData::Gray8 {
+                width,
+                height,
+                pixels,
+            } => {
+                let (w, h) = (*width as usize, *height as usize);
+                let mut out = Vec::with_capacity(w * h);
+                for y in 0..w {
+                    for x in 0..h {
+                        out.push(pixels.get((h - 1 - x) * w + y).copied().unwrap_or(0xFF));
+                    }
+                }
+                ImageData::Gray8 {
+                    width: *height,
+                    height: *width,
+                    pixels: out,
+                }
+            }
+            ImageData::Mono1 {
+                width,
+                height,
+                bits,
+            } => {
+                let (w, h) = (*width as usize, *height as usize);
+                let mut out = alloc::vec![0u8; (w * h).div_ceil(8)];
+                for y in 0..w {
+                    for x in 0..h {
+                        let src = (h - 1 - x) * w + y;
+                        let white = bits
+                            .get(src / 8)
+                            .is_none_or(|byte| (byte >> (7 - src % 8)) & 0x01 == 1);
+                        if white {
+                            let dst = y * h + x;
+                            out[dst / 8] |= 0x80 >> (dst % 8);
+                        }
+                    }
+                }
+                ImageData::Mono1 {
+                    width: *height,
+                    height: *width,
+                    bits: out,
+                }
+            }
+            ImageData::Gray2 {
+                width,
+                height,
+                pixels,
+            } => {
+                let (w, h) = (*width as usize, *height as usize);
+                let mut out = alloc::vec![0u8; (w * h).div_ceil(4)];
+                for y in 0..w {
+                    for x in 0..h {
+                        let lum = gray2_luma(pixels, (h - 1 - x) * w + y).unwrap_or(0xFF);
+                        let dst = y * h + x;
+                        out[dst / 4] |= (lum / 85) << (6 - (dst % 4) * 2);
+                    }
+                }
+                ImageData::Gray2 {
+                    width: *height,
+                    height: *width,
+                    pixels: out,
+                }
+            }
+        }
+    }
+}
+
+/// Longest side of the menu preview thumbnails, in pixels.
+pub const THUMBNAIL_MAX: u32 = 120;
+
+/// Size of a `width` x `height` image fitted inside a `max` square, keeping
+/// the aspect ratio. Small images are not enlarged.
+pub fn thumbnail_size(width: u32, height: u32, max: u32) -> (u32, u32) {
+    if width <= max && height <= max {
+        return (width.max(1), height.max(1));
+    }
+    if width >= height {
+        (max, (height as u64 * max as u64 / width as u64).max(1) as u32)
+    } else {
+        ((width as u64 * max as u64 / height as u64).max(1) as u32, max)
+    }
+}
+
+/// Mono thumbnail fitted inside a `max` square. Gray images are dithered.
+pub fn fit_thumbnail(image: &ImageData, max: u32) -> Option<ImageData> {
+    const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+    let (src_w, src_h) = match image {
+        ImageData::Mono1 { width, height, .. } => (*width, *height),
+        ImageData::Gray8 { width, height, .. } => (*width, *height),
+        ImageData::Gray2 { width, height, .. } => (*width, *height),
+    };
+    if src_w == 0 || src_h == 0 {
+        return None;
+    }
+    let (dst_w, dst_h) = thumbnail_size(src_w, src_h, max);
+    let mut bits = alloc::vec![0u8; (dst_w as usize * dst_h as usize).div_ceil(8)];
+    for y in 0..dst_h {
+        let sy = (y as u64 * src_h as u64 / dst_h as u64) as usize;
+        for x in 0..dst_w {
+            let sx = (x as u64 * src_w as u64 / dst_w as u64) as usize;
+            let src = sy * src_w as usize + sx;
+            let white = match image {
+                ImageData::Mono1 { bits, .. } => bits
+                    .get(src / 8)
+                    .is_none_or(|byte| (byte >> (7 - src % 8)) & 0x01 == 1),
+                ImageData::Gray8 { pixels, .. } => {
+                    let threshold = BAYER[y as usize & 3][x as usize & 3] * 16 + 8;
+                    pixels.get(src).copied().unwrap_or(0xFF) >= threshold
+                }
+                ImageData::Gray2 { pixels, .. } => {
+                    let threshold = BAYER[y as usize & 3][x as usize & 3] * 16 + 8;
+                    gray2_luma(pixels, src).unwrap_or(0xFF) >= threshold
+                }
+            };
+            if white {
+                let dst = y as usize * dst_w as usize + x as usize;
+                bits[dst / 8] |= 0x80 >> (dst % 8);
+            }
+        }
+    }
+    Some(ImageData::Mono1 {
+        width: dst_w,
+        height: dst_h,
+        bits,
+    })
+}
+
+/// Cache key for an entry's menu thumbnail. The modification time is part of
+/// the key so a replaced file gets a fresh preview.
+pub fn thumbnail_key(path: &[String], entry: &ImageEntry) -> String {
+    let mut key = String::from("menu:");
+    for part in path {
+        key.push_str(part);
+        key.push('/');
+    }
+    key.push_str(&entry.name);
+    if let Some(modified) = entry.modified {
+        key.push_str(&alloc::format!(":{}", modified));
+    }
+    key
 }
 
 #[derive(Clone, Debug)]
@@ -37,6 +198,175 @@ pub enum ImageError {
     Message(String),
 }
 
+/// Entry to reopen on boot, plus the page to restore when it is a book.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResumeState {
+    pub name: String,
+    pub page: Option<usize>,
+}
+
+impl ResumeState {
+    pub fn new(name: &str, page: Option<usize>) -> Self {
+        Self {
+            name: name.to_string(),
+            page,
+        }
+    }
+
+    /// Serializes as the entry path, optionally followed by a newline and the page index.
+    pub fn to_record(&self) -> String {
+        match self.page {
+            Some(page) => alloc::format!("{}\n{}", self.name, page),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Parses a resume record; name-only records from older builds are still accepted.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut lines = raw.lines();
+        let name = lines.next()?.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let page = lines
+            .next()
+            .and_then(|line| line.trim().parse::<usize>().ok());
+        Some(Self::new(name, page))
+    }
+}
+
+/// User settings kept in `.trusty_settings`. Missing keys keep their
+/// defaults, so older files and hand-edited ones still load.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// Inactivity before the device sleeps; 0 never sleeps.
+    pub idle_timeout_ms: u32,
+    /// List files newest first instead of by name.
+    pub sort_newest: bool,
+    /// Time each image stays up in the slideshow.
+    pub slideshow_interval_ms: u32,
+    /// Book page turns between full panel refreshes.
+    pub full_refresh_every: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            idle_timeout_ms: 300_000,
+            sort_newest: false,
+            slideshow_interval_ms: 10_000,
+            full_refresh_every: 10,
+        }
+    }
+}
+
+impl Settings {
+    /// Serializes as `key=value` lines.
+    pub fn to_record(&self) -> String {
+        alloc::format!(
+            "idle_timeout_ms={}\nsort={}\nslideshow_interval_ms={}\nfull_refresh_every={}\n",
+            self.idle_timeout_ms,
+            if self.sort_newest { "newest" } else { "name" },
+            self.slideshow_interval_ms,
+            self.full_refresh_every
+        )
+    }
+
+    /// Parses `key=value` lines; unknown keys and bad values are skipped.
+    pub fn parse(raw: &str) -> Self {
+        let mut settings = Self::default();
+        for line in raw.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "idle_timeout_ms" => {
+                    if let Ok(ms) = value.parse() {
+                        settings.idle_timeout_ms = ms;
+                    }
+                }
+                "sort" => settings.sort_newest = value == "newest",
+                "slideshow_interval_ms" => {
+                    if let Ok(ms) = value.parse() {
+                        settings.slideshow_interval_ms = ms;
+                    }
+                }
+                "full_refresh_every" => {
+                    if let Ok(pages) = value.parse() {
+                        settings.full_refresh_every = pages;
+                    }
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+}
+
+/// Book details from an `<stem>.epubinfo` sidecar, written by
+/// `trusty-book --epubinfo` so the device can describe an EPUB it can't parse.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpubInfo {
+    pub title: String,
+    pub author: String,
+    pub language: String,
+    pub chapters: usize,
+    pub words: u32,
+    /// FNV-1a of the EPUB, as stored at 0x24 in each converted book.
+    pub source_hash: u32,
+    /// Converted copies as (file name, page count), next to the sidecar.
+    pub books: Vec<(String, usize)>,
+}
+
+impl EpubInfo {
+    /// Serializes as `key=value` lines, one `trbk=<pages> <name>` per copy.
+    pub fn to_record(&self) -> String {
+        let mut record = alloc::format!(
+            "title={}\nauthor={}\nlanguage={}\nchapters={}\nwords={}\nsource_hash={}\n",
+            self.title,
+            self.author,
+            self.language,
+            self.chapters,
+            self.words,
+            self.source_hash
+        );
+        for (name, pages) in &self.books {
+            record.push_str(&alloc::format!("trbk={} {}\n", pages, name));
+        }
+        record
+    }
+
+    /// Parses `key=value` lines; unknown keys and bad values are skipped.
+    pub fn parse(raw: &str) -> Self {
+        let mut info = Self::default();
+        for line in raw.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "title" => info.title = value.to_string(),
+                "author" => info.author = value.to_string(),
+                "language" => info.language = value.to_string(),
+                "chapters" => info.chapters = value.parse().unwrap_or(0),
+                "words" => info.words = value.parse().unwrap_or(0),
+                "source_hash" => info.source_hash = value.parse().unwrap_or(0),
+                "trbk" => {
+                    let book = value.split_once(' ').and_then(|(pages, name)| {
+                        Some((name.trim().to_string(), pages.parse().ok()?))
+                    });
+                    if let Some(book) = book {
+                        info.books.push(book);
+                    }
+                }
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
 pub trait ImageSource {
     fn refresh(&mut self, path: &[String]) -> Result<Vec<ImageEntry>, ImageError>;
     fn load(&mut self, path: &[String], entry: &ImageEntry) -> Result<ImageData, ImageError>;
@@ -61,20 +391,79 @@ pub trait ImageSource {
         Err(ImageError::Unsupported)
     }
     fn close_trbk(&mut self) {}
+    /// Reads the `.epubinfo` sidecar next to an EPUB. `Unsupported` when
+    /// there is none.
+    fn epub_info(&mut self, _path: &[String], _entry: &ImageEntry) -> Result<EpubInfo, ImageError> {
+        Err(ImageError::Unsupported)
+    }
+    /// Title, author and opening text of an EPUB for a read-only preview.
+    /// Sources that can't unpack EPUBs report `Unsupported`, and the app
+    /// then asks for the file to be converted.
+    fn epub_preview_text(
+        &mut self,
+        _path: &[String],
+        _entry: &ImageEntry,
+    ) -> Result<String, ImageError> {
+        Err(ImageError::Unsupported)
+    }
+    /// Saves the EPUB's text as `<stem>.txt` beside it and returns the new
+    /// file's name. `Unsupported` where EPUBs can't be unpacked.
+    fn export_epub_text(
+        &mut self,
+        _path: &[String],
+        _entry: &ImageEntry,
+    ) -> Result<String, ImageError> {
+        Err(ImageError::Unsupported)
+    }
+    /// Decodes a page into the source's page cache without returning it, so
+    /// a later `trbk_page` is quick. Called between frames while the reader
+    /// is idle; sources without a cache leave it as a no-op.
+    fn prefetch_trbk_page(&mut self, _page_index: usize) {}
+    /// Indices of the open book's pages in `pages` whose text contains
+    /// `query`, ignoring case. The default reads each page through
+    /// `trbk_page`; sources with a page cache should read around it.
+    fn trbk_search(&mut self, query: &str, pages: core::ops::Range<usize>) -> Vec<usize> {
+        pages
+            .filter(|&page| {
+                self.trbk_page(page)
+                    .is_ok_and(|ops| ops.contains_text(query))
+            })
+            .collect()
+    }
+    /// Removes a file. Directories are refused with `Unsupported`.
+    fn delete(&mut self, _path: &[String], _entry: &ImageEntry) -> Result<(), ImageError> {
+        Err(ImageError::Unsupported)
+    }
     fn sleep(&mut self) {}
     fn wake(&mut self) {}
-    fn save_resume(&mut self, _name: Option<&str>) {}
-    fn load_resume(&mut self) -> Option<String> {
+    fn save_resume(&mut self, _state: Option<&ResumeState>) {}
+    fn load_resume(&mut self) -> Option<ResumeState> {
         None
     }
     fn save_book_positions(&mut self, _entries: &[(String, usize)]) {}
     fn load_book_positions(&mut self) -> Vec<(String, usize)> {
         Vec::new()
     }
+    fn save_settings(&mut self, _settings: &Settings) {}
+    fn load_settings(&mut self) -> Option<Settings> {
+        None
+    }
     fn save_recent_entries(&mut self, _entries: &[String]) {}
     fn load_recent_entries(&mut self) -> Vec<String> {
         Vec::new()
     }
+    /// Small mono preview of an image entry for the file menu. The default
+    /// decodes the image once and keeps the result in the thumbnail cache.
+    fn thumbnail(&mut self, path: &[String], entry: &ImageEntry) -> Option<ImageData> {
+        let key = thumbnail_key(path, entry);
+        if let Some(thumb) = self.load_thumbnail(&key) {
+            return Some(thumb);
+        }
+        let image = self.load(path, entry).ok()?;
+        let thumb = fit_thumbnail(&image, THUMBNAIL_MAX)?;
+        self.save_thumbnail(&key, &thumb);
+        Some(thumb)
+    }
     fn load_thumbnail(&mut self, _key: &str) -> Option<ImageData> {
         None
     }
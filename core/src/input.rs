@@ -10,16 +10,60 @@ pub enum Buttons {
     Power,
 }
 
+const BUTTON_COUNT: usize = 7;
+
+/// How long a button must be held before `is_long_press` fires.
+pub const LONG_PRESS_MS: u32 = 600;
+const REPEAT_DELAY_MS: u32 = 400;
+const REPEAT_INTERVAL_MS: u32 = 150;
+const REPEAT_FAST_INTERVAL_MS: u32 = 50;
+const REPEAT_FAST_AFTER: u8 = 8;
+
 #[derive(Clone, Copy, Default)]
 pub struct ButtonState {
     current: u8,
     previous: u8,
+    held_ms: [u32; BUTTON_COUNT],
+    next_repeat_ms: [u32; BUTTON_COUNT],
+    repeat_count: [u8; BUTTON_COUNT],
+    long_press: u8,
+    repeat: u8,
 }
 
 impl ButtonState {
-    pub fn update(&mut self, current: u8) {
+    /// Takes the raw button bits and the time since the previous update, which
+    /// drives hold durations for long-press and auto-repeat.
+    pub fn update(&mut self, current: u8, elapsed_ms: u32) {
         self.previous = self.current;
         self.current = current;
+        self.long_press = 0;
+        self.repeat = 0;
+        let held = self.held();
+        for index in 0..BUTTON_COUNT {
+            let mask = 1 << index;
+            if held & mask == 0 {
+                self.held_ms[index] = 0;
+                self.next_repeat_ms[index] = REPEAT_DELAY_MS;
+                self.repeat_count[index] = 0;
+                continue;
+            }
+            let before = self.held_ms[index];
+            let after = before.saturating_add(elapsed_ms);
+            self.held_ms[index] = after;
+            if before < LONG_PRESS_MS && after >= LONG_PRESS_MS {
+                self.long_press |= mask;
+            }
+            if after >= self.next_repeat_ms[index] {
+                self.repeat |= mask;
+                self.repeat_count[index] = self.repeat_count[index].saturating_add(1);
+                let interval = if self.repeat_count[index] >= REPEAT_FAST_AFTER {
+                    REPEAT_FAST_INTERVAL_MS
+                } else {
+                    REPEAT_INTERVAL_MS
+                };
+                self.next_repeat_ms[index] = after.saturating_add(interval);
+            }
+        }
     }
 
     fn held(&self) -> u8 {
@@ -48,4 +92,23 @@ impl ButtonState {
         let mask = 1 << (button as u8);
         (self.released() & mask) != 0
     }
+
+    /// True on the one update where `button` has been held past
+    /// `LONG_PRESS_MS`.
+    pub fn is_long_press(&self, button: Buttons) -> bool {
+        let mask = 1 << (button as u8);
+        (self.long_press & mask) != 0
+    }
+
+    /// True on each auto-repeat tick while `button` is held: once after a
+    /// short delay, then at an interval that speeds up after a few repeats.
+    pub fn is_repeat(&self, button: Buttons) -> bool {
+        let mask = 1 << (button as u8);
+        (self.repeat & mask) != 0
+    }
+
+    /// How long `button` has been held, or 0 if it is not down.
+    pub fn held_ms(&self, button: Buttons) -> u32 {
+        self.held_ms[button as usize]
+    }
 }
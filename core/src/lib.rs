@@ -1,6 +1,8 @@
 #![no_std]
 
 pub mod application;
+pub mod battery;
+pub mod clock;
 pub mod display;
 pub mod framebuffer;
 pub mod image_viewer;
@@ -5,6 +5,32 @@ use alloc::vec::Vec;
 
 use crate::image_viewer::ImageError;
 
+/// Mask for the base style (regular/bold/italic/bold-italic) in a glyph or
+/// TextRun style byte. The remaining bits hold the heading size bucket.
+pub const TRBK_STYLE_MASK: u8 = 0x03;
+
+/// Style bit for the monospace face used by code spans. It sits above the
+/// heading size bucket, so readers that only know the mask fall back to the
+/// regular glyph.
+pub const TRBK_STYLE_MONO: u8 = 0x10;
+
+/// Header flag (byte 0x05): glyph bitmaps hold two bitplanes, MSB plane first,
+/// giving four levels from white (0) to black (3).
+pub const TRBK_FLAG_GRAY_GLYPHS: u8 = 0x01;
+
+/// Cover image index written when the book has no cover.
+pub const TRBK_NO_COVER: u16 = u16::MAX;
+
+/// Fixed header size before the metadata block; v5 adds the kerning table
+/// offset at 0x30.
+pub fn trbk_fixed_header_size(version: u8) -> usize {
+    match version {
+        1 => 0x2C,
+        2..=4 => 0x30,
+        _ => 0x34,
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct TrbkMetadata {
     pub title: String,
@@ -28,9 +54,16 @@ pub struct TrbkBook {
     pub pages: Vec<TrbkPage>,
     pub metadata: TrbkMetadata,
     pub glyphs: Vec<TrbkGlyph>,
+    pub gray_glyphs: bool,
     pub page_count: usize,
     pub toc: Vec<TrbkTocEntry>,
     pub images: Vec<TrbkImageInfo>,
+    pub kerning: Vec<TrbkKernPair>,
+    /// First page of each chapter, ascending; empty when the file predates
+    /// chapter markers.
+    pub chapter_starts: Vec<u32>,
+    /// Index into `images` of the book's cover, if it declares one.
+    pub cover_image: Option<u16>,
 }
 
 #[derive(Clone, Debug)]
@@ -40,8 +73,15 @@ pub struct TrbkBookInfo {
     pub page_count: usize,
     pub metadata: TrbkMetadata,
     pub glyphs: Vec<TrbkGlyph>,
+    pub gray_glyphs: bool,
     pub toc: Vec<TrbkTocEntry>,
     pub images: Vec<TrbkImageInfo>,
+    pub kerning: Vec<TrbkKernPair>,
+    /// First page of each chapter, ascending; empty when the file predates
+    /// chapter markers.
+    pub chapter_starts: Vec<u32>,
+    /// Index into `images` of the book's cover, if it declares one.
+    pub cover_image: Option<u16>,
 }
 
 #[derive(Clone, Debug)]
@@ -59,6 +99,8 @@ pub enum TrbkOp {
         height: u16,
         image_index: u16,
     },
+    /// Scene-break rule `width` pixels long starting at `x`, centred on `y`.
+    HRule { x: i32, y: i32, width: u16 },
 }
 
 #[derive(Clone, Debug)]
@@ -73,11 +115,31 @@ pub struct TrbkGlyph {
     pub bitmap: Vec<u8>,
 }
 
+impl TrbkGlyph {
+    /// Bytes in one bitplane; gray glyphs store two planes back to back.
+    pub fn plane_len(&self) -> usize {
+        (self.width as usize * self.height as usize).div_ceil(8)
+    }
+}
+
+/// Pen adjustment between two glyphs of one style, sorted by
+/// `(style, left, right)` so lookups can binary search.
+#[derive(Clone, Copy, Debug)]
+pub struct TrbkKernPair {
+    pub style: u8,
+    pub left: u32,
+    pub right: u32,
+    pub adjust: i16,
+}
+
 #[derive(Clone, Debug)]
 pub struct TrbkTocEntry {
     pub title: String,
     pub page_index: u32,
     pub level: u8,
+    /// Pixels below the top margin of `page_index` where the entry starts
+    /// (v4+, else 0).
+    pub y_offset: u16,
 }
 
 #[derive(Clone, Debug)]
@@ -94,9 +156,10 @@ pub fn parse_trbk(data: &[u8]) -> Result<TrbkBook, ImageError> {
     }
 
     let version = data[4];
-    if version != 1 && version != 2 {
+    if !(1..=5).contains(&version) {
         return Err(ImageError::Unsupported);
     }
+    let gray_glyphs = version >= 2 && data[5] & TRBK_FLAG_GRAY_GLYPHS != 0;
 
     let header_size = read_u16(data, 0x06)? as usize;
     let screen_width = read_u16(data, 0x08)?;
@@ -116,6 +179,11 @@ pub fn parse_trbk(data: &[u8]) -> Result<TrbkBook, ImageError> {
     } else {
         (0usize, 0usize)
     };
+    let kerning_offset = if version >= 5 {
+        read_u32(data, 0x30)? as usize
+    } else {
+        0
+    };
 
     if data.len() < header_size || toc_offset != header_size {
         return Err(ImageError::Decode);
@@ -127,7 +195,7 @@ pub fn parse_trbk(data: &[u8]) -> Result<TrbkBook, ImageError> {
         return Err(ImageError::Decode);
     }
 
-    let mut cursor = if version >= 2 { 0x30 } else { 0x2C };
+    let mut cursor = trbk_fixed_header_size(version);
     let title = read_string(data, &mut cursor)?;
     let author = read_string(data, &mut cursor)?;
     let language = read_string(data, &mut cursor)?;
@@ -155,15 +223,16 @@ pub fn parse_trbk(data: &[u8]) -> Result<TrbkBook, ImageError> {
     if cursor > data.len() || cursor > header_size {
         return Err(ImageError::Decode);
     }
-
-    let toc = if toc_count > 0 {
-        parse_trbk_toc(data, toc_offset as usize, toc_count)?
+    let chapter_starts = parse_trbk_chapter_starts(&data[..header_size], cursor);
+    let images = if images_offset > 0 {
+        parse_trbk_images(data, images_offset)?
     } else {
         Vec::new()
     };
+    let cover_image = parse_trbk_cover_image(&data[..header_size], cursor, images.len());
 
-    let images = if images_offset > 0 {
-        parse_trbk_images(data, images_offset)?
+    let toc = if toc_count > 0 {
+        parse_trbk_toc(data, toc_offset as usize, toc_count, version)?
     } else {
         Vec::new()
     };
@@ -197,7 +266,13 @@ pub fn parse_trbk(data: &[u8]) -> Result<TrbkBook, ImageError> {
     }
 
     let glyphs = if version >= 2 && glyph_count > 0 {
-        parse_glyphs(data, glyph_table_offset, glyph_count)?
+        parse_glyphs(data, glyph_table_offset, glyph_count, version)?
+    } else {
+        Vec::new()
+    };
+
+    let kerning = if kerning_offset > 0 {
+        parse_kerning(data, kerning_offset)?
     } else {
         Vec::new()
     };
@@ -221,12 +296,49 @@ pub fn parse_trbk(data: &[u8]) -> Result<TrbkBook, ImageError> {
             margin_bottom,
         },
         glyphs,
+        gray_glyphs,
         page_count,
         toc,
         images,
+        kerning,
+        chapter_starts,
+        cover_image,
     })
 }
 
+impl TrbkPage {
+    /// Whether the page's text contains `needle`, ignoring case. Lines of
+    /// [`page_text`] are joined with a space.
+    pub fn contains_text(&self, needle: &str) -> bool {
+        if needle.is_empty() {
+            return false;
+        }
+        page_text(self)
+            .replace('\n', " ")
+            .to_lowercase()
+            .contains(&needle.to_lowercase())
+    }
+}
+
+/// Text of a page in drawing order, one line per baseline. Runs on a line
+/// are joined as stored, since word gaps are runs of their own. The baseline
+/// rather than x marks a new line so right-to-left pages, drawn leftwards,
+/// still come out a line at a time.
+pub fn page_text(page: &TrbkPage) -> String {
+    let mut text = String::new();
+    let mut last_y = None;
+    for op in &page.ops {
+        if let TrbkOp::TextRun { y, text: run, .. } = op {
+            if last_y.is_some_and(|prev| prev != *y) {
+                text.push('\n');
+            }
+            text.push_str(run);
+            last_y = Some(*y);
+        }
+    }
+    text
+}
+
 impl TrbkBook {
     pub fn info(&self) -> TrbkBookInfo {
         TrbkBookInfo {
@@ -235,16 +347,100 @@ impl TrbkBook {
             page_count: self.page_count,
             metadata: self.metadata.clone(),
             glyphs: self.glyphs.clone(),
+            gray_glyphs: self.gray_glyphs,
             toc: self.toc.clone(),
             images: self.images.clone(),
+            kerning: self.kerning.clone(),
+            chapter_starts: self.chapter_starts.clone(),
+            cover_image: self.cover_image,
         }
     }
 }
 
+impl TrbkBookInfo {
+    /// Extra pen advance between `left` and `right` drawn in `style`.
+    pub fn kerning_adjust(&self, style: u8, left: char, right: char) -> i16 {
+        let key = (style, left as u32, right as u32);
+        self.kerning
+            .binary_search_by(|pair| (pair.style, pair.left, pair.right).cmp(&key))
+            .map(|idx| self.kerning[idx].adjust)
+            .unwrap_or(0)
+    }
+
+    /// 1-based chapter containing `page` and the chapter count, or `None`
+    /// without chapter markers or before the first chapter.
+    pub fn chapter_at(&self, page: usize) -> Option<(usize, usize)> {
+        let index = self
+            .chapter_starts
+            .partition_point(|start| *start as usize <= page);
+        (index > 0).then_some((index, self.chapter_starts.len()))
+    }
+}
+
+/// Chapter markers that follow the margins in the metadata block: u32 count,
+/// then one u32 start page per chapter. Older files end at the margins, so a
+/// missing or short list reads as empty.
+pub fn parse_trbk_chapter_starts(header: &[u8], cursor: usize) -> Vec<u32> {
+    let Ok(count) = read_u32(header, cursor) else {
+        return Vec::new();
+    };
+    let start = cursor + 4;
+    let Some(end) = (count as usize)
+        .checked_mul(4)
+        .and_then(|len| start.checked_add(len))
+        .filter(|end| *end <= header.len())
+    else {
+        return Vec::new();
+    };
+    (start..end)
+        .step_by(4)
+        .filter_map(|pos| read_u32(header, pos).ok())
+        .collect()
+}
+
+/// Cover image index that follows the chapter markers (whose list starts at
+/// `cursor`), or `None` when the file has no cover or predates the field.
+/// Indices outside the `image_count` embedded images are ignored.
+pub fn parse_trbk_cover_image(header: &[u8], cursor: usize, image_count: usize) -> Option<u16> {
+    let count = read_u32(header, cursor).ok()? as usize;
+    let pos = count.checked_mul(4)?.checked_add(cursor + 4)?;
+    let index = read_u16(header, pos).ok()?;
+    (index != TRBK_NO_COVER && (index as usize) < image_count).then_some(index)
+}
+
+/// Kerning table: u32 count, then 12-byte entries of left (u32), right (u32),
+/// style (u8), reserved (u8) and adjust (i16).
+pub fn parse_kerning_entry(entry: &[u8]) -> TrbkKernPair {
+    TrbkKernPair {
+        left: u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]),
+        right: u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]),
+        style: entry[8],
+        adjust: i16::from_le_bytes([entry[10], entry[11]]),
+    }
+}
+
+fn parse_kerning(data: &[u8], offset: usize) -> Result<Vec<TrbkKernPair>, ImageError> {
+    let count = read_u32(data, offset)? as usize;
+    let start = offset + 4;
+    // The count comes from the file; check it against what is left before
+    // trusting it, since `count * 12` can overflow a 32-bit usize.
+    let fits = count
+        .checked_mul(12)
+        .and_then(|len| start.checked_add(len))
+        .is_some_and(|end| end <= data.len());
+    if !fits {
+        return Err(ImageError::Decode);
+    }
+    Ok((0..count)
+        .map(|idx| parse_kerning_entry(&data[start + idx * 12..start + idx * 12 + 12]))
+        .collect())
+}
+
 fn parse_trbk_toc(
     data: &[u8],
     offset: usize,
     count: usize,
+    version: u8,
 ) -> Result<Vec<TrbkTocEntry>, ImageError> {
     if offset > data.len() {
         return Err(ImageError::Decode);
@@ -261,11 +457,17 @@ fn parse_trbk_toc(
         let level = data[cursor];
         cursor += 1;
         cursor += 1; // reserved
-        cursor += 2; // reserved
+        let y_offset = if version >= 4 {
+            u16::from_le_bytes([data[cursor], data[cursor + 1]])
+        } else {
+            0
+        };
+        cursor += 2;
         entries.push(TrbkTocEntry {
             title,
             page_index,
             level,
+            y_offset,
         });
     }
     Ok(entries)
@@ -314,6 +516,15 @@ pub fn parse_trbk_page_ops(data: &[u8]) -> Result<Vec<TrbkOp>, ImageError> {
                     image_index,
                 });
             }
+            0x04 => {
+                if payload.len() < 6 {
+                    return Err(ImageError::Decode);
+                }
+                let x = u16::from_le_bytes([payload[0], payload[1]]) as i32;
+                let y = u16::from_le_bytes([payload[2], payload[3]]) as i32;
+                let width = u16::from_le_bytes([payload[4], payload[5]]);
+                ops.push(TrbkOp::HRule { x, y, width });
+            }
             _ => {
                 // Ignore unknown ops for forward compatibility.
             }
@@ -414,16 +625,23 @@ fn read_string(data: &[u8], cursor: &mut usize) -> Result<String, ImageError> {
     Ok(value)
 }
 
+/// Version 2 stores each glyph bitmap inline after its entry. Version 3 replaces
+/// the inline bitmap with an index into a shared bitmap pool that follows the
+/// entries (u32 count, then u32 length + bytes per bitmap).
 fn parse_glyphs(
     data: &[u8],
     offset: usize,
     count: usize,
+    version: u8,
 ) -> Result<Vec<TrbkGlyph>, ImageError> {
     if offset > data.len() {
         return Err(ImageError::Decode);
     }
     let mut cursor = offset;
-    let mut glyphs = Vec::with_capacity(count);
+    // Counts come from the file, so cap the reservations by the bytes that
+    // could actually hold the entries (17 bytes each, 4 per pooled bitmap).
+    let mut glyphs = Vec::with_capacity(count.min((data.len() - offset) / 17));
+    let mut bitmap_refs = Vec::new();
     for _ in 0..count {
         if cursor + 4 + 1 + 1 + 1 + 2 + 2 + 2 + 4 > data.len() {
             return Err(ImageError::Decode);
@@ -442,13 +660,20 @@ fn parse_glyphs(
         cursor += 2;
         let y_offset = i16::from_le_bytes([data[cursor], data[cursor + 1]]);
         cursor += 2;
-        let bitmap_len = read_u32(data, cursor)? as usize;
-        cursor += 4;
-        if cursor + bitmap_len > data.len() {
-            return Err(ImageError::Decode);
-        }
-        let bitmap = data[cursor..cursor + bitmap_len].to_vec();
-        cursor += bitmap_len;
+        let bitmap = if version >= 3 {
+            bitmap_refs.push(read_u32(data, cursor)? as usize);
+            cursor += 4;
+            Vec::new()
+        } else {
+            let bitmap_len = read_u32(data, cursor)? as usize;
+            cursor += 4;
+            if bitmap_len > data.len() - cursor {
+                return Err(ImageError::Decode);
+            }
+            let bitmap = data[cursor..cursor + bitmap_len].to_vec();
+            cursor += bitmap_len;
+            bitmap
+        };
         glyphs.push(TrbkGlyph {
             codepoint,
             style,
@@ -460,5 +685,57 @@ fn parse_glyphs(
             bitmap,
         });
     }
+
+    if version >= 3 {
+        let bitmap_count = read_u32(data, cursor)? as usize;
+        cursor += 4;
+        let mut bitmaps = Vec::with_capacity(bitmap_count.min((data.len() - cursor) / 4));
+        for _ in 0..bitmap_count {
+            let bitmap_len = read_u32(data, cursor)? as usize;
+            cursor += 4;
+            if bitmap_len > data.len() - cursor {
+                return Err(ImageError::Decode);
+            }
+            bitmaps.push(&data[cursor..cursor + bitmap_len]);
+            cursor += bitmap_len;
+        }
+        for (glyph, index) in glyphs.iter_mut().zip(bitmap_refs) {
+            let bitmap = bitmaps.get(index).ok_or(ImageError::Decode)?;
+            glyph.bitmap = bitmap.to_vec();
+        }
+    }
     Ok(glyphs)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two chapter markers followed by `cover`.
+    fn header_with_cover(cover: &[u8]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&2u32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&7u32.to_le_bytes());
+        header.extend_from_slice(cover);
+        header
+    }
+
+    #[test]
+    fn cover_image_follows_chapter_starts() {
+        let header = header_with_cover(&3u16.to_le_bytes());
+        assert_eq!(parse_trbk_chapter_starts(&header, 0), [0, 7]);
+        assert_eq!(parse_trbk_cover_image(&header, 0, 4), Some(3));
+    }
+
+    #[test]
+    fn cover_image_is_none_when_absent_or_invalid() {
+        let header = header_with_cover(&TRBK_NO_COVER.to_le_bytes());
+        assert_eq!(parse_trbk_cover_image(&header, 0, 4), None);
+        // Older files end after the chapter starts.
+        assert_eq!(parse_trbk_cover_image(&header_with_cover(&[]), 0, 4), None);
+        // An index past the image table is ignored.
+        let header = header_with_cover(&4u16.to_le_bytes());
+        assert_eq!(parse_trbk_cover_image(&header, 0, 4), None);
+    }
+}
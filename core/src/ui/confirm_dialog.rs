@@ -0,0 +1,169 @@
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use embedded_graphics::{
+    geometry::Size,
+    mono_font::{ascii::FONT_10X20, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    prelude::{Point, Primitive},
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+    Drawable,
+};
+
+use super::geom::Rect;
+use super::text_view::wrap_text;
+use super::view::{RenderQueue, UiContext, View};
+
+const DIALOG_MARGIN: i32 = 32;
+const DIALOG_PADDING: i32 = 16;
+const DIALOG_BORDER: u32 = 2;
+const DIALOG_MAX_LINES: usize = 4;
+const LINE_HEIGHT: i32 = 24;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 28;
+const CHAR_WIDTH: i32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfirmChoice {
+    Yes,
+    No,
+}
+
+impl ConfirmChoice {
+    pub fn toggle(self) -> Self {
+        match self {
+            ConfirmChoice::Yes => ConfirmChoice::No,
+            ConfirmChoice::No => ConfirmChoice::Yes,
+        }
+    }
+}
+
+/// Yes/No prompt drawn as a centered box over whatever is on screen. Only the
+/// box is pushed to the render queue, so it shows with a fast refresh.
+pub struct ConfirmDialog<'a> {
+    pub message: &'a str,
+    pub yes_label: &'a str,
+    pub no_label: &'a str,
+    pub selected: ConfirmChoice,
+    pub refresh: crate::display::RefreshMode,
+}
+
+impl<'a> ConfirmDialog<'a> {
+    pub fn new(message: &'a str) -> Self {
+        Self {
+            message,
+            yes_label: "Yes",
+            no_label: "No",
+            // Default to the harmless answer.
+            selected: ConfirmChoice::No,
+            refresh: crate::display::RefreshMode::Fast,
+        }
+    }
+
+    /// The box the dialog occupies when centered in `area`.
+    pub fn bounds(&self, area: Rect) -> Rect {
+        let width = (area.w - DIALOG_MARGIN * 2).max(BUTTON_WIDTH * 2 + DIALOG_PADDING * 3);
+        let lines = self.lines(width).len() as i32;
+        let height = DIALOG_PADDING * 3 + lines * LINE_HEIGHT + BUTTON_HEIGHT;
+        Rect::new(
+            area.x + (area.w - width) / 2,
+            area.y + (area.h - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    fn lines(&self, width: i32) -> Vec<&'a str> {
+        let max_chars = ((width - DIALOG_PADDING * 2) / CHAR_WIDTH).max(1) as usize;
+        let mut lines = wrap_text(self.message, max_chars);
+        lines.truncate(DIALOG_MAX_LINES);
+        lines
+    }
+
+    fn draw_button(&self, ctx: &mut UiContext<'_>, x: i32, y: i32, label: &str, selected: bool) {
+        let (fill, text) = if selected {
+            (BinaryColor::Off, BinaryColor::On)
+        } else {
+            (BinaryColor::On, BinaryColor::Off)
+        };
+        Rectangle::new(
+            Point::new(x, y),
+            Size::new(BUTTON_WIDTH as u32, BUTTON_HEIGHT as u32),
+        )
+        .into_styled(PrimitiveStyle::with_fill(fill))
+        .draw(ctx.buffers)
+        .ok();
+        Rectangle::new(
+            Point::new(x, y),
+            Size::new(BUTTON_WIDTH as u32, BUTTON_HEIGHT as u32),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::Off, 1))
+        .draw(ctx.buffers)
+        .ok();
+        let label_w = label.chars().count() as i32 * CHAR_WIDTH;
+        let style = MonoTextStyle::new(&FONT_10X20, text);
+        Text::with_baseline(
+            label,
+            Point::new(
+                x + (BUTTON_WIDTH - label_w) / 2,
+                y + (BUTTON_HEIGHT - 20) / 2,
+            ),
+            style,
+            Baseline::Top,
+        )
+        .draw(ctx.buffers)
+        .ok();
+    }
+}
+
+impl View for ConfirmDialog<'_> {
+    fn render(&mut self, ctx: &mut UiContext<'_>, rect: Rect, rq: &mut RenderQueue) {
+        let bounds = self.bounds(rect);
+        let origin = Point::new(bounds.x, bounds.y);
+        let size = Size::new(bounds.w as u32, bounds.h as u32);
+        Rectangle::new(origin, size)
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(ctx.buffers)
+            .ok();
+        Rectangle::new(origin, size)
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::Off, DIALOG_BORDER))
+            .draw(ctx.buffers)
+            .ok();
+
+        let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::Off);
+        let mut y = bounds.y + DIALOG_PADDING;
+        for line in self.lines(bounds.w) {
+            Text::with_baseline(
+                line,
+                Point::new(bounds.x + DIALOG_PADDING, y),
+                style,
+                Baseline::Top,
+            )
+            .draw(ctx.buffers)
+            .ok();
+            y += LINE_HEIGHT;
+        }
+
+        let button_y = bounds.y + bounds.h - DIALOG_PADDING - BUTTON_HEIGHT;
+        let center = bounds.x + bounds.w / 2;
+        let yes_x = center - DIALOG_PADDING / 2 - BUTTON_WIDTH;
+        let no_x = center + DIALOG_PADDING / 2;
+        self.draw_button(
+            ctx,
+            yes_x,
+            button_y,
+            self.yes_label,
+            self.selected == ConfirmChoice::Yes,
+        );
+        self.draw_button(
+            ctx,
+            no_x,
+            button_y,
+            self.no_label,
+            self.selected == ConfirmChoice::No,
+        );
+        rq.push(bounds, self.refresh);
+    }
+}
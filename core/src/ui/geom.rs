@@ -43,12 +43,19 @@ impl Rect {
         Point::new(self.x + self.w, self.y + self.h)
     }
 
+    /// True when the rect covers no pixels.
+    pub fn is_empty(&self) -> bool {
+        self.w <= 0 || self.h <= 0
+    }
+
     pub fn contains(&self, pt: Point) -> bool {
         pt.x >= self.x && pt.y >= self.y && pt.x < self.x + self.w && pt.y < self.y + self.h
     }
 
     pub fn intersects(&self, other: Rect) -> bool {
-        self.x < other.x + other.w
+        !self.is_empty()
+            && !other.is_empty()
+            && self.x < other.x + other.w
             && self.x + self.w > other.x
             && self.y < other.y + other.h
             && self.y + self.h > other.y
@@ -64,4 +71,89 @@ impl Rect {
         }
         Some(Rect::new(x0, y0, x1 - x0, y1 - y0))
     }
+
+    /// Smallest rect covering both. Empty rects don't stretch the result.
+    pub fn union(&self, other: Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return other;
+        }
+        let x0 = self.x.min(other.x);
+        let y0 = self.y.min(other.y);
+        let x1 = (self.x + self.w).max(other.x + other.w);
+        let y1 = (self.y + self.h).max(other.y + other.h);
+        Rect::new(x0, y0, x1 - x0, y1 - y0)
+    }
+
+    /// Nearest point inside the rect; an empty rect clamps to its origin.
+    pub fn clamp_point(&self, pt: Point) -> Point {
+        if self.is_empty() {
+            return self.min();
+        }
+        Point::new(
+            pt.x.clamp(self.x, self.x + self.w - 1),
+            pt.y.clamp(self.y, self.y + self.h - 1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_excludes_the_far_edges() {
+        let rect = Rect::new(10, 20, 30, 40);
+        assert!(rect.contains(Point::new(10, 20)));
+        assert!(rect.contains(Point::new(39, 59)));
+        assert!(!rect.contains(Point::new(40, 30)));
+        assert!(!rect.contains(Point::new(20, 60)));
+        assert!(!Rect::new(10, 20, 0, 40).contains(Point::new(10, 20)));
+    }
+
+    #[test]
+    fn edge_touching_rects_do_not_intersect() {
+        let left = Rect::new(0, 0, 10, 10);
+        let right = Rect::new(10, 0, 10, 10);
+        let below = Rect::new(0, 10, 10, 10);
+        assert!(!left.intersects(right));
+        assert!(!left.intersects(below));
+        assert_eq!(left.intersection(right), None);
+        assert_eq!(left.intersection(below), None);
+    }
+
+    #[test]
+    fn overlapping_rects_intersect() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(5, 6, 10, 10);
+        assert!(a.intersects(b));
+        assert_eq!(a.intersection(b), Some(Rect::new(5, 6, 5, 4)));
+        assert_eq!(
+            a.intersection(Rect::new(2, 2, 3, 3)),
+            Some(Rect::new(2, 2, 3, 3))
+        );
+    }
+
+    #[test]
+    fn empty_rects_never_intersect() {
+        let a = Rect::new(0, 0, 10, 10);
+        let empty = Rect::new(5, 5, 0, 0);
+        assert!(!a.intersects(empty));
+        assert!(!empty.intersects(a));
+        assert_eq!(a.intersection(empty), None);
+    }
+
+    #[test]
+    fn union_covers_both_and_ignores_empty_rects() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(10, 5, 10, 10);
+        assert_eq!(a.union(b), Rect::new(0, 0, 20, 15));
+        assert_eq!(b.union(a), Rect::new(0, 0, 20, 15));
+
+        let empty = Rect::new(100, 100, 0, 5);
+        assert_eq!(a.union(empty), a);
+        assert_eq!(empty.union(a), a);
+    }
 }
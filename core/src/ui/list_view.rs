@@ -12,6 +12,9 @@ use embedded_graphics::{
 use super::geom::Rect;
 use super::view::{RenderQueue, UiContext, View};
 
+const SCROLLBAR_WIDTH: i32 = 4;
+const SCROLLBAR_MARGIN: i32 = 4;
+
 pub struct ListItem<'a> {
     pub label: &'a str,
 }
@@ -27,6 +30,8 @@ pub struct ListView<'a> {
     pub list_top: i32,
     pub line_height: i32,
     pub clear: bool,
+    /// Draw a scrollbar on the right edge when the items don't all fit.
+    pub scrollbar: bool,
 }
 
 impl<'a> ListView<'a> {
@@ -42,10 +47,45 @@ impl<'a> ListView<'a> {
             list_top: 60,
             line_height: 24,
             clear: true,
+            scrollbar: true,
         }
     }
 }
 
+impl ListView<'_> {
+    fn draw_scrollbar(
+        &self,
+        ctx: &mut UiContext<'_>,
+        rect: Rect,
+        max_lines: usize,
+        start: usize,
+        end: usize,
+    ) {
+        let total = self.items.len() as i32;
+        let track_x = rect.x + rect.w - SCROLLBAR_MARGIN - SCROLLBAR_WIDTH;
+        let track_y = self.list_top - 18;
+        let track_h = max_lines as i32 * self.line_height;
+        let thumb_h = (track_h * (end - start) as i32 / total).max(SCROLLBAR_WIDTH * 2);
+        let thumb_y = track_y + (track_h - thumb_h) * start as i32
+            / (total - max_lines as i32).max(1);
+
+        Rectangle::new(
+            Point::new(track_x + SCROLLBAR_WIDTH / 2, track_y),
+            Size::new(1, track_h as u32),
+        )
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+        .draw(ctx.buffers)
+        .ok();
+        Rectangle::new(
+            Point::new(track_x, thumb_y.min(track_y + track_h - thumb_h)),
+            Size::new(SCROLLBAR_WIDTH as u32, thumb_h as u32),
+        )
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+        .draw(ctx.buffers)
+        .ok();
+    }
+}
+
 impl View for ListView<'_> {
     fn render(&mut self, ctx: &mut UiContext<'_>, rect: Rect, rq: &mut RenderQueue) {
         if self.clear {
@@ -100,6 +140,10 @@ impl View for ListView<'_> {
                         .ok();
                 }
             }
+
+            if self.scrollbar && self.items.len() > max_lines {
+                self.draw_scrollbar(ctx, rect, max_lines, start, end);
+            }
         }
 
         rq.push(rect, crate::display::RefreshMode::Fast);
@@ -1,11 +1,15 @@
+pub mod confirm_dialog;
 pub mod geom;
 pub mod list_view;
+pub mod progress_bar;
 pub mod reader_view;
 pub mod text_view;
 pub mod view;
 
+pub use confirm_dialog::{ConfirmChoice, ConfirmDialog};
 pub use geom::{Point, Rect, Size};
 pub use list_view::{ListItem, ListView};
-pub use reader_view::ReaderView;
-pub use text_view::TextView;
+pub use progress_bar::ProgressBar;
+pub use reader_view::{prefers_grayscale, render_gray_plane, ReaderView};
+pub use text_view::{wrap_text, ScrollTextView, TextView};
 pub use view::{flush_queue, RenderQueue, UiContext, View};
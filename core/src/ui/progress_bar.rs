@@ -0,0 +1,73 @@
+use embedded_graphics::{
+    geometry::Size,
+    pixelcolor::BinaryColor,
+    prelude::{Point, Primitive},
+    primitives::{PrimitiveStyle, Rectangle},
+    Drawable,
+};
+
+use super::geom::Rect;
+use super::view::{RenderQueue, UiContext, View};
+
+/// Horizontal bar: a 1px outline filled from the left by `fraction` (0..1).
+pub struct ProgressBar {
+    pub fraction: f32,
+    pub color: BinaryColor,
+    pub refresh: crate::display::RefreshMode,
+}
+
+impl ProgressBar {
+    pub fn new(fraction: f32) -> Self {
+        Self {
+            fraction,
+            color: BinaryColor::Off,
+            refresh: crate::display::RefreshMode::Fast,
+        }
+    }
+
+    /// Builds a bar for `done` out of `total` steps without going through
+    /// floats at the call site.
+    pub fn from_steps(done: usize, total: usize) -> Self {
+        if total == 0 {
+            return Self::new(0.0);
+        }
+        Self::new(done as f32 / total as f32)
+    }
+
+    /// Width of the filled part for a bar `width` pixels wide.
+    fn filled_width(&self, width: i32) -> i32 {
+        // NaN and negatives fall through to an empty bar.
+        let fraction = if self.fraction > 0.0 {
+            self.fraction.min(1.0)
+        } else {
+            0.0
+        };
+        (width as f32 * fraction) as i32
+    }
+}
+
+impl View for ProgressBar {
+    fn render(&mut self, ctx: &mut UiContext<'_>, rect: Rect, rq: &mut RenderQueue) {
+        if rect.is_empty() {
+            return;
+        }
+        Rectangle::new(
+            Point::new(rect.x, rect.y),
+            Size::new(rect.w as u32, rect.h as u32),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(self.color, 1))
+        .draw(ctx.buffers)
+        .ok();
+        let filled = self.filled_width(rect.w);
+        if filled > 0 {
+            Rectangle::new(
+                Point::new(rect.x, rect.y),
+                Size::new(filled as u32, rect.h as u32),
+            )
+            .into_styled(PrimitiveStyle::with_fill(self.color))
+            .draw(ctx.buffers)
+            .ok();
+        }
+        rq.push(rect, self.refresh);
+    }
+}
@@ -1,7 +1,7 @@
 use embedded_graphics::pixelcolor::BinaryColor;
 use embedded_graphics::prelude::{DrawTarget, OriginDimensions};
 
-use crate::image_viewer::ImageData;
+use crate::image_viewer::{gray2_luma, ImageData};
 
 use super::geom::Rect;
 use super::view::{RenderQueue, UiContext, View};
@@ -9,6 +9,9 @@ use super::view::{RenderQueue, UiContext, View};
 pub struct ReaderView<'a> {
     pub image: &'a ImageData,
     pub refresh: crate::display::RefreshMode,
+    /// Render Gray8 and Gray2 images as the black base layer of a 4-level grayscale
+    /// frame instead of dithering; see [`render_gray_plane`].
+    pub grayscale: bool,
 }
 
 impl<'a> ReaderView<'a> {
@@ -16,18 +19,19 @@ impl<'a> ReaderView<'a> {
         Self {
             image,
             refresh: crate::display::RefreshMode::Full,
+            grayscale: false,
         }
     }
 }
 
 impl View for ReaderView<'_> {
     fn render(&mut self, ctx: &mut UiContext<'_>, rect: Rect, rq: &mut RenderQueue) {
-        render_image(ctx, self.image);
+        render_image(ctx, self.image, self.grayscale);
         rq.push(rect, self.refresh);
     }
 }
 
-fn render_image(ctx: &mut UiContext<'_>, image: &ImageData) {
+fn render_image(ctx: &mut UiContext<'_>, image: &ImageData, grayscale: bool) {
     ctx.buffers.clear(BinaryColor::On).ok();
     match image {
         ImageData::Mono1 {
@@ -35,12 +39,89 @@ fn render_image(ctx: &mut UiContext<'_>, image: &ImageData) {
             height,
             bits,
         } => render_mono1(ctx, *width, *height, bits),
-        ImageData::Gray8 {
-            width,
-            height,
-            pixels,
-        } => render_gray8(ctx, *width, *height, pixels),
+        _ if grayscale => scan_gray(ctx, image, |buffers, x, y, lum| {
+            if matches!(
+                gray_level(lum),
+                GrayLevel::Black | GrayLevel::DarkGray | GrayLevel::Gray
+            ) {
+                buffers.set_pixel(x, y, BinaryColor::Off);
+            }
+        }),
+        _ => render_gray(ctx, image),
+    }
+}
+
+/// Source luminance of pixel `idx` for the grayscale variants.
+fn image_luma(image: &ImageData, idx: usize) -> Option<u8> {
+    match image {
+        ImageData::Gray8 { pixels, .. } => pixels.get(idx).copied(),
+        ImageData::Gray2 { pixels, .. } => gray2_luma(pixels, idx),
+        ImageData::Mono1 { .. } => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GrayLevel {
+    Black,
+    DarkGray,
+    Gray,
+    LightGray,
+    White,
+}
+
+fn gray_level(lum: u8) -> GrayLevel {
+    match lum {
+        0..=51 => GrayLevel::Black,
+        52..=102 => GrayLevel::DarkGray,
+        103..=153 => GrayLevel::Gray,
+        154..=204 => GrayLevel::LightGray,
+        _ => GrayLevel::White,
+    }
+}
+
+/// Heuristic for photos vs. line art: grayscale is worth the extra refresh
+/// only when a noticeable share of pixels sits in the mid-tones.
+pub fn prefers_grayscale(image: &ImageData) -> bool {
+    let pixel_count = match image {
+        ImageData::Gray8 { pixels, .. } => pixels.len(),
+        ImageData::Gray2 { width, height, .. } => *width as usize * *height as usize,
+        ImageData::Mono1 { .. } => return false,
+    };
+    if pixel_count == 0 {
+        return false;
+    }
+    let step = (pixel_count / 4096).max(1);
+    let mut sampled = 0usize;
+    let mut mid = 0usize;
+    for idx in (0..pixel_count).step_by(step) {
+        let Some(lum) = image_luma(image, idx) else {
+            continue;
+        };
+        sampled += 1;
+        if !matches!(gray_level(lum), GrayLevel::Black | GrayLevel::White) {
+            mid += 1;
+        }
     }
+    mid * 100 >= sampled * 15
+}
+
+/// Renders one bitplane of the 2-bit grayscale encoding into the active
+/// buffer (1 = plane bit set), ready for `Display::copy_to_lsb`/`copy_to_msb`.
+pub fn render_gray_plane(ctx: &mut UiContext<'_>, image: &ImageData, msb: bool) {
+    ctx.buffers.clear_screen(0x00);
+    scan_gray(ctx, image, |buffers, x, y, lum| {
+        // The grayscale waveform lifts black base pixels to dark gray (LSB)
+        // or gray (MSB), and darkens white ones to light gray (both).
+        let (lsb_bit, msb_bit) = match gray_level(lum) {
+            GrayLevel::DarkGray => (true, false),
+            GrayLevel::Gray => (false, true),
+            GrayLevel::LightGray => (true, true),
+            GrayLevel::Black | GrayLevel::White => (false, false),
+        };
+        if (msb && msb_bit) || (!msb && lsb_bit) {
+            buffers.set_pixel(x, y, BinaryColor::On);
+        }
+    });
 }
 
 fn render_mono1(ctx: &mut UiContext<'_>, width: u32, height: u32, bits: &[u8]) {
@@ -70,7 +151,37 @@ fn render_mono1(ctx: &mut UiContext<'_>, width: u32, height: u32, bits: &[u8]) {
     }
 }
 
-fn render_gray8(ctx: &mut UiContext<'_>, width: u32, height: u32, pixels: &[u8]) {
+fn render_gray(ctx: &mut UiContext<'_>, image: &ImageData) {
+    let bayer: [[u8; 4]; 4] = [
+        [0, 8, 2, 10],
+        [12, 4, 14, 6],
+        [3, 11, 1, 9],
+        [15, 7, 13, 5],
+    ];
+    scan_gray(ctx, image, |buffers, x, y, lum| {
+        let threshold = (bayer[(y as usize) & 3][(x as usize) & 3] * 16 + 8) as u8;
+        let color = if lum < threshold {
+            BinaryColor::Off
+        } else {
+            BinaryColor::On
+        };
+        buffers.set_pixel(x, y, color);
+    });
+}
+
+/// Walks the aspect-fit, centered placement of a Gray8 or Gray2 image,
+/// calling `f` with each screen position and its source luminance.
+fn scan_gray(
+    ctx: &mut UiContext<'_>,
+    image: &ImageData,
+    mut f: impl FnMut(&mut crate::framebuffer::DisplayBuffers, i32, i32, u8),
+) {
+    let (width, height) = match image {
+        ImageData::Gray8 { width, height, .. } | ImageData::Gray2 { width, height, .. } => {
+            (*width, *height)
+        }
+        ImageData::Mono1 { .. } => return,
+    };
     let target = ctx.buffers.size();
     let target_w = target.width.max(1);
     let target_h = target.height.max(1);
@@ -88,30 +199,15 @@ fn render_gray8(ctx: &mut UiContext<'_>, width: u32, height: u32, pixels: &[u8])
     let offset_x = ((target_w - scaled_w) / 2) as i32;
     let offset_y = ((target_h - scaled_h) / 2) as i32;
 
-    let bayer: [[u8; 4]; 4] = [
-        [0, 8, 2, 10],
-        [12, 4, 14, 6],
-        [3, 11, 1, 9],
-        [15, 7, 13, 5],
-    ];
-
     for y in 0..scaled_h {
         let src_y = (y as u64 * img_h as u64 / scaled_h as u64) as usize;
         for x in 0..scaled_w {
             let src_x = (x as u64 * img_w as u64 / scaled_w as u64) as usize;
             let idx = src_y * img_w as usize + src_x;
-            if idx >= pixels.len() {
+            let Some(lum) = image_luma(image, idx) else {
                 continue;
-            }
-            let lum = pixels[idx];
-            let threshold = (bayer[(y as usize) & 3][(x as usize) & 3] * 16 + 8) as u8;
-            let color = if lum < threshold {
-                BinaryColor::Off
-            } else {
-                BinaryColor::On
             };
-            ctx.buffers
-                .set_pixel(offset_x + x as i32, offset_y + y as i32, color);
+            f(ctx.buffers, offset_x + x as i32, offset_y + y as i32, lum);
         }
     }
 }
@@ -1,8 +1,12 @@
+extern crate alloc;
+
+use alloc::vec::Vec;
+
 use embedded_graphics::{
     mono_font::{ascii::FONT_10X20, MonoTextStyle},
     pixelcolor::BinaryColor,
     prelude::Point,
-    text::Text,
+    text::{Baseline, Text},
     Drawable,
 };
 
@@ -37,3 +41,93 @@ impl View for TextView<'_> {
         rq.push(rect, self.refresh);
     }
 }
+
+/// Word-wrapped text that can be longer than the view, shown from
+/// `scroll_offset` (in lines). Uses the fixed 10x20 font metrics.
+pub struct ScrollTextView<'a> {
+    pub text: &'a str,
+    pub scroll_offset: usize,
+    pub line_height: i32,
+    pub color: BinaryColor,
+    pub refresh: crate::display::RefreshMode,
+}
+
+impl<'a> ScrollTextView<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            scroll_offset: 0,
+            line_height: FONT_10X20.character_size.height as i32 + 4,
+            color: BinaryColor::Off,
+            refresh: crate::display::RefreshMode::Fast,
+        }
+    }
+
+    /// Lines of `text` wrapped to a view `width` pixels wide.
+    pub fn lines(&self, width: i32) -> Vec<&'a str> {
+        let advance = (FONT_10X20.character_size.width + FONT_10X20.character_spacing) as i32;
+        wrap_text(self.text, (width / advance).max(1) as usize)
+    }
+
+    pub fn line_count(&self, width: i32) -> usize {
+        self.lines(width).len()
+    }
+
+    /// Number of whole lines that fit in a view `height` pixels tall.
+    pub fn visible_lines(&self, height: i32) -> usize {
+        (height / self.line_height.max(1)).max(1) as usize
+    }
+
+    /// Largest useful `scroll_offset` for a view of this size.
+    pub fn max_scroll(&self, rect: Rect) -> usize {
+        self.line_count(rect.w)
+            .saturating_sub(self.visible_lines(rect.h))
+    }
+}
+
+impl View for ScrollTextView<'_> {
+    fn render(&mut self, ctx: &mut UiContext<'_>, rect: Rect, rq: &mut RenderQueue) {
+        let style = MonoTextStyle::new(&FONT_10X20, self.color);
+        let lines = self.lines(rect.w);
+        self.scroll_offset = self.scroll_offset.min(self.max_scroll(rect));
+        let visible = self.visible_lines(rect.h);
+        for (row, line) in lines.iter().skip(self.scroll_offset).take(visible).enumerate() {
+            let pos = Point::new(rect.x, rect.y + row as i32 * self.line_height);
+            Text::with_baseline(line, pos, style, Baseline::Top)
+                .draw(ctx.buffers)
+                .ok();
+        }
+        rq.push(rect, self.refresh);
+    }
+}
+
+/// Splits `text` into lines of at most `max_chars` characters, breaking at
+/// spaces where possible and at explicit newlines.
+pub fn wrap_text(text: &str, max_chars: usize) -> Vec<&str> {
+    let max_chars = max_chars.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut rest = paragraph.trim_end_matches('\r');
+        if rest.is_empty() {
+            lines.push(rest);
+            continue;
+        }
+        while !rest.is_empty() {
+            let Some((limit, _)) = rest.char_indices().nth(max_chars) else {
+                lines.push(rest);
+                break;
+            };
+            let cut = if rest[limit..].starts_with(' ') {
+                limit
+            } else {
+                match rest[..limit].rfind(' ') {
+                    Some(space) if space > 0 => space,
+                    _ => limit,
+                }
+            };
+            lines.push(rest[..cut].trim_end());
+            rest = rest[cut..].trim_start_matches(' ');
+        }
+    }
+    lines
+}
@@ -1,5 +1,6 @@
 use crate::display::RefreshMode;
 use crate::framebuffer::DisplayBuffers;
+use embedded_graphics::prelude::OriginDimensions;
 
 use super::geom::Rect;
 
@@ -46,14 +47,31 @@ pub fn flush_queue(
     rq: &mut RenderQueue,
     fallback: RefreshMode,
 ) {
+    // The panel takes one window per refresh, so all queued regions share a
+    // single bounding refresh at the strongest requested mode.
     let mut mode = None;
+    let mut bounds: Option<Rect> = None;
     for request in rq.drain() {
         mode = Some(match mode {
             Some(current) => max_refresh(current, request.refresh),
             None => request.refresh,
         });
+        bounds = Some(match bounds {
+            Some(current) => current.union(request.rect),
+            None => request.rect,
+        });
+    }
+    let mode = mode.unwrap_or(fallback);
+    // Fast updates that don't touch the whole screen only send their window.
+    if let Some(bounds) = bounds {
+        let size = buffers.size();
+        let screen = Rect::new(0, 0, size.width as i32, size.height as i32);
+        if mode == RefreshMode::Fast && bounds.intersection(screen) != Some(screen) {
+            display.display_region(buffers, bounds, mode);
+            return;
+        }
     }
-    display.display(buffers, mode.unwrap_or(fallback));
+    display.display(buffers, mode);
 }
 
 fn max_refresh(a: RefreshMode, b: RefreshMode) -> RefreshMode {
@@ -1,13 +1,24 @@
 use log::info;
 use trusty_core::{
     display::{HEIGHT, RefreshMode, WIDTH},
-    framebuffer::DisplayBuffers,
+    framebuffer::{region_rows, DisplayBuffers},
     input::{ButtonState, Buttons},
+    ui::Rect,
 };
 
 const BUFFER_SIZE: usize = WIDTH * HEIGHT / 8;
 const DISPLAY_BUFFER_SIZE: usize = WIDTH * HEIGHT;
 
+pub const WINDOW_TITLE: &str = "Trusty Desktop";
+
+const PIXEL_WHITE: u32 = 0xFFFFFFFF;
+const PIXEL_BLACK: u32 = 0xFF000000;
+// Fixed panel gray levels, one per (msb, lsb) plane pair, at the centres of
+// the luminance buckets the core uses to pick them.
+const PIXEL_DARK_GRAY: u32 = 0xFF4D4D4D;
+const PIXEL_GRAY: u32 = 0xFF808080;
+const PIXEL_LIGHT_GRAY: u32 = 0xFFB3B3B3;
+
 pub struct MinifbDisplay {
     is_grayscale: bool,
     // Simulated EInk buffers
@@ -15,8 +26,13 @@ pub struct MinifbDisplay {
     msb_buffer: [u8; BUFFER_SIZE],
     // Actual display buffer
     display_buffer: [u32; DISPLAY_BUFFER_SIZE],
+    // Nearest-neighbour copy of display_buffer when the window is resized
+    scaled_buffer: Vec<u32>,
+    window_size: (usize, usize),
     window: minifb::Window,
     buttons: ButtonState,
+    // Forced refresh mode for reproducing panel artifacts (G = full, F = fast)
+    refresh_override: Option<RefreshMode>,
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -37,8 +53,11 @@ impl MinifbDisplay {
             lsb_buffer: [0; BUFFER_SIZE],
             msb_buffer: [0; BUFFER_SIZE],
             display_buffer: [0; DISPLAY_BUFFER_SIZE],
+            scaled_buffer: Vec::new(),
+            window_size: (HEIGHT, WIDTH),
             window,
             buttons: ButtonState::default(),
+            refresh_override: None,
         };
 
         ret.display_buffer.fill(0xFFFFFFFF);
@@ -51,13 +70,34 @@ impl MinifbDisplay {
     }
 
     pub fn update_display(&mut self /*, window: &mut minifb::Window */) {
+        let (width, height) = self.window.get_size();
+        self.window_size = (width, height);
+        if (width, height) == (HEIGHT, WIDTH) || width == 0 || height == 0 {
+            self.window
+                .update_with_buffer(&self.display_buffer, HEIGHT, WIDTH)
+                .unwrap();
+            return;
+        }
+        self.scaled_buffer.resize(width * height, 0);
+        for y in 0..height {
+            let src_row = (y * WIDTH / height) * HEIGHT;
+            let dst_row = y * width;
+            for x in 0..width {
+                self.scaled_buffer[dst_row + x] = self.display_buffer[src_row + x * HEIGHT / width];
+            }
+        }
         self.window
-            .update_with_buffer(&self.display_buffer, HEIGHT, WIDTH)
+            .update_with_buffer(&self.scaled_buffer, width, height)
             .unwrap();
     }
 
-    pub fn update(&mut self) {
-        self.window.update();
+    pub fn update(&mut self, elapsed_ms: u32) {
+        // A resized window needs the frame rescaled, not just an event pump.
+        if self.window.get_size() != self.window_size {
+            self.update_display();
+        } else {
+            self.window.update();
+        }
         let mut current: u8 = 0;
         if self.window.is_key_down(minifb::Key::Left) {
             current |= 1 << (Buttons::Left as u8);
@@ -80,7 +120,28 @@ impl MinifbDisplay {
         if self.window.is_key_down(minifb::Key::P) {
             current |= 1 << (Buttons::Power as u8);
         }
-        self.buttons.update(current);
+        self.buttons.update(current, elapsed_ms);
+
+        if self.window.is_key_pressed(minifb::Key::G, minifb::KeyRepeat::No) {
+            self.toggle_refresh_override(RefreshMode::Full);
+        }
+        if self.window.is_key_pressed(minifb::Key::F, minifb::KeyRepeat::No) {
+            self.toggle_refresh_override(RefreshMode::Fast);
+        }
+    }
+
+    fn toggle_refresh_override(&mut self, mode: RefreshMode) {
+        self.refresh_override = if self.refresh_override == Some(mode) {
+            None
+        } else {
+            Some(mode)
+        };
+        let title = match self.refresh_override {
+            Some(RefreshMode::Fast) => format!("{WINDOW_TITLE} [forced fast refresh]"),
+            Some(_) => format!("{WINDOW_TITLE} [forced full refresh]"),
+            None => WINDOW_TITLE.to_string(),
+        };
+        self.window.set_title(&title);
     }
 
     pub fn get_buttons(&self) -> ButtonState {
@@ -132,13 +193,11 @@ impl MinifbDisplay {
                         let pixel_index = i * 8 + bit;
                         let lsb_bit = (lsb_byte >> (7 - bit)) & 0x01;
                         let msb_bit = (msb_byte >> (7 - bit)) & 0x01;
-                        let current_pixel = self.get_portrait_pixel(pixel_index);
                         let new_pixel = match (msb_bit, lsb_bit) {
                             (0, 0) => continue,
-                            (0, 1) => current_pixel.saturating_sub(0x555555), // Black -> Dark Gray
-                            (1, 0) => current_pixel.saturating_sub(0xAAAAAA), // Black -> Gray
-                            (1, 1) => current_pixel.saturating_add(0x333333), // White -> Light Gray
-                            _ => unreachable!(),
+                            (0, 1) => PIXEL_DARK_GRAY,
+                            (1, 0) => PIXEL_GRAY,
+                            _ => PIXEL_LIGHT_GRAY,
                         };
                         self.set_portrait_pixel(pixel_index, new_pixel);
                     }
@@ -152,13 +211,11 @@ impl MinifbDisplay {
                         let pixel_index = i * 8 + bit;
                         let lsb_bit = (lsb_byte >> (7 - bit)) & 0x01;
                         let msb_bit = (msb_byte >> (7 - bit)) & 0x01;
-                        let current_pixel = self.get_portrait_pixel(pixel_index);
+                        // Gray pixels return to the black or white base they were lifted from.
                         let new_pixel = match (msb_bit, lsb_bit) {
                             (0, 0) => continue,
-                            (0, 1) => current_pixel.saturating_add(0x555555), // Dark Gray  -> Black
-                            (1, 0) => current_pixel.saturating_add(0xAAAAAA), // Gray       -> Black
-                            (1, 1) => current_pixel.saturating_sub(0x333333), // Light Gray -> White
-                            _ => unreachable!(),
+                            (1, 1) => PIXEL_WHITE,
+                            _ => PIXEL_BLACK,
                         };
                         self.set_portrait_pixel(pixel_index, new_pixel);
                     }
@@ -183,28 +240,11 @@ impl MinifbDisplay {
             self.display_buffer[idx] = color;
         }
     }
-
-    fn get_portrait_pixel(&self, landscape_index: usize) -> u32 {
-        let x_land = (landscape_index % WIDTH) as i32;
-        let y_land = (landscape_index / WIDTH) as i32;
-        let x_portrait = (HEIGHT as i32 - 1) - y_land;
-        let y_portrait = x_land;
-        if x_portrait < 0 || y_portrait < 0 {
-            return 0xFFFFFFFF;
-        }
-        let x_portrait = x_portrait as usize;
-        let y_portrait = y_portrait as usize;
-        let idx = y_portrait * HEIGHT + x_portrait;
-        if idx < self.display_buffer.len() {
-            self.display_buffer[idx]
-        } else {
-            0xFFFFFFFF
-        }
-    }
 }
 
 impl trusty_core::display::Display for MinifbDisplay {
     fn display(&mut self, buffers: &mut DisplayBuffers, mode: RefreshMode) {
+        let mode = self.refresh_override.unwrap_or(mode);
         // revert grayscale first
         if self.is_grayscale {
             self.blit_internal(BlitMode::GrayscaleRevert);
@@ -222,6 +262,28 @@ impl trusty_core::display::Display for MinifbDisplay {
         }
         buffers.swap_buffers();
     }
+    fn display_region(&mut self, buffers: &mut DisplayBuffers, rect: Rect, mode: RefreshMode) {
+        let mode = self.refresh_override.unwrap_or(mode);
+        if self.is_grayscale || mode != RefreshMode::Fast {
+            self.display(buffers, mode);
+            return;
+        }
+        let Some(region) = buffers.physical_region(rect) else {
+            return;
+        };
+        // Mirror the panel: only the window's rows reach the simulated RAM.
+        let stride = WIDTH / 8;
+        let start = region.x as usize / 8;
+        let current = region_rows(buffers.get_active_buffer(), region);
+        let previous = region_rows(buffers.get_inactive_buffer(), region);
+        for (offset, (cur, prev)) in current.zip(previous).enumerate() {
+            let at = (region.y as usize + offset) * stride + start;
+            self.lsb_buffer[at..at + cur.len()].copy_from_slice(cur);
+            self.msb_buffer[at..at + prev.len()].copy_from_slice(prev);
+        }
+        self.blit_internal(BlitMode::Partial);
+        buffers.swap_buffers();
+    }
     fn copy_to_lsb(&mut self, buffers: &[u8; BUFFER_SIZE]) {
         self.lsb_buffer.copy_from_slice(buffers);
     }
@@ -2,7 +2,15 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use log::error;
-use trusty_core::image_viewer::{EntryKind, ImageData, ImageEntry, ImageError, ImageSource};
+use trusty_core::image_viewer::{
+    fit_thumbnail, thumbnail_size, EntryKind, ImageData, ImageEntry, ImageError, ImageSource,
+    ResumeState, Settings, THUMBNAIL_MAX,
+};
+
+/// Characters of book text shown in an EPUB preview.
+const EPUB_PREVIEW_MAX_CHARS: usize = 2000;
+/// Reading speed behind the preview's reading-time estimate.
+const READING_WPM: u32 = 200;
 
 pub struct DesktopImageSource {
     root: PathBuf,
@@ -26,9 +34,12 @@ impl DesktopImageSource {
         name.ends_with(".png")
             || name.ends_with(".jpg")
             || name.ends_with(".jpeg")
+            || name.ends_with(".webp")
+            || name.ends_with(".bmp")
             || name.ends_with(".trimg")
             || name.ends_with(".tri")
             || name.ends_with(".trbk")
+            || name.ends_with(".epub")
     }
 
     fn resume_path(&self) -> PathBuf {
@@ -39,6 +50,10 @@ impl DesktopImageSource {
         self.root.join(".trusty_books")
     }
 
+    fn settings_path(&self) -> PathBuf {
+        self.root.join(".trusty_settings")
+    }
+
     fn recent_entries_path(&self) -> PathBuf {
         self.root.join(".trusty_recents")
     }
@@ -97,10 +112,17 @@ impl ImageSource for DesktopImageSource {
             {
                 continue;
             }
+            let modified = entry
+                .metadata()
+                .ok()
+                .and_then(|meta| meta.modified().ok())
+                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|age| age.as_secs());
             if file_type.is_dir() {
                 entries.push(ImageEntry {
                     name,
                     kind: EntryKind::Dir,
+                    modified,
                 });
                 continue;
             }
@@ -111,6 +133,7 @@ impl ImageSource for DesktopImageSource {
                 entries.push(ImageEntry {
                     name,
                     kind: EntryKind::File,
+                    modified,
                 });
             }
         }
@@ -140,7 +163,7 @@ impl ImageSource for DesktopImageSource {
         }
 
         let data = fs::read(&path).map_err(|_| ImageError::Io)?;
-        let image = image::load_from_memory(&data).map_err(|_| ImageError::Decode)?;
+        let image = decode_upright(&data).map_err(|_| ImageError::Decode)?;
         let luma = image.to_luma8();
         Ok(ImageData::Gray8 {
             width: luma.width(),
@@ -149,24 +172,41 @@ impl ImageSource for DesktopImageSource {
         })
     }
 
-    fn save_resume(&mut self, name: Option<&str>) {
+    /// Built on the fly; photos are box-filtered before dithering.
+    fn thumbnail(&mut self, path: &[String], entry: &ImageEntry) -> Option<ImageData> {
+        let image = match self.load(path, entry).ok()? {
+            ImageData::Gray8 {
+                width,
+                height,
+                pixels,
+            } => {
+                let luma = image::GrayImage::from_raw(width, height, pixels)?;
+                let (w, h) = thumbnail_size(width, height, THUMBNAIL_MAX);
+                let small = image::imageops::thumbnail(&luma, w, h);
+                ImageData::Gray8 {
+                    width: small.width(),
+                    height: small.height(),
+                    pixels: small.into_raw(),
+                }
+            }
+            mono => mono,
+        };
+        fit_thumbnail(&image, THUMBNAIL_MAX)
+    }
+
+    fn save_resume(&mut self, state: Option<&ResumeState>) {
         let path = self.resume_path();
-        if let Some(name) = name {
-            let _ = fs::write(path, name.as_bytes());
+        if let Some(state) = state {
+            let _ = fs::write(path, state.to_record().as_bytes());
         } else {
             let _ = fs::remove_file(path);
         }
     }
 
-    fn load_resume(&mut self) -> Option<String> {
+    fn load_resume(&mut self) -> Option<ResumeState> {
         let path = self.resume_path();
         let data = fs::read(path).ok()?;
-        let name = String::from_utf8_lossy(&data).trim().to_string();
-        if name.is_empty() {
-            None
-        } else {
-            Some(name)
-        }
+        ResumeState::parse(&String::from_utf8_lossy(&data))
     }
 
     fn save_book_positions(&mut self, entries: &[(String, usize)]) {
@@ -210,6 +250,15 @@ impl ImageSource for DesktopImageSource {
         entries
     }
 
+    fn save_settings(&mut self, settings: &Settings) {
+        let _ = fs::write(self.settings_path(), settings.to_record().as_bytes());
+    }
+
+    fn load_settings(&mut self) -> Option<Settings> {
+        let data = fs::read(self.settings_path()).ok()?;
+        Some(Settings::parse(&String::from_utf8_lossy(&data)))
+    }
+
     fn save_recent_entries(&mut self, entries: &[String]) {
         let path = self.recent_entries_path();
         if entries.is_empty() {
@@ -323,11 +372,107 @@ impl ImageSource for DesktopImageSource {
         parse_trimg(&data[start..end])
     }
 
+    fn delete(&mut self, path: &[String], entry: &ImageEntry) -> Result<(), ImageError> {
+        if entry.kind != EntryKind::File {
+            return Err(ImageError::Unsupported);
+        }
+        let base = path.iter().fold(self.root.clone(), |acc, part| acc.join(part));
+        fs::remove_file(base.join(&entry.name)).map_err(|_| ImageError::Io)
+    }
+
     fn close_trbk(&mut self) {
         self.trbk_pages = None;
         self.trbk_data = None;
         self.trbk_images = None;
     }
+
+    fn epub_preview_text(
+        &mut self,
+        path: &[String],
+        entry: &ImageEntry,
+    ) -> Result<String, ImageError> {
+        let base = path.iter().fold(self.root.clone(), |acc, part| acc.join(part));
+        let path = base.join(&entry.name);
+        let book = trusty_epub::open_epub(&path).map_err(|err| {
+            error!("Failed to open EPUB {}: {:?}", path.display(), err);
+            ImageError::Decode
+        })?;
+        let metadata = &book.package.metadata;
+        let mut text = String::new();
+        if let Some(title) = &metadata.title {
+            text.push_str(title);
+            text.push('\n');
+        }
+        if let Some(creator) = &metadata.creator {
+            text.push_str(&format!("by {}\n", creator));
+        }
+        let cache_dir = trusty_epub::default_cache_dir(&path);
+        if let Ok((cache, _)) = trusty_epub::load_or_build_cache(&path, &cache_dir) {
+            let minutes = trusty_epub::estimated_reading_minutes(&cache, READING_WPM);
+            text.push_str(&format!(
+                "Est. reading time: {}h {}m\n",
+                minutes / 60,
+                minutes % 60
+            ));
+        }
+        let mut body = String::new();
+        for index in 0..book.package.spine.len() {
+            if body.chars().count() >= EPUB_PREVIEW_MAX_CHARS {
+                break;
+            }
+            let Ok(chapter) = trusty_epub::spine_plain_text(&path, index) else {
+                continue;
+            };
+            let chapter = chapter.trim();
+            if chapter.is_empty() {
+                continue;
+            }
+            if !body.is_empty() {
+                body.push_str("\n\n");
+            }
+            body.push_str(chapter);
+        }
+        if let Some((cut, _)) = body.char_indices().nth(EPUB_PREVIEW_MAX_CHARS) {
+            body.truncate(cut);
+            body.push_str("...");
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&body);
+        Ok(text)
+    }
+
+    fn export_epub_text(
+        &mut self,
+        path: &[String],
+        entry: &ImageEntry,
+    ) -> Result<String, ImageError> {
+        let base = path.iter().fold(self.root.clone(), |acc, part| acc.join(part));
+        let epub_path = base.join(&entry.name);
+        let txt_path = epub_path.with_extension("txt");
+        // Written beside the target and renamed at the end, so a failed
+        // export never leaves a truncated `.txt` (or clobbers an old one).
+        let tmp_path = epub_path.with_extension("txt.tmp");
+        let written = fs::File::create(&tmp_path)
+            .map_err(|_| ImageError::Io)
+            .and_then(|file| {
+                trusty_epub::export_plain_text(&epub_path, &mut std::io::BufWriter::new(file))
+                    .map_err(|err| {
+                        error!("Failed to export EPUB {}: {:?}", epub_path.display(), err);
+                        ImageError::Decode
+                    })
+            })
+            .and_then(|()| fs::rename(&tmp_path, &txt_path).map_err(|_| ImageError::Io));
+        if let Err(err) = written {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(err);
+        }
+        Ok(txt_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default())
+    }
 }
 
 fn log_trbk_header(data: &[u8], path: &Path) {
@@ -388,17 +533,44 @@ fn log_trbk_header(data: &[u8], path: &Path) {
     );
 }
 
+/// Decodes an image and applies its EXIF orientation, so phone photos come
+/// in upright. A missing or unreadable tag leaves the image as stored.
+fn decode_upright(data: &[u8]) -> image::ImageResult<image::DynamicImage> {
+    use image::ImageDecoder;
+
+    let mut decoder = image::ImageReader::new(std::io::Cursor::new(data))
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder
+        .orientation()
+        .unwrap_or(image::metadata::Orientation::NoTransforms);
+    let mut image = image::DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
 fn parse_trimg(data: &[u8]) -> Result<ImageData, ImageError> {
     if data.len() < 16 || &data[0..4] != b"TRIM" {
         return Err(ImageError::Decode);
     }
-    if data[4] != 1 || data[5] != 1 {
+    if data[4] != 1 || !matches!(data[5], 1 | 2) {
         return Err(ImageError::Unsupported);
     }
     let width = u16::from_le_bytes([data[6], data[7]]) as u32;
     let height = u16::from_le_bytes([data[8], data[9]]) as u32;
     let payload = &data[16..];
-    let expected = ((width as usize * height as usize) + 7) / 8;
+    let pixels = width as usize * height as usize;
+    if data[5] == 2 {
+        if payload.len() != pixels.div_ceil(4) {
+            return Err(ImageError::Decode);
+        }
+        return Ok(ImageData::Gray2 {
+            width,
+            height,
+            pixels: payload.to_vec(),
+        });
+    }
+    let expected = (pixels + 7) / 8;
     if payload.len() != expected {
         return Err(ImageError::Decode);
     }
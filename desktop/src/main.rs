@@ -1,5 +1,7 @@
 use trusty_core::{
     application::Application,
+    battery::BatteryMonitor,
+    clock::TimeSource,
     display::{HEIGHT, WIDTH},
     framebuffer::DisplayBuffers,
 };
@@ -10,6 +12,44 @@ use crate::image_source::DesktopImageSource;
 mod display;
 mod image_source;
 
+/// The simulator has no battery; report a fixed, fairly full cell so the
+/// indicator can be checked.
+struct DesktopBattery;
+
+impl BatteryMonitor for DesktopBattery {
+    fn battery_millivolts(&mut self) -> Option<u32> {
+        Some(3_950)
+    }
+}
+
+/// System time (UTC) plus whatever offset the settings screen applied, so
+/// setting the clock never touches the host.
+#[derive(Default)]
+struct DesktopClock {
+    offset_secs: i64,
+}
+
+impl DesktopClock {
+    fn system_secs() -> Option<u64> {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .map(|elapsed| elapsed.as_secs())
+    }
+}
+
+impl TimeSource for DesktopClock {
+    fn now_secs(&mut self) -> Option<u64> {
+        Self::system_secs().map(|secs| secs.saturating_add_signed(self.offset_secs))
+    }
+
+    fn set_now_secs(&mut self, secs: u64) {
+        if let Some(system) = Self::system_secs() {
+            self.offset_secs = secs as i64 - system as i64;
+        }
+    }
+}
+
 fn main() {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
@@ -19,11 +59,10 @@ fn main() {
         borderless: false,
         title: true,
         resize: true,
-        scale: minifb::Scale::X2,
         ..minifb::WindowOptions::default()
     };
     let mut window = minifb::Window::new(
-        "Trusty Desktop",
+        display::WINDOW_TITLE,
         HEIGHT,
         WIDTH,
         options,
@@ -38,12 +77,15 @@ fn main() {
     let mut display = Box::new(MinifbDisplay::new(window));
     let mut image_source = DesktopImageSource::new("sdcard");
     let mut application = Application::new(&mut display_buffers, &mut image_source);
+    application.update_battery(&mut DesktopBattery);
+    let mut clock = DesktopClock::default();
     let mut last_tick = std::time::Instant::now();
 
     while display.is_open() {
-        display.update();
         let elapsed_ms = last_tick.elapsed().as_millis() as u32;
         last_tick = std::time::Instant::now();
+        display.update(elapsed_ms);
+        application.sync_clock(&mut clock);
         application.update(&display.get_buttons(), elapsed_ms);
         application.draw(&mut *display);
     }
@@ -0,0 +1,315 @@
+//! Human-readable dump of a `.trbk` file, used to sanity-check conversions
+//! before copying them to the device.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+
+use crate::BookError;
+
+/// Prints header fields, metadata, TOC, per-page op counts and glyph count for
+/// `data`. Layout problems are reported inline; the return value is the number
+/// of issues found.
+pub fn dump_trbk<W: Write>(data: &[u8], out: &mut W) -> Result<usize, BookError> {
+    let mut issues = 0usize;
+
+    if data.len() < 0x2C || &data[0..4] != b"TRBK" {
+        writeln!(out, "not a TRBK file ({} bytes)", data.len())?;
+        return Ok(1);
+    }
+    let version = data[4];
+    let header_size = read_u16(data, 0x06) as usize;
+    let screen_width = read_u16(data, 0x08);
+    let screen_height = read_u16(data, 0x0A);
+    let page_count = read_u32(data, 0x0C) as usize;
+    let toc_count = read_u32(data, 0x10) as usize;
+    let page_lut_offset = read_u32(data, 0x14) as usize;
+    let toc_offset = read_u32(data, 0x18) as usize;
+    let page_data_offset = read_u32(data, 0x1C) as usize;
+    let (images_offset, glyph_count, glyph_table_offset) = if version >= 2 && data.len() >= 0x30 {
+        (
+            read_u32(data, 0x20) as usize,
+            read_u32(data, 0x28) as usize,
+            read_u32(data, 0x2C) as usize,
+        )
+    } else {
+        (0, 0, 0)
+    };
+    let kerning_offset = if version >= 5 && data.len() >= 0x34 {
+        read_u32(data, 0x30) as usize
+    } else {
+        0
+    };
+
+    writeln!(out, "Header")?;
+    writeln!(out, "  version:          {version}")?;
+    writeln!(out, "  flags:            {:#04x}", data[5])?;
+    writeln!(out, "  file size:        {}", data.len())?;
+    writeln!(out, "  header size:      {header_size}")?;
+    writeln!(out, "  screen:           {screen_width}x{screen_height}")?;
+    writeln!(out, "  pages:            {page_count}")?;
+    writeln!(out, "  toc entries:      {toc_count}")?;
+    writeln!(out, "  page lut offset:  {page_lut_offset}")?;
+    writeln!(out, "  toc offset:       {toc_offset}")?;
+    writeln!(out, "  page data offset: {page_data_offset}")?;
+    writeln!(out, "  images offset:    {images_offset}")?;
+    writeln!(out, "  glyphs:           {glyph_count}")?;
+    writeln!(out, "  glyph offset:     {glyph_table_offset}")?;
+    writeln!(out, "  kerning offset:   {kerning_offset}")?;
+    if kerning_offset > 0 {
+        if kerning_offset + 4 > data.len() {
+            report(
+                out,
+                &mut issues,
+                "kerning table starts past end of file".to_string(),
+            )?;
+        } else {
+            writeln!(out, "  kerning pairs:    {}", read_u32(data, kerning_offset))?;
+        }
+    }
+    if !(1..=5).contains(&version) {
+        report(out, &mut issues, format!("unsupported version {version}"))?;
+    }
+    if toc_offset != header_size {
+        report(
+            out,
+            &mut issues,
+            format!("toc offset {toc_offset} does not match header size {header_size}"),
+        )?;
+    }
+    if header_size > data.len() || page_lut_offset + page_count * 4 > data.len() {
+        report(
+            out,
+            &mut issues,
+            "header or page LUT extends past end of file".to_string(),
+        )?;
+        return Ok(issues);
+    }
+
+    writeln!(out, "Metadata")?;
+    let mut cursor = trusty_core::trbk::trbk_fixed_header_size(version);
+    for label in ["title", "author", "language", "identifier", "font"] {
+        match read_string(data, &mut cursor) {
+            Some(value) => writeln!(out, "  {label:<11} {value}")?,
+            None => {
+                report(
+                    out,
+                    &mut issues,
+                    format!("metadata field '{label}' is truncated"),
+                )?;
+                return Ok(issues);
+            }
+        }
+    }
+    if header_size.saturating_sub(cursor) < 12 {
+        report(out, &mut issues, "layout metrics are truncated".to_string())?;
+        return Ok(issues);
+    }
+    writeln!(out, "  char width  {}", read_u16(data, cursor))?;
+    writeln!(out, "  line height {}", read_u16(data, cursor + 2))?;
+    cursor += 4;
+    // Older files omit the ascent and store margins only.
+    if header_size.saturating_sub(cursor) >= 10 {
+        let ascent = i16::from_le_bytes([data[cursor], data[cursor + 1]]);
+        writeln!(out, "  ascent      {ascent}")?;
+        cursor += 2;
+    }
+    writeln!(
+        out,
+        "  margins     l={} r={} t={} b={}",
+        read_u16(data, cursor),
+        read_u16(data, cursor + 2),
+        read_u16(data, cursor + 4),
+        read_u16(data, cursor + 6)
+    )?;
+    let chapter_starts =
+        trusty_core::trbk::parse_trbk_chapter_starts(&data[..header_size], cursor + 8);
+    if !chapter_starts.is_empty() {
+        let pages: Vec<String> = chapter_starts
+            .iter()
+            .map(|page| (page + 1).to_string())
+            .collect();
+        writeln!(out, "  chapters    {} (pages {})", chapter_starts.len(), pages.join(", "))?;
+        if chapter_starts.windows(2).any(|pair| pair[0] >= pair[1]) {
+            report(out, &mut issues, "chapter starts are not ascending".to_string())?;
+        }
+        if let Some(last) = chapter_starts.last().filter(|last| **last as usize >= page_count) {
+            report(
+                out,
+                &mut issues,
+                format!("chapter start {last} points past the last page"),
+            )?;
+        }
+    }
+
+    writeln!(out, "TOC")?;
+    let mut cursor = toc_offset;
+    for index in 0..toc_count {
+        let Some(title) = read_string(data, &mut cursor) else {
+            report(out, &mut issues, format!("toc entry {index} is truncated"))?;
+            break;
+        };
+        if cursor + 8 > data.len() {
+            report(out, &mut issues, format!("toc entry {index} is truncated"))?;
+            break;
+        }
+        let page_index = read_u32(data, cursor) as usize;
+        let level = data[cursor + 4];
+        let y_offset = if version >= 4 { read_u16(data, cursor + 6) } else { 0 };
+        cursor += 8;
+        let indent = "  ".repeat(level as usize);
+        if y_offset > 0 {
+            writeln!(out, "  {indent}{title} -> page {}, {y_offset}px down", page_index + 1)?;
+        } else {
+            writeln!(out, "  {indent}{title} -> page {}", page_index + 1)?;
+        }
+        if page_index >= page_count {
+            report(
+                out,
+                &mut issues,
+                format!("toc entry '{title}' points past the last page ({page_index})"),
+            )?;
+        }
+    }
+
+    writeln!(out, "Pages")?;
+    let pages_end = if version >= 2 && glyph_table_offset > page_data_offset {
+        glyph_table_offset
+    } else {
+        data.len()
+    };
+    for index in 0..page_count {
+        let start = page_data_offset + read_u32(data, page_lut_offset + index * 4) as usize;
+        let end = if index + 1 < page_count {
+            page_data_offset + read_u32(data, page_lut_offset + (index + 1) * 4) as usize
+        } else {
+            pages_end
+        };
+        if start > end || end > pages_end || end > data.len() {
+            report(
+                out,
+                &mut issues,
+                format!(
+                    "page {} spans {start}..{end}, past the glyph table at {pages_end}",
+                    index + 1
+                ),
+            )?;
+            continue;
+        }
+        match trusty_core::trbk::parse_trbk_page_ops(&data[start..end]) {
+            Ok(ops) => {
+                let text = ops
+                    .iter()
+                    .filter(|op| matches!(op, trusty_core::trbk::TrbkOp::TextRun { .. }))
+                    .count();
+                let rules = ops
+                    .iter()
+                    .filter(|op| matches!(op, trusty_core::trbk::TrbkOp::HRule { .. }))
+                    .count();
+                let images = ops.len() - text - rules;
+                write!(
+                    out,
+                    "  page {:>4}: {:>6} bytes, {text} text, {images} image",
+                    index + 1,
+                    end - start
+                )?;
+                if rules > 0 {
+                    write!(out, ", {rules} rule")?;
+                }
+                writeln!(out)?;
+            }
+            Err(err) => {
+                report(
+                    out,
+                    &mut issues,
+                    format!("page {} ops failed to parse: {err:?}", index + 1),
+                )?;
+            }
+        }
+    }
+
+    match trusty_core::trbk::parse_trbk(data) {
+        Ok(book) => {
+            for message in missing_glyph_issues(&book) {
+                report(out, &mut issues, message)?;
+            }
+        }
+        Err(err) => {
+            report(
+                out,
+                &mut issues,
+                format!("device parser rejects file: {err:?}"),
+            )?;
+        }
+    }
+    writeln!(out, "{issues} issue(s) found")?;
+    Ok(issues)
+}
+
+/// Characters drawn by text runs that the glyph table can't supply, using the
+/// device's lookup (exact style, then the base style). One message per
+/// style/character pair.
+pub(crate) fn missing_glyph_issues(book: &trusty_core::trbk::TrbkBook) -> Vec<String> {
+    if book.glyphs.is_empty() {
+        return Vec::new();
+    }
+    let available: HashSet<(u8, u32)> = book
+        .glyphs
+        .iter()
+        .map(|glyph| (glyph.style, glyph.codepoint))
+        .collect();
+    let mut missing = BTreeMap::new();
+    for (index, page) in book.pages.iter().enumerate() {
+        for op in &page.ops {
+            let trusty_core::trbk::TrbkOp::TextRun { style, text, .. } = op else {
+                continue;
+            };
+            let base =
+                style & (trusty_core::trbk::TRBK_STYLE_MASK | trusty_core::trbk::TRBK_STYLE_MONO);
+            for ch in text.chars().filter(|ch| *ch != '\r' && *ch != '\n') {
+                let cp = ch as u32;
+                if !available.contains(&(*style, cp)) && !available.contains(&(base, cp)) {
+                    missing.entry((*style, cp)).or_insert(index + 1);
+                }
+            }
+        }
+    }
+    missing
+        .into_iter()
+        .map(|((style, cp), page)| {
+            format!("no glyph for U+{cp:04X} in style {style:#04x} (first on page {page})")
+        })
+        .collect()
+}
+
+fn report<W: Write>(out: &mut W, issues: &mut usize, message: String) -> Result<(), BookError> {
+    *issues += 1;
+    writeln!(out, "  !! {message}")?;
+    Ok(())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn read_string(data: &[u8], cursor: &mut usize) -> Option<String> {
+    if *cursor + 4 > data.len() {
+        return None;
+    }
+    let len = read_u32(data, *cursor) as usize;
+    *cursor += 4;
+    if *cursor + len > data.len() {
+        return None;
+    }
+    let value = String::from_utf8_lossy(&data[*cursor..*cursor + len]).to_string();
+    *cursor += len;
+    Some(value)
+}
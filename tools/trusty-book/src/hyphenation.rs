@@ -0,0 +1,198 @@
+//! Knuth-Liang hyphenation from a TeX pattern file. Break points are marked
+//! with soft hyphens before layout, so the wrapper's existing soft-hyphen
+//! splitting does the rest.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{BookError, SOFT_HYPHEN};
+
+/// Letters kept together at the start and end of a word (TeX's
+/// `\lefthyphenmin`/`\righthyphenmin` for English).
+const LEFT_MIN: usize = 2;
+const RIGHT_MIN: usize = 3;
+
+pub(crate) struct Hyphenator {
+    /// Pattern letters to the level before each letter and after the last.
+    patterns: HashMap<String, Vec<u8>>,
+    /// Whole words with fixed break points (char indices), from entries
+    /// written with explicit hyphens such as `as-so-ciate`.
+    exceptions: HashMap<String, Vec<usize>>,
+    max_pattern_len: usize,
+}
+
+impl Hyphenator {
+    pub(crate) fn load(path: &Path) -> Result<Self, BookError> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Reads whitespace-separated patterns like `.ach4` or `4b1i`, as in the
+    /// `hyph-*.pat.txt` files. `%` starts a comment; TeX commands and braces
+    /// are ignored so a `\patterns{...}` file works too.
+    pub(crate) fn parse(text: &str) -> Self {
+        let mut patterns = HashMap::new();
+        let mut exceptions = HashMap::new();
+        let mut max_pattern_len = 0;
+        for line in text.lines() {
+            let line = line.split('%').next().unwrap_or_default();
+            for token in line.split_whitespace() {
+                let token = token.trim_matches(|ch| ch == '{' || ch == '}');
+                if token.is_empty() || token.starts_with('\\') {
+                    continue;
+                }
+                if token.contains('-') && !token.chars().any(|ch| ch.is_ascii_digit()) {
+                    let mut breaks = Vec::new();
+                    let mut word = String::new();
+                    for ch in token.chars() {
+                        if ch == '-' {
+                            breaks.push(word.chars().count());
+                        } else {
+                            word.extend(ch.to_lowercase());
+                        }
+                    }
+                    exceptions.insert(word, breaks);
+                    continue;
+                }
+                let mut letters = String::new();
+                let mut levels = vec![0u8];
+                for ch in token.chars() {
+                    if let Some(level) = ch.to_digit(10) {
+                        *levels.last_mut().unwrap() = level as u8;
+                    } else {
+                        letters.extend(ch.to_lowercase());
+                        levels.push(0);
+                    }
+                }
+                max_pattern_len = max_pattern_len.max(letters.chars().count());
+                patterns.insert(letters, levels);
+            }
+        }
+        Self {
+            patterns,
+            exceptions,
+            max_pattern_len,
+        }
+    }
+
+    /// Char indices in `word` where a hyphen may go.
+    pub(crate) fn breaks(&self, word: &str) -> Vec<usize> {
+        let lower: String = word.chars().flat_map(char::to_lowercase).collect();
+        let len = lower.chars().count();
+        if len != word.chars().count() || len < LEFT_MIN + RIGHT_MIN {
+            return Vec::new();
+        }
+        if let Some(breaks) = self.exceptions.get(&lower) {
+            return breaks.clone();
+        }
+
+        let dotted: Vec<char> = format!(".{lower}.").chars().collect();
+        // levels[i] sits before dotted[i].
+        let mut levels = vec![0u8; dotted.len() + 1];
+        let mut key = String::new();
+        for start in 0..dotted.len() {
+            key.clear();
+            let end = dotted.len().min(start + self.max_pattern_len);
+            for ch in &dotted[start..end] {
+                key.push(*ch);
+                if let Some(pattern) = self.patterns.get(&key) {
+                    for (idx, level) in pattern.iter().enumerate() {
+                        let slot = &mut levels[start + idx];
+                        *slot = (*slot).max(*level);
+                    }
+                }
+            }
+        }
+        // A break before word char `i` is the level before dotted[i + 1].
+        (LEFT_MIN..=len - RIGHT_MIN)
+            .filter(|idx| levels[idx + 1] % 2 == 1)
+            .collect()
+    }
+
+    /// Adds soft hyphens at the break points of each run of letters across
+    /// `texts`, leaving spacing and punctuation untouched. A word may span
+    /// several texts (a paragraph's styled runs) and is hyphenated whole; a
+    /// `None` ends the word before it and is left alone, as for code.
+    pub(crate) fn hyphenate_texts(&self, texts: &mut [Option<&mut String>]) {
+        let mut word = String::new();
+        // (text index, byte offset) of each letter of `word`.
+        let mut letters = Vec::new();
+        // Where soft hyphens go, in text order.
+        let mut inserts = Vec::new();
+        for (index, text) in texts.iter().enumerate() {
+            let Some(text) = text else {
+                self.mark_breaks(&mut word, &mut letters, &mut inserts);
+                continue;
+            };
+            for (offset, ch) in text.char_indices() {
+                if ch.is_alphabetic() {
+                    word.push(ch);
+                    letters.push((index, offset));
+                } else {
+                    self.mark_breaks(&mut word, &mut letters, &mut inserts);
+                }
+            }
+        }
+        self.mark_breaks(&mut word, &mut letters, &mut inserts);
+        // Back to front, so earlier offsets stay valid.
+        for (index, offset) in inserts.into_iter().rev() {
+            if let Some(text) = &mut texts[index] {
+                text.insert(offset, SOFT_HYPHEN);
+            }
+        }
+    }
+
+    /// Queues the break points of the finished `word` and starts a new one.
+    /// Breaks between two texts are dropped: the wrapper only splits within
+    /// a run.
+    fn mark_breaks(
+        &self,
+        word: &mut String,
+        letters: &mut Vec<(usize, usize)>,
+        inserts: &mut Vec<(usize, usize)>,
+    ) {
+        for idx in self.breaks(word) {
+            let (index, offset) = letters[idx];
+            if letters[idx - 1].0 == index {
+                inserts.push((index, offset));
+            }
+        }
+        word.clear();
+        letters.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A few of the English patterns plus one exception, enough for "hyphenation".
+    const PATTERNS: &str =
+        "% test patterns\n\\patterns{\n hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n\n}\nta-ble";
+
+    #[test]
+    fn breaks_follow_patterns_and_exceptions() {
+        let hyphenator = Hyphenator::parse(PATTERNS);
+        assert_eq!(hyphenator.breaks("hyphenation"), vec![2, 6]);
+        assert_eq!(hyphenator.breaks("Hyphenation"), vec![2, 6]);
+        assert_eq!(hyphenator.breaks("table"), vec![2]);
+        // Too short to leave two letters before and three after a break.
+        assert!(hyphenator.breaks("hyph").is_empty());
+    }
+
+    #[test]
+    fn words_split_across_texts_are_hyphenated_whole() {
+        let hyphenator = Hyphenator::parse(PATTERNS);
+        let mut head = "Hyphe".to_string();
+        let mut tail = "nation, a table".to_string();
+        hyphenator.hyphenate_texts(&mut [Some(&mut head), Some(&mut tail)]);
+        assert_eq!(head, "Hy\u{ad}phe");
+        assert_eq!(tail, "n\u{ad}ation, a ta\u{ad}ble");
+
+        // A break right between two runs is dropped; the wrapper can't use it.
+        let mut head = "hyphen".to_string();
+        let mut tail = "ation".to_string();
+        hyphenator.hyphenate_texts(&mut [Some(&mut head), Some(&mut tail)]);
+        assert_eq!(head, "hy\u{ad}phen");
+        assert_eq!(tail, "ation");
+    }
+}
@@ -1,11 +1,16 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
 use image::GenericImageView;
 use thiserror::Error;
 
+mod dump;
+mod hyphenation;
+
+pub use dump::dump_trbk;
+
 #[derive(Debug, Error)]
 pub enum BookError {
     #[error("io error: {0}")]
@@ -14,6 +19,8 @@ pub enum BookError {
     Epub(#[from] trusty_epub::EpubError),
     #[error("invalid output")]
     InvalidOutput,
+    #[error("invalid screen geometry: {0}")]
+    InvalidGeometry(String),
 }
 
 #[derive(Debug, Clone)]
@@ -27,6 +34,175 @@ pub struct RenderOptions {
     pub ascent: i16,
     pub word_spacing: i16,
     pub max_spine_items: usize,
+    /// Pack glyphs as two bitplanes (four gray levels) instead of 1-bit.
+    pub grayscale_glyphs: bool,
+    /// Also paginate spine items marked `linear="no"`.
+    pub include_nonlinear: bool,
+    /// Pick up bold/italic from the class and element rules in linked CSS.
+    pub css_emphasis: bool,
+    /// Lay lines out right to left. Also switched on for books whose
+    /// language is written right to left.
+    pub rtl: bool,
+    /// First-line indent of body paragraphs, in pixels.
+    pub paragraph_indent: u16,
+    /// Extra vertical space after each paragraph, in pixels.
+    pub paragraph_spacing: u16,
+    /// Knuth-Liang pattern file (TeX `hyph-*.pat.txt` format). When set, body
+    /// words that don't fit a line can be split at the pattern's break points.
+    pub hyphenation_patterns: Option<PathBuf>,
+    /// Also write the `<epub stem>.epubinfo` sidecar the device shows for the
+    /// EPUB (see `docs/trbk-format.md`).
+    pub write_sidecar: bool,
+    /// Keep the glyph set to these blocks; other characters are replaced
+    /// before glyphs are collected. `None` keeps every codepoint.
+    pub codepoint_filter: Option<CodepointFilter>,
+}
+
+/// Groups of Unicode blocks a [`CodepointFilter`] can allow. Whitespace is
+/// always allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharBlock {
+    /// Basic Latin, Latin-1, the Latin extensions and combining accents.
+    Latin,
+    /// ASCII digits, punctuation and symbols, the Latin-1 signs (`«»¿¡©°`),
+    /// general punctuation, currency, letterlike symbols and number forms.
+    Punctuation,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    /// Han, kana, hangul and full-width forms.
+    Cjk,
+    /// Whichever of the above the book's language is written in.
+    Script,
+}
+
+impl CharBlock {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.trim().to_ascii_lowercase().as_str() {
+            "latin" => Self::Latin,
+            "punctuation" => Self::Punctuation,
+            "greek" => Self::Greek,
+            "cyrillic" => Self::Cyrillic,
+            "hebrew" => Self::Hebrew,
+            "arabic" => Self::Arabic,
+            "cjk" => Self::Cjk,
+            "script" => Self::Script,
+            _ => return None,
+        })
+    }
+
+    fn contains(self, ch: char) -> bool {
+        match self {
+            Self::Latin => matches!(
+                ch as u32,
+                0x0020..=0x007E | 0x00A0..=0x024F | 0x0300..=0x036F | 0x1E00..=0x1EFF
+            ),
+            Self::Punctuation => matches!(
+                ch as u32,
+                0x0020..=0x0040
+                    | 0x005B..=0x0060
+                    | 0x007B..=0x007E
+                    | 0x00A0..=0x00BF
+                    | 0x00D7
+                    | 0x00F7
+                    | 0x2000..=0x206F
+                    | 0x20A0..=0x20CF
+                    | 0x2100..=0x218F
+            ),
+            Self::Greek => matches!(ch as u32, 0x0370..=0x03FF | 0x1F00..=0x1FFF),
+            Self::Cyrillic => matches!(ch as u32, 0x0400..=0x052F),
+            Self::Hebrew => matches!(ch as u32, 0x0590..=0x05FF | 0xFB1D..=0xFB4F),
+            Self::Arabic => matches!(
+                ch as u32,
+                0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF
+            ),
+            Self::Cjk => {
+                is_cjk_char(ch)
+                    || matches!(ch as u32, 0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF)
+            }
+            // Resolved against the book's language before filtering.
+            Self::Script => false,
+        }
+    }
+
+    /// The block a book in `language` is mostly written in; Latin when the
+    /// language is missing or not listed.
+    fn for_language(language: Option<&str>) -> Self {
+        let primary = language
+            .and_then(|language| language.split(['-', '_']).next())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match primary.as_str() {
+            "el" | "grc" => Self::Greek,
+            "ru" | "uk" | "be" | "bg" | "sr" | "mk" | "kk" | "ky" | "mn" | "tg" => Self::Cyrillic,
+            "he" | "iw" | "yi" => Self::Hebrew,
+            "ar" | "fa" | "ur" | "ps" | "sd" | "ug" => Self::Arabic,
+            "zh" | "ja" | "ko" => Self::Cjk,
+            _ => Self::Latin,
+        }
+    }
+}
+
+/// Restricts the characters that get glyphs, so a stray emoji or rare symbol
+/// doesn't add a glyph (usually a missing box) to every size.
+#[derive(Debug, Clone)]
+pub struct CodepointFilter {
+    pub blocks: Vec<CharBlock>,
+    /// Drawn in place of each disallowed character or emoji sequence.
+    pub substitute: char,
+}
+
+impl CodepointFilter {
+    fn allows(&self, ch: char, script: CharBlock) -> bool {
+        ch.is_whitespace()
+            || self.blocks.iter().any(|block| match block {
+                CharBlock::Script => script.contains(ch),
+                block => block.contains(ch),
+            })
+    }
+
+    /// Replaces disallowed characters in `text`, returning how many
+    /// substitutes were written. Joiners, variation selectors and skin-tone
+    /// modifiers after a replaced character fold into its substitute, so an
+    /// emoji sequence becomes a single one.
+    fn apply(&self, text: &mut String, script: CharBlock) -> usize {
+        if text.chars().all(|ch| self.allows(ch, script)) {
+            return 0;
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut replaced = 0;
+        let mut in_sequence = false;
+        let mut joined = false;
+        for ch in text.chars() {
+            if in_sequence && is_emoji_modifier(ch) {
+                joined |= ch == '\u{200D}';
+                continue;
+            }
+            if self.allows(ch, script) {
+                out.push(ch);
+                in_sequence = false;
+            } else {
+                if !joined {
+                    out.push(self.substitute);
+                    replaced += 1;
+                }
+                in_sequence = true;
+            }
+            joined = false;
+        }
+        *text = out;
+        replaced
+    }
+}
+
+/// Zero-width joiner, variation selectors, keycap, skin tones and tag
+/// characters: the parts of an emoji sequence after its base.
+fn is_emoji_modifier(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x200D | 0x20E3 | 0xFE00..=0xFE0F | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F
+    )
 }
 
 impl Default for RenderOptions {
@@ -41,16 +217,58 @@ impl Default for RenderOptions {
             ascent: 14,
             word_spacing: 2,
             max_spine_items: 50,
+            grayscale_glyphs: false,
+            include_nonlinear: false,
+            css_emphasis: false,
+            rtl: false,
+            paragraph_indent: 0,
+            paragraph_spacing: 0,
+            hyphenation_patterns: None,
+            write_sidecar: false,
+            codepoint_filter: None,
         }
     }
 }
 
+impl RenderOptions {
+    /// Checks the screen size and margins before any layout runs. Both sides
+    /// must be whole bytes of 1-bit pixels, since the device packs rows
+    /// along either axis depending on rotation, and the margins must leave
+    /// room for text.
+    pub fn check_geometry(&self) -> Result<(), BookError> {
+        let (width, height) = (self.screen_width, self.screen_height);
+        if width == 0 || height == 0 || width % 8 != 0 || height % 8 != 0 {
+            return Err(BookError::InvalidGeometry(format!(
+                "{width}x{height} must be non-zero multiples of 8"
+            )));
+        }
+        if self.margin_x as u32 * 2 >= width as u32 || self.margin_y as u32 * 2 >= height as u32 {
+            return Err(BookError::InvalidGeometry(format!(
+                "margins {},{} leave no room on {width}x{height}",
+                self.margin_x, self.margin_y
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Conversion phase reported to the optional progress callback.
+#[derive(Debug, Clone)]
+pub enum ConvertProgress {
+    ExtractingSpine { index: usize, total: usize },
+    BuildingGlyphs { size: u16 },
+    Paginating { size: u16 },
+    Writing { size: u16, path: PathBuf },
+}
+
 #[derive(Debug, Clone)]
 pub struct TrbkMetadata {
     pub title: String,
     pub author: String,
     pub language: String,
     pub identifier: String,
+    /// [`source_hash`] of the EPUB the book was built from.
+    pub source_hash: u32,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -59,6 +277,11 @@ pub struct FontPaths {
     pub bold: Option<String>,
     pub italic: Option<String>,
     pub bold_italic: Option<String>,
+    /// Face for `<code>`/`<tt>`/`<kbd>` text. Without it that text is still
+    /// marked mono in the book but drawn with the regular face.
+    pub mono: Option<String>,
+    /// Extra fonts consulted in order for codepoints the style fonts lack.
+    pub fallback: Vec<String>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
@@ -67,12 +290,37 @@ pub enum StyleId {
     Bold = 1,
     Italic = 2,
     BoldItalic = 3,
+    Mono = 4,
+}
+
+const SOFT_HYPHEN: char = '\u{00AD}';
+const MAX_PLACEHOLDER_ALT_CHARS: usize = 48;
+/// Longest TOC label taken from a chapter's own text when the book has no
+/// usable TOC.
+const MAX_TEXT_LABEL_CHARS: usize = 48;
+/// Between table cells on the page. Plain-text export joins cells with tabs,
+/// but the layout has no tab stops and wraps on whitespace, so a tab would
+/// end up as a plain word space and the cells would run together.
+const TABLE_CELL_SEPARATOR: &str = " | ";
+
+/// Glyph scale per size bucket: 0 is body text, 1 = h1, 2 = h2, 3 = h3 and deeper.
+const HEADING_SCALES: [f32; 4] = [1.0, 1.6, 1.4, 1.2];
+
+/// Rounded kerning adjustments keyed by `(style_key, left, right)`; the
+/// ordering matches what the TRBK kerning table expects.
+type KerningTable = BTreeMap<(u8, u32, u32), i16>;
+
+/// Per-glyph advances plus kerning, keyed by `style_key`.
+struct AdvanceMap {
+    advances: HashMap<(u8, u32), i16>,
+    kerning: KerningTable,
 }
 
 #[derive(Clone, Debug)]
 pub struct Glyph {
     pub codepoint: u32,
     pub style: StyleId,
+    pub size_bucket: u8,
     pub width: u8,
     pub height: u8,
     pub x_advance: i16,
@@ -85,17 +333,28 @@ pub struct Glyph {
 struct SpineBlocks {
     spine_index: i32,
     blocks: Vec<trusty_epub::HtmlBlock>,
+    /// Element ids and the index of the block they fall in.
+    anchors: HashMap<String, usize>,
 }
 
 #[derive(Clone, Debug)]
 enum LayoutItem {
     TextLine {
         spine_index: i32,
+        size_bucket: u8,
+        centered: bool,
+        /// Pixels to skip before the first run (paragraph indent).
+        indent: u16,
         runs: Vec<trusty_epub::TextRun>,
     },
     BlankLine {
         spine_index: i32,
     },
+    /// Vertical gap of `height` pixels; dropped at a page break.
+    Spacing {
+        spine_index: i32,
+        height: u16,
+    },
     Image {
         spine_index: i32,
         image_index: u16,
@@ -105,6 +364,15 @@ enum LayoutItem {
     PageBreak {
         spine_index: i32,
     },
+    /// Scene break drawn as a short centred rule, one line tall.
+    Rule {
+        spine_index: i32,
+    },
+    /// Zero-height marker where an element id starts.
+    Anchor {
+        spine_index: i32,
+        id: String,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -119,6 +387,7 @@ enum PageOp {
         x: u16,
         y: u16,
         style: StyleId,
+        size_bucket: u8,
         text: String,
     },
     Image {
@@ -128,6 +397,12 @@ enum PageOp {
         height: u16,
         image_index: u16,
     },
+    /// Horizontal line `width` pixels long, centred on `y`.
+    HRule {
+        x: u16,
+        y: u16,
+        width: u16,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -149,6 +424,8 @@ struct TrbkTocEntry {
     title: String,
     page_index: u32,
     level: u8,
+    /// Pixels below the top margin where the entry starts on its page.
+    y_offset: u16,
 }
 
 pub fn convert_epub_to_trbk<P: AsRef<Path>, Q: AsRef<Path>>(
@@ -156,19 +433,404 @@ pub fn convert_epub_to_trbk<P: AsRef<Path>, Q: AsRef<Path>>(
     output_path: Q,
     options: &RenderOptions,
 ) -> Result<(), BookError> {
-    convert_epub_to_trbk_multi(epub_path, output_path, &[options.char_width], &FontPaths::default())
+    convert_epub_to_trbk_multi(
+        epub_path,
+        output_path,
+        &[options.char_width],
+        &FontPaths::default(),
+        options,
+    )
 }
 
+/// Converts an EPUB into one TRBK per font size. Screen size and margins come
+/// from `base_options`; the font-derived metrics (`char_width`, `line_height`,
+/// `ascent`, `word_spacing`) are recomputed for each size.
 pub fn convert_epub_to_trbk_multi<P: AsRef<Path>, Q: AsRef<Path>>(
     epub_path: P,
     output_path: Q,
     sizes: &[u16],
     font_paths: &FontPaths,
+    base_options: &RenderOptions,
 ) -> Result<(), BookError> {
+    convert_epub_to_trbk_multi_with_progress(
+        epub_path,
+        output_path,
+        sizes,
+        font_paths,
+        base_options,
+        None,
+    )
+    .map(|_| ())
+}
+
+/// Same as [`convert_epub_to_trbk_multi`], reporting each phase to `progress`.
+/// Returns the EPUB cache the books were built from, so callers can report on
+/// it (word counts, reading time) without parsing the EPUB again.
+pub fn convert_epub_to_trbk_multi_with_progress<P: AsRef<Path>, Q: AsRef<Path>>(
+    epub_path: P,
+    output_path: Q,
+    sizes: &[u16],
+    font_paths: &FontPaths,
+    base_options: &RenderOptions,
+    mut progress: Option<&mut dyn FnMut(ConvertProgress)>,
+) -> Result<trusty_epub::BookCache, BookError> {
     let epub_path = epub_path.as_ref();
     let output_path = output_path.as_ref();
+    let cache = cached_book(epub_path)?;
+    let prepared = prepare_book(epub_path, cache, font_paths, base_options, &mut progress)?;
+
+    let sizes = if sizes.is_empty() { vec![10] } else { sizes.to_vec() };
+    let multi = sizes.len() > 1;
+    let mut written = Vec::with_capacity(sizes.len());
+    for size in &sizes {
+        let output = output_path_for_size(output_path, *size, multi);
+        if let Some(parent) = output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let rendition = render_size(epub_path, &prepared, *size, base_options, &mut progress)?;
+        report_progress(
+            &mut progress,
+            ConvertProgress::Writing {
+                size: *size,
+                path: output.clone(),
+            },
+        );
+        let mut file = File::create(&output)?;
+        write_trbk(&mut file, &prepared.metadata, &rendition)?;
+        let name = output
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or(BookError::InvalidOutput)?;
+        written.push((name, rendition.pages.len()));
+    }
+
+    if base_options.write_sidecar {
+        write_epubinfo(epub_path, output_path, &prepared, written)?;
+    }
+
+    Ok(prepared.cache)
+}
+
+/// Outcome of [`convert_directory`], with paths relative to the input folder.
+#[derive(Debug, Clone, Default)]
+pub struct BatchReport {
+    pub converted: Vec<PathBuf>,
+    /// Books whose outputs already matched the current EPUB.
+    pub skipped: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Converts every `.epub`/`.epb` under `input_dir` into `output_dir`,
+/// mirroring subfolders. A book is skipped when all of its outputs exist and
+/// none is stale; one book failing doesn't stop the rest.
+pub fn convert_directory<P: AsRef<Path>, Q: AsRef<Path>>(
+    input_dir: P,
+    output_dir: Q,
+    sizes: &[u16],
+    font_paths: &FontPaths,
+    base_options: &RenderOptions,
+) -> Result<BatchReport, BookError> {
+    let input_dir = input_dir.as_ref();
+    let output_dir = output_dir.as_ref();
+    base_options.check_geometry()?;
+    let mut epubs = Vec::new();
+    collect_epubs(input_dir, &mut epubs)?;
+    epubs.sort();
+
+    let sizes = if sizes.is_empty() { vec![10] } else { sizes.to_vec() };
+    let multi = sizes.len() > 1;
+    let mut report = BatchReport::default();
+    for epub in epubs {
+        let relative = epub.strip_prefix(input_dir).unwrap_or(&epub).to_path_buf();
+        let output = output_dir.join(&relative).with_extension("trbk");
+        let up_to_date = sizes.iter().all(|size| {
+            let path = output_path_for_size(&output, *size, multi);
+            matches!(trbk_is_stale(&path, &epub), Ok(false))
+        });
+        if up_to_date {
+            log::info!("Up to date: {}", relative.display());
+            report.skipped.push(relative);
+            continue;
+        }
+        log::info!("Converting {}", relative.display());
+        match convert_epub_to_trbk_multi(&epub, &output, &sizes, font_paths, base_options) {
+            Ok(()) => report.converted.push(relative),
+            Err(err) => report.failed.push((relative, err.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+fn collect_epubs(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), BookError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            // Includes the EPUB caches kept beside each book.
+            continue;
+        }
+        if path.is_dir() {
+            collect_epubs(&path, out)?;
+            continue;
+        }
+        let is_epub = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("epub") || ext.eq_ignore_ascii_case("epb"));
+        if is_epub {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// FNV-1a over the source file, stored in each TRBK header and sidecar so a
+/// book can be matched against the EPUB it came from.
+pub fn source_hash(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c_9dc5, |hash: u32, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// True when `trbk_path` was not built from the current contents of
+/// `epub_path`. Books converted before source hashes were recorded store 0
+/// and always count as stale.
+pub fn trbk_is_stale<P: AsRef<Path>, Q: AsRef<Path>>(
+    trbk_path: P,
+    epub_path: Q,
+) -> Result<bool, BookError> {
+    let mut header = [0u8; 0x28];
+    File::open(trbk_path)?.read_exact(&mut header)?;
+    if &header[0..4] != b"TRBK" {
+        return Err(BookError::InvalidOutput);
+    }
+    let stored = u32::from_le_bytes([header[0x24], header[0x25], header[0x26], header[0x27]]);
+    Ok(stored != source_hash(&std::fs::read(epub_path)?))
+}
+
+/// Where [`RenderOptions::write_sidecar`] puts the `.epubinfo` for a
+/// conversion: `<epub stem>.epubinfo` beside `output_path`.
+pub fn epubinfo_path<P: AsRef<Path>, Q: AsRef<Path>>(epub_path: P, output_path: Q) -> PathBuf {
+    let stem = epub_path
+        .as_ref()
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "book".to_string());
+    let mut path = output_path
+        .as_ref()
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    path.push(format!("{}.epubinfo", stem));
+    path
+}
+
+/// Writes the `.epubinfo` sidecar so the device can describe the EPUB
+/// without parsing it. `books` lists each TRBK written as (file name, pages).
+fn write_epubinfo(
+    epub_path: &Path,
+    output_path: &Path,
+    prepared: &PreparedBook,
+    books: Vec<(String, usize)>,
+) -> Result<(), BookError> {
+    // The device reads one `key=value` per line.
+    let single_line = |value: &Option<String>| {
+        value
+            .as_deref()
+            .unwrap_or("")
+            .replace(['\r', '\n'], " ")
+            .trim()
+            .to_string()
+    };
+    let cache = &prepared.cache;
+    let info = trusty_core::image_viewer::EpubInfo {
+        title: single_line(&cache.metadata.title),
+        author: single_line(&cache.metadata.creator),
+        language: single_line(&cache.metadata.language),
+        chapters: if cache.toc.is_empty() {
+            cache.spine.len()
+        } else {
+            cache.toc.len()
+        },
+        words: cache.spine.iter().map(|entry| entry.word_count).sum(),
+        source_hash: prepared.metadata.source_hash,
+        books,
+    };
+    std::fs::write(epubinfo_path(epub_path, output_path), info.to_record())?;
+    Ok(())
+}
+
+/// What converting at one font size would produce.
+#[derive(Debug, Clone)]
+pub struct ConversionReport {
+    pub size: u16,
+    pub page_count: usize,
+    pub glyph_count: usize,
+    pub image_count: usize,
+    /// Size of the TRBK file, encoded in memory but not written.
+    pub output_bytes: u64,
+}
+
+/// Runs the full conversion for each size without writing any files.
+pub fn analyze_epub<P: AsRef<Path>>(
+    epub_path: P,
+    sizes: &[u16],
+    font_paths: &FontPaths,
+    base_options: &RenderOptions,
+) -> Result<Vec<ConversionReport>, BookError> {
+    let epub_path = epub_path.as_ref();
+    let mut progress = None;
+    // A dry run leaves nothing behind, not even the EPUB cache.
+    let cache = trusty_epub::scan_book(epub_path)?;
+    let prepared = prepare_book(epub_path, cache, font_paths, base_options, &mut progress)?;
+
+    let sizes = if sizes.is_empty() { vec![10] } else { sizes.to_vec() };
+    let mut reports = Vec::with_capacity(sizes.len());
+    for size in sizes {
+        let rendition = render_size(epub_path, &prepared, size, base_options, &mut progress)?;
+        let mut encoded = Vec::new();
+        write_trbk(&mut encoded, &prepared.metadata, &rendition)?;
+        reports.push(ConversionReport {
+            size,
+            page_count: rendition.pages.len(),
+            glyph_count: rendition.glyphs.len(),
+            image_count: rendition.image_assets.len(),
+            output_bytes: encoded.len() as u64,
+        });
+    }
+    Ok(reports)
+}
+
+/// Converts each size in memory, parses the bytes back with the device parser
+/// and compares the result against what was laid out. Returns one message
+/// per mismatch; an empty list means every size round-tripped.
+pub fn verify_epub<P: AsRef<Path>>(
+    epub_path: P,
+    sizes: &[u16],
+    font_paths: &FontPaths,
+    base_options: &RenderOptions,
+) -> Result<Vec<String>, BookError> {
+    let epub_path = epub_path.as_ref();
+    let mut progress = None;
+    let cache = cached_book(epub_path)?;
+    let prepared = prepare_book(epub_path, cache, font_paths, base_options, &mut progress)?;
+
+    let sizes = if sizes.is_empty() { vec![10] } else { sizes.to_vec() };
+    let mut issues = Vec::new();
+    for size in sizes {
+        let rendition = render_size(epub_path, &prepared, size, base_options, &mut progress)?;
+        let mut encoded = Vec::new();
+        write_trbk(&mut encoded, &prepared.metadata, &rendition)?;
+        issues.extend(
+            round_trip_issues(&encoded, &prepared.metadata, &rendition)
+                .into_iter()
+                .map(|issue| format!("size {size}: {issue}")),
+        );
+    }
+    Ok(issues)
+}
+
+fn round_trip_issues(encoded: &[u8], metadata: &TrbkMetadata, rendition: &Rendition) -> Vec<String> {
+    let book = match trusty_core::trbk::parse_trbk(encoded) {
+        Ok(book) => book,
+        Err(err) => return vec![format!("device parser rejects output: {err:?}")],
+    };
+    let mut issues = Vec::new();
+    if book.page_count != rendition.pages.len() || book.pages.len() != rendition.pages.len() {
+        issues.push(format!(
+            "wrote {} pages, read back {} ({} parsed)",
+            rendition.pages.len(),
+            book.page_count,
+            book.pages.len()
+        ));
+    }
+    let expected_metadata = [
+        ("title", &metadata.title, &book.metadata.title),
+        ("author", &metadata.author, &book.metadata.author),
+        ("language", &metadata.language, &book.metadata.language),
+        ("identifier", &metadata.identifier, &book.metadata.identifier),
+    ];
+    for (label, written, read) in expected_metadata {
+        if written != read {
+            issues.push(format!("{label} '{written}' read back as '{read}'"));
+        }
+    }
+    if book.toc.len() != rendition.toc_entries.len() {
+        issues.push(format!(
+            "wrote {} toc entries, read back {}",
+            rendition.toc_entries.len(),
+            book.toc.len()
+        ));
+    }
+    for (written, read) in rendition.toc_entries.iter().zip(&book.toc) {
+        if written.title != read.title
+            || written.page_index != read.page_index
+            || written.level != read.level
+            || written.y_offset != read.y_offset
+        {
+            issues.push(format!(
+                "toc entry '{}' (page {}, {}px) read back as '{}' (page {}, {}px)",
+                written.title,
+                written.page_index,
+                written.y_offset,
+                read.title,
+                read.page_index,
+                read.y_offset
+            ));
+        }
+    }
+    if book.glyphs.len() != rendition.glyphs.len() {
+        issues.push(format!(
+            "wrote {} glyphs, read back {}",
+            rendition.glyphs.len(),
+            book.glyphs.len()
+        ));
+    }
+    issues.extend(dump::missing_glyph_issues(&book));
+    issues
+}
+
+/// Everything about a book that doesn't depend on the font size.
+struct PreparedBook {
+    cache: trusty_epub::BookCache,
+    metadata: TrbkMetadata,
+    spine_blocks: Vec<SpineBlocks>,
+    used: HashMap<(StyleId, u8), BTreeSet<u32>>,
+    used_pairs: HashMap<(StyleId, u8), BTreeSet<(u32, u32)>>,
+    font_set: HashMap<StyleId, fontdue::Font>,
+    fallback_fonts: Vec<fontdue::Font>,
+}
+
+/// One size's laid-out pages and the tables that go with them.
+struct Rendition {
+    options: RenderOptions,
+    pages: Vec<PageData>,
+    glyphs: Vec<Glyph>,
+    kerning: KerningTable,
+    toc_entries: Vec<TrbkTocEntry>,
+    image_assets: Vec<ImageAsset>,
+    chapter_starts: Vec<u32>,
+    /// Index of the book's cover in `image_assets`.
+    cover_image: Option<u16>,
+}
+
+/// The EPUB's cache from its default cache dir, built there if missing.
+fn cached_book(epub_path: &Path) -> Result<trusty_epub::BookCache, BookError> {
     let cache_dir = trusty_epub::default_cache_dir(epub_path);
     let (cache, _) = trusty_epub::load_or_build_cache(epub_path, &cache_dir)?;
+    Ok(cache)
+}
+
+fn prepare_book(
+    epub_path: &Path,
+    cache: trusty_epub::BookCache,
+    font_paths: &FontPaths,
+    base_options: &RenderOptions,
+    progress: &mut Option<&mut dyn FnMut(ConvertProgress)>,
+) -> Result<PreparedBook, BookError> {
+    base_options.check_geometry()?;
 
     let metadata = TrbkMetadata {
         title: cache
@@ -195,85 +857,173 @@ pub fn convert_epub_to_trbk_multi<P: AsRef<Path>, Q: AsRef<Path>>(
             .as_deref()
             .unwrap_or("<unknown>")
             .to_string(),
+        source_hash: source_hash(&std::fs::read(epub_path)?),
     };
 
-    let spine_blocks = extract_blocks(epub_path, &cache, 200)?;
-    let used = collect_used_codepoints_from_blocks(&spine_blocks);
+    let mut spine_blocks = extract_blocks(
+        epub_path,
+        &cache,
+        200,
+        base_options.include_nonlinear,
+        base_options.css_emphasis,
+        progress,
+    )?;
+    if let Some(filter) = &base_options.codepoint_filter {
+        let script = CharBlock::for_language(cache.metadata.language.as_deref());
+        let replaced = filter_codepoints(&mut spine_blocks, filter, script);
+        if replaced > 0 {
+            eprintln!(
+                "[trusty-book] warning: replaced {replaced} character(s) outside the allowed blocks with '{}'",
+                filter.substitute
+            );
+        }
+    }
+    if let Some(path) = &base_options.hyphenation_patterns {
+        // After filtering, so the soft hyphens it inserts are never
+        // substituted, and before collecting codepoints, so the '-' drawn at a
+        // break gets a glyph. Code runs keep their monospace flag whether or
+        // not a mono font is given, so they are never hyphenated.
+        let hyphenator = hyphenation::Hyphenator::load(path)?;
+        hyphenate_blocks(&mut spine_blocks, &hyphenator);
+    }
     let font_set = load_fonts(font_paths)?;
+    let fallback_fonts = load_fallback_fonts(font_paths)?;
+    let used = collect_used_codepoints_from_blocks(&spine_blocks);
+    let used_pairs = collect_used_pairs_from_blocks(&spine_blocks);
     warn_missing_style_fonts(&used, &font_set);
 
-    let sizes = if sizes.is_empty() { vec![10] } else { sizes.to_vec() };
-    let multi = sizes.len() > 1;
-    for size in &sizes {
-        let mut options = RenderOptions::default();
-        let regular = font_set
-            .get(&StyleId::Regular)
-            .ok_or(BookError::InvalidOutput)?;
-        let (metrics, _) = regular.rasterize('n', *size as f32);
-        options.char_width = metrics.advance_width.round().max(1.0) as u16;
-        let mut codepoints = used
-            .get(&StyleId::Regular)
-            .cloned()
-            .unwrap_or_default();
-        if codepoints.is_empty() {
-            for set in used.values() {
-                codepoints.extend(set.iter().copied());
-            }
-        }
-        let ascent = compute_ascent(regular, *size, &codepoints);
-        options.ascent = ascent;
-        if let Some(lines) = regular.horizontal_line_metrics(*size as f32) {
-            let height = (lines.ascent - lines.descent + lines.line_gap)
-                .ceil()
-                .max(1.0) as u16;
-            let extra = (height / 6).max(2);
-            options.line_height = height.saturating_add(extra);
-        } else {
-            options.line_height = size.saturating_mul(2);
-        }
-        options.word_spacing = (options.char_width as i16 / 3).max(2);
-        let output = output_path_for_size(output_path, *size, multi);
-        if let Some(parent) = output.parent() {
-            std::fs::create_dir_all(parent)?;
+    Ok(PreparedBook {
+        cache,
+        metadata,
+        spine_blocks,
+        used,
+        used_pairs,
+        font_set,
+        fallback_fonts,
+    })
+}
+
+/// Derives the font metrics for `size`, then builds glyphs and paginates.
+fn render_size(
+    epub_path: &Path,
+    book: &PreparedBook,
+    size: u16,
+    base_options: &RenderOptions,
+    progress: &mut Option<&mut dyn FnMut(ConvertProgress)>,
+) -> Result<Rendition, BookError> {
+    let mut options = base_options.clone();
+    options.rtl |= book
+        .cache
+        .metadata
+        .language
+        .as_deref()
+        .is_some_and(is_rtl_language);
+    let regular = book
+        .font_set
+        .get(&StyleId::Regular)
+        .ok_or(BookError::InvalidOutput)?;
+    let (metrics, _) = regular.rasterize('n', size as f32);
+    options.char_width = metrics.advance_width.round().max(1.0) as u16;
+    let mut codepoints = book
+        .used
+        .get(&(StyleId::Regular, 0))
+        .cloned()
+        .unwrap_or_default();
+    if codepoints.is_empty() {
+        for set in book.used.values() {
+            codepoints.extend(set.iter().copied());
         }
-        let glyphs = build_glyphs(&font_set, *size, &used)?;
-        let advance_map = build_advance_map(&glyphs);
-        let (image_assets, image_map) = build_image_assets(epub_path, &spine_blocks, &options)?;
-        let items = layout_blocks(&spine_blocks, &options, &advance_map, &image_map);
-        let pages = paginate_items(&items, &options, &advance_map);
-        let spine_to_page = compute_spine_page_map(&pages, cache.spine.len());
-        let toc_entries = build_toc_entries(&cache, &spine_to_page);
-        write_trbk(
-            &output,
-            &metadata,
-            &options,
-            &pages,
-            &glyphs,
-            &toc_entries,
-            &image_assets,
-        )?;
     }
+    let ascent = compute_ascent(regular, size, &codepoints);
+    options.ascent = ascent;
+    if let Some(lines) = regular.horizontal_line_metrics(size as f32) {
+        let height = (lines.ascent - lines.descent + lines.line_gap)
+            .ceil()
+            .max(1.0) as u16;
+        let extra = (height / 6).max(2);
+        options.line_height = height.saturating_add(extra);
+    } else {
+        options.line_height = size.saturating_mul(2);
+    }
+    options.word_spacing = (options.char_width as i16 / 3).max(2);
+    report_progress(progress, ConvertProgress::BuildingGlyphs { size });
+    let glyphs = build_glyphs(
+        &book.font_set,
+        &book.fallback_fonts,
+        size,
+        &book.used,
+        options.grayscale_glyphs,
+    )?;
+    // Words are stored reversed for RTL, so logical pairs don't apply.
+    let kerning = if options.rtl {
+        KerningTable::new()
+    } else {
+        build_kerning(&book.font_set, size, &book.used_pairs)
+    };
+    let advance_map = build_advance_map(&glyphs, &kerning);
+    let cover_path = book.cache.cover_href.as_deref().map(|href| {
+        let opf_dir = trusty_epub::opf_base_dir(&book.cache.opf_path);
+        let path = trusty_epub::resolve_href(&opf_dir, &strip_fragment(href));
+        collapse_double_prefix(&normalize_path(&path), &opf_dir)
+    });
+    let (image_assets, image_map) =
+        build_image_assets(epub_path, &book.spine_blocks, cover_path.as_deref(), &options)?;
+    let cover_image = cover_path
+        .as_ref()
+        .and_then(|path| image_map.get(path))
+        .map(|image| image.index);
+    report_progress(progress, ConvertProgress::Paginating { size });
+    let items = layout_blocks(&book.spine_blocks, &options, &advance_map, &image_map);
+    let (pages, anchors) = paginate_items(&items, &options, &advance_map);
+    let spine_to_page = compute_spine_page_map(&pages, book.cache.spine.len());
+    let toc_entries = build_toc_entries(&book.cache, &book.spine_blocks, &spine_to_page, &anchors);
+    let chapter_starts = chapter_start_pages(&spine_to_page);
+    Ok(Rendition {
+        options,
+        pages,
+        glyphs,
+        kerning,
+        toc_entries,
+        image_assets,
+        chapter_starts,
+        cover_image,
+    })
+}
 
-    Ok(())
+fn report_progress(progress: &mut Option<&mut dyn FnMut(ConvertProgress)>, event: ConvertProgress) {
+    if let Some(callback) = progress {
+        callback(event);
+    }
 }
 
 fn extract_blocks(
     epub_path: &Path,
     cache: &trusty_epub::BookCache,
     max_spine_items: usize,
+    include_nonlinear: bool,
+    css_emphasis: bool,
+    progress: &mut Option<&mut dyn FnMut(ConvertProgress)>,
 ) -> Result<Vec<SpineBlocks>, BookError> {
     let mut out = Vec::new();
+    let mut stylesheets = HashMap::new();
     let max_try = cache.spine.len().min(max_spine_items).max(1);
     let opf_dir = trusty_epub::opf_base_dir(&cache.opf_path);
     for index in 0..max_try {
+        report_progress(
+            progress,
+            ConvertProgress::ExtractingSpine {
+                index,
+                total: max_try,
+            },
+        );
+        let linear = cache.spine.get(index).is_none_or(|entry| entry.linear);
+        if !linear && !include_nonlinear {
+            continue;
+        }
         let xhtml = match trusty_epub::read_spine_xhtml(epub_path, index) {
             Ok(xhtml) => xhtml,
             Err(_) => continue,
         };
-        let mut blocks = match trusty_epub::parse_xhtml_blocks(&xhtml) {
-            Ok(blocks) => blocks,
-            Err(_) => continue,
-        };
         let spine_href = cache
             .spine
             .get(index)
@@ -290,6 +1040,15 @@ fn extract_blocks(
         };
         let spine_path = collapse_double_prefix(&normalize_path(&spine_path), &opf_dir);
         let spine_dir = trusty_epub::opf_base_dir(&spine_path);
+        let styles = if css_emphasis {
+            linked_css_styles(epub_path, &xhtml, &spine_dir, &mut stylesheets)
+        } else {
+            trusty_epub::CssStyles::default()
+        };
+        let (mut blocks, anchors) = match trusty_epub::parse_xhtml_blocks_anchored(&xhtml, &styles) {
+            Ok(parsed) => parsed,
+            Err(_) => continue,
+        };
         for block in &mut blocks {
             if let trusty_epub::HtmlBlock::Image { src, .. } = block {
                 let mut cleaned = strip_fragment(src);
@@ -309,6 +1068,7 @@ fn extract_blocks(
             out.push(SpineBlocks {
                 spine_index: index as i32,
                 blocks,
+                anchors,
             });
         }
         if out.len() > 500 {
@@ -318,111 +1078,236 @@ fn extract_blocks(
     Ok(out)
 }
 
+/// Merged emphasis rules of the stylesheets a spine item links to. Parsed
+/// sheets are kept in `loaded` since most books share one stylesheet.
+fn linked_css_styles(
+    epub_path: &Path,
+    xhtml: &str,
+    spine_dir: &str,
+    loaded: &mut HashMap<String, trusty_epub::CssStyles>,
+) -> trusty_epub::CssStyles {
+    let mut styles = trusty_epub::CssStyles::default();
+    for href in trusty_epub::linked_stylesheets(xhtml) {
+        let path = normalize_path(&trusty_epub::resolve_href(spine_dir, &strip_fragment(&href)));
+        let sheet = loaded.entry(path).or_insert_with_key(|path| {
+            match trusty_epub::read_epub_resource_bytes(epub_path, path) {
+                Ok(bytes) => trusty_epub::parse_css(&String::from_utf8_lossy(&bytes)),
+                Err(err) => {
+                    log::warn!("Failed to read stylesheet {path}: {err}");
+                    trusty_epub::CssStyles::default()
+                }
+            }
+        });
+        styles.extend(sheet.clone());
+    }
+    styles
+}
+
+/// Marks break points in body paragraphs; headings, tables and code are left
+/// whole.
+fn hyphenate_blocks(blocks: &mut [SpineBlocks], hyphenator: &hyphenation::Hyphenator) {
+    for spine in blocks {
+        for block in &mut spine.blocks {
+            if let trusty_epub::HtmlBlock::Paragraph {
+                runs,
+                heading_level: None,
+            } = block
+            {
+                let mut texts: Vec<_> = runs
+                    .iter_mut()
+                    .map(|run| (!run.style.monospace).then_some(&mut run.text))
+                    .collect();
+                hyphenator.hyphenate_texts(&mut texts);
+            }
+        }
+    }
+}
+
 fn collect_used_codepoints_from_blocks(
     blocks: &[SpineBlocks],
-) -> HashMap<StyleId, BTreeSet<u32>> {
-    let mut used: HashMap<StyleId, BTreeSet<u32>> = HashMap::new();
+) -> HashMap<(StyleId, u8), BTreeSet<u32>> {
+    let mut used: HashMap<(StyleId, u8), BTreeSet<u32>> = HashMap::new();
     for spine in blocks {
         for block in &spine.blocks {
-            if let trusty_epub::HtmlBlock::Paragraph { runs, .. } = block {
+            if let trusty_epub::HtmlBlock::Paragraph {
+                runs,
+                heading_level,
+            } = block
+            {
+                let size_bucket = size_bucket_from_heading(*heading_level);
                 for run in runs {
                     let style = style_id_from_style(run.style);
-                    let entry = used.entry(style).or_default();
+                    let entry = used.entry((style, size_bucket)).or_default();
+                    // Wrapping inserts word separators in the run's own style.
+                    entry.insert(' ' as u32);
                     for ch in run.text.chars() {
+                        // Soft hyphens are only ever drawn as a visible '-'.
+                        let ch = if ch == SOFT_HYPHEN { '-' } else { ch };
                         entry.insert(ch as u32);
                     }
                 }
+            } else if let trusty_epub::HtmlBlock::TableRow { cells } = block {
+                for run in table_row_runs(cells) {
+                    let entry = used.entry((style_id_from_style(run.style), 0)).or_default();
+                    entry.insert(' ' as u32);
+                    entry.extend(run.text.chars().map(|ch| ch as u32));
+                }
+            } else if let trusty_epub::HtmlBlock::Image { alt, .. } = block {
+                // Any image may end up as a caption if it fails to embed.
+                let entry = used.entry((StyleId::Italic, 0)).or_default();
+                for ch in image_placeholder_text(alt.as_deref()).chars() {
+                    entry.insert(ch as u32);
+                }
+            }
+        }
+    }
+    used
+}
+
+/// Adjacent character pairs within words, for kerning lookups. A soft hyphen
+/// may be drawn as '-', so the pair ending there is recorded as well.
+fn collect_used_pairs_from_blocks(
+    blocks: &[SpineBlocks],
+) -> HashMap<(StyleId, u8), BTreeSet<(u32, u32)>> {
+    let mut used: HashMap<(StyleId, u8), BTreeSet<(u32, u32)>> = HashMap::new();
+    let mut add = |style: trusty_epub::TextStyle, size_bucket: u8, text: &str| {
+        let entry = used
+            .entry((style_id_from_style(style), size_bucket))
+            .or_default();
+        for word in text.split_whitespace() {
+            let mut prev: Option<char> = None;
+            for ch in word.chars() {
+                if ch == SOFT_HYPHEN {
+                    if let Some(prev) = prev {
+                        entry.insert((prev as u32, '-' as u32));
+                    }
+                    continue;
+                }
+                if let Some(prev) = prev.replace(ch) {
+                    entry.insert((prev as u32, ch as u32));
+                }
+            }
+        }
+    };
+    for spine in blocks {
+        for block in &spine.blocks {
+            match block {
+                trusty_epub::HtmlBlock::Paragraph {
+                    runs,
+                    heading_level,
+                } => {
+                    let size_bucket = size_bucket_from_heading(*heading_level);
+                    for run in runs {
+                        add(run.style, size_bucket, &run.text);
+                    }
+                }
+                trusty_epub::HtmlBlock::TableRow { cells } => {
+                    for run in table_row_runs(cells) {
+                        add(run.style, 0, &run.text);
+                    }
+                }
+                trusty_epub::HtmlBlock::Image { alt, .. } => {
+                    let style = trusty_epub::TextStyle {
+                        italic: true,
+                        ..Default::default()
+                    };
+                    add(style, 0, &image_placeholder_text(alt.as_deref()));
+                }
+                trusty_epub::HtmlBlock::PageBreak | trusty_epub::HtmlBlock::Separator => {}
             }
         }
     }
     used
 }
 
+/// Converts every image the pages show, plus the cover at `cover_path`
+/// (an archive path) even when no page shows it, keyed by archive path.
 fn build_image_assets(
     epub_path: &Path,
     blocks: &[SpineBlocks],
+    cover_path: Option<&str>,
     options: &RenderOptions,
 ) -> Result<(Vec<ImageAsset>, HashMap<String, ImageRef>), BookError> {
     let mut assets: Vec<ImageAsset> = Vec::new();
     let mut map: HashMap<String, ImageRef> = HashMap::new();
 
-    for spine in blocks {
-        for block in &spine.blocks {
-            let trusty_epub::HtmlBlock::Image { src, .. } = block else {
-                continue;
-            };
-            if map.contains_key(src) {
-                continue;
-            }
-            let mut candidates = Vec::new();
-            let mut candidate = strip_fragment(src);
+    let page_images = blocks.iter().flat_map(|spine| {
+        spine.blocks.iter().filter_map(|block| match block {
+            trusty_epub::HtmlBlock::Image { src, .. } => Some(src.as_str()),
+            _ => None,
+        })
+    });
+    for src in page_images.chain(cover_path) {
+        if map.contains_key(src) {
+            continue;
+        }
+        let mut candidates = Vec::new();
+        let mut candidate = strip_fragment(src);
+        candidates.push(normalize_path(&candidate));
+        let decoded = percent_decode(src);
+        if decoded != *src {
+            candidate = strip_fragment(&decoded);
             candidates.push(normalize_path(&candidate));
-            let decoded = percent_decode(src);
-            if decoded != *src {
-                candidate = strip_fragment(&decoded);
-                candidates.push(normalize_path(&candidate));
-            }
-            let mut bytes = None;
-            for candidate in candidates.iter().filter(|c| !c.is_empty()) {
-                match trusty_epub::read_epub_resource_bytes(epub_path, candidate) {
-                    Ok(data) => {
-                        bytes = Some(data);
-                        break;
-                    }
-                    Err(_) => {}
+        }
+        let mut bytes = None;
+        for candidate in candidates.iter().filter(|c| !c.is_empty()) {
+            match trusty_epub::read_epub_resource_bytes(epub_path, candidate) {
+                Ok(data) => {
+                    bytes = Some(data);
+                    break;
                 }
+                Err(_) => {}
             }
-            let Some(bytes) = bytes else {
-                eprintln!("[trusty-book] warning: image not found in epub: {src}");
+        }
+        let Some(bytes) = bytes else {
+            eprintln!("[trusty-book] warning: image not found in epub: {src}");
+            continue;
+        };
+        let dyn_image = match image::load_from_memory(&bytes) {
+            Ok(img) => img,
+            Err(_) => {
+                eprintln!("[trusty-book] warning: failed to decode image: {src}");
                 continue;
-            };
-            let dyn_image = match image::load_from_memory(&bytes) {
-                Ok(img) => img,
-                Err(_) => {
-                    eprintln!("[trusty-book] warning: failed to decode image: {src}");
-                    continue;
-                }
-            };
-            let (src_w, src_h) = dyn_image.dimensions();
-            let max_w = options.screen_width.max(1) as u32;
-            let max_h =
-                (options.screen_height as i32 - options.margin_y as i32 * 2).max(1) as u32;
-            let mut scale = if src_w >= max_w {
-                max_w as f64 / src_w.max(1) as f64
-            } else {
-                let up = max_w as f64 / src_w.max(1) as f64;
-                up.min(2.0)
-            };
-            let max_scale_h = max_h as f64 / src_h.max(1) as f64;
-            if scale > max_scale_h {
-                scale = max_scale_h;
             }
-            let target_w = (src_w as f64 * scale).round().max(1.0) as u32;
-            let target_h = (src_h as f64 * scale).round().max(1.0) as u32;
-            let mut convert = trusty_image::ConvertOptions::default();
-            convert.width = target_w;
-            convert.height = target_h;
-            convert.fit = trusty_image::FitMode::Contain;
-            convert.dither = trusty_image::DitherMode::Bayer;
-            convert.region_mode = trusty_image::RegionMode::None;
-            convert.invert = false;
-            convert.debug = false;
-            convert.yolo_model = None;
-            let trimg = trusty_image::convert_image(&dyn_image, convert);
-            let data = trimg_to_bytes(&trimg);
-            let index = assets.len() as u16;
-            let image_ref = ImageRef {
-                index,
-                width: trimg.width as u16,
-                height: trimg.height as u16,
-            };
-            assets.push(ImageAsset {
-                width: image_ref.width,
-                height: image_ref.height,
-                data,
-            });
-            map.insert(src.clone(), image_ref);
+        };
+        let (src_w, src_h) = dyn_image.dimensions();
+        let max_w = options.screen_width.max(1) as u32;
+        let max_h = (options.screen_height as i32 - options.margin_y as i32 * 2).max(1) as u32;
+        let mut scale = if src_w >= max_w {
+            max_w as f64 / src_w.max(1) as f64
+        } else {
+            let up = max_w as f64 / src_w.max(1) as f64;
+            up.min(2.0)
+        };
+        let max_scale_h = max_h as f64 / src_h.max(1) as f64;
+        if scale > max_scale_h {
+            scale = max_scale_h;
         }
+        let target_w = (src_w as f64 * scale).round().max(1.0) as u32;
+        let target_h = (src_h as f64 * scale).round().max(1.0) as u32;
+        let mut convert = trusty_image::ConvertOptions::default();
+        convert.width = target_w;
+        convert.height = target_h;
+        convert.fit = trusty_image::FitMode::Contain;
+        convert.dither = trusty_image::DitherMode::Bayer;
+        convert.region_mode = trusty_image::RegionMode::None;
+        convert.invert = false;
+        convert.debug = false;
+        convert.yolo_model = None;
+        let trimg = trusty_image::convert_image(&dyn_image, convert);
+        let data = trimg_to_bytes(&trimg);
+        let index = assets.len() as u16;
+        let image_ref = ImageRef {
+            index,
+            width: trimg.width as u16,
+            height: trimg.height as u16,
+        };
+        assets.push(ImageAsset {
+            width: image_ref.width,
+            height: image_ref.height,
+            data,
+        });
+        map.insert(src.to_string(), image_ref);
     }
 
     Ok((assets, map))
@@ -501,29 +1386,86 @@ fn hex_val(byte: u8) -> Option<u8> {
 fn layout_blocks(
     blocks: &[SpineBlocks],
     options: &RenderOptions,
-    advance_map: &HashMap<(StyleId, u32), i16>,
+    advance_map: &AdvanceMap,
     image_map: &HashMap<String, ImageRef>,
 ) -> Vec<LayoutItem> {
     let max_width = (options.screen_width as i32 - options.margin_x as i32 * 2).max(1);
     let mut items = Vec::new();
     for spine in blocks {
         let spine_index = spine.spine_index;
-        for block in &spine.blocks {
+        let mut anchors: Vec<(usize, &String)> =
+            spine.anchors.iter().map(|(id, index)| (*index, id)).collect();
+        anchors.sort();
+        let mut anchors = anchors.into_iter().peekable();
+        for (block_index, block) in spine.blocks.iter().enumerate() {
+            while let Some((_, id)) = anchors.next_if(|(index, _)| *index <= block_index) {
+                items.push(LayoutItem::Anchor {
+                    spine_index,
+                    id: id.clone(),
+                });
+            }
             match block {
-                trusty_epub::HtmlBlock::Paragraph { runs, .. } => {
-                    let lines = wrap_paragraph_runs(runs, max_width, options, advance_map);
-                    for line in lines {
+                trusty_epub::HtmlBlock::Paragraph {
+                    runs,
+                    heading_level,
+                } => {
+                    let size_bucket = size_bucket_from_heading(*heading_level);
+                    let indent = if heading_level.is_none() {
+                        options.paragraph_indent
+                    } else {
+                        0
+                    };
+                    let lines = wrap_paragraph_runs(
+                        runs,
+                        size_bucket,
+                        max_width,
+                        indent as i32,
+                        options,
+                        advance_map,
+                    );
+                    for (line_index, line) in lines.into_iter().enumerate() {
                         items.push(LayoutItem::TextLine {
                             spine_index,
+                            size_bucket,
+                            centered: false,
+                            indent: if line_index == 0 { indent } else { 0 },
                             runs: line,
                         });
                     }
                     items.push(LayoutItem::BlankLine { spine_index });
+                    if options.paragraph_spacing > 0 {
+                        items.push(LayoutItem::Spacing {
+                            spine_index,
+                            height: options.paragraph_spacing,
+                        });
+                    }
                 }
                 trusty_epub::HtmlBlock::PageBreak => {
                     items.push(LayoutItem::PageBreak { spine_index });
                 }
-                trusty_epub::HtmlBlock::Image { src, .. } => {
+                trusty_epub::HtmlBlock::Separator => {
+                    items.push(LayoutItem::Rule { spine_index });
+                    items.push(LayoutItem::BlankLine { spine_index });
+                }
+                trusty_epub::HtmlBlock::TableRow { cells } => {
+                    let runs = table_row_runs(cells);
+                    let lines = wrap_paragraph_runs(&runs, 0, max_width, 0, options, advance_map);
+                    for line in lines {
+                        items.push(LayoutItem::TextLine {
+                            spine_index,
+                            size_bucket: 0,
+                            centered: false,
+                            indent: 0,
+                            runs: line,
+                        });
+                    }
+                    // Rows of one table stay together; leave a gap after the last.
+                    let next = spine.blocks.get(block_index + 1);
+                    if !matches!(next, Some(trusty_epub::HtmlBlock::TableRow { .. })) {
+                        items.push(LayoutItem::BlankLine { spine_index });
+                    }
+                }
+                trusty_epub::HtmlBlock::Image { src, alt } => {
                     if let Some(image) = image_map.get(src) {
                         items.push(LayoutItem::Image {
                             spine_index,
@@ -532,56 +1474,149 @@ fn layout_blocks(
                             height: image.height,
                         });
                         items.push(LayoutItem::BlankLine { spine_index });
+                    } else {
+                        // Mark where the image would have been.
+                        let caption = trusty_epub::TextRun {
+                            text: image_placeholder_text(alt.as_deref()),
+                            style: trusty_epub::TextStyle {
+                                italic: true,
+                                ..Default::default()
+                            },
+                        };
+                        let lines =
+                            wrap_paragraph_runs(&[caption], 0, max_width, 0, options, advance_map);
+                        for line in lines {
+                            items.push(LayoutItem::TextLine {
+                                spine_index,
+                                size_bucket: 0,
+                                centered: true,
+                                indent: 0,
+                                runs: line,
+                            });
+                        }
+                        items.push(LayoutItem::BlankLine { spine_index });
                     }
                 }
             }
         }
+        for (_, id) in anchors {
+            items.push(LayoutItem::Anchor {
+                spine_index,
+                id: id.clone(),
+            });
+        }
     }
     items
 }
 
+/// Greedy word wrap; the first line is `first_indent` pixels narrower. CJK
+/// text has no spaces, so words are further split by [`line_break_segments`]
+/// and those pieces rejoin without a space when they share a line.
 fn wrap_paragraph_runs(
     runs: &[trusty_epub::TextRun],
+    size_bucket: u8,
     max_width: i32,
+    first_indent: i32,
     options: &RenderOptions,
-    advance_map: &HashMap<(StyleId, u32), i16>,
+    advance_map: &AdvanceMap,
 ) -> Vec<Vec<trusty_epub::TextRun>> {
     let mut lines = Vec::new();
     let mut current: Vec<trusty_epub::TextRun> = Vec::new();
     let mut current_width = 0i32;
 
     for run in runs {
-        for token in run.text.split_whitespace() {
-            let token_width = measure_token_width(token, run.style, options, advance_map);
-            if current_width == 0 {
-                current.push(trusty_epub::TextRun {
-                    text: token.to_string(),
-                    style: run.style,
-                });
-                current_width = token_width;
-                continue;
-            }
-            let space_width =
-                measure_token_width(" ", run.style, options, advance_map) + options.word_spacing as i32;
-            if current_width + space_width + token_width <= max_width {
-                current.push(trusty_epub::TextRun {
-                    text: " ".to_string(),
-                    style: run.style,
-                });
-                current.push(trusty_epub::TextRun {
-                    text: token.to_string(),
-                    style: run.style,
-                });
-                current_width += space_width + token_width;
-                continue;
+        for word in run.text.split_whitespace() {
+            for (segment_index, segment) in line_break_segments(word).into_iter().enumerate() {
+                let glued = segment_index > 0;
+                let mut token = segment;
+                loop {
+                    let max_width = if lines.is_empty() {
+                        max_width - first_indent
+                    } else {
+                        max_width
+                    };
+                    let visible = strip_soft_hyphens(&token);
+                    let token_width =
+                        measure_token_width(&visible, run.style, size_bucket, options, advance_map);
+                    let space_width = if current_width == 0 || glued {
+                        0
+                    } else {
+                        measure_token_width(" ", run.style, size_bucket, options, advance_map)
+                            + options.word_spacing as i32
+                    };
+                    if current_width + space_width + token_width <= max_width {
+                        let previous = current
+                            .last_mut()
+                            .filter(|last| glued && last.style == run.style && last.text != " ");
+                        if let Some(previous) = previous {
+                            // One run per stretch of CJK text instead of one per character.
+                            let before = measure_token_width(
+                                &previous.text,
+                                run.style,
+                                size_bucket,
+                                options,
+                                advance_map,
+                            );
+                            previous.text.push_str(&visible);
+                            current_width += measure_token_width(
+                                &previous.text,
+                                run.style,
+                                size_bucket,
+                                options,
+                                advance_map,
+                            ) - before;
+                            break;
+                        }
+                        if space_width > 0 {
+                            current.push(trusty_epub::TextRun {
+                                text: " ".to_string(),
+                                style: run.style,
+                            });
+                        }
+                        current.push(trusty_epub::TextRun {
+                            text: visible,
+                            style: run.style,
+                        });
+                        current_width += space_width + token_width;
+                        break;
+                    }
+                    let available = max_width - current_width - space_width;
+                    if let Some((head, tail)) = split_at_soft_hyphen(
+                        &token,
+                        available,
+                        run.style,
+                        size_bucket,
+                        options,
+                        advance_map,
+                    ) {
+                        if space_width > 0 {
+                            current.push(trusty_epub::TextRun {
+                                text: " ".to_string(),
+                                style: run.style,
+                            });
+                        }
+                        current.push(trusty_epub::TextRun {
+                            text: head,
+                            style: run.style,
+                        });
+                        lines.push(std::mem::take(&mut current));
+                        current_width = 0;
+                        token = tail;
+                        continue;
+                    }
+                    if current_width == 0 {
+                        // No break point fits; let the word overflow the line.
+                        current.push(trusty_epub::TextRun {
+                            text: visible,
+                            style: run.style,
+                        });
+                        current_width = token_width;
+                        break;
+                    }
+                    lines.push(std::mem::take(&mut current));
+                    current_width = 0;
+                }
             }
-            lines.push(current);
-            current = Vec::new();
-            current.push(trusty_epub::TextRun {
-                text: token.to_string(),
-                style: run.style,
-            });
-            current_width = token_width;
         }
         if run.text.contains('\n') {
             if !current.is_empty() {
@@ -599,12 +1634,166 @@ fn wrap_paragraph_runs(
     lines
 }
 
+/// Chinese and Japanese scripts, which wrap between any two characters.
+/// Hangul is left out since Korean separates words with spaces.
+fn is_cjk_char(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x3000..=0x30FF
+            | 0x31F0..=0x31FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xF900..=0xFAFF
+            | 0xFF00..=0xFFEF
+            | 0x20000..=0x2FFFF
+    )
+}
+
+/// Kinsoku: characters that must not start a line (closing punctuation,
+/// small kana, the prolonged sound mark).
+fn is_line_start_forbidden(ch: char) -> bool {
+    matches!(
+        ch,
+        '、' | '。' | '，' | '．' | '・' | '：' | '；' | '？' | '！' | '）' | '」' | '』' | '】'
+            | '〕' | '〉' | '》' | '］' | '｝' | '〙' | '〗' | '’' | '”' | 'ー' | '々' | 'ゝ'
+            | 'ゞ' | 'ヽ' | 'ヾ' | 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'っ' | 'ゃ' | 'ゅ' | 'ょ'
+            | 'ゎ' | 'ァ' | 'ィ' | 'ゥ' | 'ェ' | 'ォ' | 'ッ' | 'ャ' | 'ュ' | 'ョ' | 'ヮ' | 'ヵ'
+            | 'ヶ' | ')' | ']' | '}' | ',' | '.' | '!' | '?' | ':' | ';' | '％'
+    )
+}
+
+/// Kinsoku: opening brackets and quotes that must not end a line.
+fn is_line_end_forbidden(ch: char) -> bool {
+    matches!(
+        ch,
+        '（' | '「' | '『' | '【' | '〔' | '〈' | '《' | '［' | '｛' | '〘' | '〖' | '‘' | '“'
+            | '(' | '[' | '{'
+    )
+}
+
+/// Splits a space-free word where a line may break: before and after each CJK
+/// character, except where kinsoku forbids it. Words without CJK come back
+/// whole, so Latin wrapping is unchanged.
+fn line_break_segments(word: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    let mut last: Option<char> = None;
+    for ch in word.chars() {
+        if let Some(last) = last {
+            let breakable = (is_cjk_char(ch) || is_cjk_char(last))
+                && !is_line_start_forbidden(ch)
+                && !is_line_end_forbidden(last);
+            if breakable {
+                segments.push(std::mem::take(&mut segment));
+            }
+        }
+        segment.push(ch);
+        last = Some(ch);
+    }
+    if !segment.is_empty() {
+        segments.push(segment);
+    }
+    segments
+}
+
+fn image_placeholder_text(alt: Option<&str>) -> String {
+    let alt = alt.map(str::trim).unwrap_or_default();
+    if alt.is_empty() {
+        return "[ Image ]".to_string();
+    }
+    let mut label: String = alt.chars().take(MAX_PLACEHOLDER_ALT_CHARS).collect();
+    if alt.chars().count() > MAX_PLACEHOLDER_ALT_CHARS {
+        label = label.trim_end().to_string();
+        label.push_str("...");
+    }
+    format!("[ Image: {label} ]")
+}
+
+/// Primary language subtags of scripts written right to left.
+const RTL_LANGUAGES: [&str; 10] = ["ar", "he", "iw", "fa", "ur", "yi", "ps", "sd", "ug", "dv"];
+
+fn is_rtl_language(language: &str) -> bool {
+    let primary = language.split(['-', '_']).next().unwrap_or_default();
+    RTL_LANGUAGES.iter().any(|rtl| primary.eq_ignore_ascii_case(rtl))
+}
+
+/// Hebrew, Arabic and their presentation forms.
+fn is_rtl_char(ch: char) -> bool {
+    matches!(ch as u32, 0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF)
+}
+
+/// The device draws glyphs left to right, so RTL words are stored reversed.
+/// Words without RTL letters (numbers, Latin) keep their order; there is no
+/// real bidi or shaping.
+fn rtl_visual_text(text: &str) -> String {
+    if text.chars().any(is_rtl_char) {
+        text.chars().rev().collect()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Flattens a table row into one run list, separating the cells with a bar.
+/// There is no column alignment; wide rows simply wrap.
+fn table_row_runs(cells: &[Vec<trusty_epub::TextRun>]) -> Vec<trusty_epub::TextRun> {
+    let mut runs = Vec::new();
+    for (idx, cell) in cells.iter().enumerate() {
+        if idx > 0 {
+            runs.push(trusty_epub::TextRun {
+                text: TABLE_CELL_SEPARATOR.to_string(),
+                style: trusty_epub::TextStyle::default(),
+            });
+        }
+        runs.extend(cell.iter().filter(|run| !run.text.trim().is_empty()).cloned());
+    }
+    runs
+}
+
+fn strip_soft_hyphens(token: &str) -> String {
+    token.chars().filter(|ch| *ch != SOFT_HYPHEN).collect()
+}
+
+/// Breaks `token` at the last soft hyphen whose head, drawn with a visible '-',
+/// fits within `available`. Returns the head and the remaining tail.
+fn split_at_soft_hyphen(
+    token: &str,
+    available: i32,
+    style: trusty_epub::TextStyle,
+    size_bucket: u8,
+    options: &RenderOptions,
+    advance_map: &AdvanceMap,
+) -> Option<(String, String)> {
+    let breaks: Vec<usize> = token
+        .char_indices()
+        .filter(|(_, ch)| *ch == SOFT_HYPHEN)
+        .map(|(idx, _)| idx)
+        .collect();
+    for idx in breaks.into_iter().rev() {
+        let mut head = strip_soft_hyphens(&token[..idx]);
+        let tail = &token[idx + SOFT_HYPHEN.len_utf8()..];
+        if head.is_empty() || strip_soft_hyphens(tail).is_empty() {
+            continue;
+        }
+        head.push('-');
+        if measure_token_width(&head, style, size_bucket, options, advance_map) <= available {
+            return Some((head, tail.to_string()));
+        }
+    }
+    None
+}
+
+/// `(page_index, y_offset)` each anchor landed on, keyed by
+/// `(spine_index, id)`.
+type AnchorPositions = HashMap<(i32, String), (u32, u16)>;
+
+/// Lays items out into pages, also reporting where each anchor landed.
 fn paginate_items(
     items: &[LayoutItem],
     options: &RenderOptions,
-    advance_map: &HashMap<(StyleId, u32), i16>,
-) -> Vec<PageData> {
+    advance_map: &AdvanceMap,
+) -> (Vec<PageData>, AnchorPositions) {
     let mut pages = Vec::new();
+    let mut anchors = HashMap::new();
     let mut ops: Vec<PageOp> = Vec::new();
     let mut spine_index = -1i32;
     let mut cursor_y = options.margin_y as i32;
@@ -627,8 +1816,11 @@ fn paginate_items(
         let item_spine = match item {
             LayoutItem::TextLine { spine_index, .. } => *spine_index,
             LayoutItem::BlankLine { spine_index } => *spine_index,
+            LayoutItem::Spacing { spine_index, .. } => *spine_index,
             LayoutItem::Image { spine_index, .. } => *spine_index,
             LayoutItem::PageBreak { spine_index } => *spine_index,
+            LayoutItem::Rule { spine_index } => *spine_index,
+            LayoutItem::Anchor { spine_index, .. } => *spine_index,
         };
 
         if spine_index >= 0
@@ -647,27 +1839,121 @@ fn paginate_items(
             LayoutItem::PageBreak { .. } => {
                 flush_page(&mut pages, &mut ops, &mut spine_index, &mut cursor_y);
             }
+            LayoutItem::Anchor { id, .. } => {
+                // An anchor at the foot of a full page belongs to the next one.
+                let position = if !ops.is_empty() && cursor_y + line_height > max_y {
+                    (pages.len() as u32 + 1, 0)
+                } else {
+                    // Pixels rather than lines, since images, spacing and
+                    // headings above it aren't body-line sized.
+                    let offset = cursor_y - options.margin_y as i32;
+                    (pages.len() as u32, offset.clamp(0, u16::MAX as i32) as u16)
+                };
+                anchors.entry((item_spine, id.clone())).or_insert(position);
+            }
             LayoutItem::BlankLine { .. } => {
                 if cursor_y + line_height > max_y {
                     flush_page(&mut pages, &mut ops, &mut spine_index, &mut cursor_y);
                 }
                 cursor_y += line_height;
             }
-            LayoutItem::TextLine { runs, .. } => {
+            LayoutItem::Rule { .. } => {
                 if cursor_y + line_height > max_y {
                     flush_page(&mut pages, &mut ops, &mut spine_index, &mut cursor_y);
                 }
-                let baseline = cursor_y + options.ascent as i32;
-                let mut pen_x = options.margin_x as i32;
+                let max_width = options.screen_width as i32 - options.margin_x as i32 * 2;
+                let width = (max_width / 3).max(1);
+                ops.push(PageOp::HRule {
+                    x: (options.margin_x as i32 + (max_width - width) / 2).max(0) as u16,
+                    y: (cursor_y + line_height / 2) as u16,
+                    width: width as u16,
+                });
+                cursor_y += line_height;
+            }
+            LayoutItem::Spacing { height, .. } => {
+                let height = *height as i32;
+                if cursor_y + height > max_y {
+                    flush_page(&mut pages, &mut ops, &mut spine_index, &mut cursor_y);
+                } else {
+                    cursor_y += height;
+                }
+            }
+            LayoutItem::TextLine {
+                runs,
+                size_bucket,
+                centered,
+                indent,
+                ..
+            } => {
+                let line_height = scale_for_bucket(options.line_height as i32, *size_bucket);
+                if cursor_y + line_height > max_y {
+                    flush_page(&mut pages, &mut ops, &mut spine_index, &mut cursor_y);
+                }
+                let baseline = cursor_y + scale_for_bucket(options.ascent as i32, *size_bucket);
+                let mut pen_x = options.margin_x as i32 + *indent as i32;
+                if *centered {
+                    let line_width: i32 = runs
+                        .iter()
+                        .map(|run| {
+                            let mut adv = measure_token_width(
+                                &run.text,
+                                run.style,
+                                *size_bucket,
+                                options,
+                                advance_map,
+                            );
+                            if run.text == " " {
+                                adv += options.word_spacing as i32;
+                            }
+                            adv
+                        })
+                        .sum();
+                    let max_width = options.screen_width as i32 - options.margin_x as i32 * 2;
+                    pen_x += ((max_width - line_width) / 2).max(0);
+                }
+                if options.rtl {
+                    // Mirror the line: words run leftward from the right
+                    // margin (or the mirrored centre offset).
+                    let mut pen_x = options.screen_width as i32 - pen_x;
+                    for run in runs {
+                        let mut adv = measure_token_width(
+                            &run.text,
+                            run.style,
+                            *size_bucket,
+                            options,
+                            advance_map,
+                        );
+                        if run.text == " " {
+                            adv += options.word_spacing as i32;
+                        }
+                        pen_x -= adv;
+                        ops.push(PageOp::Text {
+                            x: pen_x.max(0) as u16,
+                            y: baseline as u16,
+                            style: style_id_from_style(run.style),
+                            size_bucket: *size_bucket,
+                            text: rtl_visual_text(&run.text),
+                        });
+                    }
+                    cursor_y += line_height;
+                    continue;
+                }
                 for run in runs {
                     let style_id = style_id_from_style(run.style);
                     ops.push(PageOp::Text {
                         x: pen_x as u16,
                         y: baseline as u16,
                         style: style_id,
+                        size_bucket: *size_bucket,
                         text: run.text.clone(),
                     });
-                    let mut adv = measure_token_width(&run.text, run.style, options, advance_map);
+                    let mut adv = measure_token_width(
+                        &run.text,
+                        run.style,
+                        *size_bucket,
+                        options,
+                        advance_map,
+                    );
                     if run.text == " " {
                         adv += options.word_spacing as i32;
                     }
@@ -710,19 +1996,62 @@ fn paginate_items(
                 x: options.margin_x,
                 y: (options.margin_y as i32 + options.ascent as i32) as u16,
                 style: StyleId::Regular,
+                size_bucket: 0,
                 text: "(empty)".to_string(),
             }],
         });
     }
-    pages
+    let last_page = pages.len() as u32 - 1;
+    for position in anchors.values_mut() {
+        if position.0 > last_page {
+            *position = (last_page, 0);
+        }
+    }
+    (pages, anchors)
 }
 
-fn build_advance_map(glyphs: &[Glyph]) -> HashMap<(StyleId, u32), i16> {
-    let mut map = HashMap::new();
+fn build_advance_map(glyphs: &[Glyph], kerning: &KerningTable) -> AdvanceMap {
+    let mut advances = HashMap::new();
     for glyph in glyphs {
-        map.insert((glyph.style, glyph.codepoint), glyph.x_advance);
+        advances.insert(
+            (style_key(glyph.style, glyph.size_bucket), glyph.codepoint),
+            glyph.x_advance,
+        );
     }
-    map
+    AdvanceMap {
+        advances,
+        kerning: kerning.clone(),
+    }
+}
+
+/// Looks up the font's kerning for each adjacent pair the text uses. Pairs
+/// where either glyph comes from another font are skipped.
+fn build_kerning(
+    fonts: &HashMap<StyleId, fontdue::Font>,
+    size: u16,
+    used_pairs: &HashMap<(StyleId, u8), BTreeSet<(u32, u32)>>,
+) -> KerningTable {
+    let mut table = KerningTable::new();
+    let Some(regular) = fonts.get(&StyleId::Regular) else {
+        return table;
+    };
+    for ((style, size_bucket), pairs) in used_pairs {
+        let font = fonts.get(style).unwrap_or(regular);
+        let px = size as f32 * bucket_scale(*size_bucket);
+        for (left, right) in pairs {
+            let (Some(l), Some(r)) = (char::from_u32(*left), char::from_u32(*right)) else {
+                continue;
+            };
+            if !font.has_glyph(l) || !font.has_glyph(r) {
+                continue;
+            }
+            let adjust = font.horizontal_kern(l, r, px).unwrap_or(0.0).round() as i16;
+            if adjust != 0 {
+                table.insert((style_key(*style, *size_bucket), *left, *right), adjust);
+            }
+        }
+    }
+    table
 }
 
 fn compute_ascent(font: &fontdue::Font, size: u16, codepoints: &BTreeSet<u32>) -> i16 {
@@ -751,28 +2080,41 @@ fn compute_ascent(font: &fontdue::Font, size: u16, codepoints: &BTreeSet<u32>) -
 fn measure_token_width(
     text: &str,
     style: trusty_epub::TextStyle,
+    size_bucket: u8,
     options: &RenderOptions,
-    advance_map: &HashMap<(StyleId, u32), i16>,
+    advance_map: &AdvanceMap,
 ) -> i32 {
     let mut width = 0i32;
-    let style_id = style_id_from_style(style);
+    let key = style_key(style_id_from_style(style), size_bucket);
+    let fallback = scale_for_bucket(options.char_width as i32, size_bucket);
+    let mut prev = None;
     for ch in text.chars() {
         let cp = ch as u32;
-        if let Some(adv) = advance_map.get(&(style_id, cp)) {
+        if let Some(prev) = prev.replace(cp) {
+            width += advance_map
+                .kerning
+                .get(&(key, prev, cp))
+                .copied()
+                .unwrap_or(0) as i32;
+        }
+        if let Some(adv) = advance_map.advances.get(&(key, cp)) {
             width += *adv as i32;
         } else {
-            width += options.char_width as i32;
+            width += fallback;
         }
     }
     width
 }
 
 fn warn_missing_style_fonts(
-    used: &HashMap<StyleId, BTreeSet<u32>>,
+    used: &HashMap<(StyleId, u8), BTreeSet<u32>>,
     fonts: &HashMap<StyleId, fontdue::Font>,
 ) {
     let warn = |style: StyleId, label: &str| {
-        if used.get(&style).map_or(false, |set| !set.is_empty()) && !fonts.contains_key(&style) {
+        let has_text = used
+            .iter()
+            .any(|((used_style, _), set)| *used_style == style && !set.is_empty());
+        if has_text && !fonts.contains_key(&style) {
             eprintln!(
                 "[trusty-book] warning: {label} text found but no {label} font was loaded; using regular"
             );
@@ -781,6 +2123,7 @@ fn warn_missing_style_fonts(
     warn(StyleId::Bold, "bold");
     warn(StyleId::Italic, "italic");
     warn(StyleId::BoldItalic, "bold-italic");
+    warn(StyleId::Mono, "mono");
 }
 
 fn compute_spine_page_map(pages: &[PageData], spine_count: usize) -> Vec<i32> {
@@ -796,9 +2139,24 @@ fn compute_spine_page_map(pages: &[PageData], spine_count: usize) -> Vec<i32> {
     map
 }
 
+/// Distinct first pages of the rendered spine items, in page order. Each
+/// spine item starts on a fresh page, so these are the chapter boundaries.
+fn chapter_start_pages(spine_to_page: &[i32]) -> Vec<u32> {
+    let mut starts: Vec<u32> = spine_to_page
+        .iter()
+        .filter(|page| **page >= 0)
+        .map(|page| *page as u32)
+        .collect();
+    starts.sort_unstable();
+    starts.dedup();
+    starts
+}
+
 fn build_toc_entries(
     cache: &trusty_epub::BookCache,
+    spine_blocks: &[SpineBlocks],
     spine_to_page: &[i32],
+    anchors: &AnchorPositions,
 ) -> Vec<TrbkTocEntry> {
     let mut entries = Vec::new();
     for entry in &cache.toc {
@@ -813,10 +2171,15 @@ fn build_toc_entries(
         if page_index < 0 {
             continue;
         }
+        let (page_index, y_offset) = anchors
+            .get(&(entry.spine_index, entry.anchor.clone()))
+            .copied()
+            .unwrap_or((page_index as u32, 0));
         entries.push(TrbkTocEntry {
             title: entry.title.clone(),
-            page_index: page_index as u32,
+            page_index,
             level: entry.level,
+            y_offset,
         });
     }
     if entries.is_empty() {
@@ -825,39 +2188,90 @@ fn build_toc_entries(
             if page_index < 0 {
                 continue;
             }
-            let title = spine
-                .href
-                .split('/')
-                .last()
-                .unwrap_or("Chapter")
-                .to_string();
+            let title = spine_blocks
+                .iter()
+                .find(|blocks| blocks.spine_index == idx as i32)
+                .and_then(|blocks| spine_text_label(&blocks.blocks))
+                .unwrap_or_else(|| {
+                    spine
+                        .href
+                        .split('/')
+                        .last()
+                        .unwrap_or("Chapter")
+                        .to_string()
+                });
             entries.push(TrbkTocEntry {
                 title,
                 page_index: page_index as u32,
                 level: 0,
+                y_offset: 0,
             });
         }
     }
     entries
 }
 
-fn write_trbk(
-    path: &Path,
+/// TOC label for a spine item in a book without a TOC: its first heading,
+/// else its first paragraph, cut at a word to `MAX_TEXT_LABEL_CHARS`.
+fn spine_text_label(blocks: &[trusty_epub::HtmlBlock]) -> Option<String> {
+    let paragraphs: Vec<(String, bool)> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            trusty_epub::HtmlBlock::Paragraph {
+                runs,
+                heading_level,
+            } => {
+                let text: String = runs
+                    .iter()
+                    .flat_map(|run| run.text.chars())
+                    .filter(|ch| *ch != SOFT_HYPHEN)
+                    .collect();
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                (!text.is_empty()).then_some((text, heading_level.is_some()))
+            }
+            _ => None,
+        })
+        .collect();
+    let text = paragraphs
+        .iter()
+        .find(|(_, heading)| *heading)
+        .or(paragraphs.first())
+        .map(|(text, _)| text.as_str())?;
+    if text.chars().count() <= MAX_TEXT_LABEL_CHARS {
+        return Some(text.to_string());
+    }
+    let cut: String = text.chars().take(MAX_TEXT_LABEL_CHARS).collect();
+    let mut label = match cut.rfind(' ') {
+        Some(space) if space > 0 => cut[..space].to_string(),
+        _ => cut,
+    };
+    label.truncate(label.trim_end_matches([',', ';', ':', '.']).len());
+    label.push_str("...");
+    Some(label)
+}
+
+fn write_trbk<W: Write>(
+    file: &mut W,
     metadata: &TrbkMetadata,
-    options: &RenderOptions,
-    pages: &[PageData],
-    glyphs: &[Glyph],
-    toc_entries: &[TrbkTocEntry],
-    image_assets: &[ImageAsset],
+    rendition: &Rendition,
 ) -> Result<(), BookError> {
-    let mut file = File::create(path)?;
+    let Rendition {
+        options,
+        pages,
+        glyphs,
+        kerning,
+        toc_entries,
+        image_assets,
+        chapter_starts,
+        cover_image,
+    } = rendition;
 
     let toc_count: u32 = toc_entries.len() as u32;
     let page_count = pages.len() as u32;
     let glyph_count = glyphs.len() as u32;
     let image_count = image_assets.len() as u32;
 
-    let fixed_header_size: u16 = 0x30;
+    let fixed_header_size: u16 = 0x34;
 
     let mut metadata_bytes = Vec::new();
     write_string(&mut metadata_bytes, &metadata.title)?;
@@ -872,6 +2286,22 @@ fn write_trbk(
     metadata_bytes.extend_from_slice(&options.margin_x.to_le_bytes());
     metadata_bytes.extend_from_slice(&options.margin_y.to_le_bytes());
     metadata_bytes.extend_from_slice(&options.margin_y.to_le_bytes());
+    // The header size is a u16, so drop the markers rather than overflow it.
+    let chapter_bytes = 4 + chapter_starts.len() * 4 + 2;
+    if fixed_header_size as usize + metadata_bytes.len() + chapter_bytes <= u16::MAX as usize {
+        metadata_bytes.extend_from_slice(&(chapter_starts.len() as u32).to_le_bytes());
+        for page in chapter_starts {
+            metadata_bytes.extend_from_slice(&page.to_le_bytes());
+        }
+    } else {
+        eprintln!(
+            "[trusty-book] warning: {} chapters don't fit in the header; omitting chapter markers",
+            chapter_starts.len()
+        );
+        metadata_bytes.extend_from_slice(&0u32.to_le_bytes());
+    }
+    let cover = cover_image.unwrap_or(trusty_core::trbk::TRBK_NO_COVER);
+    metadata_bytes.extend_from_slice(&cover.to_le_bytes());
 
     let header_size: u16 = fixed_header_size + metadata_bytes.len() as u16;
     let toc_offset: u32 = header_size as u32;
@@ -881,7 +2311,7 @@ fn write_trbk(
         toc_bytes.extend_from_slice(&entry.page_index.to_le_bytes());
         toc_bytes.push(entry.level);
         toc_bytes.push(0);
-        toc_bytes.extend_from_slice(&0u16.to_le_bytes());
+        toc_bytes.extend_from_slice(&entry.y_offset.to_le_bytes());
     }
     let page_lut_offset: u32 = toc_offset + toc_bytes.len() as u32;
 
@@ -894,11 +2324,17 @@ fn write_trbk(
 
         for op in &page.ops {
             match op {
-                PageOp::Text { x, y, style, text } => {
+                PageOp::Text {
+                    x,
+                    y,
+                    style,
+                    size_bucket,
+                    text,
+                } => {
                     let mut payload = Vec::new();
                     payload.extend_from_slice(&x.to_le_bytes());
                     payload.extend_from_slice(&y.to_le_bytes());
-                    payload.push(*style as u8);
+                    payload.push(style_key(*style, *size_bucket));
                     payload.push(0);
                     payload.extend_from_slice(text.as_bytes());
                     let length = payload.len() as u16;
@@ -925,21 +2361,41 @@ fn write_trbk(
                     page_data.extend_from_slice(&length.to_le_bytes());
                     page_data.extend_from_slice(&payload);
                 }
+                PageOp::HRule { x, y, width } => {
+                    let mut payload = Vec::new();
+                    payload.extend_from_slice(&x.to_le_bytes());
+                    payload.extend_from_slice(&y.to_le_bytes());
+                    payload.extend_from_slice(&width.to_le_bytes());
+                    let length = payload.len() as u16;
+                    page_data.push(0x04);
+                    page_data.extend_from_slice(&length.to_le_bytes());
+                    page_data.extend_from_slice(&payload);
+                }
             }
         }
     }
 
     let page_data_offset = page_lut_offset + page_lut.len() as u32;
     let glyph_table_offset = page_data_offset + page_data.len() as u32;
-    let images_offset = if image_count > 0 {
-        glyph_table_offset + glyphs_serialized_len(glyphs) as u32
+    let glyph_table_end = glyph_table_offset + glyphs_serialized_len(glyphs) as u32;
+    let (kerning_offset, kerning_end) = if kerning.is_empty() {
+        (0, glyph_table_end)
     } else {
-        0
+        (
+            glyph_table_end,
+            glyph_table_end + kerning_serialized_len(kerning) as u32,
+        )
     };
+    let images_offset = if image_count > 0 { kerning_end } else { 0 };
 
     file.write_all(b"TRBK")?;
-    file.write_all(&[2u8])?; // version
-    file.write_all(&[0u8])?; // flags
+    file.write_all(&[5u8])?; // version
+    let flags = if options.grayscale_glyphs {
+        trusty_core::trbk::TRBK_FLAG_GRAY_GLYPHS
+    } else {
+        0
+    };
+    file.write_all(&[flags])?;
     file.write_all(&header_size.to_le_bytes())?;
     file.write_all(&options.screen_width.to_le_bytes())?;
     file.write_all(&options.screen_height.to_le_bytes())?;
@@ -949,9 +2405,10 @@ fn write_trbk(
     file.write_all(&toc_offset.to_le_bytes())?;
     file.write_all(&page_data_offset.to_le_bytes())?;
     file.write_all(&images_offset.to_le_bytes())?;
-    file.write_all(&0u32.to_le_bytes())?; // source hash
+    file.write_all(&metadata.source_hash.to_le_bytes())?;
     file.write_all(&glyph_count.to_le_bytes())?;
     file.write_all(&glyph_table_offset.to_le_bytes())?;
+    file.write_all(&kerning_offset.to_le_bytes())?;
 
     file.write_all(&metadata_bytes)?;
 
@@ -960,9 +2417,12 @@ fn write_trbk(
     }
     file.write_all(&page_lut)?;
     file.write_all(&page_data)?;
-    write_glyph_table(&mut file, glyphs)?;
+    write_glyph_table(file, glyphs)?;
+    if !kerning.is_empty() {
+        write_kerning_table(file, kerning)?;
+    }
     if image_count > 0 {
-        write_image_table(&mut file, image_assets)?;
+        write_image_table(file, image_assets)?;
     }
     Ok(())
 }
@@ -990,7 +2450,41 @@ fn output_path_for_size(base: &Path, size: u16, multi: bool) -> PathBuf {
     out
 }
 
+fn size_bucket_from_heading(heading_level: Option<u8>) -> u8 {
+    match heading_level {
+        Some(1) => 1,
+        Some(2) => 2,
+        Some(_) => 3,
+        None => 0,
+    }
+}
+
+fn bucket_scale(size_bucket: u8) -> f32 {
+    HEADING_SCALES
+        .get(size_bucket as usize)
+        .copied()
+        .unwrap_or(1.0)
+}
+
+fn scale_for_bucket(value: i32, size_bucket: u8) -> i32 {
+    (value as f32 * bucket_scale(size_bucket)).round() as i32
+}
+
+/// Style byte as stored in the glyph table and TextRun ops: the low two bits are
+/// the `StyleId`, bits 2-3 carry the heading size bucket and bit 4 marks mono.
+fn style_key(style: StyleId, size_bucket: u8) -> u8 {
+    let base = match style {
+        StyleId::Mono => trusty_core::trbk::TRBK_STYLE_MONO,
+        style => style as u8,
+    };
+    base | (size_bucket << 2)
+}
+
+/// Mono has a single face, so bold and italic are dropped inside code spans.
 fn style_id_from_style(style: trusty_epub::TextStyle) -> StyleId {
+    if style.monospace {
+        return StyleId::Mono;
+    }
     match (style.bold, style.italic) {
         (false, false) => StyleId::Regular,
         (true, false) => StyleId::Bold,
@@ -999,6 +2493,37 @@ fn style_id_from_style(style: trusty_epub::TextStyle) -> StyleId {
     }
 }
 
+/// Runs `filter` over every run and image caption. Returns the number of
+/// substitutes written.
+fn filter_codepoints(
+    blocks: &mut [SpineBlocks],
+    filter: &CodepointFilter,
+    script: CharBlock,
+) -> usize {
+    let mut replaced = 0;
+    for spine in blocks {
+        for block in &mut spine.blocks {
+            match block {
+                trusty_epub::HtmlBlock::Paragraph { runs, .. } => {
+                    for run in runs {
+                        replaced += filter.apply(&mut run.text, script);
+                    }
+                }
+                trusty_epub::HtmlBlock::TableRow { cells } => {
+                    for run in cells.iter_mut().flatten() {
+                        replaced += filter.apply(&mut run.text, script);
+                    }
+                }
+                trusty_epub::HtmlBlock::Image { alt: Some(alt), .. } => {
+                    replaced += filter.apply(alt, script);
+                }
+                _ => {}
+            }
+        }
+    }
+    replaced
+}
+
 fn load_fonts(paths: &FontPaths) -> Result<HashMap<StyleId, fontdue::Font>, BookError> {
     let mut map = HashMap::new();
     let regular_path = paths
@@ -1064,10 +2589,37 @@ fn load_fonts(paths: &FontPaths) -> Result<HashMap<StyleId, fontdue::Font>, Book
             .map_err(|_| BookError::InvalidOutput)?;
         map.insert(StyleId::BoldItalic, font);
     }
+    if let Some(path) = paths.mono.as_deref() {
+        let bytes = std::fs::read(path).map_err(|err| {
+            BookError::Io(std::io::Error::new(
+                err.kind(),
+                format!("missing font file: {path}"),
+            ))
+        })?;
+        let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+            .map_err(|_| BookError::InvalidOutput)?;
+        map.insert(StyleId::Mono, font);
+    }
 
     Ok(map)
 }
 
+fn load_fallback_fonts(paths: &FontPaths) -> Result<Vec<fontdue::Font>, BookError> {
+    let mut fonts = Vec::with_capacity(paths.fallback.len());
+    for path in &paths.fallback {
+        let bytes = std::fs::read(path).map_err(|err| {
+            BookError::Io(std::io::Error::new(
+                err.kind(),
+                format!("missing font file: {path}"),
+            ))
+        })?;
+        let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+            .map_err(|_| BookError::InvalidOutput)?;
+        fonts.push(font);
+    }
+    Ok(fonts)
+}
+
 #[derive(Clone, Copy, Debug)]
 enum FontVariant {
     Bold,
@@ -1124,23 +2676,56 @@ fn guess_font_variant(regular_path: &str, variant: FontVariant) -> Option<String
 
 fn build_glyphs(
     fonts: &HashMap<StyleId, fontdue::Font>,
+    fallback: &[fontdue::Font],
     size: u16,
-    used: &HashMap<StyleId, BTreeSet<u32>>,
+    used: &HashMap<(StyleId, u8), BTreeSet<u32>>,
+    grayscale: bool,
 ) -> Result<Vec<Glyph>, BookError> {
     let mut glyphs = Vec::new();
-    for (style, codepoints) in used {
-        let font = fonts
-            .get(style)
-            .or_else(|| fonts.get(&StyleId::Regular))
-            .ok_or(BookError::InvalidOutput)?;
+    let mut unresolved = BTreeSet::new();
+    // Styles without their own font, and CJK text that all comes from one
+    // fallback, would otherwise rasterize the same outline once per style.
+    let mut rasterized: HashMap<(*const fontdue::Font, char, u32), (fontdue::Metrics, Vec<u8>)> =
+        HashMap::new();
+    let regular = fonts
+        .get(&StyleId::Regular)
+        .ok_or(BookError::InvalidOutput)?;
+    for ((style, size_bucket), codepoints) in used {
+        let font = fonts.get(style).unwrap_or(regular);
+        let px = size as f32 * bucket_scale(*size_bucket);
         for codepoint in codepoints {
             if let Some(ch) = char::from_u32(*codepoint) {
-                let (metrics, bitmap) = font.rasterize(ch, size as f32);
+                // Walk style font -> regular -> fallback chain; whitespace has no
+                // outline in most fonts, so the primary font always handles it.
+                let chain = std::iter::once(font)
+                    .chain(std::iter::once(regular))
+                    .chain(fallback.iter());
+                let mut chosen = None;
+                for candidate in chain {
+                    if ch.is_whitespace() || candidate.has_glyph(ch) {
+                        chosen = Some(candidate);
+                        break;
+                    }
+                }
+                let chosen = chosen.unwrap_or_else(|| {
+                    unresolved.insert(*codepoint);
+                    font
+                });
+                let (metrics, bitmap) = rasterized
+                    .entry((chosen as *const fontdue::Font, ch, px.to_bits()))
+                    .or_insert_with(|| chosen.rasterize(ch, px));
+                let metrics = *metrics;
                 let y_offset = (metrics.ymin + metrics.height as i32) as i16;
-                let packed = pack_bitmap(&bitmap, metrics.width as usize, metrics.height as usize);
+                let (width, height) = (metrics.width, metrics.height);
+                let packed = if grayscale {
+                    pack_bitmap_gray(bitmap, width, height)
+                } else {
+                    pack_bitmap(bitmap, width, height)
+                };
                 glyphs.push(Glyph {
                     codepoint: *codepoint,
                     style: *style,
+                    size_bucket: *size_bucket,
                     width: metrics.width as u8,
                     height: metrics.height as u8,
                     x_advance: metrics.advance_width.round() as i16,
@@ -1151,6 +2736,19 @@ fn build_glyphs(
             }
         }
     }
+    if !unresolved.is_empty() {
+        let list = unresolved
+            .iter()
+            .take(32)
+            .map(|cp| format!("U+{cp:04X}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let more = if unresolved.len() > 32 { ", ..." } else { "" };
+        eprintln!(
+            "[trusty-book] warning: {} codepoint(s) missing from all fonts: {list}{more}",
+            unresolved.len()
+        );
+    }
     Ok(glyphs)
 }
 
@@ -1167,30 +2765,89 @@ fn pack_bitmap(bitmap: &[u8], width: usize, height: usize) -> Vec<u8> {
     out
 }
 
+/// Packs coverage into two bitplanes, MSB plane first. Levels run from white
+/// (0) to black (3); the MSB plane alone matches the 1-bit threshold, so
+/// readers that ignore the second plane still draw sensible text.
+fn pack_bitmap_gray(bitmap: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let total = width * height;
+    let plane = total.div_ceil(8);
+    let mut out = vec![0u8; plane * 2];
+    for (i, &coverage) in bitmap.iter().take(total).enumerate() {
+        let byte = i / 8;
+        let bit = 7 - (i % 8);
+        let level = (coverage as u16 + 42) / 85;
+        if level & 0b10 != 0 {
+            out[byte] |= 1 << bit;
+        }
+        if level & 0b01 != 0 {
+            out[plane + byte] |= 1 << bit;
+        }
+    }
+    out
+}
+
 fn write_glyph_table<W: Write>(writer: &mut W, glyphs: &[Glyph]) -> Result<(), BookError> {
-    for glyph in glyphs {
+    let (bitmap_refs, bitmaps) = dedup_glyph_bitmaps(glyphs);
+    for (glyph, bitmap_ref) in glyphs.iter().zip(&bitmap_refs) {
         writer.write_all(&glyph.codepoint.to_le_bytes())?;
-        writer.write_all(&[glyph.style as u8])?;
+        writer.write_all(&[style_key(glyph.style, glyph.size_bucket)])?;
         writer.write_all(&[glyph.width])?;
         writer.write_all(&[glyph.height])?;
         writer.write_all(&glyph.x_advance.to_le_bytes())?;
         writer.write_all(&glyph.x_offset.to_le_bytes())?;
         writer.write_all(&glyph.y_offset.to_le_bytes())?;
-        let len = glyph.bitmap.len() as u32;
-        writer.write_all(&len.to_le_bytes())?;
-        writer.write_all(&glyph.bitmap)?;
+        writer.write_all(&bitmap_ref.to_le_bytes())?;
+    }
+    writer.write_all(&(bitmaps.len() as u32).to_le_bytes())?;
+    for bitmap in bitmaps {
+        writer.write_all(&(bitmap.len() as u32).to_le_bytes())?;
+        writer.write_all(bitmap)?;
     }
     Ok(())
 }
 
-fn glyphs_serialized_len(glyphs: &[Glyph]) -> usize {
-    let mut total = 0usize;
+/// Collapses identical glyph bitmaps into a shared pool. Returns the pool index
+/// for each glyph alongside the unique bitmaps in first-seen order.
+fn dedup_glyph_bitmaps(glyphs: &[Glyph]) -> (Vec<u32>, Vec<&[u8]>) {
+    let mut index_by_bitmap: HashMap<&[u8], u32> = HashMap::new();
+    let mut bitmaps: Vec<&[u8]> = Vec::new();
+    let mut refs = Vec::with_capacity(glyphs.len());
     for glyph in glyphs {
-        total += 4 + 1 + 1 + 1 + 2 + 2 + 2 + 4 + glyph.bitmap.len();
+        let index = *index_by_bitmap
+            .entry(glyph.bitmap.as_slice())
+            .or_insert_with(|| {
+                bitmaps.push(glyph.bitmap.as_slice());
+                (bitmaps.len() - 1) as u32
+            });
+        refs.push(index);
+    }
+    (refs, bitmaps)
+}
+
+fn glyphs_serialized_len(glyphs: &[Glyph]) -> usize {
+    let (_, bitmaps) = dedup_glyph_bitmaps(glyphs);
+    let mut total = glyphs.len() * (4 + 1 + 1 + 1 + 2 + 2 + 2 + 4) + 4;
+    for bitmap in bitmaps {
+        total += 4 + bitmap.len();
     }
     total
 }
 
+fn write_kerning_table<W: Write>(writer: &mut W, kerning: &KerningTable) -> Result<(), BookError> {
+    writer.write_all(&(kerning.len() as u32).to_le_bytes())?;
+    for ((style, left, right), adjust) in kerning {
+        writer.write_all(&left.to_le_bytes())?;
+        writer.write_all(&right.to_le_bytes())?;
+        writer.write_all(&[*style, 0])?;
+        writer.write_all(&adjust.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn kerning_serialized_len(kerning: &KerningTable) -> usize {
+    4 + kerning.len() * 12
+}
+
 fn write_image_table<W: Write>(writer: &mut W, images: &[ImageAsset]) -> Result<(), BookError> {
     let count = images.len() as u32;
     let table_size = 4 + images.len() * 16;
@@ -1223,3 +2880,59 @@ fn trimg_to_bytes(trimg: &trusty_image::Trimg) -> Vec<u8> {
     out.extend_from_slice(&trimg.bits);
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_texts(lines: &[Vec<trusty_epub::TextRun>]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|run| run.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn wrap_breaks_at_the_soft_hyphen_that_fits_with_its_dash() {
+        // Every character falls back to `char_width` (10px) except a wide
+        // dash, so a break only fits if the dash is measured.
+        let options = RenderOptions::default();
+        let style = trusty_epub::TextStyle::default();
+        let mut advance_map = AdvanceMap {
+            advances: HashMap::new(),
+            kerning: KerningTable::new(),
+        };
+        advance_map
+            .advances
+            .insert((style_key(StyleId::Regular, 0), '-' as u32), 40);
+        let runs = [trusty_epub::TextRun {
+            text: "extra\u{AD}ordinary\u{AD}ness".to_string(),
+            style,
+        }];
+
+        // "extraordinary" alone is exactly 130px, but "extraordinary-" is
+        // 170px, so the break moves back to "extra-" (90px).
+        let lines = wrap_paragraph_runs(&runs, 0, 130, 0, &options, &advance_map);
+        assert_eq!(line_texts(&lines), ["extra-", "ordinaryness"]);
+
+        assert_eq!(
+            split_at_soft_hyphen(&runs[0].text, 170, style, 0, &options, &advance_map),
+            Some(("extraordinary-".to_string(), "ness".to_string()))
+        );
+    }
+
+    #[test]
+    fn script_and_punctuation_keep_digits_and_ascii_punctuation() {
+        let filter = CodepointFilter {
+            blocks: vec![CharBlock::Script, CharBlock::Punctuation],
+            substitute: '?',
+        };
+        let mut text = "Глава 12: «Мир», (да)! \u{1F600}".to_string();
+        assert_eq!(filter.apply(&mut text, CharBlock::Cyrillic), 1);
+        assert_eq!(text, "Глава 12: «Мир», (да)! ?");
+        // Latin letters are still outside both blocks.
+        let mut text = "Wi-Fi".to_string();
+        assert_eq!(filter.apply(&mut text, CharBlock::Cyrillic), 4);
+        assert_eq!(text, "??-??");
+    }
+}
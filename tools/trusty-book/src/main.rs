@@ -1,22 +1,125 @@
 use std::env;
 
+/// Reading speed behind the reading-time estimate.
+const READING_WPM: u32 = 200;
+
+/// Parses `AxB` or `A,B` as two numbers, for `--screen` and `--margin`.
+fn parse_pair(value: Option<&String>, separator: char) -> Option<(u16, u16)> {
+    let (a, b) = value?.split_once(separator)?;
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
 fn main() {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
     let mut args = env::args().skip(1).collect::<Vec<_>>();
-    if args.len() < 2 {
-        eprintln!("Usage: trusty-book <input.epub> <output.trbk> [--font path.ttf] [--sizes 8,10,12] [--font-bold path.ttf] [--font-italic path.ttf] [--font-bold-italic path.ttf]");
+    if args.first().map(String::as_str) == Some("dump") {
+        let Some(path) = args.get(1) else {
+            eprintln!("Usage: trusty-book dump <file.trbk>");
+            std::process::exit(1);
+        };
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!("Failed to read {path}: {err}");
+                std::process::exit(1);
+            }
+        };
+        match trusty_book::dump_trbk(&data, &mut std::io::stdout()) {
+            Ok(0) => return,
+            Ok(_) => std::process::exit(2),
+            Err(err) => {
+                eprintln!("Dump failed: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if args.first().map(String::as_str) == Some("search") {
+        let (Some(path), Some(query)) = (args.get(1), args.get(2)) else {
+            eprintln!("Usage: trusty-book search <input.epub> <query>");
+            std::process::exit(1);
+        };
+        match trusty_epub::search_epub(path, query) {
+            Ok(hits) if hits.is_empty() => {
+                eprintln!("No matches for \"{query}\"");
+                std::process::exit(2);
+            }
+            Ok(hits) => {
+                for hit in hits {
+                    println!("spine {} @{}: {}", hit.spine_index, hit.char_offset, hit.snippet);
+                }
+                return;
+            }
+            Err(err) => {
+                eprintln!("Search failed: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if args.first().map(String::as_str) == Some("stale") {
+        let (Some(book), Some(epub)) = (args.get(1), args.get(2)) else {
+            eprintln!("Usage: trusty-book stale <book.trbk> <input.epub>");
+            std::process::exit(1);
+        };
+        match trusty_book::trbk_is_stale(book, epub) {
+            Ok(true) => {
+                println!("{book} is outdated; convert {epub} again");
+                std::process::exit(2);
+            }
+            Ok(false) => {
+                println!("{book} is up to date");
+                return;
+            }
+            Err(err) => {
+                eprintln!("Check failed: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+    let analyze = args.first().map(String::as_str) == Some("analyze");
+    let verify = args.first().map(String::as_str) == Some("verify");
+    let batch = args.first().map(String::as_str) == Some("batch");
+    if analyze || verify || batch {
+        args.remove(0);
+    }
+    let required = if analyze || verify { 1 } else { 2 };
+    if args.len() < required {
+        eprintln!("Usage: trusty-book <input.epub> <output.trbk> [--font path.ttf] [--sizes 8,10,12] [--font-bold path.ttf] [--font-italic path.ttf] [--font-bold-italic path.ttf] [--font-mono path.ttf] [--font-fallback path.ttf]... [--grayscale-glyphs] [--include-nonlinear] [--css-emphasis] [--rtl] [--paragraph-indent px] [--paragraph-spacing px] [--screen WxH] [--margin X,Y] [--hyphenate patterns.txt] [--allow-blocks latin,punctuation,script] [--substitute ?] [--epubinfo]");
+        eprintln!("       trusty-book analyze <input.epub> [same options]");
+        eprintln!("       trusty-book verify <input.epub> [same options]");
+        eprintln!("       trusty-book batch <input-dir> <output-dir> [same options]");
+        eprintln!("       trusty-book search <input.epub> <query>");
+        eprintln!("       trusty-book stale <book.trbk> <input.epub>");
         std::process::exit(1);
     }
 
     let input = args.remove(0);
-    let output = args.remove(0);
+    let output = if analyze || verify {
+        String::new()
+    } else {
+        args.remove(0)
+    };
 
     let mut font = None;
     let mut font_bold = None;
     let mut font_italic = None;
     let mut font_bold_italic = None;
+    let mut font_mono = None;
+    let mut font_fallback = Vec::new();
     let mut sizes = None;
+    let mut grayscale_glyphs = false;
+    let mut include_nonlinear = false;
+    let mut css_emphasis = false;
+    let mut rtl = false;
+    let mut paragraph_indent = 0u16;
+    let mut paragraph_spacing = 0u16;
+    let mut hyphenation_patterns = None;
+    let defaults = trusty_book::RenderOptions::default();
+    let mut screen = (defaults.screen_width, defaults.screen_height);
+    let mut margin = (defaults.margin_x, defaults.margin_y);
+    let mut epubinfo = false;
+    let mut allowed_blocks = None;
+    let mut substitute = '?';
 
     let mut i = 0;
     while i < args.len() {
@@ -37,10 +140,77 @@ fn main() {
                 i += 1;
                 font_bold_italic = args.get(i).cloned();
             }
+            "--font-mono" => {
+                i += 1;
+                font_mono = args.get(i).cloned();
+            }
+            "--font-fallback" => {
+                i += 1;
+                if let Some(path) = args.get(i) {
+                    font_fallback.push(path.clone());
+                }
+            }
             "--sizes" => {
                 i += 1;
                 sizes = args.get(i).cloned();
             }
+            "--grayscale-glyphs" => grayscale_glyphs = true,
+            "--include-nonlinear" => include_nonlinear = true,
+            "--css-emphasis" => css_emphasis = true,
+            "--rtl" => rtl = true,
+            "--screen" => {
+                i += 1;
+                screen = parse_pair(args.get(i), 'x').unwrap_or_else(|| {
+                    eprintln!("--screen expects WxH, e.g. 480x800");
+                    std::process::exit(1);
+                });
+            }
+            "--margin" => {
+                i += 1;
+                margin = parse_pair(args.get(i), ',').unwrap_or_else(|| {
+                    eprintln!("--margin expects X,Y in pixels, e.g. 16,60");
+                    std::process::exit(1);
+                });
+            }
+            "--hyphenate" => {
+                i += 1;
+                hyphenation_patterns = args.get(i).map(std::path::PathBuf::from);
+            }
+            "--allow-blocks" => {
+                i += 1;
+                let blocks = args.get(i).and_then(|names| {
+                    names
+                        .split(',')
+                        .map(trusty_book::CharBlock::from_name)
+                        .collect::<Option<Vec<_>>>()
+                });
+                allowed_blocks = Some(blocks.unwrap_or_else(|| {
+                    eprintln!(
+                        "--allow-blocks expects a list of latin, punctuation, greek, cyrillic, hebrew, arabic, cjk or script"
+                    );
+                    std::process::exit(1);
+                }));
+            }
+            "--substitute" => {
+                i += 1;
+                let mut chars = args.get(i).map(|v| v.chars()).into_iter().flatten();
+                substitute = match (chars.next(), chars.next()) {
+                    (Some(ch), None) => ch,
+                    _ => {
+                        eprintln!("--substitute expects a single character");
+                        std::process::exit(1);
+                    }
+                };
+            }
+            "--epubinfo" => epubinfo = true,
+            "--paragraph-indent" => {
+                i += 1;
+                paragraph_indent = args.get(i).and_then(|v| v.parse().ok()).unwrap_or(0);
+            }
+            "--paragraph-spacing" => {
+                i += 1;
+                paragraph_spacing = args.get(i).and_then(|v| v.parse().ok()).unwrap_or(0);
+            }
             _ => {}
         }
         i += 1;
@@ -57,12 +227,141 @@ fn main() {
         bold: font_bold,
         italic: font_italic,
         bold_italic: font_bold_italic,
+        mono: font_mono,
+        fallback: font_fallback,
     };
 
-    if let Err(err) = trusty_book::convert_epub_to_trbk_multi(&input, &output, &sizes, &font_paths) {
-        eprintln!("Conversion failed: {err}");
+    let options = trusty_book::RenderOptions {
+        screen_width: screen.0,
+        screen_height: screen.1,
+        margin_x: margin.0,
+        margin_y: margin.1,
+        grayscale_glyphs,
+        include_nonlinear,
+        css_emphasis,
+        rtl,
+        paragraph_indent,
+        paragraph_spacing,
+        hyphenation_patterns,
+        write_sidecar: epubinfo,
+        codepoint_filter: allowed_blocks
+            .map(|blocks| trusty_book::CodepointFilter { blocks, substitute }),
+        ..defaults
+    };
+    if let Err(err) = options.check_geometry() {
+        eprintln!("{err}");
         std::process::exit(1);
     }
+    if analyze {
+        match trusty_book::analyze_epub(&input, &sizes, &font_paths, &options) {
+            Ok(reports) => {
+                println!("{:>5} {:>7} {:>7} {:>7} {:>10}", "size", "pages", "glyphs", "images", "bytes");
+                for report in reports {
+                    println!(
+                        "{:>5} {:>7} {:>7} {:>7} {:>10}",
+                        report.size,
+                        report.page_count,
+                        report.glyph_count,
+                        report.image_count,
+                        report.output_bytes
+                    );
+                }
+            }
+            Err(err) => {
+                if matches!(err, trusty_book::BookError::Epub(trusty_epub::EpubError::Drm)) {
+                    eprintln!("This book is DRM-protected and can't be opened.");
+                } else {
+                    eprintln!("Analysis failed: {err}");
+                }
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if verify {
+        match trusty_book::verify_epub(&input, &sizes, &font_paths, &options) {
+            Ok(issues) if issues.is_empty() => println!("Round trip OK"),
+            Ok(issues) => {
+                for issue in &issues {
+                    println!("  !! {issue}");
+                }
+                println!("{} issue(s) found", issues.len());
+                std::process::exit(2);
+            }
+            Err(err) => {
+                eprintln!("Verification failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if batch {
+        match trusty_book::convert_directory(&input, &output, &sizes, &font_paths, &options) {
+            Ok(report) => {
+                for (path, err) in &report.failed {
+                    println!("  !! {}: {err}", path.display());
+                }
+                println!(
+                    "{} converted, {} up to date, {} failed",
+                    report.converted.len(),
+                    report.skipped.len(),
+                    report.failed.len()
+                );
+                if !report.failed.is_empty() {
+                    std::process::exit(2);
+                }
+            }
+            Err(err) => {
+                eprintln!("Batch conversion failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut on_progress = |progress: trusty_book::ConvertProgress| match progress {
+        trusty_book::ConvertProgress::ExtractingSpine { index, total } => {
+            log::debug!("Extracting spine item {}/{}", index + 1, total);
+        }
+        trusty_book::ConvertProgress::BuildingGlyphs { size } => {
+            log::info!("Building glyphs for size {size}");
+        }
+        trusty_book::ConvertProgress::Paginating { size } => {
+            log::info!("Paginating size {size}");
+        }
+        trusty_book::ConvertProgress::Writing { size, path } => {
+            log::info!("Writing size {size} to {}", path.display());
+        }
+    };
+    let cache = match trusty_book::convert_epub_to_trbk_multi_with_progress(
+        &input,
+        &output,
+        &sizes,
+        &font_paths,
+        &options,
+        Some(&mut on_progress),
+    ) {
+        Ok(cache) => cache,
+        Err(err) => {
+            if matches!(
+                err,
+                trusty_book::BookError::Epub(trusty_epub::EpubError::Drm)
+            ) {
+                eprintln!("This book is DRM-protected and can't be opened.");
+            } else {
+                eprintln!("Conversion failed: {err}");
+            }
+            std::process::exit(1);
+        }
+    };
 
     println!("Wrote TRBK output(s) starting at {output}");
+    if epubinfo {
+        println!(
+            "Wrote {}",
+            trusty_book::epubinfo_path(&input, &output).display()
+        );
+    }
+    let minutes = trusty_epub::estimated_reading_minutes(&cache, READING_WPM);
+    println!("Est. reading time: {}h {}m", minutes / 60, minutes % 60);
 }
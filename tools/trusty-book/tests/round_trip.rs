@@ -0,0 +1,70 @@
+//! Converts the fixture EPUB and reads the result back with the device parser.
+
+use std::path::{Path, PathBuf};
+
+use trusty_book::{FontPaths, RenderOptions};
+use trusty_core::trbk::{TrbkOp, parse_trbk};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+#[test]
+fn epub_round_trips_through_parse_trbk() {
+    // Convert a copy, so the EPUB cache lands in the temp dir rather than
+    // next to the fixture.
+    let work = std::env::temp_dir().join(format!("trusty-round-trip-{}", std::process::id()));
+    std::fs::create_dir_all(&work).expect("temp dir");
+    let epub = work.join("round-trip.epub");
+    std::fs::copy(fixture("round-trip.epub"), &epub).expect("fixture copy");
+    let output = work.join("round-trip.trbk");
+    let font_paths = FontPaths {
+        regular: Some(fixture("Tuffy.ttf").to_string_lossy().into_owned()),
+        ..FontPaths::default()
+    };
+    trusty_book::convert_epub_to_trbk_multi(
+        &epub,
+        &output,
+        &[10],
+        &font_paths,
+        &RenderOptions::default(),
+    )
+    .expect("conversion failed");
+    let data = std::fs::read(&output).expect("missing output");
+    let _ = std::fs::remove_dir_all(&work);
+
+    let book = parse_trbk(&data).expect("TRBK did not parse");
+    assert_eq!(book.metadata.title, "Round Trip");
+    assert_eq!(book.page_count, 2);
+    assert_eq!(book.pages.len(), 2);
+
+    let toc: Vec<(&str, u32)> = book
+        .toc
+        .iter()
+        .map(|entry| (entry.title.as_str(), entry.page_index))
+        .collect();
+    assert_eq!(toc, [("First Chapter", 0), ("Second Chapter", 1)]);
+
+    // Every drawn character needs a glyph in its style, or in the body size
+    // of that style for headings (the reader's fallback).
+    let mut drawn = 0;
+    for page in &book.pages {
+        for op in &page.ops {
+            let TrbkOp::TextRun { style, text, .. } = op else {
+                continue;
+            };
+            for ch in text.chars().filter(|ch| !ch.is_whitespace()) {
+                let covered = book.glyphs.iter().any(|glyph| {
+                    glyph.codepoint == ch as u32
+                        && (glyph.style == *style || glyph.style == *style & !0b1100)
+                });
+                assert!(covered, "no glyph for {ch:?} in style {style}");
+                drawn += 1;
+            }
+        }
+    }
+    assert!(drawn > 0, "no text was drawn");
+}
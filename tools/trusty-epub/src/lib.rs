@@ -1,10 +1,11 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::io::{Read, Seek, Write};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use quick_xml::events::{BytesStart, Event};
+use quick_xml::events::{BytesRef, BytesStart, Event};
 use quick_xml::Reader;
+use sha1::{Digest, Sha1};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -23,11 +24,22 @@ pub enum EpubError {
     MissingPackage,
     #[error("spine index out of range")]
     InvalidSpineIndex,
+    #[error("book is DRM-protected")]
+    Drm,
 }
 
 #[derive(Debug, Clone)]
 pub struct EpubContainer {
+    /// The OPF package picked from `rootfiles`.
     pub rootfile_path: String,
+    /// Every `<rootfile>` in document order.
+    pub rootfiles: Vec<ContainerRootfile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContainerRootfile {
+    pub full_path: String,
+    pub media_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -76,12 +88,30 @@ pub struct EpubBook {
     pub container: EpubContainer,
     pub package: OpfPackage,
     pub toc: Vec<TocEntry>,
+    /// Fonts listed in `encryption.xml` as obfuscated, by archive path.
+    pub obfuscated: Vec<ObfuscatedResource>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObfuscationAlgorithm {
+    /// IDPF font obfuscation: SHA-1 key, first 1040 bytes.
+    Idpf,
+    /// Adobe font mangling: UUID key, first 1024 bytes.
+    Adobe,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObfuscatedResource {
+    pub path: String,
+    pub algorithm: ObfuscationAlgorithm,
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct TextStyle {
     pub bold: bool,
     pub italic: bool,
+    /// Inside `<code>`, `<tt>` or `<kbd>`.
+    pub monospace: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -98,6 +128,17 @@ pub enum HtmlBlock {
     },
     PageBreak,
     Image { alt: Option<String>, src: String },
+    /// One `<tr>`; each cell keeps its own formatted runs.
+    TableRow { cells: Vec<Vec<TextRun>> },
+    /// An `<hr>` scene break.
+    Separator,
+}
+
+/// Bold/italic rules picked out of a stylesheet, keyed by class and element.
+#[derive(Debug, Clone, Default)]
+pub struct CssStyles {
+    classes: HashMap<String, TextStyle>,
+    elements: HashMap<String, TextStyle>,
 }
 
 #[derive(Debug, Clone)]
@@ -105,6 +146,12 @@ pub struct CacheSpineEntry {
     pub href: String,
     pub cumulative_size: u64,
     pub toc_index: i32,
+    /// Whitespace-separated words in the item's text.
+    pub word_count: u32,
+    /// False for `linear="no"` items (endnotes, ads) kept out of the reading order.
+    pub linear: bool,
+    /// `xml:lang`/`lang` of the item's `<html>` or `<body>`, if declared.
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -134,17 +181,33 @@ pub struct CacheStatus {
     pub cache_path: PathBuf,
 }
 
-const CACHE_VERSION: u8 = 1;
+/// One match from [`search_epub`].
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub spine_index: usize,
+    /// Whitespace-collapsed text around the match.
+    pub snippet: String,
+    /// Offset of the match in the spine item's plain text, in chars.
+    pub char_offset: usize,
+}
+
+const CACHE_VERSION: u8 = 6;
+
+/// Stop collecting after this many hits.
+const SEARCH_MAX_HITS: usize = 200;
+/// Chars of context kept on each side of a match.
+const SEARCH_SNIPPET_CONTEXT: usize = 40;
+
+const OPF_MEDIA_TYPE: &str = "application/oebps-package+xml";
+/// Most text a first page may carry (a title or caption) and still count as
+/// a cover page, in non-whitespace chars.
+const COVER_PAGE_MAX_TEXT_CHARS: usize = 64;
 
 pub fn open_epub<P: AsRef<Path>>(path: P) -> Result<EpubBook, EpubError> {
     let file = std::fs::File::open(path.as_ref())?;
     let mut archive = zip::ZipArchive::new(file)?;
-
-    let container_xml = read_zip_file_to_string(&mut archive, "META-INF/container.xml")?;
-    let container = parse_container(&container_xml)?;
-
-    let opf_xml = read_zip_file_to_string(&mut archive, &container.rootfile_path)?;
-    let mut package = parse_opf(&opf_xml, &container.rootfile_path)?;
+    let obfuscated = read_encryption(&mut archive)?;
+    let (container, mut package) = read_package(&mut archive)?;
 
     let toc = if let Some(nav_href) = package.nav_href.clone() {
         let nav_path = resolve_href(&package.opf_dir, &nav_href);
@@ -164,15 +227,157 @@ pub fn open_epub<P: AsRef<Path>>(path: P) -> Result<EpubBook, EpubError> {
     if package.cover_href.is_none() {
         package.cover_href = find_cover_href(&package);
     }
+    if package.cover_href.is_none() {
+        package.cover_href = find_cover_page_image(&mut archive, &package);
+    }
 
     Ok(EpubBook {
         container,
         package,
         toc,
+        obfuscated,
     })
 }
 
+/// Reads `container.xml` and the OPF package it points to.
+fn read_package<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+) -> Result<(EpubContainer, OpfPackage), EpubError> {
+    let container_xml = read_zip_file_to_string(archive, "META-INF/container.xml")?;
+    let container = parse_container(&container_xml)?;
+    let opf_xml = read_zip_file_to_string(archive, &container.rootfile_path)?;
+    let package = parse_opf(&opf_xml, &container.rootfile_path)?;
+    Ok((container, package))
+}
+
+impl OpfPackage {
+    /// Spine hrefs that belong to the main reading order.
+    pub fn linear_spine_hrefs(&self) -> Vec<String> {
+        self.spine_hrefs_where(true)
+    }
+
+    /// Spine hrefs marked `linear="no"`, such as endnotes or ads.
+    pub fn auxiliary_spine_hrefs(&self) -> Vec<String> {
+        self.spine_hrefs_where(false)
+    }
+
+    fn spine_hrefs_where(&self, linear: bool) -> Vec<String> {
+        build_spine_items(self)
+            .into_iter()
+            .filter(|(_, item_linear)| *item_linear == linear)
+            .map(|(href, _)| href)
+            .collect()
+    }
+}
+
+impl ObfuscationAlgorithm {
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        match uri {
+            "http://www.idpf.org/2008/embedding" => Some(ObfuscationAlgorithm::Idpf),
+            "http://ns.adobe.com/pdf/enc#RC" => Some(ObfuscationAlgorithm::Adobe),
+            _ => None,
+        }
+    }
+
+    /// Number of leading bytes the scheme XORs.
+    fn header_len(self) -> usize {
+        match self {
+            ObfuscationAlgorithm::Idpf => 1040,
+            ObfuscationAlgorithm::Adobe => 1024,
+        }
+    }
+
+    /// Derives the XOR key from the package's unique identifier. Adobe keys
+    /// need a UUID identifier and yield `None` otherwise.
+    pub fn key(self, identifier: &str) -> Option<Vec<u8>> {
+        match self {
+            ObfuscationAlgorithm::Idpf => {
+                let cleaned: String = identifier
+                    .chars()
+                    .filter(|c| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+                    .collect();
+                Some(Sha1::digest(cleaned.as_bytes()).to_vec())
+            }
+            ObfuscationAlgorithm::Adobe => {
+                let uuid = identifier.trim();
+                let uuid = uuid.strip_prefix("urn:uuid:").unwrap_or(uuid);
+                let hex: Vec<u8> = uuid.bytes().filter(|b| *b != b'-').collect();
+                if hex.len() != 32 {
+                    return None;
+                }
+                hex.chunks(2)
+                    .map(|pair| {
+                        let pair = std::str::from_utf8(pair).ok()?;
+                        u8::from_str_radix(pair, 16).ok()
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// XORs the obfuscated header of a font with `key`, repeating the key as
+/// needed. Applying it twice restores the original bytes.
+pub fn deobfuscate_font(bytes: &mut [u8], key: &[u8], algorithm: ObfuscationAlgorithm) {
+    if key.is_empty() {
+        return;
+    }
+    for (idx, byte) in bytes.iter_mut().take(algorithm.header_len()).enumerate() {
+        *byte ^= key[idx % key.len()];
+    }
+}
+
+/// Lists the obfuscated fonts in an `encryption.xml`. Any other encryption
+/// method means real DRM and fails with `EpubError::Drm`.
+pub fn parse_encryption_xml(xml: &str) -> Result<Vec<ObfuscatedResource>, EpubError> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut resources = Vec::new();
+    let mut algorithm: Option<ObfuscationAlgorithm> = None;
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) => {
+                let name = e.name();
+                if is_xml_name(name.as_ref(), b"EncryptedData") {
+                    algorithm = None;
+                } else if is_xml_name(name.as_ref(), b"EncryptionMethod") {
+                    let uri = attr_value(&e, b"Algorithm")?.unwrap_or_default();
+                    algorithm = Some(ObfuscationAlgorithm::from_uri(&uri).ok_or(EpubError::Drm)?);
+                } else if is_xml_name(name.as_ref(), b"CipherReference") {
+                    let uri = attr_value(&e, b"URI")?;
+                    if let (Some(algorithm), Some(uri)) = (algorithm, uri) {
+                        resources.push(ObfuscatedResource {
+                            path: uri.trim_start_matches('/').to_string(),
+                            algorithm,
+                        });
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(resources)
+}
+
 pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
+    parse_xhtml_blocks_styled(xml, &CssStyles::default())
+}
+
+/// Like [`parse_xhtml_blocks`], also applying the bold/italic rules in
+/// `styles` to elements by tag name and `class`.
+pub fn parse_xhtml_blocks_styled(xml: &str, styles: &CssStyles) -> Result<Vec<HtmlBlock>, EpubError> {
+    parse_xhtml_blocks_anchored(xml, styles).map(|(blocks, _)| blocks)
+}
+
+/// Like [`parse_xhtml_blocks_styled`], also returning the index of the block
+/// each element `id` falls in. An id after the last block maps to
+/// `blocks.len()`.
+pub fn parse_xhtml_blocks_anchored(
+    xml: &str,
+    styles: &CssStyles,
+) -> Result<(Vec<HtmlBlock>, HashMap<String, usize>), EpubError> {
     let mut reader = Reader::from_str(xml);
     reader.config_mut().trim_text(false);
 
@@ -185,12 +390,27 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
     let mut in_body = true;
     let mut skip_depth: usize = 0;
     let mut last_was_space = false;
+    let mut table_row: Option<Vec<Vec<TextRun>>> = None;
+    let mut in_cell = false;
+    // Tables open inside a cell; their text stays in that cell.
+    let mut nested_tables: usize = 0;
+    // Style to restore when each open element closes, if its CSS changed it.
+    let mut css_stack: Vec<Option<TextStyle>> = Vec::new();
+    let mut anchors: HashMap<String, usize> = HashMap::new();
 
     loop {
         match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
                 let name_buf = e.name().as_ref().to_vec();
                 let name = name_buf.as_slice();
+                css_stack.push(None);
+                let css_style = if styles.is_empty() {
+                    None
+                } else {
+                    let class = attr_value(&e, b"class")?;
+                    let style = styles.style_for(e.local_name().as_ref(), class.as_deref());
+                    Some(style).filter(|style| style.bold || style.italic)
+                };
                 if is_xml_name(name, b"body") {
                     in_body = true;
                 }
@@ -204,7 +424,34 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                     continue;
                 }
 
-                if is_block_tag(name) {
+                if in_cell && is_xml_name(name, b"table") {
+                    nested_tables += 1;
+                }
+                if nested_tables > 0 && is_nested_table_tag(name) {
+                    // A nested table reads as one line of words in its cell.
+                    push_normalized_text(" ", &mut current_text, &mut last_was_space);
+                } else if is_xml_name(name, b"tr") {
+                    flush_paragraph(
+                        &mut blocks,
+                        &mut runs,
+                        &mut current_text,
+                        current_style,
+                        heading_level,
+                    );
+                    heading_level = None;
+                    table_row = Some(Vec::new());
+                    in_cell = false;
+                    // Drop whitespace between the table markup.
+                    last_was_space = true;
+                } else if is_table_cell(name) && table_row.is_some() {
+                    runs.clear();
+                    current_text.clear();
+                    in_cell = true;
+                    last_was_space = true;
+                } else if in_cell && (is_block_tag(name) || is_xml_name(name, b"br")) {
+                    // Keep cell content on one line.
+                    push_normalized_text(" ", &mut current_text, &mut last_was_space);
+                } else if is_block_tag(name) {
                     flush_paragraph(
                         &mut blocks,
                         &mut runs,
@@ -224,7 +471,7 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                     );
                     heading_level = None;
                     last_was_space = false;
-                } else if is_xml_name(name, b"img") {
+                } else if is_xml_name(name, b"img") || is_xml_name(name, b"image") {
                     flush_paragraph(
                         &mut blocks,
                         &mut runs,
@@ -232,9 +479,8 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                         current_style,
                         heading_level,
                     );
-                    let alt = attr_value(&e, b"alt")?;
-                    if let Some(src) = attr_value(&e, b"src")? {
-                        blocks.push(HtmlBlock::Image { alt, src });
+                    if let Some(block) = image_block(&e)? {
+                        blocks.push(block);
                     }
                     heading_level = None;
                     last_was_space = false;
@@ -244,6 +490,20 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                 } else if is_xml_name(name, b"i") || is_xml_name(name, b"em") {
                     flush_text_run(&mut runs, &mut current_text, current_style, &mut last_was_space);
                     current_style.italic = true;
+                } else if is_monospace_tag(name) {
+                    flush_text_run(&mut runs, &mut current_text, current_style, &mut last_was_space);
+                    current_style.monospace = true;
+                } else if is_xml_name(name, b"hr") && !in_cell {
+                    flush_paragraph(
+                        &mut blocks,
+                        &mut runs,
+                        &mut current_text,
+                        current_style,
+                        heading_level,
+                    );
+                    blocks.push(HtmlBlock::Separator);
+                    heading_level = None;
+                    last_was_space = false;
                 } else if is_pagebreak(&e)? {
                     flush_paragraph(
                         &mut blocks,
@@ -256,11 +516,25 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                     heading_level = None;
                     last_was_space = false;
                 }
+
+                if let Some(style) = css_style {
+                    flush_text_run(&mut runs, &mut current_text, current_style, &mut last_was_space);
+                    if let Some(saved) = css_stack.last_mut() {
+                        *saved = Some(current_style);
+                    }
+                    current_style.bold |= style.bold;
+                    current_style.italic |= style.italic;
+                }
+                if let Some(id) = attr_value(&e, b"id")? {
+                    anchors.entry(id).or_insert(blocks.len());
+                }
             }
             Event::Empty(e) => {
                 let name_buf = e.name().as_ref().to_vec();
                 let name = name_buf.as_slice();
-                if is_xml_name(name, b"br") {
+                if in_cell && is_xml_name(name, b"br") {
+                    push_normalized_text(" ", &mut current_text, &mut last_was_space);
+                } else if is_xml_name(name, b"br") {
                     flush_paragraph(
                         &mut blocks,
                         &mut runs,
@@ -270,7 +544,7 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                     );
                     heading_level = None;
                     last_was_space = false;
-                } else if is_xml_name(name, b"img") {
+                } else if is_xml_name(name, b"img") || is_xml_name(name, b"image") {
                     flush_paragraph(
                         &mut blocks,
                         &mut runs,
@@ -278,12 +552,22 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                         current_style,
                         heading_level,
                     );
-                    let alt = attr_value(&e, b"alt")?;
-                    if let Some(src) = attr_value(&e, b"src")? {
-                        blocks.push(HtmlBlock::Image { alt, src });
+                    if let Some(block) = image_block(&e)? {
+                        blocks.push(block);
                     }
                     heading_level = None;
                     last_was_space = false;
+                } else if is_xml_name(name, b"hr") && !in_cell {
+                    flush_paragraph(
+                        &mut blocks,
+                        &mut runs,
+                        &mut current_text,
+                        current_style,
+                        heading_level,
+                    );
+                    blocks.push(HtmlBlock::Separator);
+                    heading_level = None;
+                    last_was_space = false;
                 } else if is_pagebreak(&e)? {
                     flush_paragraph(
                         &mut blocks,
@@ -296,10 +580,19 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                     heading_level = None;
                     last_was_space = false;
                 }
+                let id = if in_body && skip_depth == 0 {
+                    attr_value(&e, b"id")?
+                } else {
+                    None
+                };
+                if let Some(id) = id {
+                    anchors.entry(id).or_insert(blocks.len());
+                }
             }
             Event::End(e) => {
                 let name_buf = e.name().as_ref().to_vec();
                 let name = name_buf.as_slice();
+                let restore_style = css_stack.pop().flatten();
                 if is_xml_name(name, b"head") && skip_depth > 0 {
                     skip_depth = skip_depth.saturating_sub(1);
                 } else if skip_depth > 0 {
@@ -310,7 +603,33 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                     continue;
                 }
 
-                if is_block_tag(name) {
+                if nested_tables > 0 && is_nested_table_tag(name) {
+                    if is_xml_name(name, b"table") {
+                        nested_tables -= 1;
+                    }
+                    push_normalized_text(" ", &mut current_text, &mut last_was_space);
+                } else if is_table_cell(name) && in_cell {
+                    flush_text_run(&mut runs, &mut current_text, current_style, &mut last_was_space);
+                    if let Some(row) = table_row.as_mut() {
+                        row.push(merge_runs(&mut runs));
+                    }
+                    in_cell = false;
+                    last_was_space = true;
+                } else if is_xml_name(name, b"tr") {
+                    let row = table_row
+                        .take()
+                        .filter(|cells| cells.iter().flatten().any(|run| !run.text.trim().is_empty()));
+                    if let Some(cells) = row {
+                        blocks.push(HtmlBlock::TableRow { cells });
+                    }
+                    // Whitespace between cells belongs to no paragraph.
+                    runs.clear();
+                    current_text.clear();
+                    in_cell = false;
+                    last_was_space = true;
+                } else if in_cell && is_block_tag(name) {
+                    push_normalized_text(" ", &mut current_text, &mut last_was_space);
+                } else if is_block_tag(name) {
                     flush_paragraph(
                         &mut blocks,
                         &mut runs,
@@ -326,9 +645,17 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
                 } else if is_xml_name(name, b"i") || is_xml_name(name, b"em") {
                     flush_text_run(&mut runs, &mut current_text, current_style, &mut last_was_space);
                     current_style.italic = false;
+                } else if is_monospace_tag(name) {
+                    flush_text_run(&mut runs, &mut current_text, current_style, &mut last_was_space);
+                    current_style.monospace = false;
                 } else if is_xml_name(name, b"body") {
                     in_body = false;
                 }
+
+                if let Some(style) = restore_style {
+                    flush_text_run(&mut runs, &mut current_text, current_style, &mut last_was_space);
+                    current_style = style;
+                }
             }
             Event::Text(e) => {
                 if !in_body || skip_depth > 0 {
@@ -355,7 +682,200 @@ pub fn parse_xhtml_blocks(xml: &str) -> Result<Vec<HtmlBlock>, EpubError> {
         current_style,
         heading_level,
     );
-    Ok(blocks)
+    Ok((blocks, anchors))
+}
+
+/// Language declared on a spine item's `<html>` or `<body>` element, with
+/// `xml:lang` preferred over `lang` and `<body>` over `<html>`.
+pub fn detect_spine_language(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut language = None;
+    loop {
+        match reader.read_event_into(&mut buf).ok()? {
+            Event::Start(e) | Event::Empty(e) => {
+                let name = e.name();
+                let is_body = is_xml_name(name.as_ref(), b"body");
+                if is_body || is_xml_name(name.as_ref(), b"html") {
+                    let lang = attr_value(&e, b"xml:lang")
+                        .ok()
+                        .flatten()
+                        .or_else(|| attr_value(&e, b"lang").ok().flatten())
+                        .map(|lang| lang.trim().to_string())
+                        .filter(|lang| !lang.is_empty());
+                    if lang.is_some() {
+                        language = lang;
+                    }
+                }
+                if is_body {
+                    break;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    language
+}
+
+/// Hrefs of the stylesheets an XHTML document links to, as written.
+pub fn linked_stylesheets(xml: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut hrefs = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let name = e.name();
+                if is_xml_name(name.as_ref(), b"body") {
+                    break;
+                }
+                if is_xml_name(name.as_ref(), b"link") {
+                    let rel = attr_value(&e, b"rel").ok().flatten().unwrap_or_default();
+                    let href = attr_value(&e, b"href").ok().flatten();
+                    if rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("stylesheet")) {
+                        hrefs.extend(href);
+                    }
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    hrefs
+}
+
+/// Collects `font-style: italic` and bold `font-weight` rules on plain class
+/// (`.name`, `span.name`) and element (`em`) selectors. Anything fancier,
+/// such as descendant or pseudo-class selectors, is ignored.
+pub fn parse_css(css: &str) -> CssStyles {
+    let mut styles = CssStyles::default();
+    let css = strip_css_comments(css);
+    let mut rest = css.as_str();
+    while let Some(open) = rest.find('{') {
+        let prelude = rest[..open].trim();
+        // A stray `}` closing an @media block ends up in front of the selector.
+        let prelude = prelude.rsplit('}').next().unwrap_or_default().trim();
+        let after = &rest[open + 1..];
+        if prelude.starts_with("@media") || prelude.starts_with("@supports") {
+            // Nested rules still apply; read them as if at the top level.
+            rest = after;
+            continue;
+        }
+        let close = after.find('}').unwrap_or(after.len());
+        let body = &after[..close];
+        rest = after.get(close + 1..).unwrap_or_default();
+        if prelude.starts_with('@') {
+            continue;
+        }
+        let style = css_declarations_style(body);
+        if !style.bold && !style.italic {
+            continue;
+        }
+        for selector in prelude.split(',') {
+            styles.add_selector(selector.trim(), style);
+        }
+    }
+    styles
+}
+
+impl CssStyles {
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty() && self.elements.is_empty()
+    }
+
+    /// Adds the rules from another stylesheet.
+    pub fn extend(&mut self, other: CssStyles) {
+        for (class, style) in other.classes {
+            merge_text_style(self.classes.entry(class).or_default(), style);
+        }
+        for (element, style) in other.elements {
+            merge_text_style(self.elements.entry(element).or_default(), style);
+        }
+    }
+
+    /// Emphasis an element picks up from its tag name and `class` attribute.
+    fn style_for(&self, local_name: &[u8], class: Option<&str>) -> TextStyle {
+        let mut style = TextStyle::default();
+        let name = String::from_utf8_lossy(local_name).to_ascii_lowercase();
+        if let Some(rule) = self.elements.get(&name) {
+            merge_text_style(&mut style, *rule);
+        }
+        for class in class.unwrap_or_default().split_whitespace() {
+            if let Some(rule) = self.classes.get(class) {
+                merge_text_style(&mut style, *rule);
+            }
+        }
+        style
+    }
+
+    fn add_selector(&mut self, selector: &str, style: TextStyle) {
+        if selector.is_empty()
+            || selector.contains(|c: char| c.is_whitespace() || "#>+~:[*".contains(c))
+        {
+            return;
+        }
+        let mut parts = selector.split('.');
+        let element = parts.next().unwrap_or_default();
+        let classes: Vec<&str> = parts.collect();
+        let entry = match classes.as_slice() {
+            [] => self.elements.entry(element.to_ascii_lowercase()).or_default(),
+            [class] if !class.is_empty() => self.classes.entry(class.to_string()).or_default(),
+            _ => return,
+        };
+        merge_text_style(entry, style);
+    }
+}
+
+fn merge_text_style(into: &mut TextStyle, style: TextStyle) {
+    into.bold |= style.bold;
+    into.italic |= style.italic;
+}
+
+fn strip_css_comments(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out
+}
+
+fn css_declarations_style(body: &str) -> TextStyle {
+    let mut style = TextStyle::default();
+    for declaration in body.split(';') {
+        let Some((property, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        let property = property.trim().to_ascii_lowercase();
+        let value = value.replace("!important", "").trim().to_ascii_lowercase();
+        match property.as_str() {
+            "font-style" => style.italic |= value == "italic" || value == "oblique",
+            "font-weight" => style.bold |= is_bold_weight(&value),
+            "font" => {
+                for token in value.split_whitespace() {
+                    style.italic |= token == "italic" || token == "oblique";
+                    style.bold |= is_bold_weight(token);
+                }
+            }
+            _ => {}
+        }
+    }
+    style
+}
+
+fn is_bold_weight(value: &str) -> bool {
+    match value {
+        "bold" | "bolder" => true,
+        _ => value.parse::<u16>().is_ok_and(|weight| weight >= 600),
+    }
 }
 
 pub fn read_spine_xhtml<P: AsRef<Path>>(epub_path: P, spine_index: usize) -> Result<String, EpubError> {
@@ -370,12 +890,119 @@ pub fn read_spine_xhtml<P: AsRef<Path>>(epub_path: P, spine_index: usize) -> Res
     read_zip_file_to_string(&mut archive, href)
 }
 
+/// Plain text of one spine item: [`read_spine_xhtml`], [`parse_xhtml_blocks`]
+/// and [`blocks_to_plain_text`] in one call, for previews and search.
+pub fn spine_plain_text<P: AsRef<Path>>(epub_path: P, spine_index: usize) -> Result<String, EpubError> {
+    let xml = read_spine_xhtml(epub_path, spine_index)?;
+    xhtml_plain_text(&xml)
+}
+
+/// Writes the plain text of the linear spine to `out`, one chapter after
+/// another with a blank line between them. The archive is opened once for
+/// the whole book, and only the package is parsed (no TOC or cover lookup).
+pub fn export_plain_text<P: AsRef<Path>>(epub_path: P, out: &mut impl Write) -> Result<(), EpubError> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(epub_path.as_ref())?)?;
+    read_encryption(&mut archive)?;
+    let (_, package) = read_package(&mut archive)?;
+    let mut first = true;
+    for href in package.linear_spine_hrefs() {
+        let xml = read_zip_file_to_string(&mut archive, &href)?;
+        let text = xhtml_plain_text(&xml)?;
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if !first {
+            out.write_all(b"\n")?;
+        }
+        out.write_all(text.as_bytes())?;
+        out.write_all(b"\n")?;
+        first = false;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Case-insensitive substring search over the plain text of every spine item,
+/// in reading order. Results are capped at `SEARCH_MAX_HITS`.
+pub fn search_epub<P: AsRef<Path>>(path: P, query: &str) -> Result<Vec<SearchHit>, EpubError> {
+    let needle = fold_chars(query.trim());
+    if needle.is_empty() {
+        return Ok(Vec::new());
+    }
+    let path = path.as_ref();
+    let book = open_epub(path)?;
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    let mut hits = Vec::new();
+    for (spine_index, href) in build_spine_hrefs(&book.package).iter().enumerate() {
+        let Ok(xml) = read_zip_file_to_string(&mut archive, href) else {
+            continue;
+        };
+        let Ok(text) = xhtml_plain_text(&xml) else {
+            continue;
+        };
+        let chars: Vec<char> = text.chars().collect();
+        let folded = fold_chars(&text);
+        let mut start = 0;
+        while start + needle.len() <= folded.len() {
+            if folded[start..start + needle.len()] != needle[..] {
+                start += 1;
+                continue;
+            }
+            let from = start.saturating_sub(SEARCH_SNIPPET_CONTEXT);
+            let to = (start + needle.len() + SEARCH_SNIPPET_CONTEXT).min(chars.len());
+            let snippet = chars[from..to].iter().collect::<String>();
+            hits.push(SearchHit {
+                spine_index,
+                snippet: snippet.split_whitespace().collect::<Vec<_>>().join(" "),
+                char_offset: start,
+            });
+            if hits.len() >= SEARCH_MAX_HITS {
+                return Ok(hits);
+            }
+            start += needle.len();
+        }
+    }
+    Ok(hits)
+}
+
+/// Lowercases char by char, so indices line up with `text.chars()`.
+fn fold_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|ch| ch.to_lowercase().next().unwrap_or(ch))
+        .collect()
+}
+
+fn xhtml_plain_text(xml: &str) -> Result<String, EpubError> {
+    Ok(blocks_to_plain_text(&parse_xhtml_blocks(xml)?))
+}
+
 pub fn read_epub_resource_bytes<P: AsRef<Path>>(epub_path: P, href: &str) -> Result<Vec<u8>, EpubError> {
     let file = std::fs::File::open(epub_path.as_ref())?;
     let mut archive = zip::ZipArchive::new(file)?;
     read_zip_file_to_bytes(&mut archive, href)
 }
 
+/// Manifest items with an `image/*` media type, in manifest order.
+pub fn list_images(package: &OpfPackage) -> Vec<&OpfManifestItem> {
+    package
+        .manifest
+        .iter()
+        .filter(|item| item.media_type.starts_with("image/"))
+        .collect()
+}
+
+/// Reads one of the items returned by [`list_images`]. Manifest hrefs are
+/// relative to the OPF, so the container is read to resolve them.
+pub fn read_image<P: AsRef<Path>>(epub_path: P, item: &OpfManifestItem) -> Result<Vec<u8>, EpubError> {
+    let file = std::fs::File::open(epub_path.as_ref())?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let container_xml = read_zip_file_to_string(&mut archive, "META-INF/container.xml")?;
+    let container = parse_container(&container_xml)?;
+    let path = resolve_href(&opf_base_dir(&container.rootfile_path), &item.href);
+    read_zip_file_to_bytes(&mut archive, &path)
+}
+
 pub fn blocks_to_plain_text(blocks: &[HtmlBlock]) -> String {
     let mut out = String::new();
     for (idx, block) in blocks.iter().enumerate() {
@@ -395,10 +1022,31 @@ pub fn blocks_to_plain_text(blocks: &[HtmlBlock]) -> String {
             HtmlBlock::PageBreak => {
                 out.push_str("\n\n");
             }
+            HtmlBlock::Separator => {
+                if idx > 0 && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str("* * *\n\n");
+            }
             HtmlBlock::Image { alt, .. } => {
                 let label = alt.as_deref().unwrap_or("image");
                 out.push_str(&format!("[Image: {label}]\n\n"));
             }
+            HtmlBlock::TableRow { cells } => {
+                if idx > 0 && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                let row: Vec<String> = cells
+                    .iter()
+                    .map(|cell| cell.iter().map(|run| run.text.as_str()).collect::<String>())
+                    .map(|text| text.trim().to_string())
+                    .collect();
+                out.push_str(&row.join("\t"));
+                out.push('\n');
+                if !matches!(blocks.get(idx + 1), Some(HtmlBlock::TableRow { .. })) {
+                    out.push('\n');
+                }
+            }
         }
     }
     out
@@ -423,7 +1071,7 @@ pub fn blocks_to_runs(blocks: &[HtmlBlock]) -> Vec<TextRun> {
                     }
                 }
             }
-            HtmlBlock::PageBreak => {
+            HtmlBlock::PageBreak | HtmlBlock::Separator => {
                 runs.push(TextRun {
                     text: "\n\n".to_string(),
                     style: TextStyle::default(),
@@ -432,6 +1080,24 @@ pub fn blocks_to_runs(blocks: &[HtmlBlock]) -> Vec<TextRun> {
             HtmlBlock::Image { .. } => {
                 // Skip images for text runs.
             }
+            HtmlBlock::TableRow { cells } => {
+                if !first {
+                    runs.push(TextRun {
+                        text: "\n\n".to_string(),
+                        style: TextStyle::default(),
+                    });
+                }
+                first = false;
+                for (idx, cell) in cells.iter().enumerate() {
+                    if idx > 0 {
+                        runs.push(TextRun {
+                            text: "\t".to_string(),
+                            style: TextStyle::default(),
+                        });
+                    }
+                    runs.extend(cell.iter().filter(|run| !run.text.is_empty()).cloned());
+                }
+            }
         }
     }
     runs
@@ -510,10 +1176,17 @@ pub fn load_cache(epub_path: &Path, cache_path: &Path) -> Result<Option<BookCach
         let href = read_string(&mut file)?;
         let cumulative_size = read_u64(&mut file)?;
         let toc_index = read_i32(&mut file)?;
+        let word_count = read_u32(&mut file)?;
+        let linear = read_u8(&mut file)? != 0;
+        let language = read_string(&mut file)?;
+        let language = if language.is_empty() { None } else { Some(language) };
         spine.push(CacheSpineEntry {
             href,
             cumulative_size,
             toc_index,
+            word_count,
+            linear,
+            language,
         });
     }
 
@@ -556,25 +1229,77 @@ pub fn load_cache(epub_path: &Path, cache_path: &Path) -> Result<Option<BookCach
 
 pub fn build_cache(epub_path: &Path, cache_dir: &Path) -> Result<BookCache, EpubError> {
     std::fs::create_dir_all(cache_dir)?;
+    let mut cache = scan_book(epub_path)?;
+
+    let cache_path = cache_dir.join("book.bin");
+    let mut file = std::fs::File::create(&cache_path)?;
+
+    write_u8(&mut file, CACHE_VERSION)?;
+    write_u64(&mut file, cache.source_size)?;
+    write_u64(&mut file, cache.source_mtime)?;
+    write_u32(&mut file, cache.spine.len() as u32)?;
+    write_u32(&mut file, cache.toc.len() as u32)?;
+
+    write_string(&mut file, cache.metadata.title.as_deref().unwrap_or(""))?;
+    write_string(&mut file, cache.metadata.creator.as_deref().unwrap_or(""))?;
+    write_string(&mut file, cache.metadata.language.as_deref().unwrap_or(""))?;
+    write_string(&mut file, cache.metadata.identifier.as_deref().unwrap_or(""))?;
+    write_string(&mut file, cache.cover_href.as_deref().unwrap_or(""))?;
+    write_string(&mut file, &cache.opf_path)?;
+
+    for entry in &cache.spine {
+        write_string(&mut file, &entry.href)?;
+        write_u64(&mut file, entry.cumulative_size)?;
+        write_i32(&mut file, entry.toc_index)?;
+        write_u32(&mut file, entry.word_count)?;
+        write_u8(&mut file, entry.linear as u8)?;
+        write_string(&mut file, entry.language.as_deref().unwrap_or(""))?;
+    }
+
+    for entry in &cache.toc {
+        write_string(&mut file, &entry.title)?;
+        write_string(&mut file, &entry.href)?;
+        write_string(&mut file, &entry.anchor)?;
+        write_u8(&mut file, entry.level)?;
+        write_i32(&mut file, entry.spine_index)?;
+    }
 
+    cache.cache_path = cache_path;
+    Ok(cache)
+}
+
+/// Reads everything [`build_cache`] stores straight from the EPUB, without
+/// writing a cache. The returned `cache_path` is empty.
+pub fn scan_book(epub_path: &Path) -> Result<BookCache, EpubError> {
     let meta = std::fs::metadata(epub_path)?;
     let source_size = meta.len();
     let source_mtime = system_time_secs(meta.modified().ok());
 
     let book = open_epub(epub_path)?;
-    let spine_hrefs = build_spine_hrefs(&book.package);
+    let spine_items = build_spine_items(&book.package);
+    let spine_hrefs: Vec<String> = spine_items.iter().map(|(href, _)| href.clone()).collect();
 
     let mut archive = zip::ZipArchive::new(std::fs::File::open(epub_path)?)?;
-    let mut spine_entries = Vec::with_capacity(spine_hrefs.len());
+    let mut spine_entries = Vec::with_capacity(spine_items.len());
     let mut cumulative_size = 0u64;
 
-    for href in &spine_hrefs {
+    for (href, linear) in &spine_items {
         let size = zip_entry_size(&mut archive, href).unwrap_or(0);
         cumulative_size = cumulative_size.saturating_add(size);
+        let xml = read_zip_file_to_string(&mut archive, href).ok();
+        let word_count = xml
+            .as_deref()
+            .and_then(|xml| xhtml_plain_text(xml).ok())
+            .map(|text| text.split_whitespace().count() as u32)
+            .unwrap_or(0);
+        let language = xml.as_deref().and_then(detect_spine_language);
         spine_entries.push(CacheSpineEntry {
             href: href.clone(),
             cumulative_size,
             toc_index: -1,
+            word_count,
+            linear: *linear,
+            language,
         });
     }
 
@@ -592,57 +1317,27 @@ pub fn build_cache(epub_path: &Path, cache_dir: &Path) -> Result<BookCache, Epub
         }
     }
 
-    let cache_path = cache_dir.join("book.bin");
-    let mut file = std::fs::File::create(&cache_path)?;
-
-    write_u8(&mut file, CACHE_VERSION)?;
-    write_u64(&mut file, source_size)?;
-    write_u64(&mut file, source_mtime)?;
-    write_u32(&mut file, spine_entries.len() as u32)?;
-    write_u32(&mut file, toc_entries.len() as u32)?;
-
-    write_string(&mut file, book.package.metadata.title.as_deref().unwrap_or(""))?;
-    write_string(
-        &mut file,
-        book.package.metadata.creator.as_deref().unwrap_or(""),
-    )?;
-    write_string(
-        &mut file,
-        book.package.metadata.language.as_deref().unwrap_or(""),
-    )?;
-    write_string(
-        &mut file,
-        book.package.metadata.identifier.as_deref().unwrap_or(""),
-    )?;
-    write_string(&mut file, book.package.cover_href.as_deref().unwrap_or(""))?;
-    write_string(&mut file, &book.package.opf_path)?;
-
-    for entry in &spine_entries {
-        write_string(&mut file, &entry.href)?;
-        write_u64(&mut file, entry.cumulative_size)?;
-        write_i32(&mut file, entry.toc_index)?;
-    }
-
-    for entry in &toc_entries {
-        write_string(&mut file, &entry.title)?;
-        write_string(&mut file, &entry.href)?;
-        write_string(&mut file, &entry.anchor)?;
-        write_u8(&mut file, entry.level)?;
-        write_i32(&mut file, entry.spine_index)?;
-    }
-
     Ok(BookCache {
         metadata: book.package.metadata,
         opf_path: book.package.opf_path,
         cover_href: book.package.cover_href,
         spine: spine_entries,
         toc: toc_entries,
-        cache_path,
+        cache_path: PathBuf::new(),
         source_size,
         source_mtime,
     })
 }
 
+/// Minutes to read the whole book at `wpm` words per minute, rounded up.
+pub fn estimated_reading_minutes(cache: &BookCache, wpm: u32) -> u32 {
+    if wpm == 0 {
+        return 0;
+    }
+    let words: u64 = cache.spine.iter().map(|entry| entry.word_count as u64).sum();
+    words.div_ceil(wpm as u64) as u32
+}
+
 pub fn read_zip_file_to_string<R: Read + Seek>(
     archive: &mut zip::ZipArchive<R>,
     path: &str,
@@ -663,20 +1358,24 @@ pub fn read_zip_file_to_bytes<R: Read + Seek>(
     Ok(buf)
 }
 
-fn parse_container(xml: &str) -> Result<EpubContainer, EpubError> {
+/// Parses `META-INF/container.xml`. Multi-rendition books can list several
+/// rootfiles; the first OPF package by media type wins, else the first one.
+pub fn parse_container(xml: &str) -> Result<EpubContainer, EpubError> {
     let mut reader = Reader::from_str(xml);
     reader.config_mut().trim_text(true);
 
     let mut buf = Vec::new();
-    let mut rootfile_path: Option<String> = None;
+    let mut rootfiles = Vec::new();
 
     loop {
         match reader.read_event_into(&mut buf)? {
             Event::Start(e) | Event::Empty(e) => {
                 if is_xml_name(e.name().as_ref(), b"rootfile") {
-                    if let Some(path) = attr_value(&e, b"full-path")? {
-                        rootfile_path = Some(path);
-                        break;
+                    if let Some(full_path) = attr_value(&e, b"full-path")? {
+                        rootfiles.push(ContainerRootfile {
+                            full_path,
+                            media_type: attr_value(&e, b"media-type")?,
+                        });
                     }
                 }
             }
@@ -686,13 +1385,28 @@ fn parse_container(xml: &str) -> Result<EpubContainer, EpubError> {
         buf.clear();
     }
 
-    let rootfile_path = rootfile_path.ok_or(EpubError::MissingRootfile)?;
-    Ok(EpubContainer { rootfile_path })
+    let rootfile_path = rootfiles
+        .iter()
+        .find(|rootfile| {
+            rootfile
+                .media_type
+                .as_deref()
+                .is_some_and(|media_type| media_type.trim() == OPF_MEDIA_TYPE)
+        })
+        .or_else(|| rootfiles.first())
+        .map(|rootfile| rootfile.full_path.clone())
+        .ok_or(EpubError::MissingRootfile)?;
+    Ok(EpubContainer {
+        rootfile_path,
+        rootfiles,
+    })
 }
 
 fn parse_opf(xml: &str, opf_path: &str) -> Result<OpfPackage, EpubError> {
     let mut reader = Reader::from_str(xml);
-    reader.config_mut().trim_text(true);
+    // Metadata text is collapsed by hand; trimming each event would drop the
+    // spaces around nested markup.
+    reader.config_mut().trim_text(false);
 
     let opf_dir = opf_base_dir(opf_path);
 
@@ -701,6 +1415,7 @@ fn parse_opf(xml: &str, opf_path: &str) -> Result<OpfPackage, EpubError> {
     let mut in_manifest = false;
     let mut in_spine = false;
     let mut current_meta: Option<&'static str> = None;
+    let mut meta_text = String::new();
 
     let mut metadata = OpfMetadata::default();
     let mut manifest = Vec::new();
@@ -709,11 +1424,19 @@ fn parse_opf(xml: &str, opf_path: &str) -> Result<OpfPackage, EpubError> {
     let mut toc_href = None;
     let mut cover_id = None;
     let mut spine_toc_id: Option<String> = None;
+    // Books often carry several identifiers (ISBN, UUID, calibre id); the one
+    // named by `unique-identifier` is canonical, else the first one listed.
+    let mut unique_identifier_id: Option<String> = None;
+    let mut identifier_id: Option<String> = None;
+    let mut first_identifier: Option<String> = None;
 
     loop {
         match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
                 match e.name().as_ref() {
+                    name if is_xml_name(name, b"package") => {
+                        unique_identifier_id = attr_value(&e, b"unique-identifier")?;
+                    }
                     name if is_xml_name(name, b"metadata") => in_metadata = true,
                     name if is_xml_name(name, b"manifest") => in_manifest = true,
                     name if is_xml_name(name, b"spine") => {
@@ -768,15 +1491,20 @@ fn parse_opf(xml: &str, opf_path: &str) -> Result<OpfPackage, EpubError> {
                     }
                     name if in_metadata && is_xml_name(name, b"title") => {
                         current_meta = Some("title");
+                        meta_text.clear();
                     }
                     name if in_metadata && is_xml_name(name, b"creator") => {
                         current_meta = Some("creator");
+                        meta_text.clear();
                     }
                     name if in_metadata && is_xml_name(name, b"language") => {
                         current_meta = Some("language");
+                        meta_text.clear();
                     }
                     name if in_metadata && is_xml_name(name, b"identifier") => {
                         current_meta = Some("identifier");
+                        identifier_id = attr_value(&e, b"id")?;
+                        meta_text.clear();
                     }
                     _ => {}
                 }
@@ -838,23 +1566,39 @@ fn parse_opf(xml: &str, opf_path: &str) -> Result<OpfPackage, EpubError> {
                         || is_xml_name(name, b"language")
                         || is_xml_name(name, b"identifier") =>
                 {
-                    current_meta = None;
-                }
-                _ => {}
-            },
-            Event::Text(e) => {
-                if let Some(field) = current_meta {
-                    let text = e.decode().map_err(quick_xml::Error::from)?.into_owned();
-                    if !text.is_empty() {
-                        match field {
-                            "title" => metadata.title = Some(text),
-                            "creator" => metadata.creator = Some(text),
-                            "language" => metadata.language = Some(text),
-                            "identifier" => metadata.identifier = Some(text),
-                            _ => {}
+                    if let Some(field) = current_meta.take() {
+                        let text = meta_text.split_whitespace().collect::<Vec<_>>().join(" ");
+                        if !text.is_empty() {
+                            match field {
+                                "title" => metadata.title = Some(text),
+                                "creator" => metadata.creator = Some(text),
+                                "language" => metadata.language = Some(text),
+                                "identifier" => {
+                                    if unique_identifier_id.is_some()
+                                        && identifier_id == unique_identifier_id
+                                    {
+                                        metadata.identifier = Some(text);
+                                    } else if first_identifier.is_none() {
+                                        first_identifier = Some(text);
+                                    }
+                                }
+                                _ => {}
+                            }
                         }
                     }
                 }
+                _ => {}
+            },
+            // Text, entities and CDATA inside a field (including nested
+            // elements) all accumulate until its closing tag.
+            Event::Text(e) if current_meta.is_some() => {
+                meta_text.push_str(&e.decode().map_err(quick_xml::Error::from)?);
+            }
+            Event::GeneralRef(e) if current_meta.is_some() => {
+                push_xml_ref(&mut meta_text, &e)?;
+            }
+            Event::CData(e) if current_meta.is_some() => {
+                meta_text.push_str(&e.decode().map_err(quick_xml::Error::from)?);
             }
             Event::Eof => break,
             _ => {}
@@ -862,6 +1606,10 @@ fn parse_opf(xml: &str, opf_path: &str) -> Result<OpfPackage, EpubError> {
         buf.clear();
     }
 
+    if metadata.identifier.is_none() {
+        metadata.identifier = first_identifier;
+    }
+
     if let Some(toc_id) = spine_toc_id {
         if let Some(item) = manifest.iter().find(|item| item.id == toc_id) {
             toc_href = Some(item.href.clone());
@@ -971,6 +1719,35 @@ fn parse_nav_toc(xml: &str, nav_path: &str) -> Result<Vec<TocEntry>, EpubError>
     Ok(toc)
 }
 
+/// A `navPoint` while the NCX is being read; `playOrder` is only needed to
+/// order siblings.
+struct NcxNavPoint {
+    play_order: Option<u32>,
+    label: String,
+    href: String,
+    label_done: bool,
+    children: Vec<NcxNavPoint>,
+}
+
+impl NcxNavPoint {
+    /// Converts to TOC entries, ordering siblings by `playOrder` when every
+    /// one of them has it and keeping document order otherwise.
+    fn into_toc(points: Vec<NcxNavPoint>) -> Vec<TocEntry> {
+        let mut points = points;
+        if points.iter().all(|point| point.play_order.is_some()) {
+            points.sort_by_key(|point| point.play_order);
+        }
+        points
+            .into_iter()
+            .map(|point| TocEntry {
+                label: point.label.trim().to_string(),
+                href: point.href,
+                children: Self::into_toc(point.children),
+            })
+            .collect()
+    }
+}
+
 fn parse_ncx_toc(xml: &str, ncx_path: &str) -> Result<Vec<TocEntry>, EpubError> {
     let mut reader = Reader::from_str(xml);
     reader.config_mut().trim_text(true);
@@ -978,41 +1755,57 @@ fn parse_ncx_toc(xml: &str, ncx_path: &str) -> Result<Vec<TocEntry>, EpubError>
     let base_dir = opf_base_dir(ncx_path);
 
     let mut buf = Vec::new();
-    let mut toc: Vec<TocEntry> = Vec::new();
-    let mut stack: VecDeque<TocEntry> = VecDeque::new();
+    let mut toc: Vec<NcxNavPoint> = Vec::new();
+    let mut stack: Vec<NcxNavPoint> = Vec::new();
+    // Labels and content only count inside navMap; docTitle, navInfo and
+    // pageList use the same tags.
+    let mut in_nav_map = false;
     let mut in_nav_label = false;
     let mut in_label_text = false;
 
     loop {
         match reader.read_event_into(&mut buf)? {
             Event::Start(e) => match e.name().as_ref() {
-                b"navPoint" => {
-                    stack.push_back(TocEntry {
+                b"navMap" => in_nav_map = true,
+                b"navPoint" if in_nav_map => {
+                    let play_order = attr_value(&e, b"playOrder")?
+                        .and_then(|value| value.trim().parse().ok());
+                    stack.push(NcxNavPoint {
+                        play_order,
                         label: String::new(),
                         href: String::new(),
+                        label_done: false,
                         children: Vec::new(),
                     });
                 }
-                b"navLabel" => in_nav_label = true,
+                b"navLabel" if !stack.is_empty() => in_nav_label = true,
                 b"text" if in_nav_label => in_label_text = true,
-                b"content" => {
-                    if let Some(href) = attr_value(&e, b"src")? {
-                        if let Some(entry) = stack.back_mut() {
-                            entry.href = resolve_href(&base_dir, &href);
-                        }
-                    }
-                }
+                b"content" => set_ncx_href(&e, &mut stack, &base_dir)?,
                 _ => {}
             },
+            Event::Empty(e) => {
+                if e.name().as_ref() == b"content" {
+                    set_ncx_href(&e, &mut stack, &base_dir)?;
+                }
+            }
             Event::End(e) => match e.name().as_ref() {
-                b"navLabel" => in_nav_label = false,
+                b"navMap" => in_nav_map = false,
+                b"navLabel" => {
+                    if in_nav_label {
+                        // Later navLabels (other languages) don't replace the first.
+                        if let Some(point) = stack.last_mut() {
+                            point.label_done = !point.label.trim().is_empty();
+                        }
+                    }
+                    in_nav_label = false;
+                }
                 b"text" => in_label_text = false,
                 b"navPoint" => {
-                    if let Some(entry) = stack.pop_back() {
-                        if let Some(parent) = stack.back_mut() {
-                            parent.children.push(entry);
+                    if let Some(point) = stack.pop() {
+                        if let Some(parent) = stack.last_mut() {
+                            parent.children.push(point);
                         } else {
-                            toc.push(entry);
+                            toc.push(point);
                         }
                     }
                 }
@@ -1020,8 +1813,12 @@ fn parse_ncx_toc(xml: &str, ncx_path: &str) -> Result<Vec<TocEntry>, EpubError>
             },
             Event::Text(e) => {
                 if in_nav_label && in_label_text {
-                    if let Some(entry) = stack.back_mut() {
-                        entry.label = e.decode().map_err(quick_xml::Error::from)?.into_owned();
+                    if let Some(point) = stack.last_mut() {
+                        if !point.label_done {
+                            point
+                                .label
+                                .push_str(&e.decode().map_err(quick_xml::Error::from)?);
+                        }
                     }
                 }
             }
@@ -1031,7 +1828,44 @@ fn parse_ncx_toc(xml: &str, ncx_path: &str) -> Result<Vec<TocEntry>, EpubError>
         buf.clear();
     }
 
-    Ok(toc)
+    Ok(NcxNavPoint::into_toc(toc))
+}
+
+/// Takes the first `content` of the innermost open navPoint; stray ones
+/// outside a navPoint, or repeats, are ignored.
+fn set_ncx_href(
+    e: &BytesStart<'_>,
+    stack: &mut [NcxNavPoint],
+    base_dir: &str,
+) -> Result<(), EpubError> {
+    let Some(point) = stack.last_mut() else {
+        return Ok(());
+    };
+    if !point.href.is_empty() {
+        return Ok(());
+    }
+    if let Some(href) = attr_value(e, b"src")? {
+        point.href = resolve_href(base_dir, &href);
+    }
+    Ok(())
+}
+
+/// Fails with `EpubError::Drm` for Adobe or LCP protected books, before any
+/// encrypted content gets parsed. An `encryption.xml` that only lists
+/// obfuscated fonts is fine; those fonts are returned.
+fn read_encryption<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+) -> Result<Vec<ObfuscatedResource>, EpubError> {
+    if archive.by_name("META-INF/rights.xml").is_ok()
+        || archive.by_name("META-INF/license.lcpl").is_ok()
+    {
+        return Err(EpubError::Drm);
+    }
+    match read_zip_file_to_string(archive, "META-INF/encryption.xml") {
+        Ok(xml) => parse_encryption_xml(&xml),
+        Err(EpubError::Zip(zip::result::ZipError::FileNotFound)) => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
 }
 
 fn find_cover_href(package: &OpfPackage) -> Option<String> {
@@ -1042,6 +1876,63 @@ fn find_cover_href(package: &OpfPackage) -> Option<String> {
         .map(|item| item.href.clone())
 }
 
+/// Last-resort cover for books that declare none: the image on the first
+/// linear spine page, if that page holds exactly one image and at most a
+/// short caption. Returns the image's manifest href, like a declared cover.
+fn find_cover_page_image<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    package: &OpfPackage,
+) -> Option<String> {
+    let page_path = package.linear_spine_hrefs().into_iter().next()?;
+    let xml = read_zip_file_to_string(archive, &page_path).ok()?;
+    let blocks = parse_xhtml_blocks(&xml).ok()?;
+    let mut images = blocks.iter().filter_map(|block| match block {
+        HtmlBlock::Image { src, .. } => Some(src),
+        _ => None,
+    });
+    let src = images.next()?;
+    if images.next().is_some() {
+        return None;
+    }
+    let text_chars: usize = blocks
+        .iter()
+        .map(|block| match block {
+            HtmlBlock::Paragraph { runs, .. } => runs.iter().collect(),
+            HtmlBlock::TableRow { cells } => cells.iter().flatten().collect(),
+            _ => Vec::new(),
+        })
+        .flat_map(|runs| runs.into_iter().flat_map(|run| run.text.chars()))
+        .filter(|ch| !ch.is_whitespace())
+        .count();
+    if text_chars > COVER_PAGE_MAX_TEXT_CHARS {
+        return None;
+    }
+    let image_path = normalize_zip_path(&resolve_href(&opf_base_dir(&page_path), src));
+    package
+        .manifest
+        .iter()
+        .filter(|item| item.media_type.starts_with("image/"))
+        .find(|item| normalize_zip_path(&resolve_href(&package.opf_dir, &item.href)) == image_path)
+        .map(|item| item.href.clone())
+}
+
+/// Folds `.` and `..` segments and drops any fragment, so hrefs written
+/// relative to different files can be compared.
+fn normalize_zip_path(path: &str) -> String {
+    let path = path.split('#').next().unwrap_or_default();
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
 fn is_toc_nav(e: &BytesStart<'_>) -> Result<bool, EpubError> {
     let mut is_toc = false;
     if let Some(value) = attr_value(e, b"epub:type")? {
@@ -1057,6 +1948,25 @@ fn is_toc_nav(e: &BytesStart<'_>) -> Result<bool, EpubError> {
     Ok(is_toc)
 }
 
+/// Appends the text an entity or character reference stands for; unknown
+/// entities are kept verbatim.
+fn push_xml_ref(out: &mut String, e: &BytesRef<'_>) -> Result<(), EpubError> {
+    if let Some(ch) = e.resolve_char_ref()? {
+        out.push(ch);
+        return Ok(());
+    }
+    let name = e.decode().map_err(quick_xml::Error::from)?;
+    match quick_xml::escape::resolve_predefined_entity(&name) {
+        Some(text) => out.push_str(text),
+        None => {
+            out.push('&');
+            out.push_str(&name);
+            out.push(';');
+        }
+    }
+    Ok(())
+}
+
 fn attr_value(e: &BytesStart<'_>, name: &[u8]) -> Result<Option<String>, EpubError> {
     for attr in e.attributes().with_checks(false) {
         let attr = attr.map_err(quick_xml::Error::from)?;
@@ -1109,6 +2019,19 @@ fn is_block_tag(name: &[u8]) -> bool {
         || is_xml_name(name, b"h6")
 }
 
+fn is_monospace_tag(name: &[u8]) -> bool {
+    is_xml_name(name, b"code") || is_xml_name(name, b"tt") || is_xml_name(name, b"kbd")
+}
+
+fn is_table_cell(name: &[u8]) -> bool {
+    is_xml_name(name, b"td") || is_xml_name(name, b"th")
+}
+
+/// Table markup that only separates words once the table is inside a cell.
+fn is_nested_table_tag(name: &[u8]) -> bool {
+    is_table_cell(name) || is_xml_name(name, b"tr") || is_xml_name(name, b"table")
+}
+
 fn heading_level_from(name: &[u8]) -> Option<u8> {
     if is_xml_name(name, b"h1") {
         Some(1)
@@ -1127,6 +2050,27 @@ fn heading_level_from(name: &[u8]) -> Option<u8> {
     }
 }
 
+/// Image block for an HTML `<img>` or an SVG `<image>`, the latter being how
+/// many cover pages wrap their picture. SVG uses `xlink:href` (or `href` in
+/// SVG 2) and has no `alt`, so `aria-label` stands in.
+fn image_block(e: &BytesStart<'_>) -> Result<Option<HtmlBlock>, EpubError> {
+    let src = match attr_value(e, b"src")? {
+        Some(src) => Some(src),
+        None => match attr_value(e, b"xlink:href")? {
+            Some(href) => Some(href),
+            None => attr_value(e, b"href")?,
+        },
+    };
+    let Some(src) = src else {
+        return Ok(None);
+    };
+    let alt = match attr_value(e, b"alt")? {
+        Some(alt) => Some(alt),
+        None => attr_value(e, b"aria-label")?,
+    };
+    Ok(Some(HtmlBlock::Image { alt, src }))
+}
+
 fn is_pagebreak(e: &BytesStart<'_>) -> Result<bool, EpubError> {
     if let Some(value) = attr_value(e, b"epub:type")? {
         if value == "pagebreak" {
@@ -1183,6 +2127,14 @@ fn flush_paragraph(
     if runs.is_empty() {
         return;
     }
+    blocks.push(HtmlBlock::Paragraph {
+        runs: merge_runs(runs),
+        heading_level,
+    });
+}
+
+/// Drains `runs`, joining neighbours that share a style.
+fn merge_runs(runs: &mut Vec<TextRun>) -> Vec<TextRun> {
     let mut merged: Vec<TextRun> = Vec::new();
     for run in runs.drain(..) {
         if let Some(last) = merged.last_mut() {
@@ -1193,10 +2145,7 @@ fn flush_paragraph(
         }
         merged.push(run);
     }
-    blocks.push(HtmlBlock::Paragraph {
-        runs: merged,
-        heading_level,
-    });
+    merged
 }
 
 fn push_normalized_text(input: &str, buf: &mut String, last_was_space: &mut bool) {
@@ -1214,17 +2163,25 @@ fn push_normalized_text(input: &str, buf: &mut String, last_was_space: &mut bool
 }
 
 fn build_spine_hrefs(package: &OpfPackage) -> Vec<String> {
+    build_spine_items(package)
+        .into_iter()
+        .map(|(href, _)| href)
+        .collect()
+}
+
+/// Resolved spine hrefs in reading order, each with its `linear` flag.
+fn build_spine_items(package: &OpfPackage) -> Vec<(String, bool)> {
     let mut manifest_map = HashMap::new();
     for item in &package.manifest {
         manifest_map.insert(item.id.as_str(), item.href.as_str());
     }
-    let mut hrefs = Vec::new();
+    let mut items = Vec::new();
     for spine in &package.spine {
         if let Some(href) = manifest_map.get(spine.idref.as_str()) {
-            hrefs.push(resolve_href(&package.opf_dir, href));
+            items.push((resolve_href(&package.opf_dir, href), spine.linear));
         }
     }
-    hrefs
+    items
 }
 
 fn split_href_anchor(href: &str) -> (String, String) {
@@ -1330,3 +2287,312 @@ fn write_string<W: Write>(writer: &mut W, value: &str) -> Result<(), EpubError>
     writer.write_all(bytes)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opf_with_identifiers(unique_identifier: Option<&str>) -> String {
+        let unique_identifier = unique_identifier
+            .map(|id| format!(r#" unique-identifier="{id}""#))
+            .unwrap_or_default();
+        format!(
+            r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0"{unique_identifier}>
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+    <dc:title>Identifiers</dc:title>
+    <dc:identifier id="isbn" opf:scheme="ISBN">9780000000002</dc:identifier>
+    <dc:identifier id="calibre_id" opf:scheme="calibre">1234</dc:identifier>
+    <dc:identifier id="uuid_id" opf:scheme="uuid">urn:uuid:0f3c9a52-8c1b-4d7e-a3f6-5b2e9d4c1a77</dc:identifier>
+  </metadata>
+  <manifest>
+    <item id="c1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="c1"/>
+  </spine>
+</package>"#
+        )
+    }
+
+    #[test]
+    fn parse_opf_uses_the_unique_identifier() {
+        let package =
+            parse_opf(&opf_with_identifiers(Some("uuid_id")), "OEBPS/content.opf").unwrap();
+        assert_eq!(
+            package.metadata.identifier.as_deref(),
+            Some("urn:uuid:0f3c9a52-8c1b-4d7e-a3f6-5b2e9d4c1a77")
+        );
+    }
+
+    #[test]
+    fn parse_opf_falls_back_to_the_first_identifier() {
+        let package = parse_opf(&opf_with_identifiers(None), "OEBPS/content.opf").unwrap();
+        assert_eq!(
+            package.metadata.identifier.as_deref(),
+            Some("9780000000002")
+        );
+    }
+
+    const COVER_OPF: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>Cover</dc:title></metadata>
+  <manifest>
+    <item id="page" href="text/cover.xhtml" media-type="application/xhtml+xml"/>
+    <item id="img" href="images/cover.jpg" media-type="image/jpeg"/>
+    <item id="other" href="images/other.png" media-type="image/png"/>
+  </manifest>
+  <spine><itemref idref="page"/></spine>
+</package>"#;
+
+    fn cover_page_image(body: &str) -> Option<String> {
+        let page = format!(
+            r#"<?xml version="1.0"?><html xmlns="http://www.w3.org/1999/xhtml" xmlns:xlink="http://www.w3.org/1999/xlink"><body>{body}</body></html>"#
+        );
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer
+            .start_file("OEBPS/text/cover.xhtml", zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(page.as_bytes()).unwrap();
+        let mut archive = zip::ZipArchive::new(writer.finish().unwrap()).unwrap();
+        let package = parse_opf(COVER_OPF, "OEBPS/content.opf").unwrap();
+        find_cover_page_image(&mut archive, &package)
+    }
+
+    #[test]
+    fn cover_page_with_a_single_img() {
+        assert_eq!(
+            cover_page_image(r#"<div><img src="../images/cover.jpg" alt="Cover"/></div>"#)
+                .as_deref(),
+            Some("images/cover.jpg")
+        );
+    }
+
+    #[test]
+    fn cover_page_with_an_svg_image() {
+        let body = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 600 800"><image width="600" height="800" xlink:href="../images/cover.jpg"/></svg>"#;
+        assert_eq!(cover_page_image(body).as_deref(), Some("images/cover.jpg"));
+    }
+
+    #[test]
+    fn cover_page_with_two_images_is_not_a_cover() {
+        let body =
+            r#"<p><img src="../images/cover.jpg"/></p><p><img src="../images/other.png"/></p>"#;
+        assert_eq!(cover_page_image(body), None);
+    }
+
+    #[test]
+    fn cover_page_with_body_text_is_not_a_cover() {
+        let text = "word ".repeat(COVER_PAGE_MAX_TEXT_CHARS);
+        let body = format!(r#"<p><img src="../images/cover.jpg"/></p><p>{text}</p>"#);
+        assert_eq!(cover_page_image(&body), None);
+    }
+
+    #[test]
+    fn normalize_zip_path_folds_parent_segments() {
+        assert_eq!(
+            normalize_zip_path("OEBPS/text/../images/cover.jpg"),
+            "OEBPS/images/cover.jpg"
+        );
+        assert_eq!(
+            normalize_zip_path("OEBPS/./a/b/../../c.xhtml#top"),
+            "OEBPS/c.xhtml"
+        );
+        assert_eq!(normalize_zip_path("../../cover.jpg"), "cover.jpg");
+    }
+
+    fn flatten_toc(entries: &[TocEntry], depth: usize, out: &mut Vec<(usize, String, String)>) {
+        for entry in entries {
+            out.push((depth, entry.label.clone(), entry.href.clone()));
+            flatten_toc(&entry.children, depth + 1, out);
+        }
+    }
+
+    #[test]
+    fn parse_ncx_toc_nests_and_orders_by_play_order() {
+        // Siblings are listed out of playOrder at every level.
+        let ncx = r#"<?xml version="1.0"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <docTitle><text>Book</text></docTitle>
+  <navMap>
+    <navPoint id="p2" playOrder="5">
+      <navLabel><text>Part Two</text></navLabel>
+      <content src="text/part2.xhtml"/>
+    </navPoint>
+    <navPoint id="p1" playOrder="1">
+      <navLabel><text>Part One</text></navLabel>
+      <content src="text/part1.xhtml"/>
+      <navPoint id="c2" playOrder="4">
+        <navLabel><text>Chapter Two</text></navLabel>
+        <content src="text/ch2.xhtml"/>
+      </navPoint>
+      <navPoint id="c1" playOrder="2">
+        <navLabel><text>Chapter One</text></navLabel>
+        <content src="text/ch1.xhtml"/>
+        <navPoint id="s1" playOrder="3">
+          <navLabel><text>Section One</text></navLabel>
+          <content src="text/ch1.xhtml#s1"/>
+        </navPoint>
+      </navPoint>
+    </navPoint>
+  </navMap>
+</ncx>"#;
+        let toc = parse_ncx_toc(ncx, "OEBPS/toc.ncx").unwrap();
+        let mut flat = Vec::new();
+        flatten_toc(&toc, 0, &mut flat);
+        let expected = [
+            (0, "Part One", "OEBPS/text/part1.xhtml"),
+            (1, "Chapter One", "OEBPS/text/ch1.xhtml"),
+            (2, "Section One", "OEBPS/text/ch1.xhtml#s1"),
+            (1, "Chapter Two", "OEBPS/text/ch2.xhtml"),
+            (0, "Part Two", "OEBPS/text/part2.xhtml"),
+        ];
+        let flat: Vec<(usize, &str, &str)> = flat
+            .iter()
+            .map(|(depth, label, href)| (*depth, label.as_str(), href.as_str()))
+            .collect();
+        assert_eq!(flat, expected);
+    }
+
+    fn image_sources(blocks: &[HtmlBlock]) -> Vec<(&str, Option<&str>)> {
+        blocks
+            .iter()
+            .filter_map(|block| match block {
+                HtmlBlock::Image { src, alt } => Some((src.as_str(), alt.as_deref())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn svg_wrapped_images_become_image_blocks() {
+        let xml = r#"<?xml version="1.0"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:xlink="http://www.w3.org/1999/xlink">
+<body>
+  <p>Before</p>
+  <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 600 800">
+    <image width="600" height="800" xlink:href="../images/cover.jpg"/>
+  </svg>
+  <svg xmlns="http://www.w3.org/2000/svg">
+    <image href="map.png" aria-label="Map"></image>
+  </svg>
+  <p>After</p>
+</body>
+</html>"#;
+        let blocks = parse_xhtml_blocks(xml).unwrap();
+        assert_eq!(
+            image_sources(&blocks),
+            [("../images/cover.jpg", None), ("map.png", Some("Map"))]
+        );
+        assert!(matches!(blocks.first(), Some(HtmlBlock::Paragraph { .. })));
+        assert!(matches!(blocks.last(), Some(HtmlBlock::Paragraph { .. })));
+    }
+
+    #[test]
+    fn nested_tables_stay_in_their_cell() {
+        let xml = r#"<?xml version="1.0"?>
+<html xmlns="http://www.w3.org/1999/xhtml">
+<body>
+  <table>
+    <tr><td>A</td><td><table><tr><td>x</td><td>y</td></tr><tr><td>z</td></tr></table></td><td>C</td></tr>
+    <tr><td>D</td></tr>
+  </table>
+</body>
+</html>"#;
+        let rows: Vec<HtmlBlock> = parse_xhtml_blocks(xml)
+            .unwrap()
+            .into_iter()
+            .filter(|block| matches!(block, HtmlBlock::TableRow { .. }))
+            .collect();
+        assert_eq!(blocks_to_plain_text(&rows), "A\tx y z\tC\nD\n\n");
+    }
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&value[idx..idx + 2], 16).unwrap())
+            .collect()
+    }
+
+    const FONT_UUID: &str = "urn:uuid:0f3c9a52-8c1b-4d7e-a3f6-5b2e9d4c1a77";
+
+    #[test]
+    fn obfuscation_keys_follow_the_specs() {
+        // IDPF: SHA-1 of the identifier with XML whitespace removed.
+        let idpf = hex("2ca03c2308240eac4048ed1afbbd1bedfe2c20e7");
+        assert_eq!(
+            ObfuscationAlgorithm::Idpf.key(FONT_UUID),
+            Some(idpf.clone())
+        );
+        assert_eq!(
+            ObfuscationAlgorithm::Idpf.key(&format!(" {FONT_UUID}\n")),
+            Some(idpf)
+        );
+        // Adobe: the 16 bytes of the UUID itself.
+        assert_eq!(
+            ObfuscationAlgorithm::Adobe.key(FONT_UUID),
+            Some(hex("0f3c9a528c1b4d7ea3f65b2e9d4c1a77"))
+        );
+        assert_eq!(ObfuscationAlgorithm::Adobe.key("9780000000002"), None);
+    }
+
+    #[test]
+    fn deobfuscate_font_xors_only_the_header() {
+        let plain: Vec<u8> = (0..1100).map(|idx| (idx % 251) as u8).collect();
+        for (algorithm, head, boundary) in [
+            (
+                ObfuscationAlgorithm::Idpf,
+                "2ca13e20",
+                (1036, "de0d02c424252627"),
+            ),
+            (
+                ObfuscationAlgorithm::Adobe,
+                "0f3d9851",
+                (1020, "8d5d086414151617"),
+            ),
+        ] {
+            let key = algorithm.key(FONT_UUID).unwrap();
+            let mut bytes = plain.clone();
+            deobfuscate_font(&mut bytes, &key, algorithm);
+            assert_eq!(bytes[..4], hex(head)[..], "{algorithm:?}");
+            let (offset, expected) = boundary;
+            assert_eq!(
+                bytes[offset..offset + 8],
+                hex(expected)[..],
+                "{algorithm:?}"
+            );
+            deobfuscate_font(&mut bytes, &key, algorithm);
+            assert_eq!(bytes, plain, "{algorithm:?}");
+        }
+    }
+
+    #[test]
+    fn parse_opf_keeps_text_inside_nested_title_markup() {
+        let opf = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>The <span class="em">Long</span>
+      Way Home</dc:title>
+  </metadata>
+  <manifest/>
+  <spine/>
+</package>"#;
+        let package = parse_opf(opf, "content.opf").unwrap();
+        assert_eq!(package.metadata.title.as_deref(), Some("The Long Way Home"));
+    }
+
+    #[test]
+    fn parse_container_prefers_the_opf_rootfile() {
+        let xml = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/book.pdf" media-type="application/pdf"/>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+        let container = parse_container(xml).unwrap();
+        assert_eq!(container.rootfile_path, "OEBPS/content.opf");
+        assert_eq!(container.rootfiles.len(), 2);
+        assert_eq!(container.rootfiles[0].full_path, "OEBPS/book.pdf");
+    }
+}
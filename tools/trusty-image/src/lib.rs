@@ -15,6 +15,7 @@ mod onnx_detector;
 const MAGIC: &[u8; 4] = b"TRIM";
 const VERSION: u8 = 1;
 const FORMAT_MONO1: u8 = 1;
+const FORMAT_GRAY2: u8 = 2;
 
 #[derive(Clone, Copy, Debug)]
 pub enum FitMode {
@@ -39,6 +40,24 @@ pub enum RegionMode {
     Barcode,
 }
 
+/// Pixel encoding of the written `.trimg`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 1 bit per pixel, 1 = white.
+    Mono1,
+    /// 2 bits per pixel, four gray levels from 0 (black) to 3 (white).
+    Gray2,
+}
+
+/// How hard barcode detection tries before giving up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarcodeEffort {
+    /// Full-size image only; no downscaled passes or band crops.
+    Fast,
+    /// Three scales, both polarities, then likely-barcode band crops.
+    Thorough,
+}
+
 #[derive(Clone, Debug)]
 pub struct ConvertOptions {
     pub width: u32,
@@ -46,7 +65,17 @@ pub struct ConvertOptions {
     pub fit: FitMode,
     pub dither: DitherMode,
     pub region_mode: RegionMode,
+    pub barcode_effort: BarcodeEffort,
+    pub output: OutputFormat,
+    /// Inverts the picture; barcode overlays are always drawn dark on light.
     pub invert: bool,
+    /// Tone curve applied to luma before thresholding and dithering. Values
+    /// above 1.0 lift midtones; 1.0 leaves pixels untouched.
+    pub gamma: f32,
+    /// Unsharp-mask strength applied after gamma; 0.0 disables it. With
+    /// `DitherMode::None` the boosted edges decide the fixed threshold
+    /// directly, so small amounts (0.5-1.5) are usually enough.
+    pub sharpen: f32,
     pub debug: bool,
     pub yolo_model: Option<PathBuf>,
     pub yolo_num_classes: usize,
@@ -62,7 +91,11 @@ impl Default for ConvertOptions {
             fit: FitMode::Width,
             dither: DitherMode::Bayer,
             region_mode: RegionMode::Auto,
+            barcode_effort: BarcodeEffort::Thorough,
+            output: OutputFormat::Mono1,
             invert: false,
+            gamma: 1.0,
+            sharpen: 0.0,
             debug: false,
             yolo_model: None,
             yolo_num_classes: 2,
@@ -81,18 +114,35 @@ pub enum ConvertError {
 pub struct Trimg {
     pub width: u32,
     pub height: u32,
+    pub format: OutputFormat,
+    /// Row-major, MSB first; packing follows `format`.
     pub bits: Vec<u8>,
 }
 
 pub fn convert_bytes(bytes: &[u8], options: ConvertOptions) -> Result<Trimg, ConvertError> {
-    let image = image::load_from_memory(bytes).map_err(|_| ConvertError::Decode)?;
+    let image = decode_upright(bytes).map_err(|_| ConvertError::Decode)?;
     Ok(convert_image(&image, options))
 }
 
+/// Decodes an image and applies its EXIF orientation. A missing or unreadable
+/// tag leaves the image as stored.
+fn decode_upright(bytes: &[u8]) -> image::ImageResult<DynamicImage> {
+    use image::ImageDecoder;
+
+    let mut decoder = image::ImageReader::new(io::Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder
+        .orientation()
+        .unwrap_or(image::metadata::Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
 pub fn convert_image(image: &DynamicImage, options: ConvertOptions) -> Trimg {
     let gray = image.to_luma8();
     let transform = Transform::new(gray.dimensions(), options.width, options.height, options.fit);
-    let threshold = otsu_threshold(&gray);
     let (overlays, wipe_rects) = match options.region_mode {
         RegionMode::None => (Vec::new(), Vec::new()),
         RegionMode::Crisp => (Vec::new(), Vec::new()),
@@ -100,6 +150,11 @@ pub fn convert_image(image: &DynamicImage, options: ConvertOptions) -> Trimg {
             decode_and_render_overlays(image, &gray, &transform, &options)
         }
     };
+    // Barcodes decode from the untouched luma; only the rendered tones are
+    // adjusted.
+    let gray = apply_gamma(gray, options.gamma);
+    let gray = apply_sharpen(gray, options.sharpen);
+    let threshold = otsu_threshold(&gray);
     let crisp_mask = match options.region_mode {
         RegionMode::None => None,
         RegionMode::Crisp => Some(build_crisp_mask(&gray, threshold, 16)),
@@ -113,7 +168,11 @@ pub fn convert_image(image: &DynamicImage, options: ConvertOptions) -> Trimg {
         }
     };
 
-    let mut bits = vec![0u8; ((options.width as usize * options.height as usize) + 7) / 8];
+    let pixel_count = options.width as usize * options.height as usize;
+    let mut bits = match options.output {
+        OutputFormat::Mono1 => vec![0u8; (pixel_count + 7) / 8],
+        OutputFormat::Gray2 => vec![0u8; pixel_count.div_ceil(4)],
+    };
     for y in 0..options.height {
         for x in 0..options.width {
             let mut white = None;
@@ -123,40 +182,50 @@ pub fn convert_image(image: &DynamicImage, options: ConvertOptions) -> Trimg {
                     break;
                 }
             }
+            if white.is_none() && wipe_rects.iter().any(|rect| rect.contains(x, y)) {
+                white = Some(true);
+            }
 
-            let mut white = if let Some(value) = white {
-                value
+            // Gray level 0 (black) to 3 (white); Mono1 only ever sees 0 or 3.
+            // Barcode overlays and their wiped quiet zones keep dark-on-light
+            // polarity under `invert` so they stay scannable.
+            let level = if let Some(value) = white {
+                if value { 3 } else { 0 }
             } else {
-                if wipe_rects.iter().any(|rect| rect.contains(x, y)) {
-                    true
-                } else {
                 let (src_x, src_y, in_bounds) = transform.map_to_source(x, y);
                 let lum = if in_bounds {
                     gray.get_pixel(src_x, src_y).0[0]
                 } else {
                     255
                 };
-                if let Some(mask) = &crisp_mask {
-                    if in_bounds && mask.is_crisp(src_x, src_y) {
-                        lum >= threshold
-                    } else {
-                        apply_dither(lum, x, y, options.dither)
-                    }
+                let crisp = crisp_mask
+                    .as_ref()
+                    .is_some_and(|mask| in_bounds && mask.is_crisp(src_x, src_y));
+                let level = if crisp {
+                    if lum >= threshold { 3 } else { 0 }
                 } else {
-                    apply_dither(lum, x, y, options.dither)
-                }
-                }
+                    match options.output {
+                        OutputFormat::Mono1 => {
+                            if apply_dither(lum, x, y, options.dither) {
+                                3
+                            } else {
+                                0
+                            }
+                        }
+                        OutputFormat::Gray2 => quantize_gray2(lum, x, y, options.dither),
+                    }
+                };
+                if options.invert { 3 - level } else { level }
             };
 
-            if options.invert {
-                white = !white;
-            }
-
             let idx = (y * options.width + x) as usize;
-            let byte = idx / 8;
-            let bit = 7 - (idx % 8);
-            if white {
-                bits[byte] |= 1 << bit;
+            match options.output {
+                OutputFormat::Mono1 => {
+                    if level == 3 {
+                        bits[idx / 8] |= 1 << (7 - (idx % 8));
+                    }
+                }
+                OutputFormat::Gray2 => bits[idx / 4] |= level << (6 - (idx % 4) * 2),
             }
         }
     }
@@ -164,16 +233,52 @@ pub fn convert_image(image: &DynamicImage, options: ConvertOptions) -> Trimg {
     Trimg {
         width: options.width,
         height: options.height,
+        format: options.output,
         bits,
     }
 }
 
+fn apply_gamma(mut gray: GrayImage, gamma: f32) -> GrayImage {
+    if gamma == 1.0 || gamma <= 0.0 {
+        return gray;
+    }
+    let exponent = 1.0 / gamma;
+    let mut lut = [0u8; 256];
+    for (value, entry) in lut.iter_mut().enumerate() {
+        let normalized = value as f32 / 255.0;
+        *entry = (normalized.powf(exponent) * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+    for pixel in gray.pixels_mut() {
+        pixel.0[0] = lut[pixel.0[0] as usize];
+    }
+    gray
+}
+
+/// Unsharp mask: adds `amount` times the difference between the image and a
+/// small Gaussian blur of it.
+fn apply_sharpen(gray: GrayImage, amount: f32) -> GrayImage {
+    if amount <= 0.0 {
+        return gray;
+    }
+    let blurred = image::imageops::blur(&gray, 1.0);
+    let mut out = gray;
+    for (pixel, soft) in out.pixels_mut().zip(blurred.pixels()) {
+        let value = pixel.0[0] as f32;
+        let detail = value - soft.0[0] as f32;
+        pixel.0[0] = (value + amount * detail).round().clamp(0.0, 255.0) as u8;
+    }
+    out
+}
+
 pub fn write_trimg(path: &Path, trimg: &Trimg) -> io::Result<()> {
     let mut file = std::fs::File::create(path)?;
     let mut header = [0u8; 16];
     header[0..4].copy_from_slice(MAGIC);
     header[4] = VERSION;
-    header[5] = FORMAT_MONO1;
+    header[5] = match trimg.format {
+        OutputFormat::Mono1 => FORMAT_MONO1,
+        OutputFormat::Gray2 => FORMAT_GRAY2,
+    };
     header[6..8].copy_from_slice(&(trimg.width as u16).to_le_bytes());
     header[8..10].copy_from_slice(&(trimg.height as u16).to_le_bytes());
     file.write_all(&header)?;
@@ -182,18 +287,28 @@ pub fn write_trimg(path: &Path, trimg: &Trimg) -> io::Result<()> {
 }
 
 pub fn parse_trimg(data: &[u8]) -> Option<Trimg> {
-    if data.len() < 16 || &data[0..4] != MAGIC || data[4] != VERSION || data[5] != FORMAT_MONO1 {
+    if data.len() < 16 || &data[0..4] != MAGIC || data[4] != VERSION {
         return None;
     }
+    let format = match data[5] {
+        FORMAT_MONO1 => OutputFormat::Mono1,
+        FORMAT_GRAY2 => OutputFormat::Gray2,
+        _ => return None,
+    };
     let width = u16::from_le_bytes([data[6], data[7]]) as u32;
     let height = u16::from_le_bytes([data[8], data[9]]) as u32;
-    let expected = ((width as usize * height as usize) + 7) / 8;
+    let pixel_count = width as usize * height as usize;
+    let expected = match format {
+        OutputFormat::Mono1 => (pixel_count + 7) / 8,
+        OutputFormat::Gray2 => pixel_count.div_ceil(4),
+    };
     if data.len() != 16 + expected {
         return None;
     }
     Some(Trimg {
         width,
         height,
+        format,
         bits: data[16..].to_vec(),
     })
 }
@@ -223,6 +338,8 @@ impl WipeRect {
 }
 
 impl BarcodeOverlay {
+    /// Whether the output pixel is white, or `None` outside the overlay. Set
+    /// matrix bits are dark modules.
     fn sample(&self, x: u32, y: u32) -> Option<bool> {
         if x < self.x || y < self.y {
             return None;
@@ -234,44 +351,96 @@ impl BarcodeOverlay {
         }
         let mx = rx / self.scale_x;
         let my = if self.linear { 0 } else { ry / self.scale_y };
-        Some(self.matrix.get(mx, my))
+        Some(!self.matrix.get(mx, my))
     }
 }
 
-fn decode_and_render_overlays(
-    image: &DynamicImage,
-    gray: &GrayImage,
-    transform: &Transform,
+/// A barcode found by [`decode_barcodes`], with its bounding box in source
+/// image pixels.
+#[derive(Clone, Debug)]
+pub struct ScannedBarcode {
+    pub format: String,
+    pub text: String,
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+    /// Decode scales that agreed on this text.
+    pub votes: usize,
+}
+
+/// Runs the same detection the conversion uses for its overlays, without
+/// converting. Honours `barcode_effort`, the YOLO options and `debug`.
+pub fn decode_barcodes(
+    bytes: &[u8],
     options: &ConvertOptions,
-) -> (Vec<BarcodeOverlay>, Vec<WipeRect>) {
-    let mut yolo_detector = None;
-    if let Some(model_path) = &options.yolo_model {
-        match onnx_detector::OnnxDetector::load(
-            model_path,
-            640,
-            640,
-            options.yolo_num_classes,
-            options.yolo_confidence,
-            options.yolo_nms,
-        ) {
-            Ok(detector) => {
-                if options.debug {
-                    eprintln!(
-                        "[trusty-image] onnx model loaded: {} classes",
-                        options.yolo_num_classes
-                    );
-                }
-                yolo_detector = Some(detector);
+) -> Result<Vec<ScannedBarcode>, ConvertError> {
+    let image = decode_upright(bytes).map_err(|_| ConvertError::Decode)?;
+    let gray = image.to_luma8();
+    let yolo_detector = load_yolo_detector(options);
+    let detections = detect_barcodes(
+        &gray,
+        &image,
+        options.barcode_effort,
+        options.debug,
+        yolo_detector.as_ref(),
+    );
+    Ok(detections
+        .into_iter()
+        .map(|det| ScannedBarcode {
+            format: format!("{:?}", det.format),
+            text: det.text,
+            min_x: det.rect.min_x,
+            min_y: det.rect.min_y,
+            max_x: det.rect.max_x,
+            max_y: det.rect.max_y,
+            votes: det.votes,
+        })
+        .collect())
+}
+
+fn load_yolo_detector(options: &ConvertOptions) -> Option<onnx_detector::OnnxDetector> {
+    let model_path = options.yolo_model.as_ref()?;
+    match onnx_detector::OnnxDetector::load(
+        model_path,
+        640,
+        640,
+        options.yolo_num_classes,
+        options.yolo_confidence,
+        options.yolo_nms,
+    ) {
+        Ok(detector) => {
+            if options.debug {
+                eprintln!(
+                    "[trusty-image] onnx model loaded: {} classes",
+                    options.yolo_num_classes
+                );
             }
-            Err(err) => {
-                if options.debug {
-                    eprintln!("[trusty-image] onnx model load failed: {err:?}");
-                }
+            Some(detector)
+        }
+        Err(err) => {
+            if options.debug {
+                eprintln!("[trusty-image] onnx model load failed: {err:?}");
             }
+            None
         }
     }
+}
 
-    let detections = detect_barcodes(gray, image, options.debug, yolo_detector.as_ref());
+fn decode_and_render_overlays(
+    image: &DynamicImage,
+    gray: &GrayImage,
+    transform: &Transform,
+    options: &ConvertOptions,
+) -> (Vec<BarcodeOverlay>, Vec<WipeRect>) {
+    let yolo_detector = load_yolo_detector(options);
+    let detections = detect_barcodes(
+        gray,
+        image,
+        options.barcode_effort,
+        options.debug,
+        yolo_detector.as_ref(),
+    );
     if detections.is_empty() {
         if options.debug {
             eprintln!("[trusty-image] no barcodes detected");
@@ -288,8 +457,9 @@ fn decode_and_render_overlays(
                 det.text.clone()
             };
             eprintln!(
-                "[trusty-image] det[{i}] format={:?} text_len={} text=\"{}\" bbox=({:.1},{:.1})-({:.1},{:.1})",
+                "[trusty-image] det[{i}] format={:?} votes={} text_len={} text=\"{}\" bbox=({:.1},{:.1})-({:.1},{:.1})",
                 det.format,
+                det.votes,
                 det.text.len(),
                 text_preview,
                 det.rect.min_x,
@@ -349,7 +519,9 @@ fn decode_and_render_overlays(
         let scale_x = base_scale_x.min(max_scale_x).max(1);
 
         let (overlay_w, overlay_h, scale_y) = if is_linear {
-            let overlay_h = height.saturating_add(pad * 2).max(24).min(max_h).max(1);
+            // Bars fill the padded panel less a quiet margin above and below,
+            // and never grow past it.
+            let overlay_h = height.saturating_sub(pad * 2).max(24).min(height).max(1);
             (module_w.saturating_mul(scale_x), overlay_h, overlay_h)
         } else {
             let base_scale = (width / module_w).min(height / module_h).max(1);
@@ -370,11 +542,10 @@ fn decode_and_render_overlays(
             oy = transform.dst_h.saturating_sub(overlay_h);
         }
 
-        // If linear barcode, only allow horizontal growth, keep vertical within original box.
+        // If linear barcode, only allow horizontal growth; vertically the bars
+        // are centred in the (padded) white panel.
         if is_linear {
-            let overlay_h = height.saturating_add(pad * 2).max(24);
-            let scale_y = overlay_h;
-            oy = y + ((height.saturating_sub(overlay_h)) / 2);
+            oy = y + (height - overlay_h) / 2;
             let min_x = x.saturating_sub(pad);
             let max_x = (x + width + pad)
                 .saturating_sub(overlay_w)
@@ -384,11 +555,15 @@ fn decode_and_render_overlays(
             }
             if options.debug {
                 eprintln!(
-                    "[trusty-image] linear adjust: bbox_h={} overlay_h={} y={}..{} panel=({:.1},{:.1})-({:.1},{:.1})",
-                    height,
-                    overlay_h,
+                    "[trusty-image] linear adjust: overlay=({}, {}) {}x{} panel=({}, {}) {}x{} src_panel=({:.1},{:.1})-({:.1},{:.1})",
+                    ox,
                     oy,
-                    oy + overlay_h,
+                    overlay_w,
+                    overlay_h,
+                    x,
+                    y,
+                    width,
+                    height,
                     panel_rect.min_x,
                     panel_rect.min_y,
                     panel_rect.max_x,
@@ -475,15 +650,19 @@ fn bbox_from_points(points: &[Point]) -> Option<RectF> {
     })
 }
 
+#[derive(Clone)]
 struct Detection {
     format: BarcodeFormat,
     text: String,
     rect: RectF,
+    /// Number of scales that read this text in the same region.
+    votes: usize,
 }
 
 fn detect_barcodes(
     gray: &GrayImage,
     image: &DynamicImage,
+    effort: BarcodeEffort,
     debug: bool,
     yolo_detector: Option<&onnx_detector::OnnxDetector>,
 ) -> Vec<Detection> {
@@ -540,8 +719,14 @@ fn detect_barcodes(
         Vec::new()
     };
 
-    let scales = [1.0f32, 0.5, 0.25];
-    for &scale in &scales {
+    let scales: &[f32] = match effort {
+        BarcodeEffort::Fast => &[1.0],
+        BarcodeEffort::Thorough => &[1.0, 0.5, 0.25],
+    };
+    // Decode at every scale and let the scales vote, rather than trusting
+    // whichever read came first.
+    let mut candidates = Vec::new();
+    for (scale_index, &scale) in scales.iter().enumerate() {
         let scaled = if (scale - 1.0).abs() < f32::EPSILON {
             gray.clone()
         } else {
@@ -553,14 +738,18 @@ fn detect_barcodes(
         for invert in [false, true] {
             let detections = decode_with_hints(&scaled, scale, invert, &hints, (0, 0), debug);
             if !detections.is_empty() {
-                let mut detections = detections;
-                if !yolo_detections.is_empty() {
-                    refine_detections_with_yolo(&mut detections, &yolo_detections, debug);
-                }
-                return detections;
+                candidates.extend(detections.into_iter().map(|det| (scale_index, det)));
+                break;
             }
         }
     }
+    if !candidates.is_empty() {
+        let mut detections = vote_detections(candidates, debug);
+        if !yolo_detections.is_empty() {
+            refine_detections_with_yolo(&mut detections, &yolo_detections, debug);
+        }
+        return detections;
+    }
 
     if !yolo_detections.is_empty() {
         for det in &yolo_detections {
@@ -573,7 +762,7 @@ fn detect_barcodes(
                     crop_rect.3,
                 )
                 .to_image();
-                for &scale in &scales {
+                for &scale in scales {
                     let scaled = if (scale - 1.0).abs() < f32::EPSILON {
                         crop.clone()
                     } else {
@@ -612,6 +801,10 @@ fn detect_barcodes(
         }
     }
 
+    if effort == BarcodeEffort::Fast {
+        return Vec::new();
+    }
+
     // If full-frame decode fails, try cropping likely barcode bands.
     let bands = find_barcode_bands(gray, debug);
     for band in bands {
@@ -623,7 +816,7 @@ fn detect_barcodes(
             band.height,
         )
         .to_image();
-        for &scale in &scales {
+        for &scale in scales {
             let scaled = if (scale - 1.0).abs() < f32::EPSILON {
                 crop.clone()
             } else {
@@ -648,6 +841,71 @@ fn detect_barcodes(
     Vec::new()
 }
 
+/// Groups reads of the same region from different scales. Each group keeps
+/// the text read at the most scales, preferring the finest scale on a tie,
+/// and records that count in `votes`.
+fn vote_detections(candidates: Vec<(usize, Detection)>, debug: bool) -> Vec<Detection> {
+    let mut groups: Vec<Vec<(usize, Detection)>> = Vec::new();
+    for (scale_index, detection) in candidates {
+        let group = groups.iter_mut().find(|group| {
+            group
+                .iter()
+                .any(|(_, other)| same_region(other.rect, detection.rect))
+        });
+        match group {
+            Some(group) => group.push((scale_index, detection)),
+            None => groups.push(vec![(scale_index, detection)]),
+        }
+    }
+
+    let mut voted = Vec::with_capacity(groups.len());
+    for group in groups {
+        let votes_for = |detection: &Detection| {
+            let mut agreeing: Vec<usize> = group
+                .iter()
+                .filter(|(_, other)| other.format == detection.format && other.text == detection.text)
+                .map(|(scale_index, _)| *scale_index)
+                .collect();
+            agreeing.sort_unstable();
+            agreeing.dedup();
+            agreeing.len()
+        };
+        let Some((_, (_, best))) = group
+            .iter()
+            .enumerate()
+            .max_by_key(|(idx, (_, detection))| (votes_for(detection), std::cmp::Reverse(*idx)))
+        else {
+            continue;
+        };
+        let mut best = best.clone();
+        best.votes = votes_for(&best);
+        if debug && group.iter().any(|(_, other)| other.text != best.text) {
+            eprintln!(
+                "[trusty-image] conflicting reads in ({:.1},{:.1})-({:.1},{:.1}); kept \"{}\" with {} vote(s)",
+                best.rect.min_x,
+                best.rect.min_y,
+                best.rect.max_x,
+                best.rect.max_y,
+                best.text,
+                best.votes
+            );
+        }
+        voted.push(best);
+    }
+    voted
+}
+
+/// Whether two reads cover the same barcode: either box's centre lies in the
+/// other. Linear reads can be thin lines, so area overlap is unreliable.
+fn same_region(a: RectF, b: RectF) -> bool {
+    let contains_centre = |outer: RectF, inner: RectF| {
+        let cx = (inner.min_x + inner.max_x) / 2.0;
+        let cy = (inner.min_y + inner.max_y) / 2.0;
+        cx >= outer.min_x && cx <= outer.max_x && cy >= outer.min_y && cy <= outer.max_y
+    };
+    contains_centre(a, b) || contains_centre(b, a)
+}
+
 fn refine_detections_with_yolo(
     detections: &mut [Detection],
     yolo_detections: &[onnx_detector::OnnxDetection],
@@ -793,6 +1051,7 @@ fn decode_with_hints(
             format: *result.getBarcodeFormat(),
             text: result.getText().to_string(),
             rect,
+            votes: 1,
         });
     }
     detections
@@ -1099,6 +1358,27 @@ fn apply_dither(lum: u8, x: u32, y: u32, mode: DitherMode) -> bool {
     }
 }
 
+/// Maps luminance onto the four Gray2 levels, spreading the remainder
+/// between neighbouring levels with the same Bayer matrix when dithering.
+fn quantize_gray2(lum: u8, x: u32, y: u32, mode: DitherMode) -> u8 {
+    let scaled = lum as u16 * 3;
+    match mode {
+        DitherMode::None => ((scaled + 127) / 255) as u8,
+        DitherMode::Bayer => {
+            let bayer: [[u16; 4]; 4] = [
+                [0, 8, 2, 10],
+                [12, 4, 14, 6],
+                [3, 11, 1, 9],
+                [15, 7, 13, 5],
+            ];
+            let base = scaled / 255;
+            let remainder = scaled % 255;
+            let threshold = bayer[(y as usize) & 3][(x as usize) & 3] * 16 + 8;
+            (base + u16::from(remainder >= threshold)).min(3) as u8
+        }
+    }
+}
+
 fn otsu_threshold(img: &GrayImage) -> u8 {
     let mut hist = [0u32; 256];
     for pixel in img.pixels() {
@@ -1,11 +1,11 @@
 use std::env;
 use std::path::Path;
 
-use trusty_image::{ConvertOptions, DitherMode, FitMode, RegionMode};
+use trusty_image::{BarcodeEffort, ConvertOptions, DitherMode, FitMode, OutputFormat, RegionMode};
 
 fn usage() -> ! {
     eprintln!(
-        "Usage:\n  trusty-image convert <input> <output> [--size WxH] [--fit contain|cover|stretch|integer|width] [--dither bayer|none] [--region auto|none|crisp|barcode] [--yolo-model path] [--yolo-classes N] [--yolo-confidence F] [--yolo-nms F] [--invert] [--debug]\n\nDefaults: --size 480x800 --fit width --dither bayer --region auto"
+        "Usage:\n  trusty-image scan <input> [--barcode-effort fast|thorough] [--yolo-model path] [--yolo-classes N] [--yolo-confidence F] [--yolo-nms F] [--debug]\n  trusty-image convert <input> <output> [--size WxH] [--fit contain|cover|stretch|integer|width] [--dither bayer|none] [--output mono1|gray2] [--region auto|none|crisp|barcode] [--barcode-effort fast|thorough] [--yolo-model path] [--yolo-classes N] [--yolo-confidence F] [--yolo-nms F] [--gamma F] [--sharpen F] [--invert] [--debug]\n\nDefaults: --size 480x800 --fit width --dither bayer --output mono1 --region auto --barcode-effort thorough --gamma 1.0 --sharpen 0"
     );
     std::process::exit(2);
 }
@@ -20,16 +20,14 @@ fn parse_size(value: &str) -> Option<(u32, u32)> {
 fn main() {
     let mut args = env::args().skip(1);
     let cmd = args.next().unwrap_or_default();
-    if cmd != "convert" {
-        usage();
-    }
-
-    let input = args.next().unwrap_or_default();
-    let output = args.next().unwrap_or_default();
-    if input.is_empty() || output.is_empty() {
-        usage();
+    match cmd.as_str() {
+        "convert" => convert(args),
+        "scan" => scan(args),
+        _ => usage(),
     }
+}
 
+fn parse_options(mut args: impl Iterator<Item = String>) -> ConvertOptions {
     let mut options = ConvertOptions::default();
 
     while let Some(arg) = args.next() {
@@ -62,6 +60,14 @@ fn main() {
                     _ => usage(),
                 };
             }
+            "--output" => {
+                let value = args.next().unwrap_or_default();
+                options.output = match value.as_str() {
+                    "mono1" => OutputFormat::Mono1,
+                    "gray2" => OutputFormat::Gray2,
+                    _ => usage(),
+                };
+            }
             "--region" => {
                 let value = args.next().unwrap_or_default();
                 options.region_mode = match value.as_str() {
@@ -72,6 +78,14 @@ fn main() {
                     _ => usage(),
                 };
             }
+            "--barcode-effort" => {
+                let value = args.next().unwrap_or_default();
+                options.barcode_effort = match value.as_str() {
+                    "fast" => BarcodeEffort::Fast,
+                    "thorough" => BarcodeEffort::Thorough,
+                    _ => usage(),
+                };
+            }
             "--yolo-model" => {
                 let value = args.next().unwrap_or_default();
                 if value.is_empty() {
@@ -106,11 +120,82 @@ fn main() {
                     usage();
                 }
             }
+            "--gamma" => {
+                let value = args.next().unwrap_or_default();
+                let parsed = value.parse().ok().filter(|gamma: &f32| *gamma > 0.0);
+                if let Some(gamma) = parsed {
+                    options.gamma = gamma;
+                } else {
+                    usage();
+                }
+            }
+            "--sharpen" => {
+                let value = args.next().unwrap_or_default();
+                let parsed = value.parse().ok().filter(|amount: &f32| *amount >= 0.0);
+                if let Some(amount) = parsed {
+                    options.sharpen = amount;
+                } else {
+                    usage();
+                }
+            }
             "--invert" => options.invert = true,
             "--debug" => options.debug = true,
             _ => usage(),
         }
     }
+    options
+}
+
+/// Prints one tab-separated line per barcode (format, bbox, votes, text);
+/// exits with status 1 when nothing decodes.
+fn scan(mut args: impl Iterator<Item = String>) {
+    let input = args.next().unwrap_or_default();
+    if input.is_empty() {
+        usage();
+    }
+    let options = parse_options(args);
+
+    let data = match std::fs::read(Path::new(&input)) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("Failed to read input: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let barcodes = match trusty_image::decode_barcodes(&data, &options) {
+        Ok(barcodes) => barcodes,
+        Err(err) => {
+            eprintln!("Scan failed: {err:?}");
+            std::process::exit(1);
+        }
+    };
+
+    if barcodes.is_empty() {
+        eprintln!("No barcodes found");
+        std::process::exit(1);
+    }
+    for barcode in &barcodes {
+        println!(
+            "{}\t{:.0},{:.0},{:.0},{:.0}\t{}\t{}",
+            barcode.format,
+            barcode.min_x,
+            barcode.min_y,
+            barcode.max_x,
+            barcode.max_y,
+            barcode.votes,
+            barcode.text
+        );
+    }
+}
+
+fn convert(mut args: impl Iterator<Item = String>) {
+    let input = args.next().unwrap_or_default();
+    let output = args.next().unwrap_or_default();
+    if input.is_empty() || output.is_empty() {
+        usage();
+    }
+    let options = parse_options(args);
 
     let input_path = Path::new(&input);
     let output_path = Path::new(&output);
@@ -0,0 +1,91 @@
+use image::{DynamicImage, GrayImage, Luma};
+use rxing::common::HybridBinarizer;
+use rxing::multi::{GenericMultipleBarcodeReader, MultipleBarcodeReader};
+use rxing::{
+    BarcodeFormat, BinaryBitmap, DecodeHints, Luma8LuminanceSource, MultiFormatReader,
+    MultiFormatWriter, Writer,
+};
+use trusty_image::{
+    BarcodeEffort, ConvertOptions, DitherMode, FitMode, OutputFormat, RegionMode, Trimg,
+    convert_bytes, convert_image,
+};
+
+/// Plain threshold at the source size, so each output bit is one source pixel.
+fn one_to_one(width: u32, height: u32) -> ConvertOptions {
+    ConvertOptions {
+        width,
+        height,
+        fit: FitMode::Stretch,
+        dither: DitherMode::None,
+        region_mode: RegionMode::None,
+        ..ConvertOptions::default()
+    }
+}
+
+#[test]
+fn converts_a_bmp() {
+    // 8x8, 24-bit, stored bottom-up: the top-left 4x4 quadrant is black and
+    // the rest white.
+    let bytes = include_bytes!("fixtures/quadrant.bmp");
+    let trimg = convert_bytes(bytes, one_to_one(8, 8)).expect("BMP did not decode");
+    assert_eq!((trimg.width, trimg.height), (8, 8));
+    assert_eq!(trimg.format, OutputFormat::Mono1);
+    // Mono1 rows are one byte each here, with 1 = white.
+    assert_eq!(trimg.bits, [0x0F, 0x0F, 0x0F, 0x0F, 0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+/// Texts of the barcodes in a Mono1 image, read without trying inverted
+/// polarity, so only dark-on-light codes are found.
+fn read_barcodes(trimg: &Trimg) -> Vec<String> {
+    let luma = (0..trimg.width * trimg.height)
+        .map(|idx| {
+            let idx = idx as usize;
+            if trimg.bits[idx / 8] & (1 << (7 - idx % 8)) != 0 {
+                255
+            } else {
+                0
+            }
+        })
+        .collect();
+    let source = Luma8LuminanceSource::new(luma, trimg.width, trimg.height);
+    let mut bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+    let mut reader = GenericMultipleBarcodeReader::new(MultiFormatReader::default());
+    reader
+        .decode_multiple_with_hints(&mut bitmap, &DecodeHints::default())
+        .map(|results| {
+            results
+                .iter()
+                .map(|result| result.getText().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn invert_keeps_barcode_overlays_dark_on_light() {
+    let matrix = MultiFormatWriter::default()
+        .encode("TRUSTY", &BarcodeFormat::QR_CODE, 0, 0)
+        .unwrap();
+    let module = 4;
+    let mut page = GrayImage::from_pixel(240, 240, Luma([255]));
+    for y in 0..matrix.height() * module {
+        for x in 0..matrix.width() * module {
+            if matrix.get(x / module, y / module) {
+                page.put_pixel(40 + x, 40 + y, Luma([0]));
+            }
+        }
+    }
+    let options = ConvertOptions {
+        region_mode: RegionMode::Barcode,
+        barcode_effort: BarcodeEffort::Fast,
+        invert: true,
+        ..one_to_one(240, 240)
+    };
+    let trimg = convert_image(&DynamicImage::ImageLuma8(page), options);
+
+    // The white page around the code is inverted to black...
+    assert_eq!(trimg.bits[0], 0x00);
+    assert_eq!(trimg.bits[trimg.bits.len() - 1], 0x00);
+    // ...but the re-rendered code still reads as a normal dark-on-light QR.
+    assert_eq!(read_barcodes(&trimg), ["TRUSTY"]);
+}
@@ -0,0 +1,93 @@
+use core::cell::RefCell;
+use core::fmt;
+
+use esp_hal::rtc_cntl::Rtc;
+use esp_hal::rtc_cntl::sleep::WakeSource;
+use trusty_core::clock::{CLOCK_UNSET_DEFAULT_SECS, SECS_PER_DAY, TimeSource, civil_date};
+
+/// Wall clock kept by the RTC timer, which keeps counting through deep
+/// sleep. A cold boot starts it near zero, which reads as unset.
+///
+/// It is shared between the application and the FAT filesystem (which stamps
+/// written files through [`fatfs::TimeProvider`]), so the RTC sits in a
+/// `RefCell` and the clock is used through `&RtcClock`.
+pub struct RtcClock<'d> {
+    rtc: RefCell<Rtc<'d>>,
+}
+
+impl<'d> RtcClock<'d> {
+    pub fn new(rtc: Rtc<'d>) -> Self {
+        Self {
+            rtc: RefCell::new(rtc),
+        }
+    }
+
+    /// Enters deep sleep; the RTC timer keeps counting until wake-up.
+    pub fn sleep_deep(&self, wake_sources: &[&dyn WakeSource]) -> ! {
+        self.rtc.borrow_mut().sleep_deep(wake_sources)
+    }
+
+    fn current_secs(&self) -> Option<u64> {
+        let secs = self.rtc.borrow().current_time_us() / 1_000_000;
+        (secs >= CLOCK_UNSET_DEFAULT_SECS).then_some(secs)
+    }
+}
+
+impl TimeSource for &RtcClock<'_> {
+    fn now_secs(&mut self) -> Option<u64> {
+        self.current_secs()
+    }
+
+    fn set_now_secs(&mut self, secs: u64) {
+        self.rtc
+            .borrow_mut()
+            .set_current_time_us(secs.saturating_mul(1_000_000));
+    }
+}
+
+impl fmt::Debug for RtcClock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RtcClock").finish_non_exhaustive()
+    }
+}
+
+/// Until the clock is set, files get the FAT epoch (1980-01-01) like the
+/// default provider gives them.
+impl fatfs::TimeProvider for RtcClock<'_> {
+    fn get_current_date(&self) -> fatfs::Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> fatfs::DateTime {
+        let Some(secs) = self.current_secs() else {
+            return fatfs::DateTime {
+                date: fatfs::Date {
+                    year: 1980,
+                    month: 1,
+                    day: 1,
+                },
+                time: fatfs::Time {
+                    hour: 0,
+                    min: 0,
+                    sec: 0,
+                    millis: 0,
+                },
+            };
+        };
+        let (year, month, day) = civil_date(secs);
+        let time_of_day = secs % SECS_PER_DAY;
+        fatfs::DateTime {
+            date: fatfs::Date {
+                year,
+                month: month as u16,
+                day: day as u16,
+            },
+            time: fatfs::Time {
+                hour: (time_of_day / 3600) as u16,
+                min: (time_of_day / 60 % 60) as u16,
+                sec: (time_of_day % 60) as u16,
+                millis: 0,
+            },
+        }
+    }
+}
@@ -4,6 +4,7 @@
 //! optimized for the GDEQ0426T82 4.26" 800x480 e-paper display.
 //! https://github.com/CidVonHighwind/microreader/
 
+use alloc::vec::Vec;
 use embedded_hal::spi::SpiDevice;
 use esp_hal::{
     delay::Delay,
@@ -12,7 +13,8 @@ use esp_hal::{
 use log::{error, info, warn};
 use trusty_core::{
     display::{Display, RefreshMode},
-    framebuffer::{BUFFER_SIZE, DisplayBuffers},
+    framebuffer::{BUFFER_SIZE, DisplayBuffers, region_rows},
+    ui::Rect,
 };
 
 // SSD1677 Command Definitions
@@ -47,6 +49,7 @@ mod commands {
     pub const SOURCE_VOLTAGE: u8 = 0x04;
     pub const WRITE_VCOM: u8 = 0x2C;
     pub const WRITE_TEMP: u8 = 0x1A;
+    pub const READ_TEMP: u8 = 0x1B;
 
     // Power management
     pub const DEEP_SLEEP: u8 = 0x10;
@@ -58,10 +61,24 @@ const CTRL1_BYPASS_RED: u8 = 0x40;
 
 // Data entry mode
 const DATA_ENTRY_X_INC_Y_DEC: u8 = 0x01;
+/// Both counters reversed: the panel shows the RAM turned 180°.
+const DATA_ENTRY_X_DEC_Y_INC: u8 = 0x02;
 
 // Temperature sensor control
 const TEMP_SENSOR_INTERNAL: u8 = 0x80;
 
+// Display update sequence that only latches a fresh sensor reading:
+// clock on, load temperature, clock off.
+const CTRL2_LOAD_TEMPERATURE: u8 = 0xB1;
+
+/// Below this panel temperature (°C) half refreshes leave ghosting, so the
+/// full-length waveform is used instead.
+const COLD_PANEL_CELSIUS: i16 = 10;
+
+/// Operating range of the panel (°C). Readings outside it come from a bad
+/// SPI read rather than the weather, so they are dropped.
+const PANEL_RATED_CELSIUS: core::ops::RangeInclusive<i16> = -20..=60;
+
 /// Custom LUT for grayscale fast refresh
 const LUT_GRAYSCALE: &[u8] = &[
     // 00 black/white
@@ -111,6 +128,21 @@ const LUT_GRAYSCALE_REVERT: &[u8] = &[
     0x00, 0x00,
 ];
 
+/// Errors raised by the SSD1677 driver
+#[derive(Debug)]
+pub enum DisplayError<E> {
+    /// SPI transfer failed
+    Spi(E),
+    /// BUSY stayed high past the timeout; the label names the operation
+    BusyTimeout(&'static str),
+}
+
+/// Longest time to wait for BUSY before giving up
+const BUSY_TIMEOUT_MS: u32 = 10_000;
+
+/// Default largest single SPI write for frame data
+pub const DEFAULT_SPI_CHUNK_SIZE: usize = 4096;
+
 /// E-Ink Display driver for SSD1677
 pub struct EInkDisplay<'gpio, SPI> {
     spi: SPI,
@@ -121,6 +153,15 @@ pub struct EInkDisplay<'gpio, SPI> {
     is_screen_on: bool,
     custom_lut_active: bool,
     in_grayscale_mode: bool,
+    failed: bool,
+    temperature: Option<i16>,
+    waveform_override: Option<RefreshMode>,
+    /// RAM is written with both counters reversed, turning the image 180°.
+    flipped: bool,
+    spi_chunk_size: usize,
+    /// Bit-reversed copy of the chunk being sent while flipped, kept between
+    /// writes so each chunk doesn't allocate.
+    flip_buffer: Vec<u8>,
 }
 
 impl<'gpio, SPI> EInkDisplay<'gpio, SPI>
@@ -150,11 +191,24 @@ where
             is_screen_on: false,
             custom_lut_active: false,
             in_grayscale_mode: false,
+            failed: false,
+            temperature: None,
+            waveform_override: None,
+            flipped: false,
+            spi_chunk_size: DEFAULT_SPI_CHUNK_SIZE,
+            flip_buffer: Vec::new(),
         }
     }
 
+    /// Largest single SPI write used for full-frame RAM uploads. Boards whose
+    /// DMA setup takes bigger transfers can raise it to send fewer of them.
+    pub fn with_spi_chunk_size(mut self, bytes: usize) -> Self {
+        self.spi_chunk_size = bytes.max(1);
+        self
+    }
+
     /// Initialize the display
-    pub fn begin(&mut self) -> Result<(), SPI::Error> {
+    pub fn begin(&mut self) -> Result<(), DisplayError<SPI::Error>> {
         info!("Initializing E-Ink Display");
 
         // Reset display
@@ -167,7 +221,10 @@ where
         Ok(())
     }
 
-    pub fn display_gray_buffer(&mut self, turn_off_screen: bool) -> Result<(), SPI::Error> {
+    pub fn display_gray_buffer(
+        &mut self,
+        turn_off_screen: bool,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         warn!("Displaying grayscale buffer");
         self.in_grayscale_mode = true;
         self.set_custom_lut(LUT_GRAYSCALE)?;
@@ -176,7 +233,7 @@ where
         Ok(())
     }
 
-    fn grayscale_revert_internal(&mut self) -> Result<(), SPI::Error> {
+    fn grayscale_revert_internal(&mut self) -> Result<(), DisplayError<SPI::Error>> {
         warn!("Reverting grayscale buffer");
         self.in_grayscale_mode = false;
         self.set_custom_lut(LUT_GRAYSCALE_REVERT)?;
@@ -185,7 +242,7 @@ where
         Ok(())
     }
 
-    fn set_custom_lut(&mut self, lut: &[u8]) -> Result<(), SPI::Error> {
+    fn set_custom_lut(&mut self, lut: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
         info!("Setting custom LUT");
 
         self.send_command(commands::WRITE_LUT)?;
@@ -204,11 +261,80 @@ where
         Ok(())
     }
 
+    /// Last panel temperature in °C, refreshed after each full/half update.
+    pub fn temperature(&self) -> Option<i16> {
+        self.temperature
+    }
+
+    /// Forces the waveform used for full/half refreshes (`None` restores
+    /// temperature-based selection). Fast refreshes are never changed.
+    pub fn set_waveform_override(&mut self, mode: Option<RefreshMode>) {
+        self.waveform_override = mode;
+    }
+
+    /// Picks the waveform for a full/half request from the override or the
+    /// last temperature reading: cold panels get the longer full waveform.
+    fn select_waveform(&self, mode: RefreshMode) -> RefreshMode {
+        if mode == RefreshMode::Fast {
+            return mode;
+        }
+        if let Some(forced) = self.waveform_override {
+            return forced;
+        }
+        match self.temperature {
+            Some(celsius) if mode == RefreshMode::Half && celsius < COLD_PANEL_CELSIUS => {
+                info!("Panel at {} C, using full waveform", celsius);
+                RefreshMode::Full
+            }
+            _ => mode,
+        }
+    }
+
+    /// Reads the built-in temperature sensor.
+    ///
+    /// SSD1677 sequence:
+    /// 1. `0x18` (temperature sensor control) = `0x80` selects the internal
+    ///    sensor; done once in `init_display_controller`.
+    /// 2. `0x22` (display update control 2) = `0xB1`, then `0x20` (master
+    ///    activation) latches a new reading; wait for BUSY to drop.
+    /// 3. `0x1B` (read temperature), then clock two data bytes back with DC
+    ///    high. The value is a 12-bit two's complement number in the top bits
+    ///    (`[A11..A4][A3..A0 0000]`) in units of 1/16 °C.
+    ///
+    /// Returns `None` for a reading outside [`PANEL_RATED_CELSIUS`], so the
+    /// waveform falls back to the default choice.
+    fn read_temperature(&mut self) -> Result<Option<i16>, DisplayError<SPI::Error>> {
+        self.send_command(commands::DISPLAY_UPDATE_CTRL2)?;
+        self.send_data(&[CTRL2_LOAD_TEMPERATURE])?;
+        self.send_command(commands::MASTER_ACTIVATION)?;
+        self.wait_while_busy("LOAD_TEMPERATURE")?;
+
+        self.send_command(commands::READ_TEMP)?;
+        let _ = self.dc.set_high(); // Data mode
+        let mut raw = [0u8; 2];
+        self.spi.read(&mut raw).map_err(DisplayError::Spi)?;
+        let celsius = (i16::from_be_bytes(raw) >> 4) / 16;
+        if !PANEL_RATED_CELSIUS.contains(&celsius) {
+            warn!(
+                "Ignoring panel temperature {} C outside the rated range",
+                celsius
+            );
+            return Ok(None);
+        }
+        Ok(Some(celsius))
+    }
+
     /// Enter deep sleep mode
-    pub fn deep_sleep(&mut self) -> Result<(), SPI::Error> {
+    ///
+    /// The controller forgets its RAM window, LUT and power state; `begin`
+    /// must run again before the next refresh.
+    pub fn deep_sleep(&mut self) -> Result<(), DisplayError<SPI::Error>> {
         info!("Entering deep sleep mode");
         self.send_command(commands::DEEP_SLEEP)?;
         self.send_data(&[0x01])?;
+        self.is_screen_on = false;
+        self.custom_lut_active = false;
+        self.in_grayscale_mode = false;
         Ok(())
     }
 
@@ -227,37 +353,50 @@ where
         info!("Display reset complete");
     }
 
-    fn send_command(&mut self, command: u8) -> Result<(), SPI::Error> {
+    fn send_command(&mut self, command: u8) -> Result<(), DisplayError<SPI::Error>> {
         let _ = self.dc.set_low(); // Command mode
-        self.spi.write(&[command])?;
+        self.spi.write(&[command]).map_err(DisplayError::Spi)?;
         Ok(())
     }
 
-    fn send_data(&mut self, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
         let _ = self.dc.set_high(); // Data mode
-        self.spi.write(data)?;
+        self.spi.write(data).map_err(DisplayError::Spi)?;
         Ok(())
     }
 
-    fn wait_while_busy(&mut self, comment: &str) {
+    fn wait_while_busy(
+        &mut self,
+        comment: &'static str,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         let mut iterations = 0u32;
         while self.busy.is_high() {
             self.delay.delay_millis(1);
             iterations += 1;
-            if iterations > 10000 {
+            if iterations > BUSY_TIMEOUT_MS {
                 error!("Timeout waiting for busy: {}", comment);
-                break;
+                return Err(DisplayError::BusyTimeout(comment));
             }
         }
         info!("Wait complete: {} ({} ms)", comment, iterations);
+        Ok(())
     }
 
-    fn init_display_controller(&mut self) -> Result<(), SPI::Error> {
+    /// Records a failed operation so the app can surface it instead of
+    /// drawing onto a panel that is no longer responding.
+    fn check(&mut self, result: Result<(), DisplayError<SPI::Error>>) {
+        if let Err(err) = result {
+            error!("Display operation failed: {:?}", err);
+            self.failed = true;
+        }
+    }
+
+    fn init_display_controller(&mut self) -> Result<(), DisplayError<SPI::Error>> {
         info!("Initializing SSD1677 controller");
 
         // Soft reset
         self.send_command(commands::SOFT_RESET)?;
-        self.wait_while_busy("SOFT_RESET");
+        self.wait_while_busy("SOFT_RESET")?;
 
         // Temperature sensor control (internal)
         self.send_command(commands::TEMP_SENSOR_CONTROL)?;
@@ -287,17 +426,31 @@ where
         info!("Clearing RAM buffers");
         self.send_command(commands::AUTO_WRITE_BW_RAM)?;
         self.send_data(&[0xF7])?;
-        self.wait_while_busy("AUTO_WRITE_BW_RAM");
+        self.wait_while_busy("AUTO_WRITE_BW_RAM")?;
 
         self.send_command(commands::AUTO_WRITE_RED_RAM)?;
         self.send_data(&[0xF7])?;
-        self.wait_while_busy("AUTO_WRITE_RED_RAM");
+        self.wait_while_busy("AUTO_WRITE_RED_RAM")?;
 
         info!("SSD1677 controller initialized");
         Ok(())
     }
 
-    fn set_ram_area(&mut self, x: u16, y: u16, w: u16, h: u16) -> Result<(), SPI::Error> {
+    /// Sets the RAM window for the buffer area `x, y, w, h` and points the
+    /// counters at its first byte. When flipped, the window is mirrored on
+    /// both axes and the counters run backwards, so the buffer can still be
+    /// sent top to bottom.
+    fn set_ram_area(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        if self.flipped {
+            return self.set_ram_area_flipped(x, y, w, h);
+        }
+
         // Reverse Y coordinate (gates are reversed on this display)
         let y = Self::HEIGHT as u16 - y - h;
 
@@ -340,35 +493,108 @@ where
         Ok(())
     }
 
-    fn write_ram_buffer(&mut self, ram_buffer: u8, data: &[u8]) -> Result<(), SPI::Error> {
+    fn set_ram_area_flipped(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        // Mirror X; the gate reversal already cancels the Y mirror.
+        let x = Self::WIDTH as u16 - x - w;
+
+        self.send_command(commands::DATA_ENTRY_MODE)?;
+        self.send_data(&[DATA_ENTRY_X_DEC_Y_INC])?;
+
+        // X runs from the right edge of the window towards the left
+        self.send_command(commands::SET_RAM_X_RANGE)?;
+        self.send_data(&[
+            ((x + w - 1) % 256) as u8,
+            ((x + w - 1) / 256) as u8,
+            (x % 256) as u8,
+            (x / 256) as u8,
+        ])?;
+
+        // Y runs from the top of the window down
+        self.send_command(commands::SET_RAM_Y_RANGE)?;
+        self.send_data(&[
+            (y % 256) as u8,
+            (y / 256) as u8,
+            ((y + h - 1) % 256) as u8,
+            ((y + h - 1) / 256) as u8,
+        ])?;
+
+        self.send_command(commands::SET_RAM_X_COUNTER)?;
+        self.send_data(&[((x + w - 1) % 256) as u8, ((x + w - 1) / 256) as u8])?;
+
+        self.send_command(commands::SET_RAM_Y_COUNTER)?;
+        self.send_data(&[(y % 256) as u8, (y / 256) as u8])?;
+
+        Ok(())
+    }
+
+    /// Sends frame data. Pixels within a byte keep their MSB-first order
+    /// whichever way the X counter runs, so a flipped panel also needs each
+    /// byte mirrored. Either way `data` goes out as one write, so callers'
+    /// chunking (the configured SPI chunk size) is kept.
+    fn send_pixels(&mut self, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
+        if !self.flipped {
+            return self.send_data(data);
+        }
+        let mut reversed = core::mem::take(&mut self.flip_buffer);
+        reversed.clear();
+        reversed.extend(data.iter().map(|byte| byte.reverse_bits()));
+        let result = self.send_data(&reversed);
+        self.flip_buffer = reversed;
+        result
+    }
+
+    fn write_ram_buffer(
+        &mut self,
+        ram_buffer: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
         let buffer_name = if ram_buffer == commands::WRITE_RAM_BW {
             "BW"
         } else {
             "RED"
         };
         info!(
-            "Writing frame buffer to {} RAM ({} bytes)",
+            "Writing frame buffer to {} RAM ({} bytes, {} byte chunks)",
             buffer_name,
-            data.len()
+            data.len(),
+            self.spi_chunk_size
         );
 
         self.send_command(ram_buffer)?;
 
         // Write data in chunks to avoid issues with large transfers
-        const CHUNK_SIZE: usize = 4096;
-        for chunk in data.chunks(CHUNK_SIZE) {
-            self.send_data(chunk)?;
+        for chunk in data.chunks(self.spi_chunk_size) {
+            self.send_pixels(chunk)?;
         }
 
         info!("{} RAM write complete", buffer_name);
         Ok(())
     }
 
+    /// Writes a RAM window row by row; the caller has already set the area.
+    fn write_ram_rows<'b>(
+        &mut self,
+        ram_buffer: u8,
+        rows: impl Iterator<Item = &'b [u8]>,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.send_command(ram_buffer)?;
+        for row in rows {
+            self.send_pixels(row)?;
+        }
+        Ok(())
+    }
+
     fn refresh_display(
         &mut self,
         mode: RefreshMode,
         turn_off_screen: bool,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         // Configure Display Update Control 1
         self.send_command(commands::DISPLAY_UPDATE_CTRL1)?;
         let ctrl1 = match mode {
@@ -425,49 +651,48 @@ where
 
         // Wait for display to finish updating
         info!("Waiting for display refresh");
-        self.wait_while_busy(refresh_type);
+        self.wait_while_busy(refresh_type)?;
 
         Ok(())
     }
 }
 
-impl<SPI> Display for EInkDisplay<'_, SPI>
+impl<SPI> EInkDisplay<'_, SPI>
 where
     SPI: SpiDevice,
 {
-    fn display(&mut self, buffers: &mut DisplayBuffers, mut mode: RefreshMode) {
+    fn display_frame(
+        &mut self,
+        buffers: &mut DisplayBuffers,
+        mut mode: RefreshMode,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         if !self.is_screen_on {
             // Force half refresh if screen is off
             mode = RefreshMode::Half;
         }
+        mode = self.select_waveform(mode);
 
         // If currently in grayscale mode, revert first to black/white
         if self.in_grayscale_mode {
-            self.grayscale_revert_internal().unwrap();
+            self.grayscale_revert_internal()?;
         }
 
         // Set up full screen RAM area
-        self.set_ram_area(0, 0, Self::WIDTH as u16, Self::HEIGHT as u16)
-            .unwrap();
+        self.set_ram_area(0, 0, Self::WIDTH as u16, Self::HEIGHT as u16)?;
 
-        // Get raw pointers to avoid borrow checker issues
         let current = buffers.get_active_buffer();
         let previous = buffers.get_inactive_buffer();
 
         match mode {
             RefreshMode::Full | RefreshMode::Half => {
                 // For full refresh, write current buffer to both RAM buffers
-                self.write_ram_buffer(commands::WRITE_RAM_BW, current)
-                    .unwrap();
-                self.write_ram_buffer(commands::WRITE_RAM_RED, current)
-                    .unwrap();
+                self.write_ram_buffer(commands::WRITE_RAM_BW, current)?;
+                self.write_ram_buffer(commands::WRITE_RAM_RED, current)?;
             }
             RefreshMode::Fast => {
                 // For fast refresh, write current to BW and previous to RED
-                self.write_ram_buffer(commands::WRITE_RAM_BW, current)
-                    .unwrap();
-                self.write_ram_buffer(commands::WRITE_RAM_RED, previous)
-                    .unwrap();
+                self.write_ram_buffer(commands::WRITE_RAM_BW, current)?;
+                self.write_ram_buffer(commands::WRITE_RAM_RED, previous)?;
             }
         }
 
@@ -475,31 +700,132 @@ where
         buffers.swap_buffers();
 
         // Refresh the display
-        self.refresh_display(mode, false).unwrap();
+        self.refresh_display(mode, false)?;
+
+        // Sample the panel temperature while it is powered so the next
+        // full/half refresh can pick a waveform for it.
+        if mode != RefreshMode::Fast {
+            match self.read_temperature() {
+                Ok(celsius) => {
+                    if let Some(celsius) = celsius {
+                        info!("Panel temperature: {} C", celsius);
+                    }
+                    self.temperature = celsius;
+                }
+                Err(err) => warn!("Temperature read failed: {:?}", err),
+            }
+        }
+        Ok(())
+    }
+
+    fn display_window(
+        &mut self,
+        buffers: &mut DisplayBuffers,
+        region: Rect,
+        mode: RefreshMode,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        info!(
+            "Partial window {}x{} at ({}, {})",
+            region.w, region.h, region.x, region.y
+        );
+        let (x, y, w, h) = (
+            region.x as u16,
+            region.y as u16,
+            region.w as u16,
+            region.h as u16,
+        );
+        self.set_ram_area(x, y, w, h)?;
+        self.write_ram_rows(
+            commands::WRITE_RAM_BW,
+            region_rows(buffers.get_active_buffer(), region),
+        )?;
+        self.set_ram_area(x, y, w, h)?;
+        self.write_ram_rows(
+            commands::WRITE_RAM_RED,
+            region_rows(buffers.get_inactive_buffer(), region),
+        )?;
+
+        buffers.swap_buffers();
+        self.refresh_display(mode, false)
+    }
+
+    fn write_full_ram(
+        &mut self,
+        ram_buffer: u8,
+        data: &[u8; BUFFER_SIZE],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.set_ram_area(0, 0, Self::WIDTH as u16, Self::HEIGHT as u16)?;
+        self.write_ram_buffer(ram_buffer, data)
+    }
+}
+
+impl<SPI> Display for EInkDisplay<'_, SPI>
+where
+    SPI: SpiDevice,
+{
+    fn display(&mut self, buffers: &mut DisplayBuffers, mode: RefreshMode) {
+        self.flipped = buffers.panel_flipped();
+        let result = self.display_frame(buffers, mode);
+        self.check(result);
+    }
+
+    fn display_region(&mut self, buffers: &mut DisplayBuffers, rect: Rect, mode: RefreshMode) {
+        // Windowed updates only make sense for fast refreshes of a live,
+        // black/white screen; everything else goes through the full path.
+        if !self.is_screen_on || self.in_grayscale_mode || mode != RefreshMode::Fast {
+            self.display(buffers, mode);
+            return;
+        }
+        self.flipped = buffers.panel_flipped();
+        let Some(region) = buffers.physical_region(rect) else {
+            return;
+        };
+        let result = self.display_window(buffers, region, mode);
+        self.check(result);
     }
 
     fn copy_to_lsb(&mut self, buffers: &[u8; BUFFER_SIZE]) {
-        self.set_ram_area(0, 0, Self::WIDTH as u16, Self::HEIGHT as u16)
-            .unwrap();
-        self.write_ram_buffer(commands::WRITE_RAM_BW, buffers)
-            .unwrap();
+        let result = self.write_full_ram(commands::WRITE_RAM_BW, buffers);
+        self.check(result);
     }
 
     fn copy_to_msb(&mut self, buffers: &[u8; BUFFER_SIZE]) {
-        self.set_ram_area(0, 0, Self::WIDTH as u16, Self::HEIGHT as u16)
-            .unwrap();
-        self.write_ram_buffer(commands::WRITE_RAM_RED, buffers)
-            .unwrap();
+        let result = self.write_full_ram(commands::WRITE_RAM_RED, buffers);
+        self.check(result);
     }
 
     fn copy_grayscale_buffers(&mut self, lsb: &[u8; BUFFER_SIZE], msb: &[u8; BUFFER_SIZE]) {
-        self.set_ram_area(0, 0, Self::WIDTH as u16, Self::HEIGHT as u16)
-            .unwrap();
-        self.write_ram_buffer(commands::WRITE_RAM_BW, lsb).unwrap();
-        self.write_ram_buffer(commands::WRITE_RAM_RED, msb).unwrap();
+        let result = self
+            .write_full_ram(commands::WRITE_RAM_BW, lsb)
+            .and_then(|()| self.write_ram_buffer(commands::WRITE_RAM_RED, msb));
+        self.check(result);
     }
 
     fn display_grayscale(&mut self) {
-        self.display_gray_buffer(false).unwrap();
+        let result = self.display_gray_buffer(false);
+        self.check(result);
+    }
+
+    /// The reversed-counter path (`DATA_ENTRY_X_DEC_Y_INC` plus bit-reversed
+    /// bytes) has not been checked on a panel yet, so rotations stay in
+    /// software until it has.
+    fn supports_hardware_flip(&self) -> bool {
+        false
+    }
+
+    fn take_error(&mut self) -> bool {
+        core::mem::take(&mut self.failed)
+    }
+
+    fn power_down(&mut self) {
+        let result = self.deep_sleep();
+        self.check(result);
+    }
+
+    fn power_up(&mut self) {
+        // Reset + init restores the full-screen RAM window and default
+        // waveform; `is_screen_on` is false so the next refresh is a half one.
+        let result = self.begin();
+        self.check(result);
     }
 }
@@ -3,12 +3,17 @@ extern crate alloc;
 use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
+use core::cell::Cell;
 
 use core_io::{Read, Seek, SeekFrom, Write};
 use fatfs::{FileSystem, FsOptions};
-use trusty_core::image_viewer::{EntryKind, ImageData, ImageEntry, ImageError, ImageSource};
+use trusty_core::image_viewer::{
+    EntryKind, EpubInfo, ImageData, ImageEntry, ImageError, ImageSource, ResumeState, Settings,
+};
 
-use crate::sd_io::{detect_fat_partition, SdCardIo};
+use crate::sd_io::{
+    detect_fat_partition, fat_cluster_size, map_file_extents, ExtentReader, FileExtent, SdCardIo,
+};
 
 pub struct SdImageSource<D>
 where
@@ -17,15 +22,122 @@ where
 {
     sdcard: D,
     trbk: Option<TrbkStream>,
+    /// Stamps files written to the card; fatfs uses 1980-01-01 without one.
+    time_provider: Option<&'static dyn fatfs::TimeProvider>,
 }
 
+/// An open book. Page and image reads go straight to the file's extents on
+/// the card instead of remounting the filesystem on every page turn.
 struct TrbkStream {
-    path: Vec<String>,
-    name: String,
+    base_lba: u32,
+    total_blocks: u32,
+    extents: Vec<FileExtent>,
     page_offsets: Vec<u32>,
     page_data_offset: u32,
     glyph_table_offset: u32,
     info: trusty_core::trbk::TrbkBookInfo,
+    /// Dropped with the stream, so closing or switching books clears it.
+    page_cache: PageCache,
+}
+
+/// Decoded pages kept per open book: the current page and two either side.
+const PAGE_CACHE_MAX_PAGES: usize = 5;
+/// Rough heap budget for cached pages; a dense text page is a few KB.
+const PAGE_CACHE_MAX_BYTES: usize = 24 * 1024;
+
+struct CachedPage {
+    index: usize,
+    page: trusty_core::trbk::TrbkPage,
+    bytes: usize,
+}
+
+/// Recently decoded pages, least recently used first.
+#[derive(Default)]
+struct PageCache {
+    pages: Vec<CachedPage>,
+    bytes: usize,
+}
+
+impl PageCache {
+    fn get(&mut self, index: usize) -> Option<trusty_core::trbk::TrbkPage> {
+        let pos = self.pages.iter().position(|cached| cached.index == index)?;
+        let cached = self.pages.remove(pos);
+        let page = cached.page.clone();
+        self.pages.push(cached);
+        Some(page)
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.pages.iter().any(|cached| cached.index == index)
+    }
+
+    fn insert(&mut self, index: usize, page: trusty_core::trbk::TrbkPage) {
+        let bytes = page_heap_bytes(&page);
+        if bytes > PAGE_CACHE_MAX_BYTES {
+            return;
+        }
+        if let Some(pos) = self.pages.iter().position(|cached| cached.index == index) {
+            self.bytes -= self.pages.remove(pos).bytes;
+        }
+        self.pages.push(CachedPage { index, page, bytes });
+        self.bytes += bytes;
+        while self.pages.len() > PAGE_CACHE_MAX_PAGES || self.bytes > PAGE_CACHE_MAX_BYTES {
+            self.bytes -= self.pages.remove(0).bytes;
+        }
+    }
+}
+
+/// Approximate heap held by a decoded page.
+fn page_heap_bytes(page: &trusty_core::trbk::TrbkPage) -> usize {
+    page.ops
+        .iter()
+        .map(|op| {
+            let text = match op {
+                trusty_core::trbk::TrbkOp::TextRun { text, .. } => text.capacity(),
+                _ => 0,
+            };
+            core::mem::size_of::<trusty_core::trbk::TrbkOp>() + text
+        })
+        .sum()
+}
+
+impl TrbkStream {
+    fn reader<'a, D>(&'a self, sdcard: &'a D) -> ExtentReader<'a, D>
+    where
+        D: embedded_sdmmc::BlockDevice,
+        D::Error: core::fmt::Debug,
+    {
+        let io = SdCardIo::with_geometry(sdcard, self.base_lba, self.total_blocks);
+        ExtentReader::new(io, &self.extents)
+    }
+
+    fn read_page<D>(
+        &self,
+        sdcard: &D,
+        page_index: usize,
+    ) -> Result<trusty_core::trbk::TrbkPage, ImageError>
+    where
+        D: embedded_sdmmc::BlockDevice,
+        D::Error: core::fmt::Debug,
+    {
+        let mut file = self.reader(sdcard);
+        let start = self.page_data_offset + self.page_offsets[page_index];
+        let end = if page_index + 1 < self.page_offsets.len() {
+            self.page_data_offset + self.page_offsets[page_index + 1]
+        } else {
+            self.glyph_table_offset
+        };
+        if end < start {
+            return Err(ImageError::Decode);
+        }
+        let len = (end - start) as usize;
+        let mut buf = vec![0u8; len];
+        file.seek(SeekFrom::Start(start as u64))
+            .map_err(|_| ImageError::Io)?;
+        read_exact(&mut file, &mut buf)?;
+        let ops = trusty_core::trbk::parse_trbk_page_ops(&buf)?;
+        Ok(trusty_core::trbk::TrbkPage { ops })
+    }
 }
 
 impl<D> SdImageSource<D>
@@ -34,7 +146,23 @@ where
     D::Error: core::fmt::Debug,
 {
     pub fn new(sdcard: D) -> Self {
-        Self { sdcard, trbk: None }
+        Self {
+            sdcard,
+            trbk: None,
+            time_provider: None,
+        }
+    }
+
+    pub fn with_time_provider(mut self, time_provider: &'static dyn fatfs::TimeProvider) -> Self {
+        self.time_provider = Some(time_provider);
+        self
+    }
+
+    fn fs_options(&self) -> FsOptions {
+        match self.time_provider {
+            Some(time_provider) => FsOptions::new().time_provider(time_provider),
+            None => FsOptions::new(),
+        }
     }
 
     fn is_supported(name: &str) -> bool {
@@ -54,6 +182,14 @@ where
         ".trusty_recents"
     }
 
+    fn settings_filename() -> &'static str {
+        ".trusty_settings"
+    }
+
+    fn button_calibration_filename() -> &'static str {
+        ".trusty_buttons"
+    }
+
     fn thumbnails_dirname() -> &'static str {
         ".trusty_cache"
     }
@@ -77,25 +213,50 @@ where
     fn open_fs(&self) -> Result<FileSystem<SdCardIo<'_, D>>, ImageError> {
         let base_lba = detect_fat_partition(&self.sdcard).map_err(|_| ImageError::Io)?;
         let io = SdCardIo::new(&self.sdcard, base_lba).map_err(|_| ImageError::Io)?;
-        FileSystem::new(io, FsOptions::new()).map_err(|_| ImageError::Io)
+        FileSystem::new(io, self.fs_options()).map_err(|_| ImageError::Io)
+    }
+
+    /// Reads the button calibration record written by
+    /// `save_button_calibration`, if the card has one.
+    pub fn load_button_calibration(&self) -> Option<String> {
+        let fs = self.open_fs().ok()?;
+        let root_dir = fs.root_dir();
+        let mut file = root_dir.open_file(Self::button_calibration_filename()).ok()?;
+        let mut buf = [0u8; 128];
+        let read = file.read(&mut buf).ok()?;
+        core::str::from_utf8(&buf[..read]).ok().map(|text| text.to_string())
+    }
+
+    pub fn save_button_calibration(&self, record: &str) {
+        let Ok(fs) = self.open_fs() else {
+            return;
+        };
+        let root_dir = fs.root_dir();
+        let name = Self::button_calibration_filename();
+        let _ = root_dir.remove(name);
+        let Ok(mut file) = root_dir.create_file(name) else {
+            return;
+        };
+        let _ = file.truncate();
+        if write_all(&mut file, record.as_bytes()).is_err() {
+            drop(file);
+            let _ = root_dir.remove(name);
+            return;
+        }
+        let _ = file.flush();
     }
 
     fn read_resume_from_root(
         &self,
         root_dir: &fatfs::Dir<'_, SdCardIo<'_, D>>,
-    ) -> Option<String> {
+    ) -> Option<ResumeState> {
         let mut file = root_dir.open_file(Self::resume_filename()).ok()?;
-        let mut buf = [0u8; 128];
+        let mut buf = [0u8; 160];
         let read = file.read(&mut buf).ok()?;
         if read == 0 {
             return None;
         }
-        let name = core::str::from_utf8(&buf[..read]).ok()?.trim();
-        if name.is_empty() {
-            None
-        } else {
-            Some(name.to_string())
-        }
+        ResumeState::parse(core::str::from_utf8(&buf[..read]).ok()?)
     }
 
     fn read_book_positions_from_root(
@@ -248,12 +409,14 @@ fn read_trimg_from_file<R: Read>(reader: &mut R, len: usize) -> Result<ImageData
     if &header[0..4] != b"TRIM" {
         return Err(ImageError::Unsupported);
     }
-    if header[4] != 1 || header[5] != 1 {
+    if header[4] != 1 || !matches!(header[5], 1 | 2) {
         return Err(ImageError::Unsupported);
     }
+    let gray2 = header[5] == 2;
     let width = u16::from_le_bytes([header[6], header[7]]) as u32;
     let height = u16::from_le_bytes([header[8], header[9]]) as u32;
-    let expected = ((width as usize * height as usize) + 7) / 8;
+    let pixels = width as usize * height as usize;
+    let expected = if gray2 { pixels.div_ceil(4) } else { (pixels + 7) / 8 };
     if 16 + expected != len {
         return Err(ImageError::Decode);
     }
@@ -283,6 +446,13 @@ fn read_trimg_from_file<R: Read>(reader: &mut R, len: usize) -> Result<ImageData
         return Err(ImageError::Decode);
     }
 
+    if gray2 {
+        return Ok(ImageData::Gray2 {
+            width,
+            height,
+            pixels: bits,
+        });
+    }
     Ok(ImageData::Mono1 { width, height, bits })
 }
 
@@ -299,6 +469,15 @@ fn read_string(data: &[u8], cursor: &mut usize) -> Result<String, ImageError> {
     Ok(value)
 }
 
+/// Packs a FAT directory timestamp as YYYYMMDDhhmmss so it sorts by age.
+fn fat_timestamp_key(time: &fatfs::DateTime) -> u64 {
+    let date = &time.date;
+    let clock = &time.time;
+    ((date.year as u64 * 100 + date.month as u64) * 100 + date.day as u64) * 1_000_000
+        + (clock.hour as u64 * 100 + clock.min as u64) * 100
+        + clock.sec as u64
+}
+
 impl<D> ImageSource for SdImageSource<D>
 where
     D: embedded_sdmmc::BlockDevice,
@@ -324,15 +503,18 @@ where
             {
                 continue;
             }
+            let modified = Some(fat_timestamp_key(&entry.modified()));
             if entry.is_dir() {
                 entries.push(ImageEntry {
                     name,
                     kind: EntryKind::Dir,
+                    modified,
                 });
             } else if Self::is_supported(&name) {
                 entries.push(ImageEntry {
                     name,
                     kind: EntryKind::File,
+                    modified,
                 });
             }
         }
@@ -388,12 +570,14 @@ where
         if read != header.len() || &header[0..4] != b"TRIM" {
             return Err(ImageError::Unsupported);
         }
-        if header[4] != 1 || header[5] != 1 {
+        if header[4] != 1 || !matches!(header[5], 1 | 2) {
             return Err(ImageError::Unsupported);
         }
+        let gray2 = header[5] == 2;
         let width = u16::from_le_bytes([header[6], header[7]]) as u32;
         let height = u16::from_le_bytes([header[8], header[9]]) as u32;
-        let expected = ((width as usize * height as usize) + 7) / 8;
+        let pixels = width as usize * height as usize;
+        let expected = if gray2 { pixels.div_ceil(4) } else { (pixels + 7) / 8 };
         if 16 + expected != file_len {
             return Err(ImageError::Decode);
         }
@@ -423,10 +607,17 @@ where
             return Err(ImageError::Decode);
         }
 
+        if gray2 {
+            return Ok(ImageData::Gray2 {
+                width,
+                height,
+                pixels: bits,
+            });
+        }
         Ok(ImageData::Mono1 { width, height, bits })
     }
 
-    fn save_resume(&mut self, name: Option<&str>) {
+    fn save_resume(&mut self, state: Option<&ResumeState>) {
         let fs = match self.open_fs() {
             Ok(fs) => fs,
             Err(_) => return,
@@ -434,8 +625,8 @@ where
         let root_dir = fs.root_dir();
         let resume_name = Self::resume_filename();
         let temp_name = ".trusty_resume.tmp";
-        if let Some(name) = name {
-            log::info!("Saving resume state: {}", name);
+        if let Some(state) = state {
+            log::info!("Saving resume state: {:?}", state);
             let _ = root_dir.remove(temp_name);
             let mut file = match root_dir.create_file(temp_name) {
                 Ok(file) => file,
@@ -443,7 +634,8 @@ where
             };
             let _ = file.truncate();
             let mut written = 0usize;
-            let bytes = name.as_bytes();
+            let record = state.to_record();
+            let bytes = record.as_bytes();
             while written < bytes.len() {
                 match file.write(&bytes[written..]) {
                     Ok(0) | Err(_) => break,
@@ -456,7 +648,7 @@ where
             let _ = root_dir.rename(temp_name, &root_dir, resume_name);
             let readback = self.read_resume_from_root(&root_dir);
             if let Some(value) = readback {
-                log::info!("Resume state readback: {}", value);
+                log::info!("Resume state readback: {:?}", value);
             } else {
                 log::info!("Resume state readback: <none>");
             }
@@ -466,20 +658,15 @@ where
         }
     }
 
-    fn load_resume(&mut self) -> Option<String> {
+    fn load_resume(&mut self) -> Option<ResumeState> {
         let fs = self.open_fs().ok()?;
         let mut file = fs.root_dir().open_file(Self::resume_filename()).ok()?;
-        let mut buf = [0u8; 128];
+        let mut buf = [0u8; 160];
         let read = file.read(&mut buf).ok()?;
         if read == 0 {
             return None;
         }
-        let name = core::str::from_utf8(&buf[..read]).ok()?.trim();
-        if name.is_empty() {
-            None
-        } else {
-            Some(name.to_string())
-        }
+        ResumeState::parse(core::str::from_utf8(&buf[..read]).ok()?)
     }
 
     fn save_book_positions(&mut self, entries: &[(String, usize)]) {
@@ -527,6 +714,34 @@ where
         self.read_book_positions_from_root(&root_dir)
     }
 
+    fn save_settings(&mut self, settings: &Settings) {
+        let Ok(fs) = self.open_fs() else {
+            return;
+        };
+        let root_dir = fs.root_dir();
+        let name = Self::settings_filename();
+        let _ = root_dir.remove(name);
+        let Ok(mut file) = root_dir.create_file(name) else {
+            return;
+        };
+        let _ = file.truncate();
+        if write_all(&mut file, settings.to_record().as_bytes()).is_err() {
+            drop(file);
+            let _ = root_dir.remove(name);
+            return;
+        }
+        let _ = file.flush();
+    }
+
+    fn load_settings(&mut self) -> Option<Settings> {
+        let fs = self.open_fs().ok()?;
+        let root_dir = fs.root_dir();
+        let mut file = root_dir.open_file(Self::settings_filename()).ok()?;
+        let mut buf = [0u8; 256];
+        let read = file.read(&mut buf).ok()?;
+        Some(Settings::parse(core::str::from_utf8(&buf[..read]).ok()?))
+    }
+
     fn save_recent_entries(&mut self, entries: &[String]) {
         let fs = match self.open_fs() {
             Ok(fs) => fs,
@@ -792,6 +1007,38 @@ where
         trusty_core::trbk::parse_trbk(&data)
     }
 
+    fn epub_info(&mut self, path: &[String], entry: &ImageEntry) -> Result<EpubInfo, ImageError> {
+        const MAX_INFO_BYTES: usize = 2048;
+        if entry.kind != EntryKind::File {
+            return Err(ImageError::Unsupported);
+        }
+        let stem = entry
+            .name
+            .rsplit_once('.')
+            .map(|(stem, _)| stem)
+            .unwrap_or(&entry.name);
+        let fs = self.open_fs()?;
+        let mut dir = fs.root_dir();
+        for part in path {
+            dir = dir.open_dir(part).map_err(|_| ImageError::Io)?;
+        }
+        // No sidecar means the EPUB was never run through trusty-book.
+        let mut file = dir
+            .open_file(&alloc::format!("{}.epubinfo", stem))
+            .map_err(|_| ImageError::Unsupported)?;
+        let mut data = Vec::new();
+        let mut buffer = [0u8; 256];
+        while data.len() < MAX_INFO_BYTES {
+            let read = file.read(&mut buffer).map_err(|_| ImageError::Io)?;
+            if read == 0 {
+                break;
+            }
+            let take = read.min(MAX_INFO_BYTES - data.len());
+            data.extend_from_slice(&buffer[..take]);
+        }
+        Ok(EpubInfo::parse(&String::from_utf8_lossy(&data)))
+    }
+
     fn open_trbk(
         &mut self,
         path: &[String],
@@ -800,22 +1047,31 @@ where
         if entry.kind != EntryKind::File {
             return Err(ImageError::Unsupported);
         }
-        let fs = self.open_fs()?;
+        let base_lba = detect_fat_partition(&self.sdcard).map_err(|_| ImageError::Io)?;
+        let cluster_size = fat_cluster_size(&self.sdcard, base_lba).map_err(|_| ImageError::Io)?;
+        let trace = Cell::new(0);
+        let io = SdCardIo::new(&self.sdcard, base_lba)
+            .map_err(|_| ImageError::Io)?
+            .with_read_trace(&trace);
+        let total_blocks = io.total_blocks();
+        let fs = FileSystem::new(io, self.fs_options()).map_err(|_| ImageError::Io)?;
         let mut dir = fs.root_dir();
         for part in path {
             dir = dir.open_dir(part).map_err(|_| ImageError::Io)?;
         }
         let mut file = dir.open_file(&entry.name).map_err(|_| ImageError::Io)?;
 
-        let mut header = [0u8; 0x30];
+        let mut header = [0u8; 0x34];
         read_exact(&mut file, &mut header)?;
         if &header[0..4] != b"TRBK" {
             return Err(ImageError::Decode);
         }
         let version = header[4];
-        if version != 1 && version != 2 {
+        if !(1..=5).contains(&version) {
             return Err(ImageError::Unsupported);
         }
+        let gray_glyphs =
+            version >= 2 && header[5] & trusty_core::trbk::TRBK_FLAG_GRAY_GLYPHS != 0;
         let header_size = read_u16_le(&header, 0x06)? as usize;
         let screen_width = read_u16_le(&header, 0x08)?;
         let screen_height = read_u16_le(&header, 0x0A)?;
@@ -837,6 +1093,11 @@ where
         } else {
             0
         };
+        let kerning_offset = if version >= 5 {
+            read_u32_le(&header, 0x30)? as u32
+        } else {
+            0
+        };
 
         if toc_count != 0 && toc_offset as usize != header_size {
             return Err(ImageError::Decode);
@@ -847,7 +1108,7 @@ where
         file.seek(SeekFrom::Start(0)).map_err(|_| ImageError::Io)?;
         read_exact(&mut file, &mut header_buf)?;
 
-        let mut cursor = if version >= 2 { 0x30 } else { 0x2C };
+        let mut cursor = trusty_core::trbk::trbk_fixed_header_size(version);
         let title = read_string(&header_buf, &mut cursor)?;
         let author = read_string(&header_buf, &mut cursor)?;
         let language = read_string(&header_buf, &mut cursor)?;
@@ -859,7 +1120,8 @@ where
         let margin_left = read_u16_le(&header_buf, cursor)?; cursor += 2;
         let margin_right = read_u16_le(&header_buf, cursor)?; cursor += 2;
         let margin_top = read_u16_le(&header_buf, cursor)?; cursor += 2;
-        let margin_bottom = read_u16_le(&header_buf, cursor)?;
+        let margin_bottom = read_u16_le(&header_buf, cursor)?; cursor += 2;
+        let chapter_starts = trusty_core::trbk::parse_trbk_chapter_starts(&header_buf, cursor);
 
         let metadata = trusty_core::trbk::TrbkMetadata {
             title,
@@ -893,10 +1155,16 @@ where
                 read_exact(&mut file, &mut entry_buf)?;
                 let page_index = u32::from_le_bytes([entry_buf[0], entry_buf[1], entry_buf[2], entry_buf[3]]);
                 let level = entry_buf[4];
+                let y_offset = if version >= 4 {
+                    u16::from_le_bytes([entry_buf[6], entry_buf[7]])
+                } else {
+                    0
+                };
                 toc_entries.push(trusty_core::trbk::TrbkTocEntry {
                     title,
                     page_index,
                     level,
+                    y_offset,
                 });
             }
         }
@@ -918,8 +1186,13 @@ where
             ]));
         }
 
+        // Counts and lengths below come from the file; anything claiming more
+        // bytes than the file holds is rejected before it is allocated.
+        let file_len = file.seek(SeekFrom::End(0)).map_err(|_| ImageError::Io)? as usize;
+
         // Glyphs
         let mut glyphs = Vec::new();
+        let mut bitmap_refs = Vec::new();
         if glyph_count > 0 {
             file.seek(SeekFrom::Start(glyph_table_offset as u64))
                 .map_err(|_| ImageError::Io)?;
@@ -933,9 +1206,19 @@ where
                 let x_advance = i16::from_le_bytes([header[7], header[8]]);
                 let x_offset = i16::from_le_bytes([header[9], header[10]]);
                 let y_offset = i16::from_le_bytes([header[11], header[12]]);
-                let bitmap_len = u32::from_le_bytes([header[13], header[14], header[15], header[16]]) as usize;
-                let mut bitmap = vec![0u8; bitmap_len];
-                read_exact(&mut file, &mut bitmap)?;
+                let bitmap_field = u32::from_le_bytes([header[13], header[14], header[15], header[16]]) as usize;
+                let bitmap = if version >= 3 {
+                    // Version 3 references a shared bitmap pool after the entries.
+                    bitmap_refs.push(bitmap_field);
+                    Vec::new()
+                } else {
+                    if bitmap_field > file_len {
+                        return Err(ImageError::Decode);
+                    }
+                    let mut bitmap = vec![0u8; bitmap_field];
+                    read_exact(&mut file, &mut bitmap)?;
+                    bitmap
+                };
                 glyphs.push(trusty_core::trbk::TrbkGlyph {
                     codepoint,
                     style,
@@ -947,6 +1230,48 @@ where
                     bitmap,
                 });
             }
+            if version >= 3 {
+                let mut count_buf = [0u8; 4];
+                read_exact(&mut file, &mut count_buf)?;
+                let bitmap_count = u32::from_le_bytes(count_buf) as usize;
+                let pool_bytes = file_len.saturating_sub(glyph_table_offset as usize);
+                let mut bitmaps = Vec::with_capacity(bitmap_count.min(pool_bytes / 4));
+                for _ in 0..bitmap_count {
+                    let mut len_buf = [0u8; 4];
+                    read_exact(&mut file, &mut len_buf)?;
+                    let bitmap_len = u32::from_le_bytes(len_buf) as usize;
+                    if bitmap_len > pool_bytes {
+                        return Err(ImageError::Decode);
+                    }
+                    let mut bitmap = vec![0u8; bitmap_len];
+                    read_exact(&mut file, &mut bitmap)?;
+                    bitmaps.push(bitmap);
+                }
+                for (glyph, index) in glyphs.iter_mut().zip(bitmap_refs) {
+                    glyph.bitmap = bitmaps.get(index).ok_or(ImageError::Decode)?.clone();
+                }
+            }
+        }
+
+        let mut kerning = Vec::new();
+        if kerning_offset > 0 {
+            file.seek(SeekFrom::Start(kerning_offset as u64))
+                .map_err(|_| ImageError::Io)?;
+            let mut count_buf = [0u8; 4];
+            read_exact(&mut file, &mut count_buf)?;
+            let pair_count = u32::from_le_bytes(count_buf) as usize;
+            let remaining = file_len.saturating_sub(kerning_offset as usize + 4);
+            let table_len = pair_count
+                .checked_mul(12)
+                .filter(|len| *len <= remaining)
+                .ok_or(ImageError::Decode)?;
+            let mut table = vec![0u8; table_len];
+            read_exact(&mut file, &mut table)?;
+            for idx in 0..pair_count {
+                kerning.push(trusty_core::trbk::parse_kerning_entry(
+                    &table[idx * 12..idx * 12 + 12],
+                ));
+            }
         }
 
         let mut images = Vec::new();
@@ -1024,62 +1349,85 @@ where
             }
         }
 
+        let cover_image =
+            trusty_core::trbk::parse_trbk_cover_image(&header_buf, cursor, images.len());
+
         let info = trusty_core::trbk::TrbkBookInfo {
             screen_width,
             screen_height,
             page_count,
             metadata,
             glyphs: glyphs.clone(),
+            gray_glyphs,
             toc: toc_entries,
             images,
+            kerning,
+            chapter_starts,
+            cover_image,
         };
 
+        let extents =
+            map_file_extents(&mut file, &trace, cluster_size).map_err(|_| ImageError::Io)?;
+
         drop(file);
         drop(dir);
         drop(fs);
 
         self.trbk = Some(TrbkStream {
-            path: path.to_vec(),
-            name: entry.name.clone(),
+            base_lba,
+            total_blocks,
+            extents,
             page_offsets: offsets,
             page_data_offset,
             glyph_table_offset,
             info: info.clone(),
+            page_cache: PageCache::default(),
         });
 
         Ok(info)
     }
 
     fn trbk_page(&mut self, page_index: usize) -> Result<trusty_core::trbk::TrbkPage, ImageError> {
-        let Some(state) = &self.trbk else {
+        let Some(state) = &mut self.trbk else {
             return Err(ImageError::Decode);
         };
         if page_index >= state.page_offsets.len() {
             return Err(ImageError::Decode);
         }
-        let fs = self.open_fs()?;
-        let mut dir = fs.root_dir();
-        for part in &state.path {
-            dir = dir.open_dir(part).map_err(|_| ImageError::Io)?;
+        if let Some(page) = state.page_cache.get(page_index) {
+            return Ok(page);
         }
-        let mut file = dir.open_file(&state.name).map_err(|_| ImageError::Io)?;
+        let page = state.read_page(&self.sdcard, page_index)?;
+        state.page_cache.insert(page_index, page.clone());
+        Ok(page)
+    }
 
-        let start = state.page_data_offset + state.page_offsets[page_index];
-        let end = if page_index + 1 < state.page_offsets.len() {
-            state.page_data_offset + state.page_offsets[page_index + 1]
-        } else {
-            state.glyph_table_offset
+    fn prefetch_trbk_page(&mut self, page_index: usize) {
+        let Some(state) = &mut self.trbk else {
+            return;
         };
-        if end < start {
-            return Err(ImageError::Decode);
+        if page_index >= state.page_offsets.len() || state.page_cache.contains(page_index) {
+            return;
+        }
+        if let Ok(page) = state.read_page(&self.sdcard, page_index) {
+            state.page_cache.insert(page_index, page);
         }
-        let len = (end - start) as usize;
-        let mut buf = vec![0u8; len];
-        file.seek(SeekFrom::Start(start as u64))
-            .map_err(|_| ImageError::Io)?;
-        read_exact(&mut file, &mut buf)?;
-        let ops = trusty_core::trbk::parse_trbk_page_ops(&buf)?;
-        Ok(trusty_core::trbk::TrbkPage { ops })
+    }
+
+    fn trbk_search(&mut self, query: &str, pages: core::ops::Range<usize>) -> Vec<usize> {
+        let Some(state) = &self.trbk else {
+            return Vec::new();
+        };
+        // Straight from the card: going through the page cache would evict
+        // the pages around the reader.
+        let end = pages.end.min(state.page_offsets.len());
+        (pages.start..end)
+            .filter(|&page| {
+                state
+                    .read_page(&self.sdcard, page)
+                    .is_ok_and(|ops| ops.contains_text(query))
+            })
+            .collect()
     }
 
     fn trbk_image(&mut self, image_index: usize) -> Result<ImageData, ImageError> {
@@ -1091,12 +1439,7 @@ where
             .images
             .get(image_index)
             .ok_or(ImageError::Decode)?;
-        let fs = self.open_fs()?;
-        let mut dir = fs.root_dir();
-        for part in &state.path {
-            dir = dir.open_dir(part).map_err(|_| ImageError::Io)?;
-        }
-        let mut file = dir.open_file(&state.name).map_err(|_| ImageError::Io)?;
+        let mut file = state.reader(&self.sdcard);
         file.seek(SeekFrom::Start(image.data_offset as u64))
             .map_err(|_| ImageError::Io)?;
         read_trimg_from_file(&mut file, image.data_len as usize)
@@ -1105,4 +1448,16 @@ where
     fn close_trbk(&mut self) {
         self.trbk = None;
     }
+
+    fn delete(&mut self, path: &[String], entry: &ImageEntry) -> Result<(), ImageError> {
+        if entry.kind != EntryKind::File {
+            return Err(ImageError::Unsupported);
+        }
+        let fs = self.open_fs()?;
+        let mut dir = fs.root_dir();
+        for part in path {
+            dir = dir.open_dir(part).map_err(|_| ImageError::Io)?;
+        }
+        dir.remove(&entry.name).map_err(|_| ImageError::Io)
+    }
 }
@@ -1,69 +1,202 @@
+use alloc::format;
+use alloc::string::String;
+use embedded_hal::delay::DelayNs;
 use esp_hal::{
     Blocking,
     analog::adc::{Adc, AdcCalLine, AdcChannel, AdcConfig, AdcPin, Attenuation},
     gpio::{AnalogPin, Input, InputConfig, InputPin},
     peripherals::ADC1,
 };
-use log::trace;
+use log::{info, trace, warn};
+use trusty_core::battery::BatteryMonitor;
 use trusty_core::input::ButtonState;
 
 const ADC_THRESHOLDS_1: [i16; 4] = [2635, 2015, 1117, 3];
 const ADC_THRESHOLDS_2: [i16; 2] = [1680, 3];
 const ADC_TOLERANCE: i16 = 400;
+/// Readings above this mean no button on the ladder is down.
+const ADC_IDLE: i16 = 3800;
+const CALIBRATION_TIMEOUT_MS: u32 = 10_000;
+const CALIBRATION_SAMPLES: i32 = 16;
+/// The battery sense pin sits behind a 1:1 divider, so it sees half the cell.
+const BATTERY_DIVIDER: u32 = 2;
+const BATTERY_SAMPLES: u32 = 8;
+
+const LADDER_1_NAMES: [&str; 4] = ["Back", "Confirm", "Left", "Right"];
+const LADDER_2_NAMES: [&str; 2] = ["Up", "Down"];
+
+/// ADC levels for the two resistor-ladder button pins. Units vary with
+/// resistor tolerance, so these can be learned with `calibrate` and stored
+/// on the SD card; the defaults are the values measured on a reference unit.
+#[derive(Clone, Copy, Debug)]
+pub struct ButtonThresholds {
+    pub ladder1: [i16; 4],
+    pub ladder2: [i16; 2],
+    pub tolerance: i16,
+}
+
+impl Default for ButtonThresholds {
+    fn default() -> Self {
+        Self {
+            ladder1: ADC_THRESHOLDS_1,
+            ladder2: ADC_THRESHOLDS_2,
+            tolerance: ADC_TOLERANCE,
+        }
+    }
+}
+
+impl ButtonThresholds {
+    /// Serializes as `key=v1,v2,...` lines, the format read by `parse`.
+    pub fn to_record(&self) -> String {
+        format!(
+            "ladder1={},{},{},{}\nladder2={},{}\ntolerance={}\n",
+            self.ladder1[0],
+            self.ladder1[1],
+            self.ladder1[2],
+            self.ladder1[3],
+            self.ladder2[0],
+            self.ladder2[1],
+            self.tolerance
+        )
+    }
+
+    /// Parses a record written by `to_record`. Missing or malformed lines keep
+    /// their defaults.
+    pub fn parse(text: &str) -> Self {
+        let mut thresholds = Self::default();
+        for line in text.lines() {
+            let Some((key, values)) = line.split_once('=') else {
+                continue;
+            };
+            match key.trim() {
+                "ladder1" => parse_levels(values, &mut thresholds.ladder1),
+                "ladder2" => parse_levels(values, &mut thresholds.ladder2),
+                "tolerance" => {
+                    if let Ok(value) = values.trim().parse::<i16>() {
+                        if value > 0 {
+                            thresholds.tolerance = value;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        thresholds
+    }
+
+    /// Half the smallest gap between neighbouring levels, so calibrated
+    /// windows never overlap, capped at the default tolerance.
+    fn derived_tolerance(&self) -> i16 {
+        let mut tolerance = ADC_TOLERANCE;
+        for ladder in [&self.ladder1[..], &self.ladder2[..]] {
+            for (i, a) in ladder.iter().enumerate() {
+                for b in &ladder[i + 1..] {
+                    tolerance = tolerance.min(((a - b).abs() / 2).max(1));
+                }
+            }
+        }
+        tolerance
+    }
+}
+
+fn parse_levels<const N: usize>(values: &str, out: &mut [i16; N]) {
+    let mut parsed = [0i16; N];
+    let mut count = 0;
+    for value in values.split(',') {
+        let Ok(value) = value.trim().parse::<i16>() else {
+            return;
+        };
+        if count == N {
+            return;
+        }
+        parsed[count] = value;
+        count += 1;
+    }
+    if count == N {
+        *out = parsed;
+    }
+}
 
 type AdcCal<'a> = AdcCalLine<ADC1<'a>>;
 
-pub struct GpioButtonState<'a, Pin1, Pin2>
+/// Owns ADC1, so the battery sense pin is read here alongside the button
+/// ladders.
+pub struct GpioButtonState<'a, Pin1, Pin2, PinBat>
 where
     Pin1: AdcChannel + AnalogPin,
     Pin2: AdcChannel + AnalogPin,
+    PinBat: AdcChannel + AnalogPin,
 {
     inner: ButtonState,
+    thresholds: ButtonThresholds,
     pin1: AdcPin<Pin1, ADC1<'a>, AdcCal<'a>>,
     pin2: AdcPin<Pin2, ADC1<'a>, AdcCal<'a>>,
+    pin_battery: AdcPin<PinBat, ADC1<'a>, AdcCal<'a>>,
     pin_power: Input<'a>,
     adc: Adc<'a, ADC1<'a>, Blocking>,
 }
 
-impl<'a, Pin1: AdcChannel + AnalogPin, Pin2: AdcChannel + AnalogPin>
-    GpioButtonState<'a, Pin1, Pin2>
+impl<'a, Pin1, Pin2, PinBat> GpioButtonState<'a, Pin1, Pin2, PinBat>
+where
+    Pin1: AdcChannel + AnalogPin,
+    Pin2: AdcChannel + AnalogPin,
+    PinBat: AdcChannel + AnalogPin,
 {
-    pub fn new(pin1: Pin1, pin2: Pin2, pin_power: impl InputPin + 'a, adc: ADC1<'a>) -> Self {
+    pub fn new(
+        pin1: Pin1,
+        pin2: Pin2,
+        pin_battery: PinBat,
+        pin_power: impl InputPin + 'a,
+        adc: ADC1<'a>,
+    ) -> Self {
         let mut adc_config = AdcConfig::new();
 
         let pin1 = adc_config.enable_pin_with_cal::<_, AdcCal>(pin1, Attenuation::_11dB);
         let pin2 = adc_config.enable_pin_with_cal::<_, AdcCal>(pin2, Attenuation::_11dB);
+        let pin_battery =
+            adc_config.enable_pin_with_cal::<_, AdcCal>(pin_battery, Attenuation::_11dB);
         let pin_power = Input::new(pin_power, InputConfig::default());
         let adc = Adc::new(adc, adc_config);
         GpioButtonState {
             inner: ButtonState::default(),
+            thresholds: ButtonThresholds::default(),
             pin1,
             pin2,
+            pin_battery,
             pin_power,
             adc,
         }
     }
 
-    fn get_button_from_adc(value: i16, thresholds: &[i16]) -> Option<u8> {
-        if value > 3800 {
+    pub fn set_thresholds(&mut self, thresholds: ButtonThresholds) {
+        self.thresholds = thresholds;
+    }
+
+    fn get_button_from_adc(value: i16, thresholds: &[i16], tolerance: i16) -> Option<u8> {
+        if value > ADC_IDLE {
             return None;
         }
         for (i, &threshold) in thresholds.iter().enumerate() {
-            if (value - threshold).abs() < ADC_TOLERANCE {
+            if (value - threshold).abs() < tolerance {
                 return Some(i as u8);
             }
         }
         None
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, elapsed_ms: u32) {
         let mut current: u8 = 0;
-        let raw_button1 = nb::block!(self.adc.read_oneshot(&mut self.pin1)).unwrap();
-        if let Some(button) = Self::get_button_from_adc(raw_button1 as _, &ADC_THRESHOLDS_1) {
+        let tolerance = self.thresholds.tolerance;
+        let raw_button1 = self.read_ladder(true);
+        if let Some(button) =
+            Self::get_button_from_adc(raw_button1, &self.thresholds.ladder1, tolerance)
+        {
             current |= 1 << button;
         }
-        let raw_button2 = nb::block!(self.adc.read_oneshot(&mut self.pin2)).unwrap();
-        if let Some(button) = Self::get_button_from_adc(raw_button2 as _, &ADC_THRESHOLDS_2) {
+        let raw_button2 = self.read_ladder(false);
+        if let Some(button) =
+            Self::get_button_from_adc(raw_button2, &self.thresholds.ladder2, tolerance)
+        {
             current |= 1 << (button + 4);
         }
         if self.pin_power.is_low() {
@@ -73,10 +206,98 @@ impl<'a, Pin1: AdcChannel + AnalogPin, Pin2: AdcChannel + AnalogPin>
             "Button ADC Readings - Pin1: {}, Pin2: {}, Current State: {:07b}",
             raw_button1, raw_button2, current
         );
-        self.inner.update(current);
+        self.inner.update(current, elapsed_ms);
     }
 
     pub fn get_buttons(&self) -> ButtonState {
         self.inner
     }
+
+    fn read_ladder(&mut self, first: bool) -> i16 {
+        let raw = if first {
+            nb::block!(self.adc.read_oneshot(&mut self.pin1)).unwrap()
+        } else {
+            nb::block!(self.adc.read_oneshot(&mut self.pin2)).unwrap()
+        };
+        raw as i16
+    }
+
+    /// Walks through each ladder button with serial prompts, averaging the
+    /// ADC reading while it is held. Buttons that are not pressed within the
+    /// timeout keep their current level.
+    pub fn calibrate(&mut self, delay: &mut impl DelayNs) -> ButtonThresholds {
+        info!("Button calibration: press and hold each button when asked.");
+        let mut thresholds = self.thresholds;
+        for (i, name) in LADDER_1_NAMES.iter().enumerate() {
+            if let Some(level) = self.sample_press(name, true, delay) {
+                thresholds.ladder1[i] = level;
+            }
+        }
+        for (i, name) in LADDER_2_NAMES.iter().enumerate() {
+            if let Some(level) = self.sample_press(name, false, delay) {
+                thresholds.ladder2[i] = level;
+            }
+        }
+        thresholds.tolerance = thresholds.derived_tolerance();
+        info!("Button calibration done: {:?}", thresholds);
+        self.thresholds = thresholds;
+        thresholds
+    }
+
+    fn sample_press(&mut self, name: &str, first: bool, delay: &mut impl DelayNs) -> Option<i16> {
+        info!("Press and hold {name}...");
+        if !self.wait_for_ladder(first, true, delay) {
+            warn!("No press seen for {name}, keeping the previous level");
+            return None;
+        }
+        // Let the contact settle before sampling.
+        delay.delay_ms(50);
+        let mut total = 0i32;
+        for _ in 0..CALIBRATION_SAMPLES {
+            let value = self.read_ladder(first);
+            if value > ADC_IDLE {
+                // Released early: idle readings would skew the level.
+                warn!("{name} was released while sampling, keeping the previous level");
+                return None;
+            }
+            total += value as i32;
+            delay.delay_ms(5);
+        }
+        let level = (total / CALIBRATION_SAMPLES) as i16;
+        info!("{name} reads {level}, release it");
+        self.wait_for_ladder(first, false, delay);
+        Some(level)
+    }
+
+    /// Polls until the ladder is pressed (or released), giving up after
+    /// `CALIBRATION_TIMEOUT_MS`.
+    fn wait_for_ladder(&mut self, first: bool, pressed: bool, delay: &mut impl DelayNs) -> bool {
+        let mut waited = 0;
+        while waited < CALIBRATION_TIMEOUT_MS {
+            if (self.read_ladder(first) <= ADC_IDLE) == pressed {
+                return true;
+            }
+            delay.delay_ms(10);
+            waited += 10;
+        }
+        false
+    }
+}
+
+impl<Pin1, Pin2, PinBat> BatteryMonitor for GpioButtonState<'_, Pin1, Pin2, PinBat>
+where
+    Pin1: AdcChannel + AnalogPin,
+    Pin2: AdcChannel + AnalogPin,
+    PinBat: AdcChannel + AnalogPin,
+{
+    /// Averages a few calibrated (millivolt) readings of the sense pin.
+    fn battery_millivolts(&mut self) -> Option<u32> {
+        let mut total = 0u32;
+        for _ in 0..BATTERY_SAMPLES {
+            total += nb::block!(self.adc.read_oneshot(&mut self.pin_battery)).ok()? as u32;
+        }
+        let millivolts = total / BATTERY_SAMPLES * BATTERY_DIVIDER;
+        trace!("Battery: {millivolts} mV");
+        Some(millivolts)
+    }
 }
@@ -7,6 +7,7 @@
 )]
 #![deny(clippy::large_stack_frames)]
 
+pub mod clock;
 pub mod eink_display;
 pub mod image_source;
 pub mod input;
@@ -14,6 +15,7 @@ pub mod sd_io;
 
 use core::cell::RefCell;
 
+use crate::clock::RtcClock;
 use crate::eink_display::EInkDisplay;
 use crate::image_source::SdImageSource;
 use crate::input::*;
@@ -39,9 +41,15 @@ use log::info;
 use trusty_core::application::Application;
 use trusty_core::display::{Display, RefreshMode};
 use trusty_core::framebuffer::DisplayBuffers;
+use trusty_core::input::Buttons;
 
 extern crate alloc;
 const MAX_BUFFER_SIZE: usize = 512;
+/// The cell drains slowly; a reading a minute is plenty for the indicator.
+const BATTERY_POLL_MS: u32 = 60_000;
+/// Largest SPI write for frame uploads; raise it on boards whose DMA setup
+/// takes bigger transfers.
+const DISPLAY_SPI_CHUNK_SIZE: usize = eink_display::DEFAULT_SPI_CHUNK_SIZE;
 
 // This creates a default app-descriptor required by the esp-idf bootloader.
 // For more information see: <https://docs.espressif.com/projects/esp-idf/en/stable/esp32/api-reference/system/app_image_format.html#application-description>
@@ -127,7 +135,9 @@ async fn main(spawner: Spawner) {
     log_heap();
 
     let delay = Delay::new();
-    let mut rtc = Rtc::new(peripherals.LPWR);
+    // Leaked so the filesystem can hold on to it for file timestamps.
+    let mut clock: &'static RtcClock<'static> =
+        Box::leak(Box::new(RtcClock::new(Rtc::new(peripherals.LPWR))));
 
     // Initialize shared SPI bus
     let spi_cfg = Config::default()
@@ -156,10 +166,12 @@ async fn main(spawner: Spawner) {
 
     // Create E-Ink Display instance
     info!("Creating E-Ink Display driver");
-    let mut display = EInkDisplay::new(eink_spi_device, dc, rst, busy, delay);
+    let mut display = EInkDisplay::new(eink_spi_device, dc, rst, busy, delay)
+        .with_spi_chunk_size(DISPLAY_SPI_CHUNK_SIZE);
 
     // Initialize the display
     display.begin().expect("Failed to initialize display");
+    display_buffers.set_hardware_flip(display.supports_hardware_flip());
 
     info!("Clearing screen");
     display.display(&mut display_buffers, RefreshMode::Full);
@@ -174,14 +186,27 @@ async fn main(spawner: Spawner) {
         info!("SD Card Size: {} bytes", size);
     }
 
-    let mut image_source = SdImageSource::new(sdcard);
-    let mut application = Application::new(&mut display_buffers, &mut image_source);
+    let mut image_source = SdImageSource::new(sdcard).with_time_provider(clock);
     let mut button_state = GpioButtonState::new(
         peripherals.GPIO1,
         peripherals.GPIO2,
+        peripherals.GPIO0,
         peripherals.GPIO3,
         peripherals.ADC1,
     );
+    // Holding Power and Back through boot re-learns the button ladder levels.
+    button_state.update(0);
+    let boot_buttons = button_state.get_buttons();
+    if boot_buttons.is_pressed(Buttons::Power) && boot_buttons.is_pressed(Buttons::Back) {
+        let thresholds = button_state.calibrate(&mut Delay::new());
+        image_source.save_button_calibration(&thresholds.to_record());
+    } else if let Some(record) = image_source.load_button_calibration() {
+        button_state.set_thresholds(ButtonThresholds::parse(&record));
+        info!("Loaded button calibration");
+    }
+    let mut application = Application::new(&mut display_buffers, &mut image_source);
+    application.update_battery(&mut button_state);
+    application.sync_clock(&mut clock);
 
     // After initializing the SD card, increase the SPI frequency
     shared_spi
@@ -194,23 +219,31 @@ async fn main(spawner: Spawner) {
         .expect("Failed to apply the second SPI configuration");
     info!("Display complete! Starting image viewer...");
 
+    let mut battery_ms = 0u32;
     loop {
         Timer::after(Duration::from_millis(10)).await;
 
-        button_state.update();
+        battery_ms += 10;
+        if battery_ms >= BATTERY_POLL_MS {
+            battery_ms = 0;
+            application.update_battery(&mut button_state);
+        }
+        // Reading the RTC is a register access, so keep the shown time current.
+        application.sync_clock(&mut clock);
+        button_state.update(10);
         let buttons = button_state.get_buttons();
         application.update(&buttons, 10);
         application.draw(&mut display);
         let _ = application.take_wake_transition();
         if application.take_sleep_transition() {
-            display.deep_sleep().ok();
+            // The panel was already powered down after the sleep overlay.
             let mut wake_pin = unsafe { AnyPin::steal(3) };
             wake_pin.rtcio_pullup(true);
             wake_pin.rtcio_pulldown(false);
             let mut wake_pins: [(&mut dyn esp_hal::gpio::RtcPinWithResistors, WakeupLevel); 1] =
                 [(&mut wake_pin, WakeupLevel::Low)];
             let rtcio = RtcioWakeupSource::new(&mut wake_pins);
-            rtc.sleep_deep(&[&rtcio]);
+            clock.sleep_deep(&[&rtcio]);
         }
     }
 }
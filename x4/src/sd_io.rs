@@ -1,5 +1,7 @@
 extern crate alloc;
 
+use alloc::vec::Vec;
+use core::cell::Cell;
 use core::cmp::min;
 
 use embedded_sdmmc::{Block, BlockDevice, BlockIdx};
@@ -14,6 +16,7 @@ where
     pos: u64,
     base_lba: u32,
     total_blocks: u32,
+    read_trace: Option<&'a Cell<u64>>,
 }
 
 impl<'a, D> SdCardIo<'a, D>
@@ -26,12 +29,30 @@ where
             .num_blocks()
             .map_err(|_| Error::new(ErrorKind::Other, "sdmmc"))?
             .0;
-        Ok(Self {
+        Ok(Self::with_geometry(sdcard, base_lba, total_blocks))
+    }
+
+    /// Builds an IO without querying the card, for callers that cached the
+    /// partition base and card size from an earlier `new`.
+    pub fn with_geometry(sdcard: &'a D, base_lba: u32, total_blocks: u32) -> Self {
+        Self {
             sdcard,
             pos: 0,
             base_lba,
             total_blocks,
-        })
+            read_trace: None,
+        }
+    }
+
+    /// Records the partition-relative position of every read in `trace`, so a
+    /// caller can learn where fatfs found a file's data.
+    pub fn with_read_trace(mut self, trace: &'a Cell<u64>) -> Self {
+        self.read_trace = Some(trace);
+        self
+    }
+
+    pub fn total_blocks(&self) -> u32 {
+        self.total_blocks
     }
 
     fn read_block(&self, lba: u32, block: &mut Block) -> Result<(), Error> {
@@ -64,6 +85,9 @@ where
         if self.pos >= self.max_bytes() {
             return Ok(0);
         }
+        if let Some(trace) = self.read_trace {
+            trace.set(self.pos);
+        }
 
         let mut offset = 0;
         let mut remaining = buf.len();
@@ -188,3 +212,137 @@ where
 
     Ok(0)
 }
+
+/// Cluster size in bytes, read from the boot sector of the FAT partition.
+pub fn fat_cluster_size<D>(sdcard: &D, base_lba: u32) -> Result<u32, Error>
+where
+    D: BlockDevice,
+    D::Error: core::fmt::Debug,
+{
+    let mut block = Block::new();
+    sdcard
+        .read(core::slice::from_mut(&mut block), BlockIdx(base_lba))
+        .map_err(|_| Error::new(ErrorKind::Other, "sdmmc"))?;
+    let bytes_per_sector = u16::from_le_bytes([block.contents[11], block.contents[12]]) as u32;
+    let sectors_per_cluster = block.contents[13] as u32;
+    let cluster_size = bytes_per_sector * sectors_per_cluster;
+    if cluster_size == 0 {
+        return Err(Error::new(ErrorKind::InvalidData, "bpb"));
+    }
+    Ok(cluster_size)
+}
+
+/// A contiguous run of file bytes at a partition-relative disk offset.
+#[derive(Clone, Copy, Debug)]
+pub struct FileExtent {
+    pub file_offset: u64,
+    pub disk_offset: u64,
+    pub len: u64,
+}
+
+/// Resolves a file's cluster chain into extents by reading one byte per
+/// cluster through an IO whose reads are recorded in `trace`. Adjacent
+/// clusters are merged, so an unfragmented file maps to a single extent.
+pub fn map_file_extents<F>(
+    file: &mut F,
+    trace: &Cell<u64>,
+    cluster_size: u32,
+) -> Result<Vec<FileExtent>, Error>
+where
+    F: Read + Seek,
+{
+    let len = file.seek(SeekFrom::End(0))?;
+    let cluster_size = cluster_size as u64;
+    let mut extents: Vec<FileExtent> = Vec::new();
+    let mut offset = 0u64;
+    let mut byte = [0u8; 1];
+    while offset < len {
+        file.seek(SeekFrom::Start(offset))?;
+        if file.read(&mut byte)? == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "extent"));
+        }
+        let disk_offset = trace.get();
+        let span = min(cluster_size, len - offset);
+        match extents.last_mut() {
+            Some(last) if last.disk_offset + last.len == disk_offset => last.len += span,
+            _ => extents.push(FileExtent {
+                file_offset: offset,
+                disk_offset,
+                len: span,
+            }),
+        }
+        offset += cluster_size;
+    }
+    Ok(extents)
+}
+
+/// Reads a file through its resolved extents, bypassing the filesystem.
+pub struct ExtentReader<'a, D>
+where
+    D: BlockDevice,
+    D::Error: core::fmt::Debug,
+{
+    io: SdCardIo<'a, D>,
+    extents: &'a [FileExtent],
+    pos: u64,
+}
+
+impl<'a, D> ExtentReader<'a, D>
+where
+    D: BlockDevice,
+    D::Error: core::fmt::Debug,
+{
+    pub fn new(io: SdCardIo<'a, D>, extents: &'a [FileExtent]) -> Self {
+        Self {
+            io,
+            extents,
+            pos: 0,
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.extents
+            .last()
+            .map(|extent| extent.file_offset + extent.len)
+            .unwrap_or(0)
+    }
+}
+
+impl<D> Read for ExtentReader<'_, D>
+where
+    D: BlockDevice,
+    D::Error: core::fmt::Debug,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let pos = self.pos;
+        let Some(extent) = self
+            .extents
+            .iter()
+            .find(|extent| pos >= extent.file_offset && pos < extent.file_offset + extent.len)
+        else {
+            return Ok(0);
+        };
+        let within = pos - extent.file_offset;
+        let take = min(buf.len() as u64, extent.len - within) as usize;
+        self.io.seek(SeekFrom::Start(extent.disk_offset + within))?;
+        let read = self.io.read(&mut buf[..take])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<D> Seek for ExtentReader<'_, D>
+where
+    D: BlockDevice,
+    D::Error: core::fmt::Debug,
+{
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        let next = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.len().checked_add_signed(offset),
+        };
+        self.pos = next.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "seek"))?;
+        Ok(self.pos)
+    }
+}